@@ -4,5 +4,8 @@
 
 #![warn(clippy::nursery, clippy::pedantic)]
 
-mod models;
-pub use models::{Run, Phase, SquadMember, TotalTimes, ShieldChange, LegBreak, StatusEffect, LegPosition};
+pub mod models;
+pub use models::{
+    Run, RunBuilder, Phase, PhaseBuilder, SquadMember, TotalTimes, ShieldChange, LegBreak, StatusEffect,
+    LegPosition, ModelError,
+};
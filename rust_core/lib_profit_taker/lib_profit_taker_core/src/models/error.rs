@@ -0,0 +1,73 @@
+//! This module defines the `ModelError` enum, which represents the ways in which a model can fail
+//! to be constructed.
+//!
+//! A `ModelError` is returned by the builders and checked constructors of the models when the
+//! values they are given break an invariant, such as a negative duration or an unknown status
+//! effect name or ID.
+
+use thiserror::Error;
+
+/// Represents an invariant that a model was about to break.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum ModelError {
+    /// A duration is negative, infinite, or not a number.
+    #[error("the {field} time {value} is not a valid duration")]
+    InvalidDuration {
+        /// The duration the value was given for, such as `phase 2 shield`.
+        field: String,
+
+        /// The invalid value.
+        value: f64,
+    },
+
+    /// A phase number is zero or negative.
+    #[error("phase number {0} is not positive")]
+    InvalidPhaseNumber(i32),
+
+    /// A phase number appears more than once in the same run.
+    #[error("phase {0} appears more than once")]
+    DuplicatePhase(i32),
+
+    /// A leg break order is zero or negative.
+    #[error("leg break order {0} is not positive")]
+    InvalidLegOrder(i32),
+
+    /// A name does not match any `StatusEffect` variant.
+    #[error("unknown status effect `{0}`")]
+    UnknownStatusEffect(String),
+
+    /// A name does not match any `LegPosition` variant.
+    #[error("unknown leg position `{0}`")]
+    UnknownLegPosition(String),
+
+    /// An integer ID does not match any `StatusEffect` variant.
+    #[error("unknown status effect ID {0}")]
+    UnknownStatusEffectId(i64),
+
+    /// An integer ID does not match any `LegPosition` variant.
+    #[error("unknown leg position ID {0}")]
+    UnknownLegPositionId(i64),
+}
+
+/// Returns an error if `value` is not a valid duration, i.e. if it is negative, infinite, or not a
+/// number.
+///
+/// # Arguments
+///
+/// * `field` - The duration the value was given for, used in the error.
+/// * `value` - The duration to check.
+///
+/// # Errors
+///
+/// Returns `ModelError::InvalidDuration` if `value` is not a valid duration.
+pub(crate) fn check_duration(field: &str, value: f64) -> Result<(), ModelError> {
+    if value.is_finite() && value >= 0.0 {
+        Ok(())
+    } else {
+        Err(ModelError::InvalidDuration {
+            field: field.to_string(),
+            value,
+        })
+    }
+}
@@ -1,14 +1,21 @@
 //! This module defines the `LegBreak` struct, which represents a leg break event during a phase.
-//! A `LegBreak` includes the position of the leg that was broken and the order in which it was broken.
+//!
+//! A `LegBreak` includes the time it took to break the leg, the position of the leg that was broken,
+//! and the order in which it was broken.
 
-use crate::models::LegPosition;
+use crate::models::error::check_duration;
+use crate::models::{LegPosition, ModelError};
 
 /// Represents a leg break event during a phase.
 ///
-/// A `LegBreak` contains information about the position of the leg that was broken and the order
-/// in which it was broken relative to other leg breaks in the same phase.
+/// A `LegBreak` contains information about the time it took to break the leg, the position of the
+/// leg that was broken, and the order in which it was broken relative to other leg breaks in the
+/// same phase.
 #[derive(Debug)]
 pub struct LegBreak {
+    /// The time it took to break the leg.
+    pub leg_break_time: f64,
+
     /// The position of the leg that was broken.
     pub leg_position: LegPosition,
 
@@ -17,20 +24,62 @@ pub struct LegBreak {
 }
 
 impl LegBreak {
-    /// Creates a new `LegBreak` instance with the specified `leg_position` and `leg_order`.
+    /// Creates a new `LegBreak` instance with the specified `leg_break_time`, `leg_position`, and
+    /// `leg_order`.
     ///
     /// # Arguments
     ///
+    /// * `leg_break_time` - The time it took to break the leg.
     /// * `leg_position` - The position of the leg that was broken.
     /// * `leg_order` - The order in which the leg was broken.
     ///
     /// # Returns
     ///
-    /// A new `LegBreak` instance with the provided `leg_position` and `leg_order`.
-    #[must_use] pub const fn new(leg_position: LegPosition, leg_order: i32) -> Self {
+    /// A new `LegBreak` instance with the provided `leg_break_time`, `leg_position`, and `leg_order`.
+    #[must_use] pub const fn new(leg_break_time: f64, leg_position: LegPosition, leg_order: i32) -> Self {
         Self {
+            leg_break_time,
             leg_position,
             leg_order,
         }
     }
+
+    /// Creates a new `LegBreak` instance, checking that `leg_break_time` is a valid duration and
+    /// that `leg_order` is positive.
+    ///
+    /// # Arguments
+    ///
+    /// * `leg_break_time` - The time it took to break the leg.
+    /// * `leg_position` - The position of the leg that was broken.
+    /// * `leg_order` - The order in which the leg was broken, starting at 1.
+    ///
+    /// # Returns
+    ///
+    /// A new `LegBreak` instance with the provided `leg_break_time`, `leg_position`, and `leg_order`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ModelError::InvalidDuration` if `leg_break_time` is negative, infinite, or not a
+    /// number, or `ModelError::InvalidLegOrder` if `leg_order` is zero or negative.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib_profit_taker_core::{LegBreak, LegPosition};
+    ///
+    /// assert!(LegBreak::try_new(2.0, LegPosition::FrontLeft, 1).is_ok());
+    /// assert!(LegBreak::try_new(2.0, LegPosition::FrontLeft, 0).is_err());
+    /// ```
+    pub fn try_new(
+        leg_break_time: f64,
+        leg_position: LegPosition,
+        leg_order: i32,
+    ) -> Result<Self, ModelError> {
+        check_duration("leg break", leg_break_time)?;
+        if leg_order < 1 {
+            return Err(ModelError::InvalidLegOrder(leg_order));
+        }
+
+        Ok(Self::new(leg_break_time, leg_position, leg_order))
+    }
 }
@@ -1,6 +1,11 @@
 //! This module defines the `LegPosition` enum, which represents the position of a leg on a profit-taker.
+//!
 //! The enum provides a way to categorize legs into four positions: front left, front right, back left, and back right.
 
+use std::str::FromStr;
+
+use crate::models::ModelError;
+
 /// Represents the position of a leg on a profit-taker.
 ///
 /// The `LegPosition` enum is used to categorize legs into four distinct positions:
@@ -10,19 +15,22 @@
 /// - `BackRight`
 ///
 /// This is useful for tracking leg-specific events, such as leg breaks, in a structured way.
-#[derive(Debug)]
+///
+/// Each variant also has a fixed integer ID, which is how it is stored in the database, so the IDs
+/// of existing variants must never change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LegPosition {
     /// The front left leg of the profit-taker.
-    FrontLeft,
+    FrontLeft = 1,
 
     /// The front right leg of the profit-taker.
-    FrontRight,
+    FrontRight = 2,
 
     /// The back left leg of the profit-taker.
-    BackLeft,
+    BackLeft = 3,
 
     /// The back right leg of the profit-taker.
-    BackRight,
+    BackRight = 4,
 }
 
 impl LegPosition {
@@ -35,7 +43,7 @@ impl LegPosition {
     /// # Examples
     ///
     /// ```
-    /// use models::leg_position::LegPosition;
+    /// use lib_profit_taker_core::LegPosition;
     ///
     /// let position = LegPosition::FrontLeft;
     /// assert_eq!(position.to_string(), "FrontLeft");
@@ -49,3 +57,69 @@ impl LegPosition {
         }
     }
 }
+
+impl FromStr for LegPosition {
+    type Err = ModelError;
+
+    /// Parses a `LegPosition` from its string representation, as given by `to_string`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ModelError::UnknownLegPosition` if `name` is not the name of a variant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib_profit_taker_core::LegPosition;
+    ///
+    /// let position: LegPosition = "BackLeft".parse().unwrap();
+    /// assert_eq!(position.to_string(), "BackLeft");
+    /// assert!("Middle".parse::<LegPosition>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(match name {
+            "FrontLeft" => Self::FrontLeft,
+            "FrontRight" => Self::FrontRight,
+            "BackLeft" => Self::BackLeft,
+            "BackRight" => Self::BackRight,
+            _ => return Err(ModelError::UnknownLegPosition(name.to_string())),
+        })
+    }
+}
+
+impl From<LegPosition> for i64 {
+    /// Converts a `LegPosition` into its integer ID, which is also its ID in the `leg_position` table
+    /// of the database.
+    fn from(value: LegPosition) -> Self {
+        value as Self
+    }
+}
+
+impl TryFrom<i64> for LegPosition {
+    type Error = ModelError;
+
+    /// Converts an integer ID, as given by `i64::from`, back into a `LegPosition`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ModelError::UnknownLegPositionId` if `id` is not the ID of a variant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib_profit_taker_core::LegPosition;
+    ///
+    /// let id = i64::from(LegPosition::FrontRight);
+    /// assert_eq!(LegPosition::try_from(id), Ok(LegPosition::FrontRight));
+    /// assert!(LegPosition::try_from(0).is_err());
+    /// ```
+    fn try_from(id: i64) -> Result<Self, Self::Error> {
+        Ok(match id {
+            1 => Self::FrontLeft,
+            2 => Self::FrontRight,
+            3 => Self::BackLeft,
+            4 => Self::BackRight,
+            _ => return Err(ModelError::UnknownLegPositionId(id)),
+        })
+    }
+}
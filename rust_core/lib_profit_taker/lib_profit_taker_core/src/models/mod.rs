@@ -13,6 +13,11 @@
 /// - `LegBreak`: Represents a leg break during a phase, including the leg's position and break order.
 /// - `StatusEffect`: Enum representing various status effects that can apply to a shield or player during a phase.
 /// - `LegPosition`: Enum representing possible positions of a leg that can be broken during the run.
+/// - `ModelError`: Enum representing the invariants a model can break while it is being built.
+///
+/// `Run` and `Phase` can also be created with `RunBuilder` and `PhaseBuilder`, and `ShieldChange` and
+/// `LegBreak` with their `try_new` constructors, which reject values that break an invariant, such
+/// as a negative duration.
 ///
 /// This module serves as a convenient entry point for working with the data models by re-exporting all the core 
 /// structures and enums to provide a clean and flat API. You can import the necessary models directly from this module 
@@ -32,12 +37,14 @@ pub mod shield_change;
 pub mod leg_break;
 pub mod status_effect;
 pub mod leg_position;
+pub mod error;
 
-pub use run::Run;
-pub use phase::Phase;
+pub use run::{Run, RunBuilder};
+pub use phase::{Phase, PhaseBuilder};
 pub use squad_member::SquadMember;
 pub use total_times::TotalTimes;
 pub use shield_change::ShieldChange;
 pub use leg_break::LegBreak;
 pub use status_effect::StatusEffect;
 pub use leg_position::LegPosition;
+pub use error::ModelError;
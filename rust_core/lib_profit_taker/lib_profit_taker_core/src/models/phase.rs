@@ -1,8 +1,13 @@
 //! This module defines the `Phase` struct, which represents a single phase within a run.
+//!
 //! A phase includes details such as the phase number, total times for various metrics (shield, leg, body, pylon),
 //! as well as a list of shield changes and leg breaks that occurred during the phase.
+//!
+//! Phases can be created directly with `Phase::new`, or with a `PhaseBuilder`, which checks that
+//! every duration of the phase is valid before handing it out.
 
-use crate::models::{ShieldChange, LegBreak};
+use crate::models::error::check_duration;
+use crate::models::{ShieldChange, LegBreak, ModelError};
 use std::vec::Vec;
 
 /// Represents a single phase within a run.
@@ -26,7 +31,8 @@ pub struct Phase {
     /// The total time spent on body kill-related activities during the phase.
     pub total_body_kill_time: f64,
 
-    /// The total time spent on pylon-related activities during the phase.
+    /// The total time spent on pylon-related activities during the phase, or zero if the phase had
+    /// no pylons, as only the first and third phases do.
     pub total_pylon_time: f64,
 
     /// A vector of shield changes that occurred during the phase.
@@ -59,4 +65,136 @@ impl Phase {
             leg_breaks: Vec::new(),
         }
     }
+
+    /// Creates a `PhaseBuilder` for a phase with the specified `phase_number`.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase_number` - The number of the phase within the run, starting at 1.
+    ///
+    /// # Returns
+    ///
+    /// A new `PhaseBuilder` whose times are all zero and which has no shield changes or leg breaks.
+    #[must_use] pub const fn builder(phase_number: i32) -> PhaseBuilder {
+        PhaseBuilder { phase: Self::new(phase_number) }
+    }
+}
+
+/// Builds a `Phase`, checking its invariants once every value has been set.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_core::{LegBreak, LegPosition, Phase, ShieldChange, StatusEffect};
+///
+/// let phase = Phase::builder(1)
+///     .total_time(20.0)
+///     .shield_time(8.0)
+///     .leg_time(10.0)
+///     .body_kill_time(2.0)
+///     .shield_change(ShieldChange::new(8.0, StatusEffect::Viral))
+///     .leg_break(LegBreak::new(2.5, LegPosition::FrontLeft, 1))
+///     .build()
+///     .unwrap();
+/// assert_eq!(phase.total_time, 20.0);
+///
+/// assert!(Phase::builder(1).total_time(-1.0).build().is_err());
+/// ```
+#[derive(Debug)]
+pub struct PhaseBuilder {
+    /// The phase being built.
+    phase: Phase,
+}
+
+impl PhaseBuilder {
+    /// Sets the total time taken to complete the phase.
+    #[must_use] pub const fn total_time(mut self, total_time: f64) -> Self {
+        self.phase.total_time = total_time;
+        self
+    }
+
+    /// Sets the total time spent on shield-related activities during the phase.
+    #[must_use] pub const fn shield_time(mut self, total_shield_time: f64) -> Self {
+        self.phase.total_shield_time = total_shield_time;
+        self
+    }
+
+    /// Sets the total time spent on leg-related activities during the phase.
+    #[must_use] pub const fn leg_time(mut self, total_leg_time: f64) -> Self {
+        self.phase.total_leg_time = total_leg_time;
+        self
+    }
+
+    /// Sets the total time spent on body kill-related activities during the phase.
+    #[must_use] pub const fn body_kill_time(mut self, total_body_kill_time: f64) -> Self {
+        self.phase.total_body_kill_time = total_body_kill_time;
+        self
+    }
+
+    /// Sets the total time spent on pylon-related activities during the phase.
+    #[must_use] pub const fn pylon_time(mut self, total_pylon_time: f64) -> Self {
+        self.phase.total_pylon_time = total_pylon_time;
+        self
+    }
+
+    /// Adds a shield change after the ones added so far.
+    #[must_use] pub fn shield_change(mut self, shield_change: ShieldChange) -> Self {
+        self.phase.shield_changes.push(shield_change);
+        self
+    }
+
+    /// Adds a leg break after the ones added so far.
+    #[must_use] pub fn leg_break(mut self, leg_break: LegBreak) -> Self {
+        self.phase.leg_breaks.push(leg_break);
+        self
+    }
+
+    /// Checks the invariants of the phase and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ModelError::InvalidPhaseNumber` if the phase number is zero or negative,
+    /// `ModelError::InvalidDuration` if any time of the phase, its shield changes, or its leg
+    /// breaks is negative, infinite, or not a number, or `ModelError::InvalidLegOrder` if a leg
+    /// break order is zero or negative.
+    pub fn build(self) -> Result<Phase, ModelError> {
+        check_phase(&self.phase)?;
+
+        Ok(self.phase)
+    }
+}
+
+/// Checks the invariants of a phase, whether or not it was created with a `PhaseBuilder`.
+///
+/// # Errors
+///
+/// Returns the same errors as `PhaseBuilder::build`.
+pub(crate) fn check_phase(phase: &Phase) -> Result<(), ModelError> {
+    let phase_number = phase.phase_number;
+    if phase_number < 1 {
+        return Err(ModelError::InvalidPhaseNumber(phase_number));
+    }
+
+    for (segment, time) in [
+        ("", phase.total_time),
+        (" shield", phase.total_shield_time),
+        (" leg", phase.total_leg_time),
+        (" body kill", phase.total_body_kill_time),
+        (" pylon", phase.total_pylon_time),
+    ] {
+        check_duration(&format!("phase {phase_number}{segment}"), time)?;
+    }
+
+    for shield_change in &phase.shield_changes {
+        check_duration(&format!("phase {phase_number} shield change"), shield_change.shield_time)?;
+    }
+
+    for leg_break in &phase.leg_breaks {
+        check_duration(&format!("phase {phase_number} leg break"), leg_break.leg_break_time)?;
+        if leg_break.leg_order < 1 {
+            return Err(ModelError::InvalidLegOrder(leg_break.leg_order));
+        }
+    }
+
+    Ok(())
 }
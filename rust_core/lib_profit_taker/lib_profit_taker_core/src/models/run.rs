@@ -2,8 +2,14 @@
 //! A run includes details such as the run ID, timestamp, run name, player name, and various flags
 //! indicating the run's status. It also contains data about the total times, phases, and squad members
 //! associated with the run.
+//!
+//! Runs can be created directly with `Run::new`, or with a `RunBuilder`, which checks that every
+//! duration of the run and its phases is valid before handing it out.
 
-use crate::models::{SquadMember, Phase, TotalTimes};
+use crate::models::error::check_duration;
+use crate::models::phase::check_phase;
+use crate::models::{SquadMember, Phase, TotalTimes, ModelError};
+use std::collections::HashSet;
 use std::vec::Vec;
 
 /// Represents a single run in the application.
@@ -12,28 +18,61 @@ use std::vec::Vec;
 /// that indicate whether the run is bugged, aborted, or a solo run. It also includes data about the
 /// total times, phases, and squad members associated with the run.
 #[derive(Debug)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "each flag is an independent property of the run, mirroring its database columns"
+)]
 pub struct Run {
     /// The unique identifier for the run. This is typically the primary key in a database.
-    pub run_id: i32,
+    pub run_id: i64,
+
+    /// The globally unique identifier for the run, which stays the same when the run is exported,
+    /// imported, or merged into another database. `None` until the run is first stored, at which
+    /// point one is generated.
+    pub run_uuid: Option<String>,
 
     /// The Unix timestamp indicating when the run was created or started.
     pub time_stamp: i64,
 
+    /// The offset from UTC, in seconds, of the local time where the run was played, such as
+    /// `3600` for UTC+1, if known. This decides which day the run counts towards, even after the
+    /// player moves to another time zone.
+    pub utc_offset: Option<i32>,
+
     /// The name of the run.
     pub run_name: String,
 
     /// The name of the player who initiated the run.
     pub player_name: String,
 
+    /// The version of Warframe the run was played on, such as `2024.12.18.14.38`, if known.
+    pub game_version: Option<String>,
+
+    /// The platform the run was played on, such as `PC`, if known.
+    pub platform: Option<String>,
+
     /// A flag indicating whether the run is bugged.
     pub is_bugged_run: bool,
 
+    /// Why the run is bugged, such as a shield phase desync, if it is bugged and the reason is
+    /// known.
+    pub bugged_reason: Option<String>,
+
     /// A flag indicating whether the run was aborted.
     pub is_aborted_run: bool,
 
+    /// Why the run was aborted, if it was aborted and the reason is known.
+    pub aborted_reason: Option<String>,
+
     /// A flag indicating whether the run is a solo run (i.e., no squad members).
     pub is_solo_run: bool,
 
+    /// A flag indicating whether the user marked the run as a favorite.
+    pub is_favorite: bool,
+
+    /// Free-form notes the user wrote about the run, such as the strat used or what went wrong.
+    pub notes: Option<String>,
+
     /// The total times associated with the run, such as total duration, split times, etc.
     pub total_times: TotalTimes,
 
@@ -56,20 +95,192 @@ impl Run {
     ///
     /// # Returns
     ///
-    /// A new `Run` instance with default values for `is_bugged_run`, `is_aborted_run`, `is_solo_run`,
-    /// `total_times`, `phases`, and `squad_members`.
-    #[must_use] pub fn new(run_id: i32, time_stamp: i64, run_name: &str, player_name: &str) -> Self {
+    /// A new `Run` instance with default values for `run_uuid`, `utc_offset`, `game_version`,
+    /// `platform`, `is_bugged_run`, `bugged_reason`, `is_aborted_run`, `aborted_reason`,
+    /// `is_solo_run`, `is_favorite`, `notes`, `total_times`, `phases`, and `squad_members`.
+    #[must_use] pub fn new(run_id: i64, time_stamp: i64, run_name: &str, player_name: &str) -> Self {
         Self {
             run_id,
+            run_uuid: None,
             time_stamp,
+            utc_offset: None,
             run_name: run_name.to_string(),
             player_name: player_name.to_string(),
+            game_version: None,
+            platform: None,
             is_bugged_run: false,
+            bugged_reason: None,
             is_aborted_run: false,
+            aborted_reason: None,
             is_solo_run: false,
+            is_favorite: false,
+            notes: None,
             total_times: TotalTimes::default(),
             phases: Vec::new(),
             squad_members: Vec::new(),
         }
     }
+
+    /// Creates a `RunBuilder` for a run with the specified `time_stamp`, `run_name`, and
+    /// `player_name`.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_stamp` - The Unix timestamp indicating when the run was created or started.
+    /// * `run_name` - The name of the run.
+    /// * `player_name` - The name of the player who initiated the run.
+    ///
+    /// # Returns
+    ///
+    /// A new `RunBuilder` with a `run_id` of 0 and the same defaults as `Run::new` for everything
+    /// else.
+    #[must_use] pub fn builder(time_stamp: i64, run_name: &str, player_name: &str) -> RunBuilder {
+        RunBuilder { run: Self::new(0, time_stamp, run_name, player_name) }
+    }
+}
+
+/// Builds a `Run`, checking its invariants once every value has been set.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_core::{Phase, Run, TotalTimes};
+///
+/// let run = Run::builder(1675271234, "Test Run", "Player1")
+///     .total_times(TotalTimes::new(60.0, 5.0, 20.0, 25.0, 5.0, 5.0))
+///     .phase(Phase::builder(1).total_time(55.0).build().unwrap())
+///     .squad_member("Player2")
+///     .build()
+///     .unwrap();
+/// assert_eq!(run.phases.len(), 1);
+/// assert!(!run.is_solo_run);
+///
+/// let duplicate_phases = Run::builder(1675271234, "Test Run", "Player1")
+///     .phase(Phase::new(1))
+///     .phase(Phase::new(1))
+///     .build();
+/// assert!(duplicate_phases.is_err());
+/// ```
+#[derive(Debug)]
+pub struct RunBuilder {
+    /// The run being built.
+    run: Run,
+}
+
+impl RunBuilder {
+    /// Sets the unique identifier for the run, for a run that is already stored.
+    #[must_use] pub const fn run_id(mut self, run_id: i64) -> Self {
+        self.run.run_id = run_id;
+        self
+    }
+
+    /// Sets the globally unique identifier for the run, instead of having one generated when it
+    /// is first stored.
+    #[must_use] pub fn run_uuid(mut self, run_uuid: &str) -> Self {
+        self.run.run_uuid = Some(run_uuid.to_string());
+        self
+    }
+
+    /// Sets the offset from UTC, in seconds, of the local time where the run was played.
+    #[must_use] pub const fn utc_offset(mut self, utc_offset: i32) -> Self {
+        self.run.utc_offset = Some(utc_offset);
+        self
+    }
+
+    /// Sets the version of Warframe the run was played on.
+    #[must_use] pub fn game_version(mut self, game_version: &str) -> Self {
+        self.run.game_version = Some(game_version.to_string());
+        self
+    }
+
+    /// Sets the platform the run was played on.
+    #[must_use] pub fn platform(mut self, platform: &str) -> Self {
+        self.run.platform = Some(platform.to_string());
+        self
+    }
+
+    /// Marks the run as bugged, with the reason if it is known.
+    #[must_use] pub fn bugged(mut self, reason: Option<&str>) -> Self {
+        self.run.is_bugged_run = true;
+        self.run.bugged_reason = reason.map(str::to_string);
+        self
+    }
+
+    /// Marks the run as aborted, with the reason if it is known.
+    #[must_use] pub fn aborted(mut self, reason: Option<&str>) -> Self {
+        self.run.is_aborted_run = true;
+        self.run.aborted_reason = reason.map(str::to_string);
+        self
+    }
+
+    /// Sets whether the run is a solo run.
+    #[must_use] pub const fn solo(mut self, is_solo_run: bool) -> Self {
+        self.run.is_solo_run = is_solo_run;
+        self
+    }
+
+    /// Sets whether the user marked the run as a favorite.
+    #[must_use] pub const fn favorite(mut self, is_favorite: bool) -> Self {
+        self.run.is_favorite = is_favorite;
+        self
+    }
+
+    /// Sets the notes the user wrote about the run.
+    #[must_use] pub fn notes(mut self, notes: &str) -> Self {
+        self.run.notes = Some(notes.to_string());
+        self
+    }
+
+    /// Sets the total times of the run.
+    #[must_use] pub const fn total_times(mut self, total_times: TotalTimes) -> Self {
+        self.run.total_times = total_times;
+        self
+    }
+
+    /// Adds a phase after the ones added so far.
+    #[must_use] pub fn phase(mut self, phase: Phase) -> Self {
+        self.run.phases.push(phase);
+        self
+    }
+
+    /// Adds a squad member after the ones added so far.
+    #[must_use] pub fn squad_member(mut self, member_name: &str) -> Self {
+        self.run.squad_members.push(SquadMember::new(member_name));
+        self
+    }
+
+    /// Checks the invariants of the run and returns it.
+    ///
+    /// Unlike the checks of the database's validation, these only reject values that cannot be
+    /// right, not runs that merely look wrong: a run with a missing phase or with phase times that
+    /// do not add up to its total time is still built.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ModelError::InvalidDuration` if any total time of the run is negative, infinite,
+    /// or not a number, `ModelError::DuplicatePhase` if two phases have the same number, or any
+    /// error of `PhaseBuilder::build` for a phase that breaks its invariants.
+    pub fn build(self) -> Result<Run, ModelError> {
+        let times = &self.run.total_times;
+        for (segment, time) in [
+            ("total", times.total_time),
+            ("flight", times.total_flight_time),
+            ("shield", times.total_shield_time),
+            ("leg", times.total_leg_time),
+            ("body", times.total_body_time),
+            ("pylon", times.total_pylon_time),
+        ] {
+            check_duration(segment, time)?;
+        }
+
+        let mut phase_numbers = HashSet::new();
+        for phase in &self.run.phases {
+            check_phase(phase)?;
+            if !phase_numbers.insert(phase.phase_number) {
+                return Err(ModelError::DuplicatePhase(phase.phase_number));
+            }
+        }
+
+        Ok(self.run)
+    }
 }
@@ -1,7 +1,10 @@
 //! This module defines the `ShieldChange` struct, which represents a change in shield status during a phase.
-//! A `ShieldChange` includes the time at which the shield change occurred and the associated status effect.
+//!
+//! A `ShieldChange` includes the time at which the shield change occurred, the associated status effect,
+//! and whether the shield was stripped with overshields.
 
-use crate::models::StatusEffect;
+use crate::models::error::check_duration;
+use crate::models::{ModelError, StatusEffect};
 
 /// Represents a change in shield status during a phase.
 ///
@@ -14,6 +17,10 @@ pub struct ShieldChange {
 
     /// The status effect associated with the shield change.
     pub status_effect: StatusEffect,
+
+    /// Whether the shield was stripped with overshields instead of being broken with damage, so
+    /// that the shield phase was skipped.
+    pub is_overshield: bool,
 }
 
 impl ShieldChange {
@@ -26,11 +33,43 @@ impl ShieldChange {
     ///
     /// # Returns
     ///
-    /// A new `ShieldChange` instance with the provided `shield_time` and `status_effect`.
+    /// A new `ShieldChange` instance with the provided `shield_time` and `status_effect`, and
+    /// `is_overshield` set to `false`.
     #[must_use] pub const fn new(shield_time: f64, status_effect: StatusEffect) -> Self {
         Self {
             shield_time,
             status_effect,
+            is_overshield: false,
         }
     }
+
+    /// Creates a new `ShieldChange` instance, checking that `shield_time` is a valid duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `shield_time` - The time at which the shield change occurred.
+    /// * `status_effect` - The status effect associated with the shield change.
+    ///
+    /// # Returns
+    ///
+    /// A new `ShieldChange` instance with the provided `shield_time` and `status_effect`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ModelError::InvalidDuration` if `shield_time` is negative, infinite, or not a
+    /// number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib_profit_taker_core::{ShieldChange, StatusEffect};
+    ///
+    /// assert!(ShieldChange::try_new(1.5, StatusEffect::Viral).is_ok());
+    /// assert!(ShieldChange::try_new(-1.5, StatusEffect::Viral).is_err());
+    /// ```
+    pub fn try_new(shield_time: f64, status_effect: StatusEffect) -> Result<Self, ModelError> {
+        check_duration("shield change", shield_time)?;
+
+        Ok(Self::new(shield_time, status_effect))
+    }
 }
@@ -25,7 +25,7 @@ impl SquadMember {
     /// # Examples
     ///
     /// ```
-    /// use models::squad_member::SquadMember;
+    /// use lib_profit_taker_core::SquadMember;
     ///
     /// let member = SquadMember::new("Alice");
     /// assert_eq!(member.member_name, "Alice");
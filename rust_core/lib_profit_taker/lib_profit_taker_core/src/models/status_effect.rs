@@ -1,50 +1,58 @@
 //! This module defines the `StatusEffect` enum, which represents various status effects that can be applied in the application.
+//!
 //! A `StatusEffect` is used to categorize different types of effects, such as damage types or environmental effects.
 
+use std::str::FromStr;
+
+use crate::models::ModelError;
+
 /// Represents a status effect that can be applied in the application.
 ///
 /// The `StatusEffect` enum is used to categorize different types of effects, such as damage types or environmental effects.
 /// Each variant represents a specific type of status effect.
-#[derive(Debug)]
+///
+/// Each variant also has a fixed integer ID, which is how it is stored in the database, so the IDs
+/// of existing variants must never change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum StatusEffect {
     /// Impact damage type.
-    Impact,
+    Impact = 1,
 
     /// Puncture damage type.
-    Puncture,
+    Puncture = 2,
 
     /// Slash damage type.
-    Slash,
+    Slash = 3,
 
     /// Heat damage type.
-    Heat,
+    Heat = 4,
 
     /// Cold damage type.
-    Cold,
+    Cold = 5,
 
     /// Electric damage type.
-    Electric,
+    Electric = 6,
 
     /// Toxin damage type.
-    Toxin,
+    Toxin = 7,
 
     /// Blast damage type.
-    Blast,
+    Blast = 8,
 
     /// Radiation damage type.
-    Radiation,
+    Radiation = 9,
 
     /// Gas damage type.
-    Gas,
+    Gas = 10,
 
     /// Magnetic damage type.
-    Magnetic,
+    Magnetic = 11,
 
     /// Viral damage type.
-    Viral,
+    Viral = 12,
 
     /// Corrosive damage type.
-    Corrosive,
+    Corrosive = 13,
 }
 
 impl StatusEffect {
@@ -57,7 +65,7 @@ impl StatusEffect {
     /// # Examples
     ///
     /// ```
-    /// use models::status_effect::StatusEffect;
+    /// use lib_profit_taker_core::StatusEffect;
     ///
     /// let effect = StatusEffect::Heat;
     /// assert_eq!(effect.to_string(), "Heat");
@@ -80,3 +88,87 @@ impl StatusEffect {
         }
     }
 }
+
+impl FromStr for StatusEffect {
+    type Err = ModelError;
+
+    /// Parses a `StatusEffect` from its string representation, as given by `to_string`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ModelError::UnknownStatusEffect` if `name` is not the name of a variant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib_profit_taker_core::StatusEffect;
+    ///
+    /// let effect: StatusEffect = "Viral".parse().unwrap();
+    /// assert_eq!(effect.to_string(), "Viral");
+    /// assert!("Void".parse::<StatusEffect>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(match name {
+            "Impact" => Self::Impact,
+            "Puncture" => Self::Puncture,
+            "Slash" => Self::Slash,
+            "Heat" => Self::Heat,
+            "Cold" => Self::Cold,
+            "Electric" => Self::Electric,
+            "Toxin" => Self::Toxin,
+            "Blast" => Self::Blast,
+            "Radiation" => Self::Radiation,
+            "Gas" => Self::Gas,
+            "Magnetic" => Self::Magnetic,
+            "Viral" => Self::Viral,
+            "Corrosive" => Self::Corrosive,
+            _ => return Err(ModelError::UnknownStatusEffect(name.to_string())),
+        })
+    }
+}
+
+impl From<StatusEffect> for i64 {
+    /// Converts a `StatusEffect` into its integer ID, which is also its ID in the `status_effects` table
+    /// of the database.
+    fn from(value: StatusEffect) -> Self {
+        value as Self
+    }
+}
+
+impl TryFrom<i64> for StatusEffect {
+    type Error = ModelError;
+
+    /// Converts an integer ID, as given by `i64::from`, back into a `StatusEffect`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ModelError::UnknownStatusEffectId` if `id` is not the ID of a variant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib_profit_taker_core::StatusEffect;
+    ///
+    /// let id = i64::from(StatusEffect::Puncture);
+    /// assert_eq!(StatusEffect::try_from(id), Ok(StatusEffect::Puncture));
+    /// assert!(StatusEffect::try_from(0).is_err());
+    /// ```
+    fn try_from(id: i64) -> Result<Self, Self::Error> {
+        Ok(match id {
+            1 => Self::Impact,
+            2 => Self::Puncture,
+            3 => Self::Slash,
+            4 => Self::Heat,
+            5 => Self::Cold,
+            6 => Self::Electric,
+            7 => Self::Toxin,
+            8 => Self::Blast,
+            9 => Self::Radiation,
+            10 => Self::Gas,
+            11 => Self::Magnetic,
+            12 => Self::Viral,
+            13 => Self::Corrosive,
+            _ => return Err(ModelError::UnknownStatusEffectId(id)),
+        })
+    }
+}
@@ -1,4 +1,5 @@
 //! This module defines the `TotalTimes` struct, which represents the total times for various parts of a run.
+//!
 //! A `TotalTimes` instance tracks the total time spent on different aspects of the run, such as the overall time, flight time, shield time, leg time, body time, and pylon time.
 
 /// Represents the total times for various parts of a run.
@@ -46,7 +47,7 @@ impl TotalTimes {
     /// # Examples
     ///
     /// ```
-    /// use models::total_times::TotalTimes;
+    /// use lib_profit_taker_core::TotalTimes;
     ///
     /// let times = TotalTimes::new(120.5, 30.0, 20.0, 15.0, 10.0, 5.0);
     /// assert_eq!(times.total_time, 120.5);
@@ -0,0 +1,2279 @@
+//! This module provides aggregate queries over the stored runs, such as personal bests.
+//!
+//! These are computed in SQL rather than by the frontend, so the UI never has to load every run
+//! just to find or summarize a handful of them.
+//!
+//! The `cached_overview` function goes one step further and reads totals that the database keeps
+//! up to date itself, so the home screen does not have to wait on any aggregate at all.
+//!
+//! The `goal_progress` function compares the recent and best times of the user with the target
+//! times set through the [`goals`](crate::goals) module.
+
+use lib_profit_taker_core::{LegPosition, Phase, Run, StatusEffect, TotalTimes};
+use chrono::{Datelike, Days, Local, Months, NaiveDate, Weekday};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+use crate::duration::Duration;
+use crate::error::{DatabaseError, Result};
+use crate::fetch::{fetch_run_by_id, fetch_runs_paged, RunFilter, SortBy, SortOrder};
+use crate::goals::{fetch_goals, Goal};
+use crate::local_time::RUN_LOCAL_TIME_SQL;
+use crate::lookup::{get_leg_position, get_status_effect};
+
+/// A category of runs whose times are comparable with each other.
+///
+/// Solo and squad runs are tracked separately, as are bugged runs, which can be much faster or
+/// slower than a normal run through no merit of the player. Balance patches can also change what
+/// times are achievable, so a category can be narrowed down to a single game version.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct RunCategory {
+    /// Whether the category contains solo runs rather than squad runs.
+    pub solo: bool,
+
+    /// Whether the category contains bugged runs rather than normal runs.
+    pub bugged: bool,
+
+    /// The version of Warframe the runs of the category were played on, or `None` to include
+    /// every version.
+    pub game_version: Option<String>,
+
+    /// The Warframe of the [loadout](crate::loadouts) the runs of the category were played with,
+    /// ignoring case, or `None` to include runs played with any Warframe or without a loadout.
+    pub warframe: Option<String>,
+}
+
+impl RunCategory {
+    /// Returns a filter matching the valid runs of this category.
+    ///
+    /// Aborted runs and runs in the trash are never valid.
+    pub(crate) fn to_filter(&self) -> RunFilter {
+        RunFilter {
+            solo: Some(self.solo),
+            bugged: Some(self.bugged),
+            aborted: Some(false),
+            game_version: self.game_version.clone(),
+            warframe: self.warframe.clone(),
+            ..RunFilter::default()
+        }
+    }
+
+    /// Returns a query selecting the IDs of the valid runs of this category, along with the values
+    /// of its parameters.
+    fn run_ids_sql(&self) -> (String, Vec<Value>) {
+        let (condition, values) = self.to_filter().to_sql();
+
+        (format!("SELECT id FROM runs WHERE {condition}"), values)
+    }
+}
+
+/// Fetches the personal best of a category, meaning its fastest valid run.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `category` - The category to find the personal best of.
+///
+/// # Returns
+/// * `Result<Option<Run>>` - The fully hydrated personal best run, including its phase
+///   breakdown, or `None` if the category has no valid runs.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn fetch_pb(conn: &Connection, category: &RunCategory) -> Result<Option<Run>> {
+    let runs = fetch_runs_paged(
+        conn,
+        0,
+        1,
+        SortBy::Time(SortOrder::Ascending),
+        &category.to_filter(),
+    )?;
+
+    Ok(runs.into_iter().next())
+}
+
+/// A run that beat the personal best of its category at the time it was played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PbImprovement {
+    /// The ID of the run.
+    pub run_id: i64,
+
+    /// The Unix timestamp of when the run was started.
+    pub time_stamp: i64,
+
+    /// The total time of the run, which was the personal best from then on until the next
+    /// improvement.
+    pub time: Duration,
+}
+
+/// Lists every time the personal best of a category improved, for drawing the personal best over
+/// time.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `category` - The category to trace the personal best of.
+///
+/// # Returns
+/// * `Result<Vec<PbImprovement>>` - The runs that set a new personal best, oldest first, starting
+///   with the first valid run of the category. The last one is the current personal best.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn pb_history(conn: &Connection, category: &RunCategory) -> Result<Vec<PbImprovement>> {
+    let (condition, values) = category.to_filter().to_sql();
+
+    conn.prepare_cached(&format!(
+        "SELECT id, time_stamp, total_time FROM (
+            SELECT id, time_stamp, total_time, MIN(total_time) OVER (
+                ORDER BY time_stamp, id ROWS BETWEEN UNBOUNDED PRECEDING AND 1 PRECEDING
+            ) AS previous_best
+            FROM runs WHERE {condition}
+        )
+        WHERE previous_best IS NULL OR total_time < previous_best
+        ORDER BY time_stamp, id"
+    ))?
+    .query_map(params_from_iter(values), |row| {
+        Ok(PbImprovement {
+            run_id: row.get(0)?,
+            time_stamp: row.get(1)?,
+            time: row.get(2)?,
+        })
+    })?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
+
+/// The best time ever recorded for each segment of a run, combined into an ideal run.
+///
+/// This is what speedrunners call the "sum of best": no single run has to have achieved it, but
+/// it shows how fast a run could be if every segment went as well as it ever has.
+#[derive(Debug)]
+pub struct SumOfBest {
+    /// The sum of the best flight time and the best time of each phase.
+    pub total_time: Duration,
+
+    /// The best flight time.
+    pub flight_time: Duration,
+
+    /// The best times of each phase, ordered by phase number.
+    pub phases: Vec<BestPhase>,
+}
+
+/// The best times ever recorded for a single phase.
+///
+/// Each field holds its own best, so the times may come from different runs.
+#[derive(Debug)]
+pub struct BestPhase {
+    /// The number of the phase within the run.
+    pub phase_number: i32,
+
+    /// The best total time of the phase.
+    pub phase_time: Duration,
+
+    /// The best time spent on shields during the phase.
+    pub shield_time: Duration,
+
+    /// The best time spent on legs during the phase.
+    pub leg_time: Duration,
+
+    /// The best time spent on the body kill during the phase.
+    pub body_kill_time: Duration,
+
+    /// The best time spent on pylons during the phase, or zero if the phase never had pylons.
+    pub pylon_time: Duration,
+
+    /// The best time of the first, second, third, etc. shield change of the phase, each with the
+    /// status effect it was achieved with.
+    pub shield_changes: Vec<BestShieldChange>,
+
+    /// The best time of the first, second, third, and fourth leg break of the phase, each with the
+    /// leg position it was achieved on.
+    pub leg_breaks: Vec<BestLegBreak>,
+}
+
+/// The best time ever recorded for a shield change at a given point of a phase.
+#[derive(Debug)]
+pub struct BestShieldChange {
+    /// The best time the shield change took.
+    pub shield_time: Duration,
+
+    /// The status effect of the shield in the run that achieved the best time.
+    pub status_effect: StatusEffect,
+}
+
+/// The best time ever recorded for a leg break at a given point of a phase.
+#[derive(Debug)]
+pub struct BestLegBreak {
+    /// The best time the leg break took.
+    pub leg_break_time: Duration,
+
+    /// The leg broken in the run that achieved the best time.
+    pub leg_position: LegPosition,
+
+    /// Whether this is the first, second, third, or fourth leg broken during the phase.
+    pub leg_order: i32,
+}
+
+/// Computes the sum of best of a category from every valid run in it.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `category` - The category to compute the sum of best of.
+///
+/// # Returns
+/// * `Result<Option<SumOfBest>>` - The best time of every segment, or `None` if the category has
+///   no valid runs.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or a row references an unknown status effect or leg
+/// position.
+pub fn sum_of_best(conn: &Connection, category: &RunCategory) -> Result<Option<SumOfBest>> {
+    let (run_ids, values) = category.run_ids_sql();
+
+    let flight_time: Option<Duration> = conn
+        .prepare_cached(&format!(
+            "SELECT MIN(total_flight_time) FROM runs WHERE id IN ({run_ids})"
+        ))?
+        .query_row(params_from_iter(&values), |row| row.get(0))?;
+    let Some(flight_time) = flight_time else {
+        return Ok(None);
+    };
+
+    let mut phases = conn
+        .prepare_cached(&format!(
+            "SELECT phase_number, MIN(phase_time), MIN(shield_time), MIN(leg_time),
+                MIN(body_kill_time), MIN(NULLIF(pylon_time, 0))
+            FROM phases WHERE run_id IN ({run_ids})
+            GROUP BY phase_number ORDER BY phase_number"
+        ))?
+        .query_map(params_from_iter(&values), |row| {
+            Ok(BestPhase {
+                phase_number: row.get(0)?,
+                phase_time: row.get(1)?,
+                shield_time: row.get::<_, Option<Duration>>(2)?.unwrap_or_default(),
+                leg_time: row.get(3)?,
+                body_kill_time: row.get(4)?,
+                pylon_time: row.get::<_, Option<Duration>>(5)?.unwrap_or_default(),
+                shield_changes: Vec::new(),
+                leg_breaks: Vec::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // SQLite takes bare columns from the row holding the minimum, which is used here to report
+    // which status effect or leg position each best time was achieved with
+    let mut shield_changes = conn.prepare_cached(&format!(
+        "SELECT phase_number, MIN(shield_time), status_effect_id
+        FROM (
+            SELECT phase_number, shield_time, status_effect_id,
+                ROW_NUMBER() OVER (PARTITION BY run_id, phase_number ORDER BY id) AS slot
+            FROM shield_changes WHERE run_id IN ({run_ids})
+        )
+        GROUP BY phase_number, slot ORDER BY phase_number, slot"
+    ))?;
+    let shield_changes = shield_changes.query_map(params_from_iter(&values), |row| {
+        Ok((
+            row.get(0)?,
+            BestShieldChange {
+                shield_time: row.get(1)?,
+                status_effect: get_status_effect(row, 2)?,
+            },
+        ))
+    })?;
+    for shield_change in shield_changes {
+        let (phase_number, shield_change) = shield_change?;
+        if let Some(phase) = find_phase(&mut phases, phase_number) {
+            phase.shield_changes.push(shield_change);
+        }
+    }
+
+    let mut leg_breaks = conn.prepare_cached(&format!(
+        "SELECT phase_number, MIN(break_time), leg_position_id, break_order
+        FROM leg_breaks WHERE run_id IN ({run_ids})
+        GROUP BY phase_number, break_order ORDER BY phase_number, break_order"
+    ))?;
+    let leg_breaks = leg_breaks.query_map(params_from_iter(&values), |row| {
+        Ok((
+            row.get(0)?,
+            BestLegBreak {
+                leg_break_time: row.get(1)?,
+                leg_position: get_leg_position(row, 2)?,
+                leg_order: row.get(3)?,
+            },
+        ))
+    })?;
+    for leg_break in leg_breaks {
+        let (phase_number, leg_break) = leg_break?;
+        if let Some(phase) = find_phase(&mut phases, phase_number) {
+            phase.leg_breaks.push(leg_break);
+        }
+    }
+
+    let total_time = flight_time + phases.iter().map(|phase| phase.phase_time).sum();
+
+    Ok(Some(SumOfBest {
+        total_time,
+        flight_time,
+        phases,
+    }))
+}
+
+/// Where a run places among the other valid runs of its category.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunRank {
+    /// The category the run was ranked in, across every game version.
+    pub category: RunCategory,
+
+    /// The place of the run in its category, starting from 1 for the fastest. Runs with the same
+    /// total time share the same place.
+    pub rank: usize,
+
+    /// The number of valid runs in the category, including the ranked run.
+    pub run_count: usize,
+
+    /// The rank as a percentage of the number of runs in the category, such as `5.0` for a run in
+    /// the top 5%.
+    pub top_percent: f64,
+}
+
+/// Ranks a run by total time among the valid runs of its category, for end-of-run screens such
+/// as "your 3rd best solo run, top 5%".
+///
+/// The category of a run is given by whether it is solo and whether it is bugged. Aborted runs and
+/// runs in the trash are not valid, so they have no rank.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to rank.
+///
+/// # Returns
+/// * `Result<Option<RunRank>>` - The rank of the run, or `None` if it is aborted or in the trash.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// a query fails.
+pub fn rank_of_run(conn: &Connection, run_id: i64) -> Result<Option<RunRank>> {
+    let (solo, bugged, valid, total_time): (bool, bool, bool, Duration) = conn
+        .prepare_cached(
+            "SELECT solo_run, bugged_run,
+                deleted_at IS NULL AND status != 'in_progress' AND NOT aborted_run, total_time
+            FROM runs WHERE id = ?1",
+        )?
+        .query_row([run_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .optional()?
+        .ok_or(DatabaseError::RunNotFound(run_id))?;
+    if !valid {
+        return Ok(None);
+    }
+
+    let category = RunCategory {
+        solo,
+        bugged,
+        game_version: None,
+        warframe: None,
+    };
+    let (condition, mut values) = category.to_filter().to_sql();
+    values.insert(0, Value::Integer(total_time.as_millis()));
+
+    let (run_count, faster): (usize, usize) = conn
+        .prepare_cached(&format!(
+            "SELECT COUNT(*), COUNT(*) FILTER (WHERE total_time < ?) FROM runs WHERE {condition}"
+        ))?
+        .query_row(params_from_iter(values), |row| Ok((row.get(0)?, row.get(1)?)))?;
+    let rank = faster + 1;
+
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "there will never be anywhere near 2^52 runs"
+    )]
+    let top_percent = rank as f64 / run_count as f64 * 100.0;
+
+    Ok(Some(RunRank {
+        category,
+        rank,
+        run_count,
+        top_percent,
+    }))
+}
+
+/// A place on the leaderboard of a category, as listed by `top_runs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    /// The place of the run in its category, starting from 1 for the fastest. Runs with the same
+    /// total time share the same place.
+    pub rank: usize,
+
+    /// The ID of the run.
+    pub run_id: i64,
+
+    /// The name of the run.
+    pub run_name: String,
+
+    /// The Unix timestamp of when the run was started.
+    pub time_stamp: i64,
+
+    /// The name of the player who recorded the run.
+    pub player_name: String,
+
+    /// The total time of the run.
+    pub total_time: Duration,
+
+    /// How much slower the run was than the fastest run of the category.
+    pub behind_first: Duration,
+}
+
+/// Lists the fastest valid runs of a category, for a leaderboard of the user's own history.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `n` - The maximum number of runs to list, such as 3 for the first, second, and third best.
+/// * `category` - The category to list the fastest runs of.
+///
+/// # Returns
+/// * `Result<Vec<LeaderboardEntry>>` - The fastest runs, fastest first. Runs with the same total
+///   time are listed oldest first.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn top_runs(
+    conn: &Connection,
+    n: u32,
+    category: &RunCategory,
+) -> Result<Vec<LeaderboardEntry>> {
+    let (condition, mut values) = category.to_filter().to_sql();
+    values.push(Value::Integer(n.into()));
+
+    conn.prepare_cached(&format!(
+        "SELECT RANK() OVER (ORDER BY total_time), id, run_name, time_stamp, player_name,
+            total_time, total_time - MIN(total_time) OVER ()
+        FROM runs WHERE {condition}
+        ORDER BY total_time, time_stamp, id LIMIT ?"
+    ))?
+    .query_map(params_from_iter(values), |row| {
+        Ok(LeaderboardEntry {
+            rank: row.get(0)?,
+            run_id: row.get(1)?,
+            run_name: row.get(2)?,
+            time_stamp: row.get(3)?,
+            player_name: row.get(4)?,
+            total_time: row.get(5)?,
+            behind_first: row.get(6)?,
+        })
+    })?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
+
+/// Summary statistics of a set of times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeStats {
+    /// The number of times the statistics were computed from.
+    pub count: usize,
+
+    /// The arithmetic mean of the times.
+    pub mean: Duration,
+
+    /// The median of the times. For an even number of times, this is the mean of the two middle
+    /// times.
+    pub median: Duration,
+
+    /// The population standard deviation of the times.
+    pub std_dev: Duration,
+}
+
+impl TimeStats {
+    /// Computes the statistics of the given times, or returns `None` if there are none.
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "there will never be anywhere near 2^52 runs"
+    )]
+    fn from_times(mut times: Vec<Duration>) -> Option<Self> {
+        if times.is_empty() {
+            return None;
+        }
+
+        times.sort_unstable();
+        let millis: Vec<f64> = times.iter().map(|time| time.as_millis() as f64).collect();
+
+        let count = millis.len();
+        let mean = millis.iter().sum::<f64>() / count as f64;
+        let median = if count.is_multiple_of(2) {
+            f64::midpoint(millis[count / 2 - 1], millis[count / 2])
+        } else {
+            millis[count / 2]
+        };
+        let variance = millis.iter().map(|time| (time - mean).powi(2)).sum::<f64>() / count as f64;
+
+        Some(Self {
+            count,
+            mean: Duration::from_millis_f64(mean),
+            median: Duration::from_millis_f64(median),
+            std_dev: Duration::from_millis_f64(variance.sqrt()),
+        })
+    }
+}
+
+/// Summary statistics of the times of a set of runs.
+#[derive(Debug)]
+pub struct AverageTimes {
+    /// The statistics of the total times of the runs.
+    pub total_time: TimeStats,
+
+    /// The statistics of the flight times of the runs.
+    pub flight_time: TimeStats,
+
+    /// The statistics of the times of each phase, ordered by phase number.
+    pub phases: Vec<PhaseTimeStats>,
+}
+
+/// Summary statistics of the times of a single phase.
+#[derive(Debug)]
+pub struct PhaseTimeStats {
+    /// The number of the phase within the run.
+    pub phase_number: i32,
+
+    /// The statistics of the times of this phase.
+    pub phase_time: TimeStats,
+
+    /// The statistics of the time spent on pylons during this phase, over the runs in which the
+    /// phase had pylons, or `None` if it never did. Pylon spawns are the largest source of luck in
+    /// a run, so this separates it from the rest of the phase.
+    pub pylon_time: Option<TimeStats>,
+}
+
+/// Computes the mean, median, and standard deviation of the total time, flight time, and each
+/// phase time and pylon time of the most recent valid runs.
+///
+/// Valid runs are those that are neither bugged, aborted, nor in the trash, unless
+/// `include_flagged` is set. Solo and squad runs are both included.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `last_n` - The number of most recent valid runs to include, or `None` to include all of them.
+/// * `include_flagged` - Whether to also include bugged and aborted runs.
+///
+/// # Returns
+/// * `Result<Option<AverageTimes>>` - The statistics of the included runs, or `None` if there are
+///   no valid runs.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn average_times(
+    conn: &Connection,
+    last_n: Option<u32>,
+    include_flagged: bool,
+) -> Result<Option<AverageTimes>> {
+    let flag = Some(false).filter(|_| !include_flagged);
+    let filter = RunFilter {
+        bugged: flag,
+        aborted: flag,
+        ..RunFilter::default()
+    };
+
+    average_times_matching(conn, &filter, last_n)
+}
+
+/// Computes the statistics of `average_times` over the most recent runs matching a filter.
+fn average_times_matching(
+    conn: &Connection,
+    filter: &RunFilter,
+    last_n: Option<u32>,
+) -> Result<Option<AverageTimes>> {
+    let (condition, mut values) = filter.to_sql();
+    // A negative limit means no limit to SQLite
+    values.push(Value::Integer(last_n.map_or(-1, i64::from)));
+    let recent_runs = format!(
+        "SELECT id, total_time, total_flight_time FROM runs WHERE {condition}
+        ORDER BY time_stamp DESC, id DESC LIMIT ?"
+    );
+
+    let (total_times, flight_times): (Vec<Duration>, Vec<Duration>) = conn
+        .prepare_cached(&recent_runs)?
+        .query_map(params_from_iter(&values), |row| {
+            Ok((row.get(1)?, row.get(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<(Duration, Duration)>>>()?
+        .into_iter()
+        .unzip();
+    let (Some(total_time), Some(flight_time)) = (
+        TimeStats::from_times(total_times),
+        TimeStats::from_times(flight_times),
+    ) else {
+        return Ok(None);
+    };
+
+    let mut phase_times: Vec<(i32, Vec<Duration>, Vec<Duration>)> = Vec::new();
+    let mut rows = conn.prepare_cached(&format!(
+        "SELECT phase_number, phase_time, pylon_time FROM phases
+        WHERE run_id IN (SELECT id FROM ({recent_runs}))
+        ORDER BY phase_number"
+    ))?;
+    for row in rows.query_map(params_from_iter(&values), |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get::<_, Option<Duration>>(2)?))
+    })? {
+        let (phase_number, phase_time, pylon_time) = row?;
+        if !matches!(phase_times.last(), Some((last, ..)) if *last == phase_number) {
+            phase_times.push((phase_number, Vec::new(), Vec::new()));
+        }
+        if let Some((_, times, pylon_times)) = phase_times.last_mut() {
+            times.push(phase_time);
+            // Phases without pylons store a pylon time of zero
+            pylon_times.extend(pylon_time.filter(|&time| time > Duration::ZERO));
+        }
+    }
+
+    let phases = phase_times
+        .into_iter()
+        .filter_map(|(phase_number, times, pylon_times)| {
+            Some(PhaseTimeStats {
+                phase_number,
+                phase_time: TimeStats::from_times(times)?,
+                pylon_time: TimeStats::from_times(pylon_times),
+            })
+        })
+        .collect();
+
+    Ok(Some(AverageTimes {
+        total_time,
+        flight_time,
+        phases,
+    }))
+}
+
+/// The number of runs whose total time falls in a range, as one bar of a histogram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeBucket {
+    /// The start of the range, inclusive.
+    pub start: Duration,
+
+    /// The end of the range, exclusive.
+    pub end: Duration,
+
+    /// The number of runs whose total time falls in the range.
+    pub count: usize,
+}
+
+/// Counts how many runs fall into each range of total times, so that the frontend can draw a
+/// histogram without loading every run.
+///
+/// The ranges all have the same width and start at multiples of it. Every range between the
+/// fastest and the slowest run is included, even if no run falls into it, so that the histogram
+/// has no gaps.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `bucket_size` - The width of each range.
+/// * `filter` - The runs to count. [`RunFilter::default`] counts every run that is not in the
+///   trash, including bugged and aborted runs.
+///
+/// # Returns
+/// * `Result<Vec<TimeBucket>>` - The ranges, fastest first, or nothing if no run matches `filter`.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if `bucket_size` is not positive, or another error if
+/// the query fails.
+pub fn time_distribution(
+    conn: &Connection,
+    bucket_size: Duration,
+    filter: &RunFilter,
+) -> Result<Vec<TimeBucket>> {
+    if bucket_size <= Duration::ZERO {
+        return Err(DatabaseError::InvalidData(format!(
+            "the bucket size must be positive, not {bucket_size}"
+        )));
+    }
+
+    let (condition, mut values) = filter.to_sql();
+    values.insert(0, Value::Integer(bucket_size.as_millis()));
+    let counts: Vec<(i64, usize)> = conn
+        .prepare_cached(&format!(
+            // Times are never negative, so dividing integers rounds down
+            "SELECT total_time / ? AS bucket, COUNT(*) FROM runs
+            WHERE {condition} GROUP BY bucket ORDER BY bucket"
+        ))?
+        .query_map(params_from_iter(values), |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let (Some(&(first, _)), Some(&(last, _))) = (counts.first(), counts.last()) else {
+        return Ok(Vec::new());
+    };
+
+    let mut counts = counts.into_iter().peekable();
+    let bucket_start = |bucket: i64| {
+        Duration::from_millis(bucket.saturating_mul(bucket_size.as_millis()))
+    };
+    let buckets = (first..=last)
+        .map(|bucket| TimeBucket {
+            start: bucket_start(bucket),
+            end: bucket_start(bucket + 1),
+            count: counts
+                .next_if(|&(counted, _)| counted == bucket)
+                .map_or(0, |(_, count)| count),
+        })
+        .collect();
+
+    Ok(buckets)
+}
+
+/// A group of runs played in one sitting, such as a single evening.
+///
+/// A session is identified by the ID of its first run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    /// The ID of the first run of the session.
+    pub session_id: i64,
+
+    /// The Unix timestamp of the start of the first run of the session.
+    pub first_time_stamp: i64,
+
+    /// The Unix timestamp of the start of the last run of the session.
+    pub last_time_stamp: i64,
+
+    /// The number of runs in the session, including bugged and aborted runs.
+    pub run_count: usize,
+}
+
+impl Session {
+    /// Returns a filter matching the runs of this session.
+    #[must_use]
+    pub fn to_filter(&self) -> RunFilter {
+        RunFilter {
+            since: Some(self.first_time_stamp),
+            until: Some(self.last_time_stamp),
+            ..RunFilter::default()
+        }
+    }
+}
+
+/// The statistics of a single session.
+#[derive(Debug)]
+pub struct SessionSummary {
+    /// The session the statistics are of.
+    pub session: Session,
+
+    /// The fastest valid run of the session, or `None` if it has no valid runs.
+    pub best_run: Option<RunTime>,
+
+    /// The statistics of the times of the valid runs of the session, or `None` if it has no valid
+    /// runs.
+    pub average_times: Option<AverageTimes>,
+}
+
+/// Groups the complete runs that are not in the trash into sessions.
+///
+/// A run starts a new session if it started at least `gap` after the previous run ended.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `gap` - The shortest break between two runs that separates them into different sessions.
+///
+/// # Returns
+/// * `Result<Vec<Session>>` - Every session, newest first.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn fetch_sessions(conn: &Connection, gap: std::time::Duration) -> Result<Vec<Session>> {
+    conn.prepare_cached(&format!(
+        "{SESSIONS_SQL}
+        SELECT session_id, MIN(time_stamp), MAX(time_stamp), COUNT(*) FROM sessions
+        GROUP BY session_id ORDER BY MIN(time_stamp) DESC, session_id DESC"
+    ))?
+    .query_map([gap.as_secs_f64()], session_from_row)?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
+
+/// Computes the statistics of the session containing the given run.
+///
+/// Sessions are split the same way as by `fetch_sessions`. Only valid runs, meaning those that
+/// are neither bugged nor aborted, are included in the statistics.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `session_id` - The ID of the session, or of any other run in it.
+/// * `gap` - The shortest break between two runs that separates them into different sessions.
+///
+/// # Returns
+/// * `Result<SessionSummary>` - The statistics of the session.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists outside of the trash,
+/// or another error if a query fails.
+pub fn session_summary(
+    conn: &Connection,
+    session_id: i64,
+    gap: std::time::Duration,
+) -> Result<SessionSummary> {
+    let session = conn
+        .prepare_cached(&format!(
+            "{SESSIONS_SQL}
+            SELECT session_id, MIN(time_stamp), MAX(time_stamp), COUNT(*) FROM sessions
+            WHERE session_id = (SELECT session_id FROM sessions WHERE id = ?2)
+            GROUP BY session_id"
+        ))?
+        .query_row(params![gap.as_secs_f64(), session_id], session_from_row)
+        .optional()?
+        .ok_or(DatabaseError::RunNotFound(session_id))?;
+
+    let filter = RunFilter {
+        bugged: Some(false),
+        aborted: Some(false),
+        ..session.to_filter()
+    };
+    let (condition, values) = filter.to_sql();
+    let best_run = conn
+        .prepare_cached(&format!(
+            "SELECT id, total_time FROM runs WHERE {condition} ORDER BY total_time, id LIMIT 1"
+        ))?
+        .query_row(params_from_iter(&values), |row| {
+            Ok(RunTime {
+                run_id: row.get(0)?,
+                time: row.get(1)?,
+            })
+        })
+        .optional()?;
+
+    Ok(SessionSummary {
+        session,
+        best_run,
+        average_times: average_times_matching(conn, &filter, None)?,
+    })
+}
+
+/// A span of the calendar that `period_summary` summarizes the runs of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    /// A week, from Monday to Sunday.
+    Week,
+
+    /// A calendar month.
+    Month,
+}
+
+impl Period {
+    /// Returns the first day of the period containing the given day, and of the period before it.
+    fn starts(self, day: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        match self {
+            Self::Week => {
+                let start =
+                    day.checked_sub_days(Days::new(day.weekday().num_days_from_monday().into()))?;
+                Some((start, start.checked_sub_days(Days::new(7))?))
+            }
+            Self::Month => {
+                let start = day.with_day(1)?;
+                Some((start, start.checked_sub_months(Months::new(1))?))
+            }
+        }
+    }
+
+    /// Returns the first day of the period after the one starting on the given day.
+    const fn next_start(self, start: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Self::Week => start.checked_add_days(Days::new(7)),
+            Self::Month => start.checked_add_months(Months::new(1)),
+        }
+    }
+}
+
+/// The statistics of the runs of a single period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeriodStats {
+    /// The first day of the period.
+    pub start: NaiveDate,
+
+    /// The first day after the period.
+    pub end: NaiveDate,
+
+    /// The number of runs started during the period, including bugged and aborted runs.
+    pub run_count: usize,
+
+    /// The fastest total time of the valid runs of the period, or `None` if it has no valid runs.
+    pub best_time: Option<Duration>,
+
+    /// The average total time of the valid runs of the period, or `None` if it has no valid runs.
+    pub average_time: Option<Duration>,
+
+    /// The sum of the total times of every run of the period, including bugged and aborted runs.
+    pub time_spent: Duration,
+}
+
+/// The statistics of the current period, compared with those of the period before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeriodSummary {
+    /// The statistics of the current period, which is still in progress.
+    pub current: PeriodStats,
+
+    /// The statistics of the period before the current one.
+    pub previous: PeriodStats,
+}
+
+impl PeriodSummary {
+    /// Returns how much faster the best time of the current period is than that of the previous
+    /// one, or `None` if either has no valid runs.
+    #[must_use]
+    pub fn best_improvement(&self) -> Option<Duration> {
+        Some(self.previous.best_time? - self.current.best_time?)
+    }
+
+    /// Returns how much faster the average time of the current period is than that of the
+    /// previous one, or `None` if either has no valid runs.
+    #[must_use]
+    pub fn average_improvement(&self) -> Option<Duration> {
+        Some(self.previous.average_time? - self.current.average_time?)
+    }
+}
+
+/// Summarizes the runs of the current week or month, for a "your week in Profit-Taker" screen.
+///
+/// The current period is the one containing today in the local time zone. Runs count towards the
+/// period containing the day they were played on, in the local time where they were played, so a
+/// week starts at midnight on Monday wherever the user was. Valid runs are those that are neither
+/// bugged nor aborted, of any category. Runs in the trash are never included.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `period` - Whether to summarize the current week or the current month.
+///
+/// # Returns
+/// * `Result<PeriodSummary>` - The statistics of the current period and of the one before it.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the current date is too close to the limits of the
+/// calendar to compute the periods, or another error if a query fails.
+pub fn period_summary(conn: &Connection, period: Period) -> Result<PeriodSummary> {
+    let out_of_range = || DatabaseError::InvalidData("the current period is out of range".into());
+    let (current_start, previous_start) = period
+        .starts(Local::now().date_naive())
+        .ok_or_else(out_of_range)?;
+    let current_end = period.next_start(current_start).ok_or_else(out_of_range)?;
+
+    Ok(PeriodSummary {
+        current: period_stats(conn, current_start, current_end)?,
+        previous: period_stats(conn, previous_start, current_start)?,
+    })
+}
+
+/// Computes the statistics of the runs played from the day `start` up to but excluding the day
+/// `end`.
+fn period_stats(conn: &Connection, start: NaiveDate, end: NaiveDate) -> Result<PeriodStats> {
+    let filter = RunFilter {
+        from_date: Some(start),
+        to_date: end.pred_opt(),
+        ..RunFilter::default()
+    };
+    let (condition, values) = filter.to_sql();
+
+    conn.prepare_cached(&format!(
+        "SELECT COUNT(*), MIN(total_time) FILTER (WHERE NOT bugged_run AND NOT aborted_run),
+            AVG(total_time) FILTER (WHERE NOT bugged_run AND NOT aborted_run),
+            COALESCE(SUM(total_time), 0)
+        FROM runs WHERE {condition}"
+    ))?
+    .query_row(params_from_iter(values), |row| {
+        Ok(PeriodStats {
+            start,
+            end,
+            run_count: row.get(0)?,
+            best_time: row.get(1)?,
+            average_time: row.get(2)?,
+            time_spent: row.get(3)?,
+        })
+    })
+    .map_err(Into::into)
+}
+
+/// The statistics of the runs started during a single hour of the day or day of the week.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSlotStats<T> {
+    /// The hour of the day, from 0 to 23, or the day of the week.
+    pub slot: T,
+
+    /// The number of valid runs started during the slot.
+    pub run_count: usize,
+
+    /// The average total time of those runs.
+    pub average_time: Duration,
+
+    /// The fastest total time of those runs.
+    pub best_time: Duration,
+}
+
+/// How the times of the runs of a category depend on when they were played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeOfDayBreakdown {
+    /// The statistics of each hour of the day during which runs were started, in order.
+    pub by_hour: Vec<TimeSlotStats<u32>>,
+
+    /// The statistics of each day of the week on which runs were started, from Monday to Sunday.
+    pub by_weekday: Vec<TimeSlotStats<Weekday>>,
+}
+
+/// Groups the valid runs of a category by the hour of the day and the day of the week they were
+/// started in, so players can see whether they actually run faster at 2 AM.
+///
+/// Hours and days are in the local time where each run was played. Slots in which no run was
+/// started are left out.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `category` - The category whose runs to group.
+///
+/// # Returns
+/// * `Result<TimeOfDayBreakdown>` - The statistics of each hour and each day of the week.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn time_of_day_breakdown(
+    conn: &Connection,
+    category: &RunCategory,
+) -> Result<TimeOfDayBreakdown> {
+    let by_hour = time_slot_stats(conn, category, "%H")?;
+    let mut by_weekday = time_slot_stats(conn, category, "%w")?
+        .into_iter()
+        .filter_map(|stats| {
+            // SQLite numbers days from 0 for Sunday, chrono from 0 for Monday
+            let weekday = Weekday::try_from(u8::try_from((stats.slot + 6) % 7).ok()?).ok()?;
+
+            Some(TimeSlotStats {
+                slot: weekday,
+                run_count: stats.run_count,
+                average_time: stats.average_time,
+                best_time: stats.best_time,
+            })
+        })
+        .collect::<Vec<_>>();
+    by_weekday.sort_by_key(|stats| stats.slot.num_days_from_monday());
+
+    Ok(TimeOfDayBreakdown {
+        by_hour,
+        by_weekday,
+    })
+}
+
+/// Computes the statistics of the valid runs of a category, grouped by a `strftime` format of
+/// their local start time that yields a number.
+fn time_slot_stats(
+    conn: &Connection,
+    category: &RunCategory,
+    format: &str,
+) -> Result<Vec<TimeSlotStats<u32>>> {
+    let (condition, mut values) = category.to_filter().to_sql();
+    values.insert(0, Value::Text(format.to_owned()));
+
+    conn.prepare_cached(&format!(
+        "SELECT CAST(strftime(?, {RUN_LOCAL_TIME_SQL}) AS INTEGER) AS slot,
+            COUNT(*), AVG(total_time), MIN(total_time)
+        FROM runs WHERE {condition}
+        GROUP BY slot ORDER BY slot"
+    ))?
+    .query_map(params_from_iter(values), |row| {
+        Ok(TimeSlotStats {
+            slot: row.get(0)?,
+            run_count: row.get(1)?,
+            average_time: row.get(2)?,
+            best_time: row.get(3)?,
+        })
+    })?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
+
+/// Streaks of days with runs and of fast runs, as computed by `streaks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Streaks {
+    /// The number of consecutive days, up to today, on which at least one run was started. A
+    /// streak that reaches yesterday still counts, since the user may run again today.
+    pub current_daily: usize,
+
+    /// The most consecutive days on which at least one run was started.
+    pub longest_daily: usize,
+
+    /// The number of most recent runs in a row that finished under the threshold.
+    pub current_under_threshold: usize,
+
+    /// The most runs in a row that finished under the threshold.
+    pub longest_under_threshold: usize,
+}
+
+/// Computes streaks of consecutive days with runs and of consecutive runs under a time, for
+/// gamified statistics.
+///
+/// Days are in the local time where each run was played, and today is in the local time zone.
+/// Every run outside the trash counts towards a daily streak, except those still in progress.
+/// Bugged runs are ignored by the streaks of fast runs, but an aborted run ends them.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `threshold` - The total time that runs must finish under to continue a streak.
+///
+/// # Returns
+/// * `Result<Streaks>` - The current and longest streaks, which are all zero if there are no
+///   runs.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn streaks(conn: &Connection, threshold: Duration) -> Result<Streaks> {
+    let (days, today): (Vec<i64>, i64) = {
+        // Julian day numbers, so consecutive days are consecutive integers
+        let days = conn
+            .prepare_cached(&format!(
+                "SELECT DISTINCT CAST(
+                    julianday({RUN_LOCAL_TIME_SQL}, 'start of day') AS INTEGER
+                ) AS day
+                FROM runs WHERE deleted_at IS NULL AND status != 'in_progress' ORDER BY day"
+            ))?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        let today = conn.query_row(
+            "SELECT CAST(julianday('now', 'localtime', 'start of day') AS INTEGER)",
+            [],
+            |row| row.get(0),
+        )?;
+
+        (days, today)
+    };
+
+    let mut streaks = Streaks::default();
+    let mut daily = 0;
+    for (i, &day) in days.iter().enumerate() {
+        daily = if i > 0 && days[i - 1] == day - 1 {
+            daily + 1
+        } else {
+            1
+        };
+        streaks.longest_daily = streaks.longest_daily.max(daily);
+    }
+    if days.last().is_some_and(|&last| last >= today - 1) {
+        streaks.current_daily = daily;
+    }
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT total_time < ?1 AND NOT aborted_run FROM runs
+        WHERE deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run
+        ORDER BY time_stamp, id",
+    )?;
+    for under in stmt.query_map([threshold], |row| row.get::<_, bool>(0))? {
+        streaks.current_under_threshold = if under? {
+            streaks.current_under_threshold + 1
+        } else {
+            0
+        };
+        streaks.longest_under_threshold = streaks
+            .longest_under_threshold
+            .max(streaks.current_under_threshold);
+    }
+
+    Ok(streaks)
+}
+
+/// The best average of a number of consecutive runs, as computed by `best_average_of`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AverageOf {
+    /// The average total time of the runs, after trimming the fastest and slowest.
+    pub average: Duration,
+
+    /// The consecutive runs the average was computed from, oldest first, including those that
+    /// were trimmed.
+    pub runs: Vec<RunTime>,
+}
+
+/// Computes the best average of `n` consecutive valid runs, such as the "Ao5" or "Ao12" of
+/// speedcubing, to measure how consistent the user is rather than how fast they can be once.
+///
+/// As in speedcubing, the fastest and slowest 5% of the runs of each average, rounded up, are
+/// left out of it, so an Ao5 or Ao12 drops its single best and worst run. Averages of fewer than
+/// five runs are plain means. Valid runs are those that are neither bugged, aborted, nor in the
+/// trash, and they are consecutive if no other valid run was started in between.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `n` - The number of consecutive runs to average.
+///
+/// # Returns
+/// * `Result<Option<AverageOf>>` - The fastest average, or `None` if there are fewer than `n`
+///   valid runs. Ties go to the oldest average.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if `n` is zero, or another error if the query fails.
+pub fn best_average_of(conn: &Connection, n: usize) -> Result<Option<AverageOf>> {
+    if n == 0 {
+        return Err(DatabaseError::InvalidData(
+            "an average must include at least one run".to_string(),
+        ));
+    }
+
+    let runs: Vec<RunTime> = conn
+        .prepare_cached(
+            "SELECT id, total_time FROM runs
+            WHERE deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run
+                AND NOT aborted_run
+            ORDER BY time_stamp, id",
+        )?
+        .query_map([], |row| {
+            Ok(RunTime {
+                run_id: row.get(0)?,
+                time: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let trimmed = if n < 5 { 0 } else { n.div_ceil(20) };
+    let mut best: Option<(f64, &[RunTime])> = None;
+    for window in runs.windows(n) {
+        let mut times: Vec<Duration> = window.iter().map(|run| run.time).collect();
+        times.sort_unstable();
+        let counted = &times[trimmed..n - trimmed];
+        // In fractions of a millisecond, so that averages that round the same are still told
+        // apart
+        #[expect(
+            clippy::cast_precision_loss,
+            reason = "there will never be anywhere near 2^52 runs, nor milliseconds in a run"
+        )]
+        let average = counted.iter().copied().sum::<Duration>().as_millis() as f64
+            / counted.len() as f64;
+
+        if best.is_none_or(|(best_average, _)| average < best_average) {
+            best = Some((average, window));
+        }
+    }
+
+    Ok(best.map(|(average, window)| AverageOf {
+        average: Duration::from_millis_f64(average),
+        runs: window.to_vec(),
+    }))
+}
+
+/// A common table expression named `sessions`, assigning every run that is neither in the trash
+/// nor still in progress to the session it belongs to, as the columns `id`, `time_stamp`, and
+/// `session_id`.
+///
+/// The shortest break between sessions, in seconds, is bound to `?1`.
+const SESSIONS_SQL: &str = "WITH
+    starts AS (
+        SELECT id, time_stamp,
+            COALESCE(
+                time_stamp - LAG(time_stamp + total_time / 1000.0) OVER runs_by_time >= ?1,
+                TRUE
+            ) AS starts_session
+        FROM runs WHERE deleted_at IS NULL AND status != 'in_progress'
+        WINDOW runs_by_time AS (ORDER BY time_stamp, id)
+    ),
+    numbered AS (
+        SELECT id, time_stamp, SUM(starts_session) OVER (ORDER BY time_stamp, id) AS session
+        FROM starts
+    ),
+    sessions AS (
+        SELECT id, time_stamp,
+            FIRST_VALUE(id) OVER (PARTITION BY session ORDER BY time_stamp, id) AS session_id
+        FROM numbered
+    )";
+
+/// Reads a session from a row of its ID, first and last time stamps, and run count.
+fn session_from_row(row: &Row) -> rusqlite::Result<Session> {
+    Ok(Session {
+        session_id: row.get(0)?,
+        first_time_stamp: row.get(1)?,
+        last_time_stamp: row.get(2)?,
+        run_count: row.get(3)?,
+    })
+}
+
+/// A time recorded in a specific run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunTime {
+    /// The ID of the run the time was recorded in.
+    pub run_id: i64,
+
+    /// The time.
+    pub time: Duration,
+}
+
+/// Statistics of the runs done with a single squad member.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SquadMemberStats {
+    /// The ID of the player the squad member was grouped into, or `None` if their name was not
+    /// linked to a player yet.
+    pub player_id: Option<i64>,
+
+    /// The name of the squad member, which is the display name of their player if they have one.
+    pub member_name: String,
+
+    /// The number of complete runs outside the trash the squad member took part in, including
+    /// bugged and aborted runs.
+    pub run_count: usize,
+
+    /// The mean total time of the valid runs with the squad member, or `None` if there are none.
+    pub average_time: Option<Duration>,
+
+    /// The fastest valid run with the squad member, or `None` if there are none.
+    pub best_run: Option<RunTime>,
+}
+
+/// Computes how often each squad member took part in a run, and how fast those runs were.
+///
+/// Squad members are grouped by [player](crate::players), so a friend recorded under several
+/// names counts as one squad member. Names not linked to a player yet, such as those stored before
+/// players existed and not linked by [`sync_players`](crate::players::sync_players), are counted
+/// on their own.
+///
+/// Times are computed from valid runs only, meaning those that are neither bugged, aborted, nor in
+/// the trash.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Vec<SquadMemberStats>>` - The statistics of every squad member, most frequent first,
+///   with ties ordered by name.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn squad_member_stats(conn: &Connection) -> Result<Vec<SquadMemberStats>> {
+    conn.prepare_cached(
+        "WITH members AS (
+            SELECT DISTINCT
+                players.id AS player_id,
+                COALESCE(players.display_name, squad_members.member_name) AS member_name,
+                runs.id AS run_id,
+                total_time,
+                NOT bugged_run AND NOT aborted_run AS valid
+            FROM squad_members
+            JOIN runs ON runs.id = squad_members.run_id
+            LEFT JOIN player_names ON player_names.member_name = squad_members.member_name
+            LEFT JOIN players ON players.id = player_names.player_id
+            WHERE deleted_at IS NULL AND status != 'in_progress'
+        )
+        SELECT
+            player_id,
+            member_name,
+            COUNT(*) AS run_count,
+            AVG(total_time) FILTER (WHERE valid),
+            (
+                SELECT other.run_id FROM members AS other
+                WHERE other.player_id IS members.player_id
+                    AND other.member_name = members.member_name
+                    AND other.valid
+                ORDER BY other.total_time, other.run_id LIMIT 1
+            ),
+            MIN(total_time) FILTER (WHERE valid)
+        FROM members
+        GROUP BY player_id, member_name
+        ORDER BY run_count DESC, member_name",
+    )?
+    .query_map([], |row| {
+        let best_run = match (row.get(4)?, row.get(5)?) {
+            (Some(run_id), Some(time)) => Some(RunTime { run_id, time }),
+            _ => None,
+        };
+
+        Ok(SquadMemberStats {
+            player_id: row.get(0)?,
+            member_name: row.get(1)?,
+            run_count: row.get(2)?,
+            average_time: row.get(3)?,
+            best_run,
+        })
+    })?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
+
+/// The fastest recording of each segment of a run, in the style of the "gold splits" of
+/// LiveSplit.
+///
+/// Unlike [`SumOfBest`], each split also records which run it was achieved in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BestSplits {
+    /// The fastest flight time.
+    pub flight: RunTime,
+
+    /// The fastest time of each phase, ordered by phase number.
+    pub phases: Vec<PhaseSplit>,
+}
+
+/// The fastest recording of a single phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseSplit {
+    /// The number of the phase within the run.
+    pub phase_number: i32,
+
+    /// The fastest time of the phase, and the run it was achieved in.
+    pub best: RunTime,
+}
+
+/// Finds the fastest flight and phase times across every valid run, and the runs they were
+/// achieved in.
+///
+/// Valid runs are those that are neither bugged, aborted, nor in the trash. Solo and squad runs
+/// are both included.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Option<BestSplits>>` - The fastest time of each segment, or `None` if there are no
+///   valid runs.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn best_splits(conn: &Connection) -> Result<Option<BestSplits>> {
+    let (run_ids, values) = valid_run_ids_sql();
+
+    let flight = conn
+        .prepare_cached(&format!(
+            "SELECT id, total_flight_time FROM runs WHERE id IN ({run_ids})
+            ORDER BY total_flight_time, id LIMIT 1"
+        ))?
+        .query_row(params_from_iter(&values), |row| {
+            Ok(RunTime {
+                run_id: row.get(0)?,
+                time: row.get(1)?,
+            })
+        })
+        .optional()?;
+    let Some(flight) = flight else {
+        return Ok(None);
+    };
+
+    // SQLite takes bare columns from the row holding the minimum, which is used here to report
+    // which run each best time was achieved in
+    let phases = conn
+        .prepare_cached(&format!(
+            "SELECT phase_number, run_id, MIN(phase_time)
+            FROM phases WHERE run_id IN ({run_ids})
+            GROUP BY phase_number ORDER BY phase_number"
+        ))?
+        .query_map(params_from_iter(&values), |row| {
+            Ok(PhaseSplit {
+                phase_number: row.get(0)?,
+                best: RunTime {
+                    run_id: row.get(1)?,
+                    time: row.get(2)?,
+                },
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(Some(BestSplits { flight, phases }))
+}
+
+/// Summary statistics of a set of shield or leg break times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakTimes {
+    /// The number of times the statistics were computed from.
+    pub count: usize,
+
+    /// The mean of the times.
+    pub average_time: Duration,
+
+    /// The fastest of the times.
+    pub best_time: Duration,
+}
+
+impl BreakTimes {
+    /// Reads the statistics from the three consecutive columns of a row starting at `idx`, in the
+    /// order `COUNT(...), AVG(...), MIN(...)`.
+    fn from_row(row: &Row, idx: usize) -> rusqlite::Result<Self> {
+        Ok(Self {
+            count: row.get(idx)?,
+            average_time: row.get(idx + 1)?,
+            best_time: row.get(idx + 2)?,
+        })
+    }
+}
+
+/// Statistics of the shield changes of a single status effect in a single phase.
+#[derive(Debug)]
+pub struct ShieldElementStats {
+    /// The number of the phase within the run.
+    pub phase_number: i32,
+
+    /// The status effect the shields were broken with.
+    pub status_effect: StatusEffect,
+
+    /// The statistics of the shield times.
+    pub times: BreakTimes,
+}
+
+/// Computes how long shields took to break with each status effect, in each phase.
+///
+/// Times are computed from valid runs only, meaning those that are neither bugged, aborted, nor in
+/// the trash.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Vec<ShieldElementStats>>` - The statistics of every status effect shields were
+///   broken with, ordered by phase number, then slowest average time first.
+///
+/// # Errors
+///
+/// Returns an error if the query fails or a row references an unknown status effect.
+pub fn shield_element_stats(conn: &Connection) -> Result<Vec<ShieldElementStats>> {
+    let (run_ids, values) = valid_run_ids_sql();
+
+    conn.prepare_cached(&format!(
+        "SELECT phase_number, status_effect_id,
+            COUNT(*), AVG(shield_time) AS average_time, MIN(shield_time)
+        FROM shield_changes WHERE run_id IN ({run_ids})
+        GROUP BY phase_number, status_effect_id
+        ORDER BY phase_number, average_time DESC, status_effect_id"
+    ))?
+    .query_map(params_from_iter(values), |row| {
+        Ok(ShieldElementStats {
+            phase_number: row.get(0)?,
+            status_effect: get_status_effect(row, 1)?,
+            times: BreakTimes::from_row(row, 2)?,
+        })
+    })?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
+
+/// How often the runs of a category stripped shields with overshields instead of breaking them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OvershieldStats {
+    /// The number of valid runs in the category.
+    pub run_count: usize,
+
+    /// The number of those runs that stripped at least one shield with overshields.
+    pub overshield_run_count: usize,
+
+    /// The number of shield changes of those runs.
+    pub shield_change_count: usize,
+
+    /// The number of those shield changes that were stripped with overshields.
+    pub overshield_change_count: usize,
+
+    /// The average total time of the runs that stripped at least one shield with overshields, or
+    /// `None` if none did.
+    pub average_time_with: Option<Duration>,
+
+    /// The average total time of the runs that broke every shield with damage, or `None` if none
+    /// did.
+    pub average_time_without: Option<Duration>,
+}
+
+impl OvershieldStats {
+    /// Returns the share of runs that stripped at least one shield with overshields, between 0
+    /// and 1.
+    #[must_use]
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "there will never be anywhere near 2^52 runs"
+    )]
+    pub fn overshield_run_share(&self) -> f64 {
+        self.overshield_run_count as f64 / self.run_count as f64
+    }
+}
+
+/// Computes how often the valid runs of a category stripped shields with overshields.
+///
+/// The average times of the runs that did and did not are reported as well, so that players can
+/// tell what the overshield strat is worth to them.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `category` - The category to compute the statistics of.
+///
+/// # Returns
+/// * `Result<Option<OvershieldStats>>` - The statistics of the category, or `None` if it has no
+///   valid runs.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn overshield_stats(
+    conn: &Connection,
+    category: &RunCategory,
+) -> Result<Option<OvershieldStats>> {
+    let (run_ids, values) = category.run_ids_sql();
+
+    let stats = conn
+        .prepare_cached(&format!(
+            "WITH run_shields AS (
+                SELECT runs.total_time, COUNT(shield_changes.run_id) AS shield_changes,
+                    IFNULL(SUM(shield_changes.is_overshield), 0) AS overshields
+                FROM runs LEFT JOIN shield_changes ON shield_changes.run_id = runs.id
+                WHERE runs.id IN ({run_ids})
+                GROUP BY runs.id
+            )
+            SELECT COUNT(*), COUNT(*) FILTER (WHERE overshields > 0),
+                IFNULL(SUM(shield_changes), 0), IFNULL(SUM(overshields), 0),
+                AVG(total_time) FILTER (WHERE overshields > 0),
+                AVG(total_time) FILTER (WHERE overshields = 0)
+            FROM run_shields"
+        ))?
+        .query_row(params_from_iter(values), |row| {
+            Ok(OvershieldStats {
+                run_count: row.get(0)?,
+                overshield_run_count: row.get(1)?,
+                shield_change_count: row.get(2)?,
+                overshield_change_count: row.get(3)?,
+                average_time_with: row.get(4)?,
+                average_time_without: row.get(5)?,
+            })
+        })?;
+
+    Ok(Some(stats).filter(|stats| stats.run_count > 0))
+}
+
+/// Statistics of the leg breaks of a single leg position.
+#[derive(Debug)]
+pub struct LegPositionStats {
+    /// The position of the broken leg.
+    pub leg_position: LegPosition,
+
+    /// The statistics of the break times across every phase.
+    pub times: BreakTimes,
+
+    /// The statistics of the break times within each phase, ordered by phase number.
+    pub phases: Vec<PhaseBreakTimes>,
+}
+
+/// Statistics of the break times of a single phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseBreakTimes {
+    /// The number of the phase within the run.
+    pub phase_number: i32,
+
+    /// The statistics of the break times in this phase.
+    pub times: BreakTimes,
+}
+
+/// Computes how long each leg took to break, overall and in each phase.
+///
+/// Times are computed from valid runs only, meaning those that are neither bugged, aborted, nor in
+/// the trash.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Vec<LegPositionStats>>` - The statistics of every leg position that was broken,
+///   ordered front left, front right, back left, back right.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or a row references an unknown leg position.
+pub fn leg_break_stats(conn: &Connection) -> Result<Vec<LegPositionStats>> {
+    let (run_ids, values) = valid_run_ids_sql();
+
+    let mut positions = conn
+        .prepare_cached(&format!(
+            "SELECT leg_position_id, COUNT(*), AVG(break_time), MIN(break_time)
+            FROM leg_breaks WHERE run_id IN ({run_ids})
+            GROUP BY leg_position_id ORDER BY leg_position_id"
+        ))?
+        .query_map(params_from_iter(&values), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                LegPositionStats {
+                    leg_position: get_leg_position(row, 0)?,
+                    times: BreakTimes::from_row(row, 1)?,
+                    phases: Vec::new(),
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut phases = conn.prepare_cached(&format!(
+        "SELECT leg_position_id, phase_number, COUNT(*), AVG(break_time), MIN(break_time)
+        FROM leg_breaks WHERE run_id IN ({run_ids})
+        GROUP BY leg_position_id, phase_number ORDER BY leg_position_id, phase_number"
+    ))?;
+    let phases = phases.query_map(params_from_iter(&values), |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            PhaseBreakTimes {
+                phase_number: row.get(1)?,
+                times: BreakTimes::from_row(row, 2)?,
+            },
+        ))
+    })?;
+    for phase in phases {
+        let (leg_position_id, phase) = phase?;
+        if let Some((_, position)) = positions.iter_mut().find(|(id, _)| *id == leg_position_id) {
+            position.phases.push(phase);
+        }
+    }
+
+    Ok(positions
+        .into_iter()
+        .map(|(_, position)| position)
+        .collect())
+}
+
+/// The time two runs took for the same segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentDelta {
+    /// The time of the first run, or `None` if it did not reach this segment.
+    pub time_a: Option<Duration>,
+
+    /// The time of the second run, or `None` if it did not reach this segment.
+    pub time_b: Option<Duration>,
+}
+
+impl SegmentDelta {
+    /// Returns how much slower the second run was than the first, or `None` if either run did not
+    /// reach this segment.
+    ///
+    /// A negative delta means that the second run was faster.
+    #[must_use]
+    pub fn delta(&self) -> Option<Duration> {
+        Some(self.time_b? - self.time_a?)
+    }
+}
+
+/// A segment-by-segment comparison of two runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunComparison {
+    /// The ID of the first run.
+    pub run_id_a: i64,
+
+    /// The ID of the second run.
+    pub run_id_b: i64,
+
+    /// The total times of the runs.
+    pub total: SegmentDelta,
+
+    /// The flight times of the runs.
+    pub flight: SegmentDelta,
+
+    /// The comparison of each phase either run reached, ordered by phase number.
+    pub phases: Vec<PhaseComparison>,
+}
+
+/// A segment-by-segment comparison of a single phase of two runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhaseComparison {
+    /// The number of the phase within the runs.
+    pub phase_number: i32,
+
+    /// The total times of the phase.
+    pub phase: SegmentDelta,
+
+    /// The time spent on shields during the phase.
+    pub shield: SegmentDelta,
+
+    /// The time spent on legs during the phase.
+    pub leg: SegmentDelta,
+
+    /// The time spent on the body kill during the phase.
+    pub body_kill: SegmentDelta,
+
+    /// The time spent on pylons during the phase.
+    pub pylon: SegmentDelta,
+
+    /// The times of the first, second, third, etc. shield change of the phase.
+    pub shield_changes: Vec<SegmentDelta>,
+
+    /// The times of the first, second, third, and fourth leg break of the phase.
+    pub leg_breaks: Vec<SegmentDelta>,
+}
+
+/// Compares two runs segment by segment, for showing them side by side.
+///
+/// Phases are matched by phase number, and shield changes and leg breaks by the order they
+/// happened in within their phase. A segment only one of the runs reached, such as the phases
+/// after an aborted run ended, is still listed, with the time of the other run left as `None`.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id_a` - The ID of the first run, which the second is compared against.
+/// * `run_id_b` - The ID of the second run.
+///
+/// # Returns
+/// * `Result<RunComparison>` - The times of both runs for every segment.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if either run does not exist, or another error if a
+/// query fails.
+pub fn compare_runs(conn: &Connection, run_id_a: i64, run_id_b: i64) -> Result<RunComparison> {
+    let run_a = fetch_run_by_id(conn, run_id_a)?;
+    let run_b = fetch_run_by_id(conn, run_id_b)?;
+
+    let phase_numbers: BTreeSet<i32> = run_a
+        .phases
+        .iter()
+        .chain(&run_b.phases)
+        .map(|phase| phase.phase_number)
+        .collect();
+    let phases = phase_numbers
+        .into_iter()
+        .map(|phase_number| {
+            compare_phases(
+                phase_number,
+                run_phase(&run_a, phase_number),
+                run_phase(&run_b, phase_number),
+            )
+        })
+        .collect();
+
+    let times = |time: fn(&TotalTimes) -> f64| SegmentDelta {
+        time_a: Some(Duration::from_secs_f64(time(&run_a.total_times))),
+        time_b: Some(Duration::from_secs_f64(time(&run_b.total_times))),
+    };
+
+    Ok(RunComparison {
+        run_id_a,
+        run_id_b,
+        total: times(|times| times.total_time),
+        flight: times(|times| times.total_flight_time),
+        phases,
+    })
+}
+
+/// Compares a phase of two runs, either of which may not have reached it.
+fn compare_phases(
+    phase_number: i32,
+    phase_a: Option<&Phase>,
+    phase_b: Option<&Phase>,
+) -> PhaseComparison {
+    let times = |time: fn(&Phase) -> f64| SegmentDelta {
+        time_a: phase_a.map(|phase| Duration::from_secs_f64(time(phase))),
+        time_b: phase_b.map(|phase| Duration::from_secs_f64(time(phase))),
+    };
+
+    let shield_times = |phase: &Phase| -> Vec<Duration> {
+        phase
+            .shield_changes
+            .iter()
+            .map(|shield_change| Duration::from_secs_f64(shield_change.shield_time))
+            .collect()
+    };
+    let leg_times = |phase: &Phase| -> Vec<Duration> {
+        phase
+            .leg_breaks
+            .iter()
+            .map(|leg_break| Duration::from_secs_f64(leg_break.leg_break_time))
+            .collect()
+    };
+
+    PhaseComparison {
+        phase_number,
+        phase: times(|phase| phase.total_time),
+        shield: times(|phase| phase.total_shield_time),
+        leg: times(|phase| phase.total_leg_time),
+        body_kill: times(|phase| phase.total_body_kill_time),
+        pylon: times(|phase| phase.total_pylon_time),
+        shield_changes: pair_times(
+            &phase_a.map(shield_times).unwrap_or_default(),
+            &phase_b.map(shield_times).unwrap_or_default(),
+        ),
+        leg_breaks: pair_times(
+            &phase_a.map(leg_times).unwrap_or_default(),
+            &phase_b.map(leg_times).unwrap_or_default(),
+        ),
+    }
+}
+
+/// Finds the phase of a run with the given phase number.
+fn run_phase(run: &Run, phase_number: i32) -> Option<&Phase> {
+    run.phases
+        .iter()
+        .find(|phase| phase.phase_number == phase_number)
+}
+
+/// Pairs up two lists of times by position, padding the shorter list with `None`.
+fn pair_times(times_a: &[Duration], times_b: &[Duration]) -> Vec<SegmentDelta> {
+    (0..times_a.len().max(times_b.len()))
+        .map(|index| SegmentDelta {
+            time_a: times_a.get(index).copied(),
+            time_b: times_b.get(index).copied(),
+        })
+        .collect()
+}
+
+/// A segment of a run, as part of a [`TimeLoss`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    /// The flight to Profit-Taker.
+    Flight,
+
+    /// The time spent on shields during a phase.
+    Shield { phase_number: i32 },
+
+    /// The time spent on legs during a phase.
+    Leg { phase_number: i32 },
+
+    /// The time spent on the body kill during a phase.
+    BodyKill { phase_number: i32 },
+
+    /// The time spent on pylons during a phase.
+    Pylon { phase_number: i32 },
+}
+
+/// How much slower a segment of a run was than the best time ever recorded for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLoss {
+    /// The segment of the run.
+    pub segment: Segment,
+
+    /// The time of the segment in the run.
+    pub time: Duration,
+
+    /// The best time of the segment in the category of the run.
+    pub best_time: Duration,
+
+    /// How much slower the run was than the best time.
+    ///
+    /// This is never negative for a valid run, since its own times count towards the best times,
+    /// but can be for an aborted run or one in the trash.
+    pub time_lost: Duration,
+}
+
+/// Where a run lost time compared to the best segments of its category, as computed by
+/// `time_loss_breakdown`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeLossBreakdown {
+    /// The category the run was compared in, across every game version.
+    pub category: RunCategory,
+
+    /// The sum of the time lost in every segment, which is how much faster the run would have been
+    /// had every segment gone as well as it ever has.
+    pub total_time_lost: Duration,
+
+    /// The segments of the run, most time lost first.
+    pub segments: Vec<TimeLoss>,
+}
+
+/// Compares each segment of a run against the best time ever recorded for it, for showing where
+/// the user loses the most time.
+///
+/// The best times are those of [`sum_of_best`], taken from the valid runs of the category of the
+/// run, which is given by whether it is solo and whether it is bugged. Only the segments that
+/// both the run and the best times reached are compared, and pylons only in the phases that had
+/// them.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to break down.
+///
+/// # Returns
+/// * `Result<Option<TimeLossBreakdown>>` - The time lost in every segment, or `None` if the
+///   category of the run has no valid runs to compare against.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// a query fails.
+pub fn time_loss_breakdown(conn: &Connection, run_id: i64) -> Result<Option<TimeLossBreakdown>> {
+    let run = fetch_run_by_id(conn, run_id)?;
+    let category = RunCategory {
+        solo: run.is_solo_run,
+        bugged: run.is_bugged_run,
+        game_version: None,
+        warframe: None,
+    };
+    let Some(best) = sum_of_best(conn, &category)? else {
+        return Ok(None);
+    };
+
+    let loss = |segment, time: f64, best_time: Duration| {
+        let time = Duration::from_secs_f64(time);
+
+        TimeLoss {
+            segment,
+            time,
+            best_time,
+            time_lost: time - best_time,
+        }
+    };
+    let mut segments = vec![loss(
+        Segment::Flight,
+        run.total_times.total_flight_time,
+        best.flight_time,
+    )];
+    for phase in &run.phases {
+        let phase_number = phase.phase_number;
+        let Some(best_phase) = best
+            .phases
+            .iter()
+            .find(|best_phase| best_phase.phase_number == phase_number)
+        else {
+            continue;
+        };
+
+        segments.extend([
+            loss(
+                Segment::Shield { phase_number },
+                phase.total_shield_time,
+                best_phase.shield_time,
+            ),
+            loss(
+                Segment::Leg { phase_number },
+                phase.total_leg_time,
+                best_phase.leg_time,
+            ),
+            loss(
+                Segment::BodyKill { phase_number },
+                phase.total_body_kill_time,
+                best_phase.body_kill_time,
+            ),
+        ]);
+        if phase.total_pylon_time > 0.0 && best_phase.pylon_time > Duration::ZERO {
+            segments.push(loss(
+                Segment::Pylon { phase_number },
+                phase.total_pylon_time,
+                best_phase.pylon_time,
+            ));
+        }
+    }
+
+    let total_time_lost = segments.iter().map(|segment| segment.time_lost).sum();
+    // A stable sort, so segments that lost the same time stay in the order they were played in
+    segments.sort_by_key(|segment| Reverse(segment.time_lost));
+
+    Ok(Some(TimeLossBreakdown {
+        category,
+        total_time_lost,
+        segments,
+    }))
+}
+
+/// Running totals of a segment across every valid run, as kept in the statistics cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachedSegmentStats {
+    /// The sum of the times of the segment.
+    pub sum: Duration,
+
+    /// The mean time of the segment.
+    pub mean: Duration,
+
+    /// The fastest time of the segment.
+    pub best: Duration,
+}
+
+/// An overview of every valid run, read from the statistics cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsOverview {
+    /// The number of valid runs.
+    pub run_count: usize,
+
+    /// The running totals of the total times of the runs.
+    pub total_time: CachedSegmentStats,
+
+    /// The running totals of the flight times of the runs.
+    pub flight_time: CachedSegmentStats,
+
+    /// The running totals of the shield times of the runs, summed over their phases.
+    pub shield_time: CachedSegmentStats,
+
+    /// The running totals of the leg times of the runs, summed over their phases.
+    pub leg_time: CachedSegmentStats,
+
+    /// The running totals of the body times of the runs, summed over their phases.
+    pub body_time: CachedSegmentStats,
+
+    /// The running totals of the pylon times of the runs, summed over their phases.
+    pub pylon_time: CachedSegmentStats,
+}
+
+/// Reads the number of valid runs and the sum, mean, and best of each of their total times.
+///
+/// Unlike the other functions of this module, this does not look at the runs at all: it reads a
+/// cache that is kept up to date as runs are inserted, updated, trashed, and deleted, so it takes
+/// the same time however many runs there are. This makes it suitable for the home screen, while
+/// the other functions are better suited to screens that need more than an overview.
+///
+/// Valid runs are those that are neither bugged, aborted, still in progress, nor in the trash.
+/// Solo and squad runs are both included. The cache only covers the main database, so runs moved
+/// into an archive by [`archive_runs`](crate::maintenance::archive_runs) are left out, even while
+/// the archive is attached.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Option<StatsOverview>>` - The overview of the valid runs, or `None` if there are no
+///   valid runs.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn cached_overview(conn: &Connection) -> Result<Option<StatsOverview>> {
+    let overview = conn
+        .prepare_cached(
+            "SELECT
+                run_count,
+                total_time_sum, total_time_best,
+                flight_time_sum, flight_time_best,
+                shield_time_sum, shield_time_best,
+                leg_time_sum, leg_time_best,
+                body_time_sum, body_time_best,
+                pylon_time_sum, pylon_time_best
+            FROM stats_cache WHERE run_count > 0",
+        )?
+        .query_row([], |row| {
+            let run_count: usize = row.get(0)?;
+            #[expect(
+                clippy::cast_precision_loss,
+                reason = "there will never be anywhere near 2^52 runs, nor milliseconds of them"
+            )]
+            let segment = |index| -> rusqlite::Result<_> {
+                let sum: Duration = row.get(index)?;
+                Ok(CachedSegmentStats {
+                    sum,
+                    mean: Duration::from_millis_f64(sum.as_millis() as f64 / run_count as f64),
+                    best: row.get(index + 1)?,
+                })
+            };
+
+            Ok(StatsOverview {
+                run_count,
+                total_time: segment(1)?,
+                flight_time: segment(3)?,
+                shield_time: segment(5)?,
+                leg_time: segment(7)?,
+                body_time: segment(9)?,
+                pylon_time: segment(11)?,
+            })
+        })
+        .optional()?;
+
+    Ok(overview)
+}
+
+/// How many of the most recent runs `goal_progress` averages.
+pub const GOAL_RECENT_RUN_COUNT: u32 = 10;
+
+/// How close the user is to reaching a goal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GoalProgress {
+    /// The goal.
+    pub goal: Goal,
+
+    /// The best time recorded for the segment of the goal, or `None` if there are no valid runs
+    /// to take it from.
+    pub best_time: Option<Duration>,
+
+    /// The average time of the segment over the last [`GOAL_RECENT_RUN_COUNT`] valid runs, or
+    /// `None` if there are none.
+    pub recent_average: Option<Duration>,
+}
+
+impl GoalProgress {
+    /// Returns whether the best time beats the target time.
+    #[must_use]
+    pub fn is_reached(&self) -> bool {
+        self.best_time
+            .is_some_and(|best_time| best_time <= self.goal.target_time)
+    }
+
+    /// Returns how far the best time is from the target time, which is negative once the goal has
+    /// been reached.
+    #[must_use]
+    pub fn best_gap(&self) -> Option<Duration> {
+        self.best_time.map(|time| time - self.goal.target_time)
+    }
+
+    /// Returns how far the recent average is from the target time, which is negative once the
+    /// user reaches the goal consistently.
+    #[must_use]
+    pub fn recent_gap(&self) -> Option<Duration> {
+        self.recent_average.map(|time| time - self.goal.target_time)
+    }
+}
+
+/// Reports how close the best and recent times are to each goal that has been set.
+///
+/// Times are taken from the valid, non-bugged runs of the category of each goal, across every
+/// game version. For a phase goal, only runs that reached the phase count.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Vec<GoalProgress>>` - The progress towards each goal, in the same order as
+///   [`fetch_goals`].
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn goal_progress(conn: &Connection) -> Result<Vec<GoalProgress>> {
+    fetch_goals(conn)?
+        .into_iter()
+        .map(|goal| {
+            let category = RunCategory {
+                solo: goal.solo,
+                ..RunCategory::default()
+            };
+            let (condition, mut values) = category.to_filter().to_sql();
+
+            // Ordered like the runs they were recorded in, so the last runs can be averaged
+            let times_sql = goal.phase_number.map_or_else(
+                || format!("SELECT total_time AS time FROM runs WHERE {condition}"),
+                |phase_number| {
+                    values.push(Value::Integer(phase_number.into()));
+                    format!(
+                        "SELECT phases.phase_time AS time FROM runs
+                        JOIN phases ON phases.run_id = runs.id
+                        WHERE {condition} AND phases.phase_number = ?"
+                    )
+                },
+            );
+            // The times are selected twice, once for the best time and once for the average
+            let mut query_values = values.clone();
+            query_values.extend(values);
+            query_values.push(Value::Integer(GOAL_RECENT_RUN_COUNT.into()));
+
+            let (best_time, recent_average) = conn
+                .prepare_cached(&format!(
+                    "SELECT (SELECT MIN(time) FROM ({times_sql})),
+                        (SELECT AVG(time) FROM (
+                            {times_sql} ORDER BY runs.time_stamp DESC, runs.id DESC LIMIT ?
+                        ))"
+                ))?
+                .query_row(params_from_iter(query_values), |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?;
+
+            Ok(GoalProgress {
+                goal,
+                best_time,
+                recent_average,
+            })
+        })
+        .collect()
+}
+
+/// Returns a query selecting the IDs of every valid run, regardless of category, along with the
+/// values of its parameters.
+fn valid_run_ids_sql() -> (String, Vec<Value>) {
+    let filter = RunFilter {
+        bugged: Some(false),
+        aborted: Some(false),
+        ..RunFilter::default()
+    };
+    let (condition, values) = filter.to_sql();
+
+    (format!("SELECT id FROM runs WHERE {condition}"), values)
+}
+
+/// Finds the phase with the given phase number.
+fn find_phase(phases: &mut [BestPhase], phase_number: i32) -> Option<&mut BestPhase> {
+    phases
+        .iter_mut()
+        .find(|phase| phase.phase_number == phase_number)
+}
@@ -0,0 +1,233 @@
+//! This module provides async versions of the insert, fetch, and analytics functions, for callers
+//! that cannot block while the database is busy, such as the Flutter UI isolate.
+//!
+//! It is only available with the `async` feature enabled. An [`AsyncDatabase`] runs each call on a
+//! dedicated pool of worker threads, using a connection borrowed from a [`Pool`], and returns a
+//! future that completes once the call has finished. The futures do not depend on any particular
+//! async runtime.
+//!
+//! ```no_run
+//! use lib_profit_taker_database::asynchronous::AsyncDatabase;
+//! use lib_profit_taker_database::error::Result;
+//! use lib_profit_taker_database::pool::create_pool;
+//!
+//! async fn newest_run_name() -> Result<String> {
+//!     let database = AsyncDatabase::new(create_pool("data/runs.db")?);
+//!     let run = database.fetch_run_by_id(1).await?;
+//!
+//!     Ok(run.run_name)
+//! }
+//! ```
+
+use futures_channel::oneshot;
+use lib_profit_taker_core::Run;
+use rusqlite::Connection;
+use threadpool::ThreadPool;
+
+use crate::analytics::{
+    self, AverageTimes, BestSplits, LegPositionStats, RunCategory, ShieldElementStats,
+    SquadMemberStats, SumOfBest,
+};
+use crate::error::{DatabaseError, Result};
+use crate::fetch::{self, RunCursor, RunFilter, RunPage, SortBy};
+use crate::insert;
+use crate::pool::Pool;
+
+/// A handle for running database calls on background threads.
+///
+/// Cloning the handle is cheap, and the clones share the same connections and worker threads.
+#[derive(Debug, Clone)]
+pub struct AsyncDatabase {
+    /// The connections the worker threads use.
+    pool: Pool,
+
+    /// The threads the database calls are run on.
+    workers: ThreadPool,
+}
+
+impl AsyncDatabase {
+    /// Creates a handle with one worker thread for each connection `pool` can open.
+    ///
+    /// # Arguments
+    /// * `pool` - The connections to run database calls with.
+    #[must_use]
+    pub fn new(pool: Pool) -> Self {
+        let threads = usize::try_from(pool.max_size()).unwrap_or(usize::MAX);
+        Self::with_threads(pool, threads)
+    }
+
+    /// Creates a handle with the given number of worker threads.
+    ///
+    /// # Arguments
+    /// * `pool` - The connections to run database calls with.
+    /// * `threads` - The number of worker threads. There is little point in having more threads
+    ///   than `pool` has connections, as the extra threads would only wait for a connection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is zero.
+    #[must_use]
+    pub fn with_threads(pool: Pool, threads: usize) -> Self {
+        let workers = ThreadPool::with_name("profit-taker-database".to_string(), threads);
+        Self { pool, workers }
+    }
+
+    /// Runs an arbitrary database call on a worker thread.
+    ///
+    /// This is what the other methods are built on, and can be used to call any other function of
+    /// this library asynchronously.
+    ///
+    /// # Arguments
+    /// * `f` - The function to run, given a connection from the pool.
+    ///
+    /// # Returns
+    /// * `Result<T>` - The value `f` returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::PoolFailed`] if no connection could be taken from the pool,
+    /// [`DatabaseError::TaskFailed`] if `f` panicked, or the error `f` returned.
+    pub async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let pool = self.pool.clone();
+
+        self.workers.execute(move || {
+            let result = pool.get().map_err(Into::into).and_then(|conn| f(&conn));
+            // The receiver having been dropped just means the caller stopped waiting
+            let _ = sender.send(result);
+        });
+
+        receiver.await.map_err(|_| DatabaseError::TaskFailed)?
+    }
+
+    /// Inserts a run into the database. See [`insert::insert_run`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`insert::insert_run`] and [`AsyncDatabase::run`].
+    pub async fn insert_run(&self, run: Run) -> Result<i64> {
+        self.run(move |conn| insert::insert_run(conn, &run)).await
+    }
+
+    /// Inserts several runs into the database in a single transaction. See
+    /// [`insert::insert_runs_batch`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`insert::insert_runs_batch`] and [`AsyncDatabase::run`].
+    pub async fn insert_runs_batch(&self, runs: Vec<Run>) -> Result<Vec<i64>> {
+        self.run(move |conn| insert::insert_runs_batch(conn, &runs, |_, _| {}))
+            .await
+    }
+
+    /// Fetches a run by its ID. See [`fetch::fetch_run_by_id`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`fetch::fetch_run_by_id`] and [`AsyncDatabase::run`].
+    pub async fn fetch_run_by_id(&self, run_id: i64) -> Result<Run> {
+        self.run(move |conn| fetch::fetch_run_by_id(conn, run_id))
+            .await
+    }
+
+    /// Fetches a page of runs. See [`fetch::fetch_runs_paged`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`fetch::fetch_runs_paged`] and [`AsyncDatabase::run`].
+    pub async fn fetch_runs_paged(
+        &self,
+        page: u32,
+        page_size: u32,
+        sort: SortBy,
+        filter: RunFilter,
+    ) -> Result<Vec<Run>> {
+        self.run(move |conn| fetch::fetch_runs_paged(conn, page, page_size, sort, &filter))
+            .await
+    }
+
+    /// Fetches the page of runs that follows a cursor. See [`fetch::fetch_runs_after`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`fetch::fetch_runs_after`] and [`AsyncDatabase::run`].
+    pub async fn fetch_runs_after(&self, cursor: RunCursor, limit: u32) -> Result<RunPage> {
+        self.run(move |conn| fetch::fetch_runs_after(conn, &cursor, limit))
+            .await
+    }
+
+    /// Fetches the personal best in a category. See [`analytics::fetch_pb`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`analytics::fetch_pb`] and [`AsyncDatabase::run`].
+    pub async fn fetch_pb(&self, category: RunCategory) -> Result<Option<Run>> {
+        self.run(move |conn| analytics::fetch_pb(conn, &category))
+            .await
+    }
+
+    /// Calculates the sum of best in a category. See [`analytics::sum_of_best`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`analytics::sum_of_best`] and [`AsyncDatabase::run`].
+    pub async fn sum_of_best(&self, category: RunCategory) -> Result<Option<SumOfBest>> {
+        self.run(move |conn| analytics::sum_of_best(conn, &category))
+            .await
+    }
+
+    /// Calculates average times. See [`analytics::average_times`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`analytics::average_times`] and [`AsyncDatabase::run`].
+    pub async fn average_times(
+        &self,
+        last_n: Option<u32>,
+        include_flagged: bool,
+    ) -> Result<Option<AverageTimes>> {
+        self.run(move |conn| analytics::average_times(conn, last_n, include_flagged))
+            .await
+    }
+
+    /// Calculates statistics for each squad member. See [`analytics::squad_member_stats`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`analytics::squad_member_stats`] and [`AsyncDatabase::run`].
+    pub async fn squad_member_stats(&self) -> Result<Vec<SquadMemberStats>> {
+        self.run(analytics::squad_member_stats).await
+    }
+
+    /// Finds the best flight and phase times. See [`analytics::best_splits`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`analytics::best_splits`] and [`AsyncDatabase::run`].
+    pub async fn best_splits(&self) -> Result<Option<BestSplits>> {
+        self.run(analytics::best_splits).await
+    }
+
+    /// Calculates shield break times by element. See [`analytics::shield_element_stats`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`analytics::shield_element_stats`] and
+    /// [`AsyncDatabase::run`].
+    pub async fn shield_element_stats(&self) -> Result<Vec<ShieldElementStats>> {
+        self.run(analytics::shield_element_stats).await
+    }
+
+    /// Calculates leg break times by leg position. See [`analytics::leg_break_stats`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`analytics::leg_break_stats`] and [`AsyncDatabase::run`].
+    pub async fn leg_break_stats(&self) -> Result<Vec<LegPositionStats>> {
+        self.run(analytics::leg_break_stats).await
+    }
+}
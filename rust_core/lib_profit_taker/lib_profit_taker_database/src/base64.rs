@@ -0,0 +1,52 @@
+//! This module encodes bytes as URL-safe base64 without padding, and decodes them back, for the
+//! run sharing codes of the `export` and `import` modules.
+//!
+//! The URL-safe alphabet is used so that codes stay intact when pasted into links and chat
+//! messages, which may treat `+` and `/` specially.
+
+/// The characters each 6-bit group is encoded as, in order.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes bytes as URL-safe base64 without padding.
+pub fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0_u32, |group, (i, &byte)| {
+            group | (u32::from(byte) << (16 - 8 * i))
+        });
+
+        // A chunk of n bytes takes n + 1 characters
+        for i in 0..=chunk.len() {
+            encoded.push(char::from(
+                ALPHABET[((group >> (18 - 6 * i)) & 0x3f) as usize],
+            ));
+        }
+    }
+
+    encoded
+}
+
+/// Decodes URL-safe base64 without padding, or `None` if `encoded` is not valid base64.
+pub fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3 + 2);
+
+    for chunk in encoded.as_bytes().chunks(4) {
+        // A single character cannot hold a whole byte
+        if chunk.len() == 1 {
+            return None;
+        }
+
+        let mut group = 0_u32;
+        for (i, &char) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|&found| found == char)?;
+            group |= u32::try_from(value).ok()? << (18 - 6 * i);
+        }
+
+        for i in 0..chunk.len() - 1 {
+            decoded.push(group.to_be_bytes()[i + 1]);
+        }
+    }
+
+    Some(decoded)
+}
@@ -0,0 +1,169 @@
+//! This module provides a cache of the slowest analytics results, so that the frontend can ask for
+//! them every time a screen is shown without running the aggregate queries again.
+//!
+//! Results are kept by an [`AnalyticsCache`] along with the generation of the database they were
+//! computed at. The generation is a counter that triggers bump on every change to a run or to its
+//! phases, shield changes, leg breaks, squad members, or loadout, so every lookup first reads it
+//! with `generation` and drops every cached result once it has moved on. Reading it is a
+//! single-row lookup, which is far cheaper than the queries it saves.
+//!
+//! The generation is stored in the database rather than counted by a connection, so changes made
+//! through any connection are noticed, including those of the background
+//! [`writer`](crate::writer) and of other processes.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use crate::analytics::{self, RunCategory, SumOfBest, TimeBucket};
+use crate::duration::Duration;
+use crate::error::Result;
+use crate::fetch::RunFilter;
+
+/// Reads the generation of the database, which changes whenever a run or any of its data does.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<i64>` - The generation, which only ever increases.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn generation(conn: &Connection) -> Result<i64> {
+    Ok(conn
+        .prepare_cached("SELECT generation FROM generation")?
+        .query_row([], |row| row.get(0))?)
+}
+
+/// Memoized results of the analytics queries of a single database.
+///
+/// Each result is computed the first time it is asked for, and returned from the cache until the
+/// [`generation`] of the database changes. A cache must only be used with connections to one
+/// database, as the generations of two databases can be equal; use [`AnalyticsCache::clear`]
+/// when switching to another one.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_database::analytics::RunCategory;
+/// use lib_profit_taker_database::cache::AnalyticsCache;
+/// use lib_profit_taker_database::connection::open_in_memory;
+///
+/// let conn = open_in_memory()?;
+/// let mut cache = AnalyticsCache::new();
+/// let category = RunCategory {
+///     solo: true,
+///     ..RunCategory::default()
+/// };
+///
+/// // Only the first call runs the queries
+/// assert!(cache.sum_of_best(&conn, &category)?.is_none());
+/// assert!(cache.sum_of_best(&conn, &category)?.is_none());
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct AnalyticsCache {
+    /// The generation of the database the cached results were computed at, or `None` if nothing
+    /// has been cached yet.
+    generation: Option<i64>,
+
+    /// The results of `sum_of_best`, by category.
+    sum_of_best: HashMap<RunCategory, Option<SumOfBest>>,
+
+    /// The results of `time_distribution`, by bucket size and filter.
+    time_distribution: HashMap<(Duration, RunFilter), Vec<TimeBucket>>,
+}
+
+impl AnalyticsCache {
+    /// Creates an empty cache.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops every cached result, so that each is computed again the next time it is asked for.
+    pub fn clear(&mut self) {
+        self.generation = None;
+        self.sum_of_best.clear();
+        self.time_distribution.clear();
+    }
+
+    /// Returns the sum of best of a category, as computed by
+    /// [`sum_of_best`](analytics::sum_of_best).
+    ///
+    /// # Arguments
+    /// * `conn` - A reference to the active SQLite database connection.
+    /// * `category` - The category to compute the sum of best of.
+    ///
+    /// # Returns
+    /// * `Result<Option<&SumOfBest>>` - The best time of every segment, or `None` if the category
+    ///   has no valid runs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a query fails or a row references an unknown status effect or leg
+    /// position. Errors are not cached.
+    pub fn sum_of_best(
+        &mut self,
+        conn: &Connection,
+        category: &RunCategory,
+    ) -> Result<Option<&SumOfBest>> {
+        self.refresh(conn)?;
+
+        let sum_of_best = match self.sum_of_best.entry(category.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(analytics::sum_of_best(conn, category)?),
+        };
+
+        Ok(sum_of_best.as_ref())
+    }
+
+    /// Returns how many runs fall into each range of total times, as computed by
+    /// [`time_distribution`](analytics::time_distribution).
+    ///
+    /// # Arguments
+    /// * `conn` - A reference to the active SQLite database connection.
+    /// * `bucket_size` - The width of each range.
+    /// * `filter` - The runs to count.
+    ///
+    /// # Returns
+    /// * `Result<&[TimeBucket]>` - The ranges, fastest first, or nothing if no run matches
+    ///   `filter`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::InvalidData`](crate::error::DatabaseError::InvalidData) if
+    /// `bucket_size` is not positive, or another error if the query fails. Errors are not cached.
+    pub fn time_distribution(
+        &mut self,
+        conn: &Connection,
+        bucket_size: Duration,
+        filter: &RunFilter,
+    ) -> Result<&[TimeBucket]> {
+        self.refresh(conn)?;
+
+        let buckets = match self.time_distribution.entry((bucket_size, filter.clone())) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(analytics::time_distribution(conn, bucket_size, filter)?)
+            }
+        };
+
+        Ok(buckets)
+    }
+
+    /// Drops every cached result if the generation of the database has changed since they were
+    /// computed.
+    fn refresh(&mut self, conn: &Connection) -> Result<()> {
+        let current = generation(conn)?;
+        if self.generation != Some(current) {
+            self.clear();
+            self.generation = Some(current);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+//! This module computes checksums of the timing data of runs, which identify a run by what
+//! happened in it rather than by where it is stored.
+//!
+//! The checksum of a run is stored alongside it when it is inserted, and used by
+//! [`import`](crate::import) to recognize runs that were already stored even if they lost their
+//! UUID along the way. Exported runs carry their checksum too, so `find_edited_runs` can tell
+//! whether the times of a shared file were changed by hand since it was exported.
+//!
+//! Only timing data is covered: the timestamp, total times, phases, shield changes, and leg
+//! breaks, each rounded to the nearest millisecond as it is when stored. Renaming a run, writing
+//! notes, or marking it as bugged does not change its checksum.
+//! The checksum is a 64-bit FNV-1a hash, which is enough to tell runs apart, but is not meant to
+//! stop someone determined to forge a run.
+
+use lib_profit_taker_core::{LegPosition, Run, StatusEffect};
+use rusqlite::{params, Connection};
+use std::io::Read;
+
+use crate::duration::Duration;
+use crate::error::{DatabaseError, Result};
+use crate::export::{ExportFile, FORMAT_VERSION};
+use crate::fetch::fetch_run_by_id;
+
+/// The starting state of a 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// The multiplier of a 64-bit FNV-1a hash.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A 64-bit FNV-1a hash, fed one field at a time.
+struct Hasher(u64);
+
+impl Hasher {
+    /// Feeds raw bytes into the hash.
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Feeds an integer into the hash.
+    fn integer(&mut self, integer: i64) {
+        self.bytes(&integer.to_le_bytes());
+    }
+
+    /// Feeds a time into the hash, rounded to the nearest millisecond like every stored time.
+    fn time(&mut self, time: f64) {
+        self.integer(Duration::from_secs_f64(time).as_millis());
+    }
+
+    /// Feeds a name into the hash, followed by its length so that consecutive names cannot run
+    /// into each other.
+    fn name(&mut self, name: &str) {
+        self.bytes(name.as_bytes());
+        self.integer(i64::try_from(name.len()).unwrap_or(i64::MAX));
+    }
+}
+
+/// Computes the checksum of the timing data of a run.
+///
+/// # Returns
+/// * `String` - The checksum, as 16 lowercase hexadecimal digits.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_core::Run;
+/// use lib_profit_taker_database::checksum::run_checksum;
+///
+/// let run = Run::new(0, 1_700_000_000, "Run", "Player");
+/// let renamed = Run::new(0, 1_700_000_000, "Renamed", "Player");
+/// let later = Run::new(0, 1_700_000_001, "Run", "Player");
+///
+/// assert_eq!(run_checksum(&run), run_checksum(&renamed));
+/// assert_ne!(run_checksum(&run), run_checksum(&later));
+/// assert_eq!(run_checksum(&run).len(), 16);
+/// ```
+#[must_use]
+pub fn run_checksum(run: &Run) -> String {
+    let mut hasher = Hasher(FNV_OFFSET_BASIS);
+
+    hasher.integer(run.time_stamp);
+    let times = &run.total_times;
+    for time in [
+        times.total_time,
+        times.total_flight_time,
+        times.total_shield_time,
+        times.total_leg_time,
+        times.total_body_time,
+        times.total_pylon_time,
+    ] {
+        hasher.time(time);
+    }
+
+    hasher.integer(i64::try_from(run.phases.len()).unwrap_or(i64::MAX));
+    for phase in &run.phases {
+        hasher.integer(phase.phase_number.into());
+        for time in [
+            phase.total_time,
+            phase.total_shield_time,
+            phase.total_leg_time,
+            phase.total_body_kill_time,
+            phase.total_pylon_time,
+        ] {
+            hasher.time(time);
+        }
+
+        hasher.integer(i64::try_from(phase.shield_changes.len()).unwrap_or(i64::MAX));
+        for shield_change in &phase.shield_changes {
+            hasher.time(shield_change.shield_time);
+            hasher.name(StatusEffect::to_string(&shield_change.status_effect));
+            hasher.integer(shield_change.is_overshield.into());
+        }
+
+        hasher.integer(i64::try_from(phase.leg_breaks.len()).unwrap_or(i64::MAX));
+        for leg_break in &phase.leg_breaks {
+            hasher.time(leg_break.leg_break_time);
+            hasher.name(LegPosition::to_string(&leg_break.leg_position));
+            hasher.integer(leg_break.leg_order.into());
+        }
+    }
+
+    format!("{:016x}", hasher.0)
+}
+
+/// Computes and stores the checksum of every run that does not have one yet, such as runs stored
+/// before checksums existed.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<usize>` - The number of runs that were given a checksum.
+///
+/// # Errors
+///
+/// Returns an error if a run cannot be read or a query fails.
+pub fn backfill_checksums(conn: &Connection) -> Result<usize> {
+    let run_ids = conn
+        .prepare_cached("SELECT id FROM runs WHERE checksum IS NULL ORDER BY id")?
+        .query_map([], |row| row.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for &run_id in &run_ids {
+        let checksum = run_checksum(&fetch_run_by_id(conn, run_id)?);
+        conn.prepare_cached("UPDATE runs SET checksum = ?2 WHERE id = ?1")?
+            .execute(params![run_id, checksum])?;
+    }
+
+    Ok(run_ids.len())
+}
+
+/// Finds the runs of an exported JSON file whose times were changed since it was exported.
+///
+/// Runs exported before checksums existed have no checksum to compare against, so they are never
+/// reported.
+///
+/// # Arguments
+/// * `reader` - Where to read the JSON from, in the format written by the `export` module.
+///
+/// # Returns
+/// * `Result<Vec<i64>>` - The `run_id` fields of the edited runs, in the order they appear in the
+///   file.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Json`] if the file is not valid JSON in the expected format,
+/// [`DatabaseError::UnsupportedFormatVersion`] if it was written by a newer version of this
+/// library, or [`DatabaseError::InvalidData`] if it contains an unknown status effect or leg
+/// position.
+pub fn find_edited_runs(reader: impl Read) -> Result<Vec<i64>> {
+    let file: ExportFile = serde_json::from_reader(reader)?;
+    if file.format_version > FORMAT_VERSION {
+        return Err(DatabaseError::UnsupportedFormatVersion(file.format_version));
+    }
+
+    let mut edited = Vec::new();
+    for mut exported in file.runs {
+        let Some(checksum) = exported.checksum.take() else {
+            continue;
+        };
+        let run_id = exported.run_id;
+
+        if run_checksum(&Run::try_from(exported)?) != checksum {
+            edited.push(run_id);
+        }
+    }
+
+    Ok(edited)
+}
@@ -3,15 +3,52 @@
 //! 
 //! The `create_database` function ensures that the database file exists at the given path,
 //! creating the necessary directory structure if needed. It then opens a connection to the database
-//! and initializes the schema by executing the SQL statements stored in `SCHEMA_SQL`.
+//! and initializes the schema by applying any pending migrations.
 //!
-//! The `initialize_schema` function is responsible for executing the SQL schema commands to set
-//! up the database tables and insert any default data required for the application to function correctly.
+//! The `initialize_schema` function is responsible for bringing the database schema up to date by
+//! applying any pending migrations, which set up the database tables and insert any default data
+//! required for the application to function correctly.
+//!
+//! The `open_in_memory` function instead creates a throwaway database that is never saved to
+//! disk, for tests and dry runs, and `open_active_profile` opens the database of whichever
+//! profile the user last switched to.
+//!
+//! The `open_read_only` function opens a database that must not be changed, such as one shared by
+//! another player, or the app's own database from the overlay process while the app writes to it.
+//!
+//! Connections opened with [`ConnectionOptions`] are configured for a desktop app that writes
+//! small transactions while the UI reads: write-ahead logging, a busy timeout instead of
+//! immediate lock errors, and enforced foreign keys. With the `tracing` feature enabled, they also
+//! log how long each query takes. Writes that still find the database locked once the busy
+//! timeout runs out, for example because a second instance of the app is writing, are retried as
+//! set by the [`RetryPolicy`] of the options the connection was opened with.
+//!
+//! The `open_with_recovery` function checks the database when the app starts, and replaces a
+//! damaged database with its latest backup or with the runs of its exports, so that the app can
+//! always start.
+//!
+//! With the `encryption` feature enabled, `open_encrypted` opens encrypted databases, for users
+//! who sync their runs through cloud storage, and `rekey` changes their key.
+//!
+//! Callers can group several operations into one atomic unit with `transaction`.
+//!
+//! Query modules prepare their statements through `with_cached_stmt`, which reuses the statement
+//! compiled the last time the same SQL text ran on the connection, so the live log parser does not
+//! re-parse the same inserts for every run.
 
-use rusqlite::{Connection, Result};
+use rusqlite::{CachedStatement, Connection, ErrorCode, OpenFlags, Params};
 use std::fs;
-use std::path::Path;
-use crate::schema::SCHEMA_SQL;
+use std::io;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::error::{DatabaseError, Result};
+use crate::import::{import_json, ImportStrategy};
+use crate::maintenance::restore_from;
+use crate::migrations::{self, LATEST_VERSION};
+use crate::profiles::Profiles;
 
 /// Creates an SQLite database file at the given path if it does not exist.
 /// Ensures the directory structure is created before attempting to create the database.
@@ -21,31 +58,1061 @@ use crate::schema::SCHEMA_SQL;
 /// 
 /// # Returns
 /// * `Result<()>` - Returns `Ok(())` if the database file is successfully created or already exists.
-/// If an error occurs during directory creation or database initialization, it is returned.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Io`] if the directory structure cannot be created,
+/// [`DatabaseError::ConnectionFailed`] if the database cannot be opened, or
+/// [`DatabaseError::MigrationFailed`] if the schema fails to initialize.
 pub fn create_database(path: &str) -> Result<()> {
     // Ensure the directory exists
     if let Some(parent) = Path::new(path).parent() {
-        fs::create_dir_all(parent).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        fs::create_dir_all(parent)?;
     }
 
     // Create and initialize the database
-    let conn = Connection::open(path)?;
+    let conn = Connection::open(path).map_err(DatabaseError::ConnectionFailed)?;
     initialize_schema(&conn)?;
     
     Ok(())
 }
 
-/// Initializes the SQLite database schema by executing SQL statements from the constant.
+/// How SQLite records changes before writing them to the database file.
+///
+/// See <https://www.sqlite.org/pragma.html#pragma_journal_mode>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalMode {
+    /// Keep a rollback journal, deleting it at the end of each transaction.
+    Delete,
+
+    /// Keep a rollback journal, truncating it at the end of each transaction.
+    Truncate,
+
+    /// Keep a rollback journal, overwriting its header at the end of each transaction.
+    Persist,
+
+    /// Keep the rollback journal in memory, risking corruption if the app crashes mid-write.
+    Memory,
+
+    /// Use a write-ahead log, which lets readers keep reading while a writer commits.
+    Wal,
+
+    /// Do not keep a journal at all, so transactions cannot be rolled back safely.
+    Off,
+}
+
+impl JournalMode {
+    /// Returns the value of the `journal_mode` pragma for this mode.
+    const fn to_sql(self) -> &'static str {
+        match self {
+            Self::Delete => "DELETE",
+            Self::Truncate => "TRUNCATE",
+            Self::Persist => "PERSIST",
+            Self::Memory => "MEMORY",
+            Self::Wal => "WAL",
+            Self::Off => "OFF",
+        }
+    }
+}
+
+/// How often SQLite waits for written data to reach the disk.
+///
+/// See <https://www.sqlite.org/pragma.html#pragma_synchronous>.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Synchronous {
+    /// Never wait, risking corruption if the computer loses power.
+    Off,
+
+    /// Wait at the most critical moments. With write-ahead logging, this cannot corrupt the
+    /// database, but the last transactions may be lost if the computer loses power.
+    Normal,
+
+    /// Wait after every transaction.
+    Full,
+
+    /// Like `Full`, but also wait for the journal's directory after deleting the journal.
+    Extra,
+}
+
+impl Synchronous {
+    /// Returns the value of the `synchronous` pragma for this level.
+    const fn to_sql(self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::Normal => "NORMAL",
+            Self::Full => "FULL",
+            Self::Extra => "EXTRA",
+        }
+    }
+}
+
+/// How often, and how long apart, a write is retried when another connection holds the write
+/// lock for longer than the busy timeout.
+///
+/// Each retry waits twice as long as the one before, starting from the initial delay and never
+/// waiting longer than the maximum delay, on top of the busy timeout itself.
+///
+/// Every write of this library, including migrations and the transactions of the background
+/// [`writer`](crate::writer), retries with the policy of the [`ConnectionOptions`] its connection
+/// was opened or configured with. Connections opened some other way retry with the default
+/// policy.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use lib_profit_taker_core::Run;
+/// use lib_profit_taker_database::connection::{ConnectionOptions, RetryPolicy};
+/// use lib_profit_taker_database::insert::insert_run;
+///
+/// let options = ConnectionOptions::new()
+///     .busy_timeout(Duration::from_secs(1))
+///     .retry_policy(RetryPolicy::new().max_retries(10));
+/// let conn = options.open_in_memory()?;
+/// // Retried up to ten times if another instance of the app holds the write lock
+/// insert_run(&conn, &Run::new(0, 1_675_271_234, "Run #1", "Player1"))?;
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times a write is retried before the busy error is returned.
+    max_retries: u32,
+
+    /// How long to wait before the first retry.
+    initial_delay: Duration,
+
+    /// The longest time to wait before any retry.
+    max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries, returning the busy error as soon as the busy timeout runs out.
+    pub const NEVER: Self = Self::new().max_retries(0);
+
+    /// Creates the default policy, which retries three times, waiting 100 milliseconds before
+    /// the first retry and at most two seconds before any retry.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_retries: 3,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+        }
+    }
+
+    /// Sets how many times a write is retried before the busy error is returned.
+    #[must_use]
+    pub const fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets how long to wait before the first retry.
+    #[must_use]
+    pub const fn initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    /// Sets the longest time to wait before any retry.
+    #[must_use]
+    pub const fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Returns how long to wait before the given retry, counting from zero.
+    fn delay(self, retry: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2_u32.saturating_pow(retry))
+            .min(self.max_delay)
+    }
+
+    /// Returns the policy of the options the connection was configured with, or the default
+    /// policy if it was opened some other way.
+    fn of(conn: &Connection) -> Self {
+        RETRY_POLICIES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&handle_address(conn))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Makes this the policy of the connection, as returned by `of`.
+    fn store(self, conn: &Connection) {
+        RETRY_POLICIES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(handle_address(conn), self);
+    }
+
+    /// Runs `f`, running it again as set by this policy for as long as it fails because another
+    /// connection holds the write lock.
+    ///
+    /// Only operations that leave nothing behind when they fail this way, such as a single
+    /// statement or taking the lock of a transaction, may be retried.
+    fn retry<T>(self, mut f: impl FnMut() -> rusqlite::Result<T>) -> Result<T> {
+        let mut retry = 0;
+
+        loop {
+            match f() {
+                Err(e)
+                    if e.sqlite_error_code() == Some(ErrorCode::DatabaseBusy)
+                        && retry < self.max_retries =>
+                {
+                    thread::sleep(self.delay(retry));
+                    retry += 1;
+                }
+                result => return result.map_err(Into::into),
+            }
+        }
+    }
+}
+
+/// The retry policies of the connections configured with [`ConnectionOptions`], by the address of
+/// their SQLite handle.
+///
+/// A `rusqlite` connection cannot carry data of its own, and its handle is what identifies it for
+/// as long as it is open. Either kind of configuration stores a policy, default or not, so an
+/// entry left behind by a closed connection is replaced before a new connection that reuses its
+/// handle writes anything.
+static RETRY_POLICIES: Mutex<BTreeMap<usize, RetryPolicy>> = Mutex::new(BTreeMap::new());
+
+/// Returns the address of the SQLite handle of a connection, which no other open connection
+/// shares.
+fn handle_address(conn: &Connection) -> usize {
+    // SAFETY: The handle is only used as a key, and is never dereferenced
+    unsafe { conn.handle() }.addr()
+}
+
+/// Settings applied to every connection when it is opened.
+///
+/// The defaults suit a desktop app that writes small transactions while the UI reads:
+/// write-ahead logging with [`Synchronous::Normal`], a five second busy timeout with the default
+/// [`RetryPolicy`], and enforced foreign keys.
+///
+/// # Examples
+///
+/// ```no_run
+/// use lib_profit_taker_database::connection::{ConnectionOptions, Synchronous};
+///
+/// let conn = ConnectionOptions::new()
+///     .synchronous(Synchronous::Full)
+///     .open("runs.sqlite")?;
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionOptions {
+    /// The value of the `journal_mode` pragma.
+    journal_mode: JournalMode,
+
+    /// The value of the `synchronous` pragma.
+    synchronous: Synchronous,
+
+    /// How long to wait for another connection's write lock.
+    busy_timeout: Duration,
+
+    /// How writes through the connection are retried once the busy timeout runs out.
+    retry_policy: RetryPolicy,
+
+    /// Whether foreign key constraints are enforced.
+    foreign_keys: bool,
+
+    /// How many prepared statements are kept compiled.
+    statement_cache_capacity: usize,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConnectionOptions {
+    /// Creates the default options.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            journal_mode: JournalMode::Wal,
+            synchronous: Synchronous::Normal,
+            busy_timeout: Duration::from_secs(5),
+            retry_policy: RetryPolicy::new(),
+            foreign_keys: true,
+            statement_cache_capacity: STATEMENT_CACHE_CAPACITY,
+        }
+    }
+
+    /// Sets how SQLite records changes before writing them to the database file.
+    ///
+    /// In-memory databases always use [`JournalMode::Memory`] or [`JournalMode::Off`], and
+    /// ignore other modes.
+    #[must_use]
+    pub const fn journal_mode(mut self, journal_mode: JournalMode) -> Self {
+        self.journal_mode = journal_mode;
+        self
+    }
+
+    /// Sets how often SQLite waits for written data to reach the disk.
+    #[must_use]
+    pub const fn synchronous(mut self, synchronous: Synchronous) -> Self {
+        self.synchronous = synchronous;
+        self
+    }
+
+    /// Sets how long a connection waits for another connection's write lock before giving up.
+    #[must_use]
+    pub const fn busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout = busy_timeout;
+        self
+    }
+
+    /// Sets how writes through the connection are retried when another connection still holds
+    /// the write lock once the busy timeout runs out, such as a second instance of the app or the
+    /// overlay.
+    #[must_use]
+    pub const fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets whether foreign key constraints are enforced.
+    #[must_use]
+    pub const fn foreign_keys(mut self, foreign_keys: bool) -> Self {
+        self.foreign_keys = foreign_keys;
+        self
+    }
+
+    /// Sets how many prepared statements the connection keeps compiled.
+    #[must_use]
+    pub const fn statement_cache_capacity(mut self, statement_cache_capacity: usize) -> Self {
+        self.statement_cache_capacity = statement_cache_capacity;
+        self
+    }
+
+    /// Opens the database at the given path with these options, creating the database file and
+    /// its directory structure if they do not exist, and applies any pending migrations.
+    ///
+    /// # Arguments
+    /// * `path` - The file path of the SQLite database.
+    ///
+    /// # Returns
+    /// * `Result<Connection>` - The configured connection to the up-to-date database.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::Io`] if the directory structure cannot be created,
+    /// [`DatabaseError::ConnectionFailed`] if the database cannot be opened or configured, or a
+    /// migration error if the schema cannot be brought up to date.
+    pub fn open(&self, path: &str) -> Result<Connection> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        #[cfg_attr(not(feature = "tracing"), expect(unused_mut))]
+        let mut conn = Connection::open(path).map_err(DatabaseError::ConnectionFailed)?;
+        #[cfg(feature = "tracing")]
+        crate::instrumentation::instrument(&mut conn);
+        self.apply(&conn)?;
+        initialize_schema(&conn)?;
+
+        Ok(conn)
+    }
+
+    /// Opens an existing database at the given path with these options, without ever writing to
+    /// it.
+    ///
+    /// No migrations are applied, so the database must already be at [`LATEST_VERSION`]. Any
+    /// attempt to write through the connection fails. The journal mode and synchronous level are
+    /// left as they are, since changing them would require writing to the database.
+    ///
+    /// # Arguments
+    /// * `path` - The file path of the SQLite database.
+    ///
+    /// # Returns
+    /// * `Result<Connection>` - The configured, read-only connection to the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::ConnectionFailed`] if the database does not exist or cannot be
+    /// opened or configured, or [`DatabaseError::SchemaVersionMismatch`] if its schema is older or
+    /// newer than the one this library expects.
+    pub fn open_read_only(&self, path: &str) -> Result<Connection> {
+        #[cfg_attr(not(feature = "tracing"), expect(unused_mut))]
+        let mut conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+                | OpenFlags::SQLITE_OPEN_URI
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(DatabaseError::ConnectionFailed)?;
+        #[cfg(feature = "tracing")]
+        crate::instrumentation::instrument(&mut conn);
+        self.configure_read_only(&conn).map_err(DatabaseError::ConnectionFailed)?;
+
+        let version = migrations::current_version(&conn)?;
+        if version != LATEST_VERSION {
+            return Err(DatabaseError::SchemaVersionMismatch {
+                expected: LATEST_VERSION,
+                found: version,
+            });
+        }
+
+        Ok(conn)
+    }
+
+    /// Opens the encrypted database at the given path with these options, as with `open`.
+    ///
+    /// If the database does not exist yet, it is created and encrypted with the given key.
+    ///
+    /// # Arguments
+    /// * `path` - The file path of the SQLite database.
+    /// * `key` - The passphrase the database is encrypted with.
+    ///
+    /// # Returns
+    /// * `Result<Connection>` - The configured connection to the up-to-date database.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::WrongKey`] if the database cannot be decrypted with the key, which
+    /// is also the case for a database that is not encrypted, or the same errors as `open`
+    /// otherwise.
+    #[cfg(feature = "encryption")]
+    pub fn open_encrypted(&self, path: &str, key: &str) -> Result<Connection> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        #[cfg_attr(not(feature = "tracing"), expect(unused_mut))]
+        let mut conn = Connection::open(path).map_err(DatabaseError::ConnectionFailed)?;
+        #[cfg(feature = "tracing")]
+        crate::instrumentation::instrument(&mut conn);
+        // Must come before anything else reads the database
+        conn.pragma_update(None, "key", key).map_err(DatabaseError::ConnectionFailed)?;
+        check_key(&conn)?;
+        self.apply(&conn)?;
+        initialize_schema(&conn)?;
+
+        Ok(conn)
+    }
+
+    /// Opens the database of the active profile with these options, as with `open`.
+    ///
+    /// # Arguments
+    /// * `profiles` - The profiles to open the active one of.
+    ///
+    /// # Returns
+    /// * `Result<Connection>` - The configured connection to the up-to-date database.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::Io`] if the active profile cannot be determined, or the same
+    /// errors as `open` otherwise.
+    pub fn open_active_profile(&self, profiles: &Profiles) -> Result<Connection> {
+        self.open(&profiles.active_path()?)
+    }
+
+    /// Opens a new, empty database in memory with these options, and applies every migration.
+    ///
+    /// # Returns
+    /// * `Result<Connection>` - The configured connection to the up-to-date database.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::ConnectionFailed`] if the database cannot be opened or configured,
+    /// or a migration error if the schema cannot be created.
+    pub fn open_in_memory(&self) -> Result<Connection> {
+        #[cfg_attr(not(feature = "tracing"), expect(unused_mut))]
+        let mut conn = Connection::open_in_memory().map_err(DatabaseError::ConnectionFailed)?;
+        #[cfg(feature = "tracing")]
+        crate::instrumentation::instrument(&mut conn);
+        self.apply(&conn)?;
+        initialize_schema(&conn)?;
+
+        Ok(conn)
+    }
+
+    /// Applies these options to an already open connection.
+    ///
+    /// # Arguments
+    /// * `conn` - A reference to the connection to configure.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::ConnectionFailed`] if a setting cannot be applied, for example
+    /// because the journal mode cannot be changed while another connection has the database open.
+    pub fn apply(&self, conn: &Connection) -> Result<()> {
+        self.configure(conn).map_err(DatabaseError::ConnectionFailed)
+    }
+
+    /// Runs `f` inside a transaction like [`transaction`], retrying to take the write lock and to
+    /// commit as set by the [`RetryPolicy`] of these options rather than that of the connection.
+    ///
+    /// # Arguments
+    /// * `conn` - A reference to the active SQLite database connection, usually opened with these
+    ///   options.
+    /// * `f` - The operations to perform inside the transaction.
+    ///
+    /// # Returns
+    /// * `Result<T, E>` - The value returned by `f`, or the first error encountered.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`transaction`].
+    pub fn transaction<T, E: From<DatabaseError>>(
+        &self,
+        conn: &Connection,
+        f: impl FnOnce(&Connection) -> Result<T, E>,
+    ) -> Result<T, E> {
+        transaction_with(conn, self.retry_policy, f)
+    }
+
+    /// Applies these options, returning the raw SQLite error on failure.
+    pub(crate) fn configure(&self, conn: &Connection) -> rusqlite::Result<()> {
+        // SQLite reports the resulting mode, which differs for in-memory databases; that is fine
+        conn.pragma_update_and_check(None, "journal_mode", self.journal_mode.to_sql(), |_| {
+            Ok(())
+        })?;
+        conn.pragma_update(None, "synchronous", self.synchronous.to_sql())?;
+        conn.busy_timeout(self.busy_timeout)?;
+        conn.pragma_update(None, "foreign_keys", self.foreign_keys)?;
+        conn.set_prepared_statement_cache_capacity(self.statement_cache_capacity);
+        self.retry_policy.store(conn);
+
+        Ok(())
+    }
+
+    /// Applies the options that do not require writing to the database.
+    fn configure_read_only(&self, conn: &Connection) -> rusqlite::Result<()> {
+        // Also rejects writes to temporary tables, which the read-only flag alone would allow
+        conn.pragma_update(None, "query_only", true)?;
+        conn.busy_timeout(self.busy_timeout)?;
+        conn.set_prepared_statement_cache_capacity(self.statement_cache_capacity);
+        self.retry_policy.store(conn);
+
+        Ok(())
+    }
+}
+
+/// Opens a new, empty database in memory with the default [`ConnectionOptions`], and applies
+/// every migration.
+///
+/// Nothing written to the database is ever saved to disk, and it is discarded when the connection
+/// is closed. This is useful for integration tests in other crates, and for a practice mode whose
+/// runs should not end up in the user's history.
+///
+/// # Returns
+/// * `Result<Connection>` - The connection to the up-to-date, empty database.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::ConnectionFailed`] if the database cannot be opened or configured,
+/// or a migration error if the schema cannot be created.
+pub fn open_in_memory() -> Result<Connection> {
+    ConnectionOptions::default().open_in_memory()
+}
+
+/// Opens the encrypted database at the given path with the default [`ConnectionOptions`],
+/// creating it if needed, and applies any pending migrations.
+///
+/// # Arguments
+/// * `path` - The file path of the SQLite database.
+/// * `key` - The passphrase the database is encrypted with, or will be if it is created.
+///
+/// # Returns
+/// * `Result<Connection>` - The connection to the up-to-date database.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::WrongKey`] if the database cannot be decrypted with the key, or the
+/// same errors as [`ConnectionOptions::open`] otherwise.
+#[cfg(feature = "encryption")]
+pub fn open_encrypted(path: &str, key: &str) -> Result<Connection> {
+    ConnectionOptions::default().open_encrypted(path, key)
+}
+
+/// Changes the key of an encrypted database, re-encrypting all of it.
+///
+/// # Arguments
+/// * `conn` - A connection opened with [`open_encrypted`].
+/// * `new_key` - The passphrase to encrypt the database with from now on.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be re-encrypted, in which case it keeps its old key.
+#[cfg(feature = "encryption")]
+pub fn rekey(conn: &Connection, new_key: &str) -> Result<()> {
+    conn.pragma_update(None, "rekey", new_key)?;
+
+    Ok(())
+}
+
+/// Returns [`DatabaseError::WrongKey`] if the database cannot be read with the key it was opened
+/// with.
+#[cfg(feature = "encryption")]
+fn check_key(conn: &Connection) -> Result<()> {
+    // Decryption only fails once a page is read, which the first query does
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(())) {
+        Err(e) if e.sqlite_error_code() == Some(rusqlite::ErrorCode::NotADatabase) => {
+            Err(DatabaseError::WrongKey)
+        }
+        result => result.map_err(DatabaseError::ConnectionFailed),
+    }
+}
+
+/// Opens an existing database at the given path with the default [`ConnectionOptions`], refusing
+/// to migrate it or write to it.
+///
+/// This is meant for inspecting a database shared by another player without risking changes to
+/// it, and for the overlay process, which reads the database while the app owns the writes.
+///
+/// # Arguments
+/// * `path` - The file path of the SQLite database.
+///
+/// # Returns
+/// * `Result<Connection>` - The read-only connection to the database.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::ConnectionFailed`] if the database does not exist or cannot be
+/// opened, or [`DatabaseError::SchemaVersionMismatch`] if its schema is not the one this library
+/// expects, since it cannot be migrated.
+pub fn open_read_only(path: &str) -> Result<Connection> {
+    ConnectionOptions::default().open_read_only(path)
+}
+
+/// Opens the database of the active profile with the default [`ConnectionOptions`], creating it
+/// if needed, and applies any pending migrations.
+///
+/// # Arguments
+/// * `profiles` - The profiles to open the active one of.
+///
+/// # Returns
+/// * `Result<Connection>` - The connection to the up-to-date database of the active profile.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Io`] if the active profile cannot be determined, or the same errors
+/// as [`ConnectionOptions::open`] otherwise.
+pub fn open_active_profile(profiles: &Profiles) -> Result<Connection> {
+    ConnectionOptions::default().open_active_profile(profiles)
+}
+
+/// The name of the directory next to the database that `open_with_recovery` looks for backups in.
+pub const BACKUP_DIR: &str = "backups";
+
+/// The name of the directory next to the database that `open_with_recovery` looks for JSON
+/// exports in.
+pub const EXPORT_DIR: &str = "exports";
+
+/// What `open_with_recovery` did to get a working database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recovery {
+    /// The database was healthy, so nothing had to be done.
+    NotNeeded,
+
+    /// The database was replaced with a backup.
+    RestoredBackup {
+        /// The path of the backup that was restored.
+        backup_path: String,
+    },
+
+    /// The database was replaced with a new one, into which the runs of the exports were
+    /// imported.
+    RebuiltFromExports {
+        /// The paths of the exports that were imported.
+        export_paths: Vec<String>,
+
+        /// The number of runs imported from them.
+        imported: usize,
+    },
+
+    /// The database was replaced with a new, empty one, as there was nothing to recover it from.
+    StartedEmpty,
+}
+
+/// What was wrong with the database when it was opened with `open_with_recovery`, and what was
+/// done about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// What was done to get a working database.
+    pub recovery: Recovery,
+
+    /// What was wrong with the database, or `None` if it was healthy.
+    pub problem: Option<String>,
+
+    /// Where the damaged database was moved to, so that it can still be attached to a bug report
+    /// or recovered by hand, or `None` if it was healthy.
+    pub damaged_path: Option<String>,
+}
+
+/// Opens the database at the given path like [`ConnectionOptions::open`], replacing it if it is
+/// damaged, so that the app never fails to start because of a bad database.
+///
+/// The database counts as damaged only if SQLite reports it as corrupt or as not being a
+/// database, or if a quick integrity check finds problems. Any other failure, such as a migration
+/// failing because the disk is full, is returned without touching the database. A damaged database is moved
+/// aside rather than deleted, and replaced with the newest backup in the [`BACKUP_DIR`] directory
+/// next to it that can be restored. If there is none, the runs of every JSON export in the
+/// [`EXPORT_DIR`] directory next to it are imported into a new database instead, and if there are
+/// no exports either, the new database is left empty.
+///
+/// # Arguments
+/// * `path` - The file path of the SQLite database.
+///
+/// # Returns
+/// * `Result<(Connection, RecoveryReport)>` - The connection to the working database, and what was
+///   done to get it.
 ///
-/// This function runs the schema creation queries stored in `SCHEMA_SQL` to set up the required tables and default values.
+/// # Errors
+///
+/// Returns the errors of [`ConnectionOptions::open`] that do not mean the database is damaged,
+/// such as [`DatabaseError::SchemaVersionMismatch`] for a database created by a newer version of
+/// this library or [`DatabaseError::MigrationFailed`] for an I/O error during a migration, which
+/// leave the database untouched. Returns [`DatabaseError::Io`] if the damaged database
+/// cannot be moved aside, or another error if the new database cannot be created.
+pub fn open_with_recovery(path: &str) -> Result<(Connection, RecoveryReport)> {
+    let problem = match open_checked(path) {
+        Ok(conn) => {
+            let report = RecoveryReport {
+                recovery: Recovery::NotNeeded,
+                problem: None,
+                damaged_path: None,
+            };
+            return Ok((conn, report));
+        }
+        Err(e) if is_damage(&e) => e.to_string(),
+        Err(e) => return Err(e),
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let damaged_path = format!("{path}.damaged-{timestamp}");
+    fs::rename(path, &damaged_path)?;
+    for suffix in ["-wal", "-shm"] {
+        // The log of the damaged database may hold some of its data, so it is kept along with it
+        if Path::new(&format!("{path}{suffix}")).exists() {
+            fs::rename(format!("{path}{suffix}"), format!("{damaged_path}{suffix}"))?;
+        }
+    }
+
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let mut conn = ConnectionOptions::default().open(path)?;
+    let mut recovery = Recovery::StartedEmpty;
+
+    for backup in files_newest_first(&dir.join(BACKUP_DIR), "sqlite") {
+        let backup_path = backup.to_string_lossy().into_owned();
+        if restore_from(&mut conn, &backup_path).is_ok() {
+            recovery = Recovery::RestoredBackup { backup_path };
+            break;
+        }
+
+        // A failed restore can leave the database half-written, so it is started over
+        drop(conn);
+        remove_database_files(path)?;
+        conn = ConnectionOptions::default().open(path)?;
+    }
+
+    if recovery == Recovery::StartedEmpty {
+        let mut export_paths = Vec::new();
+        let mut imported = 0;
+        for export in files_newest_first(&dir.join(EXPORT_DIR), "json") {
+            // An export that cannot be read is skipped, so that the others can still be imported
+            let report = fs::File::open(&export)
+                .map_err(DatabaseError::from)
+                .and_then(|file| {
+                    import_json(&conn, io::BufReader::new(file), ImportStrategy::Skip)
+                });
+            if let Ok(report) = report {
+                export_paths.push(export.to_string_lossy().into_owned());
+                imported += report.imported;
+            }
+        }
+
+        if !export_paths.is_empty() {
+            recovery = Recovery::RebuiltFromExports {
+                export_paths,
+                imported,
+            };
+        }
+    }
+
+    let report = RecoveryReport {
+        recovery,
+        problem: Some(problem),
+        damaged_path: Some(damaged_path),
+    };
+
+    Ok((conn, report))
+}
+
+/// Opens the database like [`ConnectionOptions::open`], then checks it for damage.
+fn open_checked(path: &str) -> Result<Connection> {
+    let conn = ConnectionOptions::default().open(path)?;
+
+    let problems = conn
+        .prepare("PRAGMA quick_check")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .filter(|message| !matches!(message.as_deref(), Ok("ok")))
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if !problems.is_empty() {
+        return Err(DatabaseError::IntegrityCheckFailed(problems.join("; ")));
+    }
+
+    Ok(conn)
+}
+
+/// Returns whether an error returned by `open_checked` means the database is damaged.
+fn is_damage(error: &DatabaseError) -> bool {
+    match error {
+        DatabaseError::ConnectionFailed(e)
+        | DatabaseError::MigrationFailed(e)
+        | DatabaseError::Sqlite(e) => matches!(
+            e.sqlite_error_code(),
+            Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase)
+        ),
+        DatabaseError::IntegrityCheckFailed(_) => true,
+        _ => false,
+    }
+}
+
+/// Lists the files with the given extension in a directory, most recently modified first.
+///
+/// A directory that does not exist or cannot be read has no files.
+fn files_newest_first(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            let path = entry.path();
+
+            path.extension()
+                .is_some_and(|found| found == extension)
+                .then_some((modified, path))
+        })
+        .collect::<Vec<_>>();
+    files.sort_unstable_by(|(a, _), (b, _)| b.cmp(a));
+
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Initializes the SQLite database schema by applying all pending migrations.
+///
+/// This function runs the migrations in `MIGRATIONS` that have not been applied yet to set up the
+/// required tables and default values. It is safe to call on an already up-to-date database.
 /// 
 /// # Arguments
 /// * `conn` - A reference to the active SQLite database connection.
 /// 
 /// # Returns
 /// * `Result<()>` - Returns `Ok(())` if the schema is successfully created, otherwise returns an error.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::MigrationFailed`] if a migration fails to apply, or
+/// [`DatabaseError::SchemaVersionMismatch`] if the database was created by a newer version of
+/// this library.
 pub fn initialize_schema(conn: &Connection) -> Result<()> {
-    conn.execute_batch(SCHEMA_SQL)?; // Execute the schema from the constant
+    migrations::migrate(conn)
+}
+
+/// Removes a database file along with the write-ahead log and shared memory files next to it.
+///
+/// The latter are left behind if the app was closed while a connection was open, so they may or
+/// may not exist.
+pub(crate) fn remove_database_files(path: &str) -> io::Result<()> {
+    fs::remove_file(path)?;
+    for suffix in ["-wal", "-shm"] {
+        match fs::remove_file(format!("{path}{suffix}")) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
 
     Ok(())
 }
+
+/// How many prepared statements each connection keeps compiled, keyed by their SQL text.
+///
+/// This comfortably fits every distinct statement of this library, so none of them are evicted
+/// and recompiled during normal use.
+pub const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+/// Runs `f` with a prepared statement for `sql`, compiling it only if the connection has not
+/// cached it already.
+///
+/// The statement is returned to the cache of the connection once `f` returns, so later calls
+/// with the same SQL text skip parsing and planning entirely.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `sql` - The SQL text of the statement.
+/// * `f` - The operations to perform with the statement.
+///
+/// # Returns
+/// * `Result<T>` - The value returned by `f`, or the error encountered preparing or running the
+///   statement.
+pub(crate) fn with_cached_stmt<T>(
+    conn: &Connection,
+    sql: &str,
+    f: impl FnOnce(&mut CachedStatement<'_>) -> rusqlite::Result<T>,
+) -> Result<T> {
+    let mut stmt = conn.prepare_cached(sql)?;
+
+    f(&mut stmt).map_err(Into::into)
+}
+
+/// Runs `f` inside a transaction, committing it if `f` succeeds and rolling it back if it fails.
+///
+/// This lets callers combine several operations of this library, such as inserting a run and
+/// updating related data, so that either all of them take effect or none do. If a transaction is
+/// already active on the connection, a savepoint nested inside it is used instead, so the
+/// operations are only committed along with the outer transaction.
+///
+/// A new transaction takes the write lock immediately, rather than when `f` first writes, so it
+/// cannot fail halfway through because another connection started writing in the meantime. If
+/// another connection still holds the lock once the busy timeout runs out, taking it is retried
+/// as set by the [`RetryPolicy`] of the connection, and so is committing. Use
+/// [`ConnectionOptions::transaction`] to retry with another policy.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `f` - The operations to perform inside the transaction. Their error type only has to be
+///   convertible from [`DatabaseError`], so callers can use their own error types.
+///
+/// # Returns
+/// * `Result<T, E>` - The value returned by `f`, or the first error encountered.
+///
+/// # Errors
+///
+/// Returns the error of `f`, or a [`DatabaseError`] if the transaction cannot be started or
+/// committed. In either case, nothing done by `f` is kept.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_core::Run;
+/// use lib_profit_taker_database::connection::{open_in_memory, transaction};
+/// use lib_profit_taker_database::insert::insert_run;
+/// use lib_profit_taker_database::update::set_favorite;
+///
+/// let conn = open_in_memory()?;
+/// transaction(&conn, |conn| {
+///     let run_id = insert_run(conn, &Run::new(0, 1_675_271_234, "Run #1", "Player1"))?;
+///     set_favorite(conn, run_id, true)
+/// })?;
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+pub fn transaction<T, E: From<DatabaseError>>(
+    conn: &Connection,
+    f: impl FnOnce(&Connection) -> Result<T, E>,
+) -> Result<T, E> {
+    transaction_with(conn, RetryPolicy::of(conn), f)
+}
+
+/// Runs `f` inside a transaction like `transaction`, retrying as set by the given policy.
+fn transaction_with<T, E: From<DatabaseError>>(
+    conn: &Connection,
+    policy: RetryPolicy,
+    f: impl FnOnce(&Connection) -> Result<T, E>,
+) -> Result<T, E> {
+    let (begin, commit, rollback) = if conn.is_autocommit() {
+        ("BEGIN IMMEDIATE", "COMMIT", "ROLLBACK")
+    } else {
+        (
+            "SAVEPOINT caller_transaction",
+            "RELEASE caller_transaction",
+            "ROLLBACK TO caller_transaction; RELEASE caller_transaction",
+        )
+    };
+    // Only a new transaction waits on other connections, so a savepoint is never retried
+    let is_new = conn.is_autocommit();
+    let execute = |sql| {
+        if is_new {
+            policy.retry(|| conn.execute_batch(sql))
+        } else {
+            conn.execute_batch(sql).map_err(DatabaseError::from)
+        }
+        .map_err(E::from)
+    };
+
+    execute(begin)?;
+
+    // A failed commit leaves the transaction open, so it is rolled back like a failure of `f`
+    match f(conn).and_then(|value| execute(commit).map(|()| value)) {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            // The original error is the one worth reporting, so a failure here is ignored
+            let _ = conn.execute_batch(rollback);
+            Err(e)
+        }
+    }
+}
+
+/// Runs `f` inside a named savepoint, releasing it on success and rolling it back on failure.
+///
+/// Unlike `BEGIN`, savepoints can be nested inside an outer transaction. This lets write
+/// operations be atomic on their own while still composing into larger transactions started by
+/// the caller. When no transaction is active, `f` runs in a new one started by `transaction`
+/// instead, so that it takes the write lock up front and retries as the transaction would.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `name` - The name of the savepoint. It is interpolated into the SQL, so it must be a plain
+///   identifier.
+/// * `f` - The operations to perform inside the savepoint.
+///
+/// # Returns
+/// * `Result<T>` - The value returned by `f`, or the first error encountered.
+pub(crate) fn with_savepoint<T>(
+    conn: &Connection,
+    name: &str,
+    f: impl FnOnce(&Connection) -> Result<T>,
+) -> Result<T> {
+    // Outside of a transaction, a savepoint would only take the write lock once `f` first writes,
+    // at which point SQLite fails immediately if another connection wrote since `f` started
+    // reading, without waiting for the busy timeout or allowing a retry
+    if conn.is_autocommit() {
+        return transaction(conn, f);
+    }
+
+    conn.execute_batch(&format!("SAVEPOINT {name}"))?;
+
+    match f(conn) {
+        Ok(value) => {
+            conn.execute_batch(&format!("RELEASE {name}"))?;
+            Ok(value)
+        }
+        Err(e) => {
+            // Undo everything since the savepoint, then discard it; the original error is the
+            // one worth reporting, so a failure here is deliberately ignored.
+            let _ = conn.execute_batch(&format!("ROLLBACK TO {name}; RELEASE {name}"));
+            Err(e)
+        }
+    }
+}
+
+/// Executes a single statement that writes, retrying it as set by the [`RetryPolicy`] of the
+/// connection for as long as it fails because another connection holds the write lock.
+///
+/// # Returns
+/// * `Result<usize>` - The number of rows that were changed.
+pub(crate) fn execute_retrying(
+    conn: &Connection,
+    sql: &str,
+    params: impl Params + Copy,
+) -> Result<usize> {
+    RetryPolicy::of(conn).retry(|| conn.prepare_cached(sql)?.execute(params))
+}
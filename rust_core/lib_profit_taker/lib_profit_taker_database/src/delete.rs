@@ -0,0 +1,145 @@
+//! This module provides functions for removing runs from the SQLite database.
+//!
+//! Runs can either be deleted permanently with `delete_run`, or moved to the trash with
+//! `soft_delete_run`. Trashed runs are hidden from run listings but can be brought back with
+//! `restore_run` until they are permanently removed by `purge_trashed`, giving users a grace
+//! period to undo accidental deletions.
+
+use rusqlite::Connection;
+use std::time::Duration;
+
+use crate::connection::{execute_retrying, with_savepoint};
+use crate::error::{DatabaseError, Result};
+
+/// Permanently deletes a run and all of its phases, shield changes, leg breaks, squad members,
+/// and tags.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to delete.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// a query fails. In either case, nothing is deleted.
+pub fn delete_run(conn: &Connection, run_id: i64) -> Result<()> {
+    with_savepoint(conn, "delete_run", |conn| {
+        delete_children(conn, run_id)?;
+
+        let deleted = conn
+            .prepare_cached("DELETE FROM runs WHERE id = ?1")?
+            .execute([run_id])?;
+        if deleted == 0 {
+            return Err(DatabaseError::RunNotFound(run_id));
+        }
+
+        Ok(())
+    })
+}
+
+/// Moves a run to the trash, hiding it from run listings without deleting any of its data.
+///
+/// Trashing a run that is already in the trash keeps its original deletion time.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to move to the trash.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// the query fails.
+pub fn soft_delete_run(conn: &Connection, run_id: i64) -> Result<()> {
+    let updated = execute_retrying(
+        conn,
+        "UPDATE runs SET deleted_at = COALESCE(deleted_at, unixepoch()) WHERE id = ?1",
+        [run_id],
+    )?;
+    if updated == 0 {
+        return Err(DatabaseError::RunNotFound(run_id));
+    }
+
+    Ok(())
+}
+
+/// Restores a run from the trash.
+///
+/// Restoring a run that is not in the trash does nothing.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to restore.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// the query fails.
+pub fn restore_run(conn: &Connection, run_id: i64) -> Result<()> {
+    let updated = execute_retrying(
+        conn,
+        "UPDATE runs SET deleted_at = NULL WHERE id = ?1",
+        [run_id],
+    )?;
+    if updated == 0 {
+        return Err(DatabaseError::RunNotFound(run_id));
+    }
+
+    Ok(())
+}
+
+/// Permanently deletes every run that has been in the trash for at least `older_than`.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `older_than` - How long a run must have been in the trash to be purged. A zero duration
+///   empties the trash entirely.
+///
+/// # Returns
+/// * `Result<usize>` - The number of runs that were deleted.
+///
+/// # Errors
+///
+/// Returns an error if a query fails, in which case nothing is deleted.
+pub fn purge_trashed(conn: &Connection, older_than: Duration) -> Result<usize> {
+    let older_than = i64::try_from(older_than.as_secs()).unwrap_or(i64::MAX);
+
+    with_savepoint(conn, "purge_trashed", |conn| {
+        let run_ids = conn
+            .prepare_cached(
+                "SELECT id FROM runs
+                WHERE deleted_at IS NOT NULL AND deleted_at <= unixepoch() - ?1",
+            )?
+            .query_map([older_than], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+        for &run_id in &run_ids {
+            delete_children(conn, run_id)?;
+            conn.prepare_cached("DELETE FROM runs WHERE id = ?1")?
+                .execute([run_id])?;
+        }
+
+        Ok(run_ids.len())
+    })
+}
+
+/// Deletes every row belonging to a run, without deleting the run itself.
+///
+/// The schema declares these relations with `ON DELETE CASCADE`, but SQLite only enforces that
+/// on connections with foreign keys enabled, so the children are deleted explicitly.
+fn delete_children(conn: &Connection, run_id: i64) -> Result<()> {
+    for table in [
+        "shield_changes",
+        "leg_breaks",
+        "phases",
+        "squad_members",
+        "tags",
+        "raw_logs",
+        "media",
+        "loadouts",
+    ] {
+        conn.prepare_cached(&format!("DELETE FROM {table} WHERE run_id = ?1"))?
+            .execute([run_id])?;
+    }
+
+    Ok(())
+}
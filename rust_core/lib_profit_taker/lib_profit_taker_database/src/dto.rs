@@ -0,0 +1,365 @@
+//! This module defines flat data transfer objects for the `flutter_rust_bridge` frontend API.
+//!
+//! The internal models nest their data and use enums that the bridge would have to mirror on the
+//! Dart side, so these structs flatten them into plain fields of numbers, strings, and lists,
+//! converted from the models with `From`. Each struct holds exactly what one screen needs:
+//!
+//! - `RunSummaryDto` for an entry of the run list.
+//! - `RunDetailDto`, with `PhaseDto`, `ShieldChangeDto`, and `LegBreakDto`, for the run screen.
+//! - `OverviewStatsDto` for the home screen.
+//!
+//! Every time is given as seconds in `f64`, like in the models, including the averages of
+//! `OverviewStatsDto`, which are converted from the [`Duration`](crate::duration::Duration)s of
+//! the analytics so that the frontend only ever deals with one unit.
+//!
+//! They also serialize to JSON as is, for the HTTP API of the `server` module.
+
+use lib_profit_taker_core::{LegBreak, Phase, Run, ShieldChange};
+use serde::Serialize;
+
+use crate::analytics::StatsOverview;
+use crate::fetch::shield_change_count;
+
+/// The fields of a run shown in the run list.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "each flag is an independent property of the run, mirroring its database columns"
+)]
+pub struct RunSummaryDto {
+    /// The ID of the run.
+    pub run_id: i64,
+
+    /// The name of the run.
+    pub run_name: String,
+
+    /// The Unix timestamp of when the run was started.
+    pub time_stamp: i64,
+
+    /// The name of the player who recorded the run.
+    pub player_name: String,
+
+    /// The total time of the run, in seconds.
+    pub total_time: f64,
+
+    /// Whether the run is bugged.
+    pub is_bugged_run: bool,
+
+    /// Whether the run was aborted.
+    pub is_aborted_run: bool,
+
+    /// Whether the run is a solo run.
+    pub is_solo_run: bool,
+
+    /// Whether the user marked the run as a favorite.
+    pub is_favorite: bool,
+
+    /// The number of phases the run has.
+    pub phase_count: usize,
+
+    /// The number of shield changes of the run, across all phases.
+    pub shield_change_count: usize,
+
+    /// The number of players in the squad, including the host.
+    pub squad_size: usize,
+}
+
+impl From<&Run> for RunSummaryDto {
+    fn from(run: &Run) -> Self {
+        Self {
+            run_id: run.run_id,
+            run_name: run.run_name.clone(),
+            time_stamp: run.time_stamp,
+            player_name: run.player_name.clone(),
+            total_time: run.total_times.total_time,
+            is_bugged_run: run.is_bugged_run,
+            is_aborted_run: run.is_aborted_run,
+            is_solo_run: run.is_solo_run,
+            is_favorite: run.is_favorite,
+            phase_count: run.phases.len(),
+            shield_change_count: shield_change_count(run),
+            squad_size: run.squad_members.len() + 1,
+        }
+    }
+}
+
+/// Every field of a run, shown on the run screen.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "each flag is an independent property of the run, mirroring its database columns"
+)]
+pub struct RunDetailDto {
+    /// The ID of the run.
+    pub run_id: i64,
+
+    /// The globally unique identifier of the run, if it has been stored.
+    pub run_uuid: Option<String>,
+
+    /// The Unix timestamp of when the run was started.
+    pub time_stamp: i64,
+
+    /// The offset from UTC, in seconds, of the local time where the run was played, if known.
+    pub utc_offset: Option<i32>,
+
+    /// The name of the run.
+    pub run_name: String,
+
+    /// The name of the player who recorded the run.
+    pub player_name: String,
+
+    /// The version of Warframe the run was played on, if known.
+    pub game_version: Option<String>,
+
+    /// The platform the run was played on, if known.
+    pub platform: Option<String>,
+
+    /// Whether the run is bugged.
+    pub is_bugged_run: bool,
+
+    /// Why the run is bugged, if known.
+    pub bugged_reason: Option<String>,
+
+    /// Whether the run was aborted.
+    pub is_aborted_run: bool,
+
+    /// Why the run was aborted, if known.
+    pub aborted_reason: Option<String>,
+
+    /// Whether the run is a solo run.
+    pub is_solo_run: bool,
+
+    /// Whether the user marked the run as a favorite.
+    pub is_favorite: bool,
+
+    /// The notes the user wrote about the run, if any.
+    pub notes: Option<String>,
+
+    /// The total time of the run, in seconds.
+    pub total_time: f64,
+
+    /// The time spent flying to Profit-Taker, in seconds.
+    pub total_flight_time: f64,
+
+    /// The time spent on shields over every phase, in seconds.
+    pub total_shield_time: f64,
+
+    /// The time spent on legs over every phase, in seconds.
+    pub total_leg_time: f64,
+
+    /// The time spent on the body over every phase, in seconds.
+    pub total_body_time: f64,
+
+    /// The time spent on pylons over every phase, in seconds.
+    pub total_pylon_time: f64,
+
+    /// The phases of the run, in order.
+    pub phases: Vec<PhaseDto>,
+
+    /// The names of the other members of the squad.
+    pub squad_members: Vec<String>,
+}
+
+impl From<&Run> for RunDetailDto {
+    fn from(run: &Run) -> Self {
+        Self {
+            run_id: run.run_id,
+            run_uuid: run.run_uuid.clone(),
+            time_stamp: run.time_stamp,
+            utc_offset: run.utc_offset,
+            run_name: run.run_name.clone(),
+            player_name: run.player_name.clone(),
+            game_version: run.game_version.clone(),
+            platform: run.platform.clone(),
+            is_bugged_run: run.is_bugged_run,
+            bugged_reason: run.bugged_reason.clone(),
+            is_aborted_run: run.is_aborted_run,
+            aborted_reason: run.aborted_reason.clone(),
+            is_solo_run: run.is_solo_run,
+            is_favorite: run.is_favorite,
+            notes: run.notes.clone(),
+            total_time: run.total_times.total_time,
+            total_flight_time: run.total_times.total_flight_time,
+            total_shield_time: run.total_times.total_shield_time,
+            total_leg_time: run.total_times.total_leg_time,
+            total_body_time: run.total_times.total_body_time,
+            total_pylon_time: run.total_times.total_pylon_time,
+            phases: run.phases.iter().map(PhaseDto::from).collect(),
+            squad_members: run
+                .squad_members
+                .iter()
+                .map(|member| member.member_name.clone())
+                .collect(),
+        }
+    }
+}
+
+/// A phase of a run, as part of a `RunDetailDto`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PhaseDto {
+    /// The number of the phase within the run, starting from 1.
+    pub phase_number: i32,
+
+    /// The total time of the phase, in seconds.
+    pub total_time: f64,
+
+    /// The time spent on shields, in seconds.
+    pub total_shield_time: f64,
+
+    /// The time spent on legs, in seconds.
+    pub total_leg_time: f64,
+
+    /// The time spent on the body, in seconds.
+    pub total_body_kill_time: f64,
+
+    /// The time spent on pylons, in seconds.
+    pub total_pylon_time: f64,
+
+    /// The shield changes of the phase, in order.
+    pub shield_changes: Vec<ShieldChangeDto>,
+
+    /// The leg breaks of the phase, in order.
+    pub leg_breaks: Vec<LegBreakDto>,
+}
+
+impl From<&Phase> for PhaseDto {
+    fn from(phase: &Phase) -> Self {
+        Self {
+            phase_number: phase.phase_number,
+            total_time: phase.total_time,
+            total_shield_time: phase.total_shield_time,
+            total_leg_time: phase.total_leg_time,
+            total_body_kill_time: phase.total_body_kill_time,
+            total_pylon_time: phase.total_pylon_time,
+            shield_changes: phase
+                .shield_changes
+                .iter()
+                .map(ShieldChangeDto::from)
+                .collect(),
+            leg_breaks: phase.leg_breaks.iter().map(LegBreakDto::from).collect(),
+        }
+    }
+}
+
+/// A shield change of a phase, as part of a `PhaseDto`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShieldChangeDto {
+    /// The time the shield was up, in seconds.
+    pub shield_time: f64,
+
+    /// The name of the status effect the shield was weak to, such as `"Impact"`.
+    pub status_effect: String,
+
+    /// Whether the shield was an overshield.
+    pub is_overshield: bool,
+}
+
+impl From<&ShieldChange> for ShieldChangeDto {
+    fn from(shield_change: &ShieldChange) -> Self {
+        Self {
+            shield_time: shield_change.shield_time,
+            status_effect: shield_change.status_effect.to_string().to_string(),
+            is_overshield: shield_change.is_overshield,
+        }
+    }
+}
+
+/// A leg break of a phase, as part of a `PhaseDto`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LegBreakDto {
+    /// The time it took to break the leg, in seconds.
+    pub leg_break_time: f64,
+
+    /// The name of the position of the leg, such as `"FrontLeft"`.
+    pub leg_position: String,
+
+    /// The order the leg was broken in within the phase, starting from 1.
+    pub leg_order: i32,
+}
+
+impl From<&LegBreak> for LegBreakDto {
+    fn from(leg_break: &LegBreak) -> Self {
+        Self {
+            leg_break_time: leg_break.leg_break_time,
+            leg_position: leg_break.leg_position.to_string().to_string(),
+            leg_order: leg_break.leg_order,
+        }
+    }
+}
+
+/// The overview of every valid run shown on the home screen.
+///
+/// Every field is zero if there are no valid runs, so the screen does not have to handle a
+/// missing overview separately.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct OverviewStatsDto {
+    /// The number of valid runs.
+    pub run_count: usize,
+
+    /// The sum of the total times of the runs, in seconds.
+    pub total_time_sum: f64,
+
+    /// The mean total time of the runs, in seconds.
+    pub total_time_mean: f64,
+
+    /// The best total time of the runs, in seconds.
+    pub total_time_best: f64,
+
+    /// The mean flight time of the runs, in seconds.
+    pub flight_time_mean: f64,
+
+    /// The best flight time of the runs, in seconds.
+    pub flight_time_best: f64,
+
+    /// The mean shield time of the runs, in seconds.
+    pub shield_time_mean: f64,
+
+    /// The best shield time of the runs, in seconds.
+    pub shield_time_best: f64,
+
+    /// The mean leg time of the runs, in seconds.
+    pub leg_time_mean: f64,
+
+    /// The best leg time of the runs, in seconds.
+    pub leg_time_best: f64,
+
+    /// The mean body time of the runs, in seconds.
+    pub body_time_mean: f64,
+
+    /// The best body time of the runs, in seconds.
+    pub body_time_best: f64,
+
+    /// The mean pylon time of the runs, in seconds.
+    pub pylon_time_mean: f64,
+
+    /// The best pylon time of the runs, in seconds.
+    pub pylon_time_best: f64,
+}
+
+impl From<StatsOverview> for OverviewStatsDto {
+    fn from(overview: StatsOverview) -> Self {
+        Self {
+            run_count: overview.run_count,
+            total_time_sum: overview.total_time.sum.as_secs_f64(),
+            total_time_mean: overview.total_time.mean.as_secs_f64(),
+            total_time_best: overview.total_time.best.as_secs_f64(),
+            flight_time_mean: overview.flight_time.mean.as_secs_f64(),
+            flight_time_best: overview.flight_time.best.as_secs_f64(),
+            shield_time_mean: overview.shield_time.mean.as_secs_f64(),
+            shield_time_best: overview.shield_time.best.as_secs_f64(),
+            leg_time_mean: overview.leg_time.mean.as_secs_f64(),
+            leg_time_best: overview.leg_time.best.as_secs_f64(),
+            body_time_mean: overview.body_time.mean.as_secs_f64(),
+            body_time_best: overview.body_time.best.as_secs_f64(),
+            pylon_time_mean: overview.pylon_time.mean.as_secs_f64(),
+            pylon_time_best: overview.pylon_time.best.as_secs_f64(),
+        }
+    }
+}
+
+impl From<Option<StatsOverview>> for OverviewStatsDto {
+    /// Converts the result of `cached_overview`, which is `None` if there are no valid runs.
+    fn from(overview: Option<StatsOverview>) -> Self {
+        overview.map_or_else(Self::default, Self::from)
+    }
+}
@@ -0,0 +1,187 @@
+//! This module provides [`Duration`], a length of time counted in whole milliseconds.
+//!
+//! The parser measures times as floating-point seconds, which rarely land on an exact
+//! millisecond: a phase of 12.345 seconds may be recorded as 12.344999, shown as 12.344 if it is
+//! truncated, and sorted before a phase that was really just as fast. Every duration is therefore
+//! rounded to the nearest millisecond before it is stored, and read back through [`Duration`], so
+//! that equal times compare as equal and are always shown the same way.
+//!
+//! Every duration column holds whole milliseconds, and [`Duration`] is how they are used as query
+//! parameters and read from rows, including the averages SQLite computes from them. The models of
+//! the parser still measure seconds, so `insert` and `fetch` convert at that boundary, and
+//! `format_seconds` and `format_clock` format durations for display.
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Sub};
+
+/// The number of milliseconds in a minute.
+const MILLIS_PER_MINUTE: u64 = 60_000;
+
+/// A length of time, counted in whole milliseconds.
+///
+/// A duration is negative when it is the difference between a time and a longer one, such as how
+/// far ahead of a personal best a run is.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Duration(i64);
+
+impl Duration {
+    /// A duration of no time at all.
+    pub const ZERO: Self = Self(0);
+
+    /// Creates a duration from a number of milliseconds.
+    #[must_use]
+    pub const fn from_millis(millis: i64) -> Self {
+        Self(millis)
+    }
+
+    /// Creates a duration from a number of seconds, rounded to the nearest millisecond.
+    ///
+    /// Times that are not a number are treated as no time at all, and times too long to count in
+    /// milliseconds are clamped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib_profit_taker_database::duration::Duration;
+    ///
+    /// assert_eq!(Duration::from_secs_f64(12.344_999_9).as_millis(), 12_345);
+    /// assert_eq!(Duration::from_secs_f64(0.000_4).as_millis(), 0);
+    /// ```
+    #[must_use]
+    pub fn from_secs_f64(seconds: f64) -> Self {
+        Self::from_millis_f64(seconds * 1000.0)
+    }
+
+    /// Creates a duration from a fractional number of milliseconds, such as an average, rounded to
+    /// the nearest millisecond.
+    #[must_use]
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "float to integer casts saturate, and no run lasts anywhere near that long"
+    )]
+    pub const fn from_millis_f64(millis: f64) -> Self {
+        Self(millis.round() as i64)
+    }
+
+    /// Returns the number of whole milliseconds in this duration.
+    #[must_use]
+    pub const fn as_millis(self) -> i64 {
+        self.0
+    }
+
+    /// Returns this duration as a number of seconds, which is as close to a whole number of
+    /// milliseconds as a floating-point number can be.
+    #[must_use]
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "no run lasts anywhere near 2^52 milliseconds"
+    )]
+    pub fn as_secs_f64(self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+
+    /// Returns the absolute value of this duration.
+    #[must_use]
+    pub const fn abs(self) -> Self {
+        Self(self.0.abs())
+    }
+
+    /// Formats this duration as seconds with exactly three decimal places, such as `"72.345"`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib_profit_taker_database::duration::Duration;
+    ///
+    /// assert_eq!(Duration::from_millis(72_345).format_seconds(), "72.345");
+    /// assert_eq!(Duration::from_millis(-50).format_seconds(), "-0.050");
+    /// ```
+    #[must_use]
+    pub fn format_seconds(self) -> String {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let millis = self.0.unsigned_abs();
+
+        format!("{sign}{}.{:03}", millis / 1000, millis % 1000)
+    }
+
+    /// Formats this duration as minutes and seconds with exactly three decimal places, such as
+    /// `"1:12.345"`, leaving out the minutes if there are none.
+    ///
+    /// This is also how durations are formatted by [`Display`](fmt::Display).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib_profit_taker_database::duration::Duration;
+    ///
+    /// assert_eq!(Duration::from_millis(72_345).format_clock(), "1:12.345");
+    /// assert_eq!(Duration::from_millis(60_005).format_clock(), "1:00.005");
+    /// assert_eq!(Duration::from_millis(9_870).format_clock(), "9.870");
+    /// ```
+    #[must_use]
+    pub fn format_clock(self) -> String {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let millis = self.0.unsigned_abs();
+        let minutes = millis / MILLIS_PER_MINUTE;
+        let millis = millis % MILLIS_PER_MINUTE;
+
+        if minutes == 0 {
+            format!("{sign}{}.{:03}", millis / 1000, millis % 1000)
+        } else {
+            format!("{sign}{minutes}:{:02}.{:03}", millis / 1000, millis % 1000)
+        }
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format_clock())
+    }
+}
+
+impl Add for Duration {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl Sub for Duration {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Sum for Duration {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+/// Stores the duration as a number of milliseconds, like every duration column.
+impl ToSql for Duration {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0))
+    }
+}
+
+/// Reads a duration from a number of milliseconds, rounding it to a whole millisecond if it has a
+/// fraction, as averages do.
+impl FromSql for Duration {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Integer(millis) => Ok(Self(millis)),
+            ValueRef::Real(millis) => Ok(Self::from_millis_f64(millis)),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! This module defines the error type returned by the public API of this library.
+//!
+//! Rather than exposing raw `rusqlite` errors, every fallible function returns a
+//! [`DatabaseError`], which distinguishes the failures callers are likely to want to handle
+//! (such as a missing run or a constraint violation) from unexpected SQLite errors.
+
+use lib_profit_taker_core::ModelError;
+use rusqlite::ErrorCode;
+use thiserror::Error;
+
+use crate::validation::ValidationWarning;
+
+/// A specialized `Result` type for database operations.
+pub type Result<T, E = DatabaseError> = std::result::Result<T, E>;
+
+/// Represents the ways in which a database operation can fail.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum DatabaseError {
+    /// The database file could not be opened.
+    #[error("failed to open the database: {0}")]
+    ConnectionFailed(#[source] rusqlite::Error),
+
+    /// A connection could not be retrieved from a connection pool.
+    #[error("failed to get a connection from the pool: {0}")]
+    PoolFailed(#[from] r2d2::Error),
+
+    /// The shared connection manager was used before a database was opened with it.
+    #[error("no database has been opened yet")]
+    NotInitialized,
+
+    /// The shared connection manager was asked to open a database while another was still open.
+    #[error("the database `{0}` is already open")]
+    AlreadyInitialized(String),
+
+    /// An encrypted database could not be read with the given key.
+    #[cfg(feature = "encryption")]
+    #[error("the key does not match the key the database was encrypted with")]
+    WrongKey,
+
+    /// The database schema could not be created or upgraded.
+    #[error("failed to migrate the database schema: {0}")]
+    MigrationFailed(#[source] rusqlite::Error),
+
+    /// The integrity check of the database found problems, which are listed.
+    #[error("the database failed its integrity check: {0}")]
+    IntegrityCheckFailed(String),
+
+    /// The database schema version does not match the version this library expects.
+    #[error("expected schema version {expected}, found version {found}")]
+    SchemaVersionMismatch {
+        /// The schema version this library expects.
+        expected: u32,
+
+        /// The schema version found in the database.
+        found: u32,
+    },
+
+    /// No migration to the given schema version exists.
+    #[error("no migration to schema version {0} exists")]
+    UnknownSchemaVersion(u32),
+
+    /// No run with the given ID exists.
+    #[error("no run with ID {0} exists")]
+    RunNotFound(i64),
+
+    /// No player with the given ID exists.
+    #[error("no player with ID {0} exists")]
+    PlayerNotFound(i64),
+
+    /// No profile with the given name exists.
+    #[error("no profile named \"{0}\" exists")]
+    ProfileNotFound(String),
+
+    /// A profile could not be created because a profile with the same name already exists.
+    #[error("a profile named \"{0}\" already exists")]
+    ProfileExists(String),
+
+    /// A write was rejected because it would violate a constraint, such as a primary key or a
+    /// foreign key.
+    #[error("constraint violation: {0}")]
+    ConstraintViolation(#[source] rusqlite::Error),
+
+    /// A filesystem operation failed, for example while creating the database directory.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Data could not be serialized to or deserialized from JSON.
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// Data could not be written as CSV.
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
+    /// An imported file was written in a newer format than this library understands.
+    #[error("unsupported format version {0}")]
+    UnsupportedFormatVersion(u32),
+
+    /// Data being read or imported is malformed or inconsistent.
+    #[error("invalid data: {0}")]
+    InvalidData(String),
+
+    /// A run was rejected because it failed validation.
+    #[error("the run failed validation with {} warnings", .0.len())]
+    InvalidRun(Vec<ValidationWarning>),
+
+    /// A background thread stopped before finishing a task, for example because the task
+    /// panicked.
+    #[error("a background task stopped before it finished")]
+    TaskFailed,
+
+    /// Any other SQLite error.
+    #[error("SQLite error: {0}")]
+    Sqlite(#[source] rusqlite::Error),
+}
+
+impl From<rusqlite::Error> for DatabaseError {
+    /// Wraps a `rusqlite` error, singling out constraint violations.
+    fn from(error: rusqlite::Error) -> Self {
+        match error.sqlite_error_code() {
+            Some(ErrorCode::ConstraintViolation) => Self::ConstraintViolation(error),
+            _ => Self::Sqlite(error),
+        }
+    }
+}
+
+impl From<ModelError> for DatabaseError {
+    /// Reports a model that could not be constructed as invalid data.
+    fn from(error: ModelError) -> Self {
+        Self::InvalidData(error.to_string())
+    }
+}
@@ -0,0 +1,117 @@
+//! This module provides notifications for when runs are inserted, updated, or deleted, so that the
+//! frontend can refresh the run list when something changes instead of polling the database.
+//!
+//! Notifications are built on SQLite's update hooks, so they are tied to a single connection: only
+//! changes made through the connection passed to `watch_runs` or `run_events` are reported.
+//! Changes are buffered until the transaction that made them commits, and are discarded if it is
+//! rolled back, so listeners never see a run that does not exist.
+//!
+//! Only changes to the runs themselves are reported. Moving a run to the trash, restoring it,
+//! favoriting it, and editing its name or note are all reported as [`RunEvent::Updated`], but
+//! adding or removing a tag is not.
+
+use std::mem;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use rusqlite::hooks::Action;
+use rusqlite::Connection;
+
+/// A change to a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RunEvent {
+    /// The run with the given ID was inserted.
+    Inserted(i64),
+
+    /// The run with the given ID was updated.
+    Updated(i64),
+
+    /// The run with the given ID was permanently deleted.
+    Deleted(i64),
+}
+
+/// Calls `callback` for every change to a run made through `conn`, once the change is committed.
+///
+/// A connection can only have one listener, so this replaces any listener registered before, and
+/// any receiver returned by `run_events` stops receiving events.
+///
+/// # Arguments
+/// * `conn` - A reference to the SQLite database connection to watch.
+/// * `callback` - The function to call for each change. It is called while SQLite is committing the
+///   transaction, so it must not use `conn`, and should return quickly.
+pub fn watch_runs<F>(conn: &Connection, mut callback: F)
+where
+    F: FnMut(RunEvent) + Send + 'static,
+{
+    let pending = Arc::new(Mutex::new(Vec::new()));
+
+    let buffer = Arc::clone(&pending);
+    conn.update_hook(Some(
+        move |action: Action, database: &str, table: &str, run_id: i64| {
+            if database != "main" || table != "runs" {
+                return;
+            }
+
+            let event = match action {
+                Action::SQLITE_INSERT => RunEvent::Inserted(run_id),
+                Action::SQLITE_UPDATE => RunEvent::Updated(run_id),
+                Action::SQLITE_DELETE => RunEvent::Deleted(run_id),
+                _ => return,
+            };
+            buffer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(event);
+        },
+    ));
+
+    let committed = Arc::clone(&pending);
+    conn.commit_hook(Some(move || {
+        let events = mem::take(&mut *committed.lock().unwrap_or_else(PoisonError::into_inner));
+        events.into_iter().for_each(&mut callback);
+
+        // Returning `true` would turn the commit into a rollback
+        false
+    }));
+
+    conn.rollback_hook(Some(move || {
+        pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }));
+}
+
+/// Returns a channel that receives every change to a run made through `conn`, once the change is
+/// committed.
+///
+/// This is a convenience wrapper around `watch_runs`, and likewise replaces any listener
+/// registered on `conn` before.
+///
+/// # Arguments
+/// * `conn` - A reference to the SQLite database connection to watch.
+///
+/// # Returns
+/// * `Receiver<RunEvent>` - The receiving end of the channel. Once it is dropped, events are
+///   silently discarded until `unwatch_runs` is called or another listener is registered.
+#[must_use]
+pub fn run_events(conn: &Connection) -> Receiver<RunEvent> {
+    let (sender, receiver) = mpsc::channel();
+    watch_runs(conn, move |event| {
+        // The receiver having been dropped just means nobody is listening anymore
+        let _ = sender.send(event);
+    });
+
+    receiver
+}
+
+/// Stops reporting changes made through `conn` to the listener registered by `watch_runs` or
+/// `run_events`.
+///
+/// # Arguments
+/// * `conn` - A reference to the SQLite database connection to stop watching.
+pub fn unwatch_runs(conn: &Connection) {
+    conn.update_hook(None::<fn(Action, &str, &str, i64)>);
+    conn.commit_hook(None::<fn() -> bool>);
+    conn.rollback_hook(None::<fn()>);
+}
@@ -0,0 +1,1200 @@
+//! This module provides functions for exporting runs out of the SQLite database, so they can be
+//! shared with teammates or attached to bug reports.
+//!
+//! Runs can also be exported as CSV with `export_csv`, with one row per run and a configurable set
+//! of [`CsvColumn`]s, for analysis in a spreadsheet, or as a separate database containing only a
+//! selection of runs with `export_selection`. A single run can also be exported as a LiveSplit
+//! splits file with `export_livesplit`, to race against it in LiveSplit. The statistics of every
+//! run can be summarized with `stats_card`, as a compact JSON [`StatsCard`] for the frontend to
+//! render as a shareable image.
+//!
+//! A single run can also be encoded as a short sharing code with `encode_share_code`, to paste
+//! into a chat message.
+//!
+//! Every export but the LiveSplit one takes [`ExportOptions`], which can anonymize the exported
+//! runs so they can be shared publicly without revealing who played them or when.
+//!
+//! # JSON format
+//!
+//! Runs are exported as an [`ExportFile`], which looks like this:
+//!
+//! ```json
+//! {
+//!   "format_version": 1,
+//!   "runs": [
+//!     {
+//!       "run_id": 1,
+//!       "run_uuid": "3f2b6c1e-8d4a-4c7e-9b1f-5a6d2e8c7b40",
+//!       "checksum": "8c3a5f0e2d41b976",
+//!       "time_stamp": 1675271234,
+//!       "utc_offset": 3600,
+//!       "run_name": "Run #1",
+//!       "player_name": "Player1",
+//!       "game_version": "2024.12.18.14.38",
+//!       "platform": "PC",
+//!       "is_bugged_run": false,
+//!       "bugged_reason": null,
+//!       "is_aborted_run": false,
+//!       "aborted_reason": null,
+//!       "is_solo_run": true,
+//!       "is_favorite": false,
+//!       "notes": "New strat, went well",
+//!       "total_times": {
+//!         "total_time": 52.1,
+//!         "total_flight_time": 4.2,
+//!         "total_shield_time": 12.3,
+//!         "total_leg_time": 10.4,
+//!         "total_body_time": 3.5,
+//!         "total_pylon_time": 14.6
+//!       },
+//!       "phases": [
+//!         {
+//!           "phase_number": 1,
+//!           "total_time": 12.0,
+//!           "total_shield_time": 5.1,
+//!           "total_leg_time": 4.2,
+//!           "total_body_kill_time": 2.7,
+//!           "total_pylon_time": 0.0,
+//!           "shield_changes": [
+//!             { "shield_time": 1.2, "status_effect": "Heat", "is_overshield": false }
+//!           ],
+//!           "leg_breaks": [{ "leg_break_time": 1.1, "leg_position": "FrontLeft", "leg_order": 1 }]
+//!         }
+//!       ],
+//!       "squad_members": []
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Times are in seconds and timestamps are Unix timestamps. Status effects and leg positions use
+//! the names of the [`StatusEffect`] and [`LegPosition`] variants. `run_id` is the ID the run had
+//! in the exporting database, and is only informational, while `run_uuid` identifies the run in
+//! every database it ends up in. `checksum` is the [checksum](crate::checksum) of the timing data
+//! of the run, used to tell whether it was edited since it was exported.
+//!
+//! `format_version` is [`FORMAT_VERSION`], and is incremented whenever the format changes in a
+//! way that older readers could not understand. Files in this format can be read back with
+//! [`import_json`](crate::import::import_json).
+
+use chrono::DateTime;
+use lib_profit_taker_core::{
+    LegBreak, LegPosition, Phase, Run, ShieldChange, StatusEffect, TotalTimes,
+};
+use rusqlite::{params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::analytics::{cached_overview, fetch_pb, shield_element_stats, sum_of_best, RunCategory};
+use crate::base64;
+use crate::checksum::run_checksum;
+use crate::connection::{initialize_schema, transaction};
+use crate::duration::Duration;
+use crate::error::{DatabaseError, Result};
+use crate::fetch::{fetch_raw_log, fetch_run_by_id, iter_runs, RunFilter};
+use crate::insert::{insert_run, store_raw_log};
+use crate::loadouts::{fetch_loadout, set_loadout};
+use crate::media::{add_media, fetch_media};
+use crate::tags::{add_tag, fetch_tags};
+
+/// The version of the JSON format written by this module.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The top-level object of an exported JSON file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportFile {
+    /// The version of the format the file was written in.
+    pub format_version: u32,
+
+    /// The exported runs, oldest first.
+    pub runs: Vec<ExportedRun>,
+}
+
+/// A run, as represented in an exported JSON file.
+#[derive(Debug, Serialize, Deserialize)]
+#[expect(clippy::struct_excessive_bools, reason = "mirrors the flags of `Run`")]
+pub struct ExportedRun {
+    /// The ID of the run in the exporting database.
+    pub run_id: i64,
+
+    /// The globally unique identifier of the run. Files written before runs had UUIDs do not have
+    /// this field.
+    #[serde(default)]
+    pub run_uuid: Option<String>,
+
+    /// The checksum of the timing data of the run, as computed by
+    /// [`run_checksum`](crate::checksum::run_checksum). Files written before runs had checksums
+    /// do not have this field.
+    #[serde(default)]
+    pub checksum: Option<String>,
+
+    /// The Unix timestamp indicating when the run was started.
+    pub time_stamp: i64,
+
+    /// The offset from UTC, in seconds, of the local time where the run was played. Files
+    /// written before offsets were recorded do not have this field.
+    #[serde(default)]
+    pub utc_offset: Option<i32>,
+
+    /// The name of the run.
+    pub run_name: String,
+
+    /// The name of the player who initiated the run.
+    pub player_name: String,
+
+    /// The version of Warframe the run was played on, if known. Files written before game
+    /// versions were recorded do not have this field.
+    #[serde(default)]
+    pub game_version: Option<String>,
+
+    /// The platform the run was played on, if known. Files written before platforms were
+    /// recorded do not have this field.
+    #[serde(default)]
+    pub platform: Option<String>,
+
+    /// Whether the run is bugged.
+    pub is_bugged_run: bool,
+
+    /// Why the run is bugged, if known. Files written before reasons were recorded do not have
+    /// this field.
+    #[serde(default)]
+    pub bugged_reason: Option<String>,
+
+    /// Whether the run was aborted.
+    pub is_aborted_run: bool,
+
+    /// Why the run was aborted, if known. Files written before reasons were recorded do not have
+    /// this field.
+    #[serde(default)]
+    pub aborted_reason: Option<String>,
+
+    /// Whether the run is a solo run.
+    pub is_solo_run: bool,
+
+    /// Whether the run was marked as a favorite.
+    pub is_favorite: bool,
+
+    /// The notes the user wrote about the run, if any. Files written before notes existed do not
+    /// have this field.
+    #[serde(default)]
+    pub notes: Option<String>,
+
+    /// The total times of the run.
+    pub total_times: ExportedTotalTimes,
+
+    /// The phases of the run, ordered by phase number.
+    pub phases: Vec<ExportedPhase>,
+
+    /// The names of the squad members participating in the run.
+    pub squad_members: Vec<String>,
+}
+
+/// The total times of a run, as represented in an exported JSON file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedTotalTimes {
+    /// The total time of the run.
+    pub total_time: f64,
+
+    /// The total time spent in flight.
+    pub total_flight_time: f64,
+
+    /// The total time spent on shields.
+    pub total_shield_time: f64,
+
+    /// The total time spent on legs.
+    pub total_leg_time: f64,
+
+    /// The total time spent on the body.
+    pub total_body_time: f64,
+
+    /// The total time spent on pylons.
+    pub total_pylon_time: f64,
+}
+
+/// A phase, as represented in an exported JSON file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedPhase {
+    /// The number of the phase within the run.
+    pub phase_number: i32,
+
+    /// The total time of the phase.
+    pub total_time: f64,
+
+    /// The time spent on shields during the phase.
+    pub total_shield_time: f64,
+
+    /// The time spent on legs during the phase.
+    pub total_leg_time: f64,
+
+    /// The time spent on the body kill during the phase.
+    pub total_body_kill_time: f64,
+
+    /// The time spent on pylons during the phase.
+    pub total_pylon_time: f64,
+
+    /// The shield changes of the phase, in the order they occurred.
+    pub shield_changes: Vec<ExportedShieldChange>,
+
+    /// The leg breaks of the phase, in the order they occurred.
+    pub leg_breaks: Vec<ExportedLegBreak>,
+}
+
+/// A shield change, as represented in an exported JSON file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedShieldChange {
+    /// The time of the shield change.
+    pub shield_time: f64,
+
+    /// The name of the status effect of the shield change.
+    pub status_effect: String,
+
+    /// Whether the shield was stripped with overshields. Missing from files exported before it
+    /// was recorded, in which case it is assumed to be `false`.
+    #[serde(default)]
+    pub is_overshield: bool,
+}
+
+/// A leg break, as represented in an exported JSON file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedLegBreak {
+    /// The time it took to break the leg.
+    pub leg_break_time: f64,
+
+    /// The name of the position of the leg.
+    pub leg_position: String,
+
+    /// The order in which the leg was broken, starting from 1.
+    pub leg_order: i32,
+}
+
+impl From<&Run> for ExportedRun {
+    fn from(run: &Run) -> Self {
+        Self {
+            run_id: run.run_id,
+            run_uuid: run.run_uuid.clone(),
+            checksum: Some(run_checksum(run)),
+            time_stamp: run.time_stamp,
+            utc_offset: run.utc_offset,
+            run_name: run.run_name.clone(),
+            player_name: run.player_name.clone(),
+            game_version: run.game_version.clone(),
+            platform: run.platform.clone(),
+            is_bugged_run: run.is_bugged_run,
+            bugged_reason: run.bugged_reason.clone(),
+            is_aborted_run: run.is_aborted_run,
+            aborted_reason: run.aborted_reason.clone(),
+            is_solo_run: run.is_solo_run,
+            is_favorite: run.is_favorite,
+            notes: run.notes.clone(),
+            total_times: (&run.total_times).into(),
+            phases: run.phases.iter().map(Into::into).collect(),
+            squad_members: run
+                .squad_members
+                .iter()
+                .map(|member| member.member_name.clone())
+                .collect(),
+        }
+    }
+}
+
+impl From<&TotalTimes> for ExportedTotalTimes {
+    fn from(times: &TotalTimes) -> Self {
+        Self {
+            total_time: times.total_time,
+            total_flight_time: times.total_flight_time,
+            total_shield_time: times.total_shield_time,
+            total_leg_time: times.total_leg_time,
+            total_body_time: times.total_body_time,
+            total_pylon_time: times.total_pylon_time,
+        }
+    }
+}
+
+impl From<&Phase> for ExportedPhase {
+    fn from(phase: &Phase) -> Self {
+        Self {
+            phase_number: phase.phase_number,
+            total_time: phase.total_time,
+            total_shield_time: phase.total_shield_time,
+            total_leg_time: phase.total_leg_time,
+            total_body_kill_time: phase.total_body_kill_time,
+            total_pylon_time: phase.total_pylon_time,
+            shield_changes: phase.shield_changes.iter().map(Into::into).collect(),
+            leg_breaks: phase.leg_breaks.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&ShieldChange> for ExportedShieldChange {
+    fn from(shield_change: &ShieldChange) -> Self {
+        Self {
+            shield_time: shield_change.shield_time,
+            status_effect: StatusEffect::to_string(&shield_change.status_effect).to_string(),
+            is_overshield: shield_change.is_overshield,
+        }
+    }
+}
+
+impl From<&LegBreak> for ExportedLegBreak {
+    fn from(leg_break: &LegBreak) -> Self {
+        Self {
+            leg_break_time: leg_break.leg_break_time,
+            leg_position: LegPosition::to_string(&leg_break.leg_position).to_string(),
+            leg_order: leg_break.leg_order,
+        }
+    }
+}
+
+/// How runs are written by the functions of this module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExportOptions {
+    /// Whether to leave out everything that could identify the player, so that the export can be
+    /// shared publicly, such as with developers for debugging.
+    ///
+    /// Player and squad member names are replaced with `Player 1`, `Player 2`, and so on, each
+    /// name keeping its number throughout the export. Timestamps count from the start of the
+    /// oldest exported run, which is at 0, instead of from the Unix epoch, and UTC offsets are
+    /// written as 0. UUIDs and notes are left out, as are the media and log excerpts of runs, as
+    /// they can hold any of the above.
+    pub anonymize: bool,
+}
+
+/// Replaces the identifying details of runs when exporting with [`ExportOptions::anonymize`].
+#[derive(Debug, Default)]
+struct Anonymizer {
+    /// The pseudonym given to each name seen so far.
+    names: HashMap<String, String>,
+
+    /// The timestamp of the first run anonymized, which later timestamps count from.
+    first_time_stamp: Option<i64>,
+}
+
+impl Anonymizer {
+    /// Returns an anonymizer if the options ask for one.
+    fn new(options: ExportOptions) -> Option<Self> {
+        options.anonymize.then(Self::default)
+    }
+
+    /// Replaces the identifying details of a run.
+    ///
+    /// Runs must be anonymized oldest first, so that no timestamp is negative.
+    fn anonymize(&mut self, run: &mut Run) {
+        let first_time_stamp = *self.first_time_stamp.get_or_insert(run.time_stamp);
+        run.time_stamp -= first_time_stamp;
+        run.utc_offset = Some(0);
+        run.run_uuid = None;
+        run.notes = None;
+
+        run.player_name = self.pseudonym(&run.player_name);
+        for member in &mut run.squad_members {
+            member.member_name = self.pseudonym(&member.member_name);
+        }
+    }
+
+    /// Replaces the identifying details of a run that was already converted for exporting, like
+    /// `anonymize`. Its checksum is left out too, as it covers the original timestamp.
+    fn anonymize_exported(&mut self, run: &mut ExportedRun) {
+        let first_time_stamp = *self.first_time_stamp.get_or_insert(run.time_stamp);
+        run.time_stamp -= first_time_stamp;
+        run.utc_offset = Some(0);
+        run.run_uuid = None;
+        run.checksum = None;
+        run.notes = None;
+
+        run.player_name = self.pseudonym(&run.player_name);
+        for member_name in &mut run.squad_members {
+            *member_name = self.pseudonym(member_name);
+        }
+    }
+
+    /// Returns the pseudonym of a name, giving it the next one if it has none yet.
+    fn pseudonym(&mut self, name: &str) -> String {
+        let next = self.names.len() + 1;
+        self.names
+            .entry(name.to_owned())
+            .or_insert_with(|| format!("Player {next}"))
+            .clone()
+    }
+}
+
+/// Exports a single run as a JSON string.
+///
+/// The run is wrapped in an [`ExportFile`], so the result can be read back the same way as a
+/// full export.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to export.
+/// * `options` - How to write the run.
+///
+/// # Returns
+/// * `Result<String>` - The run in the JSON format documented in this module.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// a query fails.
+pub fn export_run_json(conn: &Connection, run_id: i64, options: ExportOptions) -> Result<String> {
+    let mut run = fetch_run_by_id(conn, run_id)?;
+    if let Some(mut anonymizer) = Anonymizer::new(options) {
+        anonymizer.anonymize(&mut run);
+    }
+    let file = ExportFile {
+        format_version: FORMAT_VERSION,
+        runs: vec![(&run).into()],
+    };
+
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+/// The version of the run sharing codes written by `encode_share_code`.
+pub const SHARE_CODE_VERSION: u32 = 1;
+
+/// How hard sharing codes are compressed, on `miniz_oxide`'s scale from 0 to 10.
+const SHARE_CODE_COMPRESSION_LEVEL: u8 = 10;
+
+/// Encodes a run as a sharing code: a single line of text that can be pasted into a chat message,
+/// and read back with [`decode_share_code`](crate::import::decode_share_code).
+///
+/// The code holds every field of the run in the JSON format documented in this module, except
+/// for the notes and whether the run is a favorite, which are only meant for whoever recorded
+/// it. The JSON is compressed with zlib and written as URL-safe base64, after a `PTA1:` prefix
+/// naming the [`SHARE_CODE_VERSION`].
+///
+/// zlib is used rather than zstd, which compresses a little better, since `miniz_oxide` is pure
+/// Rust while every zstd crate builds the C library.
+///
+/// # Arguments
+/// * `run` - The run to encode.
+/// * `options` - How to write the run.
+///
+/// # Returns
+/// * `Result<String>` - The sharing code, which only contains ASCII letters, digits, `-`, `_`,
+///   and the `:` of its prefix.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Json`] if the run cannot be written as JSON.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_core::Run;
+/// use lib_profit_taker_database::export::{encode_share_code, ExportOptions};
+/// use lib_profit_taker_database::import::decode_share_code;
+///
+/// let run = Run::new(0, 1_675_271_234, "Run #1", "Player1");
+/// let code = encode_share_code(&run, ExportOptions::default())?;
+/// assert!(code.starts_with("PTA1:"));
+/// assert_eq!(decode_share_code(&code)?.player_name, "Player1");
+///
+/// let anonymous = encode_share_code(&run, ExportOptions { anonymize: true })?;
+/// assert_eq!(decode_share_code(&anonymous)?.player_name, "Player 1");
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+pub fn encode_share_code(run: &Run, options: ExportOptions) -> Result<String> {
+    let mut exported = ExportedRun::from(run);
+    exported.notes = None;
+    exported.is_favorite = false;
+    if let Some(mut anonymizer) = Anonymizer::new(options) {
+        anonymizer.anonymize_exported(&mut exported);
+    }
+
+    let compressed = miniz_oxide::deflate::compress_to_vec_zlib(
+        &serde_json::to_vec(&exported)?,
+        SHARE_CODE_COMPRESSION_LEVEL,
+    );
+
+    Ok(format!(
+        "PTA{SHARE_CODE_VERSION}:{}",
+        base64::encode(&compressed)
+    ))
+}
+
+/// Exports every run that is not in the trash as JSON, oldest first.
+///
+/// Runs are loaded and written one at a time, so memory usage does not grow with the size of the
+/// database.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `writer` - Where to write the exported JSON to.
+/// * `options` - How to write the runs.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or the JSON cannot be written.
+pub fn export_all_json(
+    conn: &Connection,
+    mut writer: impl Write,
+    options: ExportOptions,
+) -> Result<()> {
+    let mut anonymizer = Anonymizer::new(options);
+
+    // The runs are streamed into the array by hand, as serializing an `ExportFile` would require
+    // every run to be in memory at once
+    write!(writer, "{{\"format_version\":{FORMAT_VERSION},\"runs\":[")?;
+
+    for (i, run) in iter_runs(conn).enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        let mut run = run?;
+        if let Some(anonymizer) = &mut anonymizer {
+            anonymizer.anonymize(&mut run);
+        }
+        serde_json::to_writer(&mut writer, &ExportedRun::from(&run))?;
+    }
+
+    writer.write_all(b"]}")?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// A column of a CSV export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvColumn {
+    /// The ID of the run.
+    RunId,
+
+    /// The globally unique identifier of the run.
+    RunUuid,
+
+    /// The name of the run.
+    RunName,
+
+    /// The date and time the run was started, in UTC, formatted as `YYYY-MM-DD HH:MM:SS`.
+    Date,
+
+    /// The version of Warframe the run was played on, or empty if it is not known.
+    GameVersion,
+
+    /// The platform the run was played on, or empty if it is not known.
+    Platform,
+
+    /// The total time of the run.
+    TotalTime,
+
+    /// The time spent in flight.
+    FlightTime,
+
+    /// The total time of the phase with the given number, or empty if the run has no such phase.
+    PhaseTime(i32),
+
+    /// The total time spent on shields across all phases.
+    ShieldTime,
+
+    /// The total time spent on legs across all phases.
+    LegTime,
+
+    /// The total time spent on the body across all phases.
+    BodyTime,
+
+    /// The total time spent on pylons across all phases.
+    PylonTime,
+
+    /// The number of players in the squad, including the host.
+    SquadSize,
+
+    /// Whether the run is bugged.
+    Bugged,
+
+    /// Whether the run was aborted.
+    Aborted,
+}
+
+/// The columns used by a typical CSV export.
+pub const DEFAULT_CSV_COLUMNS: &[CsvColumn] = &[
+    CsvColumn::Date,
+    CsvColumn::RunName,
+    CsvColumn::TotalTime,
+    CsvColumn::FlightTime,
+    CsvColumn::PhaseTime(1),
+    CsvColumn::PhaseTime(2),
+    CsvColumn::PhaseTime(3),
+    CsvColumn::PhaseTime(4),
+    CsvColumn::ShieldTime,
+    CsvColumn::LegTime,
+    CsvColumn::SquadSize,
+];
+
+impl CsvColumn {
+    /// Returns the header of this column.
+    fn header(self) -> String {
+        match self {
+            Self::RunId => "run_id".to_string(),
+            Self::RunUuid => "run_uuid".to_string(),
+            Self::RunName => "run_name".to_string(),
+            Self::Date => "date".to_string(),
+            Self::GameVersion => "game_version".to_string(),
+            Self::Platform => "platform".to_string(),
+            Self::TotalTime => "total_time".to_string(),
+            Self::FlightTime => "flight_time".to_string(),
+            Self::PhaseTime(phase_number) => format!("phase_{phase_number}_time"),
+            Self::ShieldTime => "shield_time".to_string(),
+            Self::LegTime => "leg_time".to_string(),
+            Self::BodyTime => "body_time".to_string(),
+            Self::PylonTime => "pylon_time".to_string(),
+            Self::SquadSize => "squad_size".to_string(),
+            Self::Bugged => "bugged".to_string(),
+            Self::Aborted => "aborted".to_string(),
+        }
+    }
+
+    /// Returns the value of this column for the given run.
+    fn value(self, run: &Run) -> String {
+        let times = &run.total_times;
+
+        match self {
+            Self::RunId => run.run_id.to_string(),
+            Self::RunUuid => run.run_uuid.clone().unwrap_or_default(),
+            Self::RunName => run.run_name.clone(),
+            Self::Date => DateTime::from_timestamp(run.time_stamp, 0)
+                .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            Self::GameVersion => run.game_version.clone().unwrap_or_default(),
+            Self::Platform => run.platform.clone().unwrap_or_default(),
+            Self::TotalTime => times.total_time.to_string(),
+            Self::FlightTime => times.total_flight_time.to_string(),
+            Self::PhaseTime(phase_number) => run
+                .phases
+                .iter()
+                .find(|phase| phase.phase_number == phase_number)
+                .map(|phase| phase.total_time.to_string())
+                .unwrap_or_default(),
+            Self::ShieldTime => times.total_shield_time.to_string(),
+            Self::LegTime => times.total_leg_time.to_string(),
+            Self::BodyTime => times.total_body_time.to_string(),
+            Self::PylonTime => times.total_pylon_time.to_string(),
+            Self::SquadSize => (run.squad_members.len() + 1).to_string(),
+            Self::Bugged => run.is_bugged_run.to_string(),
+            Self::Aborted => run.is_aborted_run.to_string(),
+        }
+    }
+}
+
+/// Exports every run that is not in the trash as CSV, with one row per run, oldest first.
+///
+/// The first row is a header naming each column. Times are in seconds.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `writer` - Where to write the exported CSV to.
+/// * `columns` - The columns to export, in order. [`DEFAULT_CSV_COLUMNS`] is a good default.
+/// * `options` - How to write the runs. When anonymizing, dates are those of the anonymized
+///   timestamps, so the oldest run is dated `1970-01-01 00:00:00`.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or the CSV cannot be written.
+pub fn export_csv(
+    conn: &Connection,
+    writer: impl Write,
+    columns: &[CsvColumn],
+    options: ExportOptions,
+) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(columns.iter().map(|column| column.header()))?;
+    let mut anonymizer = Anonymizer::new(options);
+
+    for run in iter_runs(conn) {
+        let mut run = run?;
+        if let Some(anonymizer) = &mut anonymizer {
+            anonymizer.anonymize(&mut run);
+        }
+        writer.write_record(columns.iter().map(|column| column.value(&run)))?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Creates a new database file containing only the given runs, such as a handful of best runs to
+/// share with a teammate.
+///
+/// The new database is fully migrated, so it can be opened or merged with
+/// [`merge_database`](crate::import::merge_database) like any other. Each run is copied with its
+/// phases, squad members, notes, tags, media, loadout, and log excerpt, and keeps its UUID, but
+/// is given a new ID. If anything fails, the new file is removed again.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_ids` - The IDs of the runs to copy.
+/// * `path` - The file path to create the new database at. The directory structure is created if
+///   needed.
+/// * `options` - How to write the runs. When anonymizing, runs are copied oldest first, and lose
+///   their UUID, media, and log excerpt.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Io`] if a file already exists at `path` or the directory structure
+/// cannot be created, [`DatabaseError::RunNotFound`] if one of the runs does not exist,
+/// [`DatabaseError::ConnectionFailed`] if the new database cannot be created, or another error if
+/// a run fails to copy.
+pub fn export_selection(
+    conn: &Connection,
+    run_ids: &[i64],
+    path: &str,
+    options: ExportOptions,
+) -> Result<()> {
+    let path = Path::new(path);
+    if path.exists() {
+        return Err(DatabaseError::Io(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("a file already exists at `{}`", path.display()),
+        )));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let target = Connection::open(path).map_err(DatabaseError::ConnectionFailed)?;
+    let result = initialize_schema(&target).and_then(|()| {
+        transaction(&target, |target| {
+            let mut runs = run_ids
+                .iter()
+                .map(|&run_id| fetch_run_by_id(conn, run_id))
+                .collect::<Result<Vec<_>>>()?;
+            let mut anonymizer = Anonymizer::new(options);
+            if anonymizer.is_some() {
+                runs.sort_by_key(|run| run.time_stamp);
+            }
+
+            for mut run in runs {
+                let run_id = run.run_id;
+                if let Some(anonymizer) = &mut anonymizer {
+                    anonymizer.anonymize(&mut run);
+                }
+                let copied_id = insert_run(target, &run)?;
+                for tag in fetch_tags(conn, run_id)? {
+                    add_tag(target, copied_id, &tag)?;
+                }
+                if anonymizer.is_none() {
+                    for media in fetch_media(conn, run_id)? {
+                        add_media(target, copied_id, &media.location)?;
+                    }
+                    if let Some(log) = fetch_raw_log(conn, run_id)? {
+                        store_raw_log(target, copied_id, &log)?;
+                    }
+                }
+                if let Some(loadout) = fetch_loadout(conn, run_id)? {
+                    set_loadout(target, copied_id, &loadout)?;
+                }
+            }
+
+            Ok(())
+        })
+    });
+
+    if result.is_err() {
+        drop(target);
+        // The original error is the one worth reporting, so a failure here is ignored
+        let _ = fs::remove_file(path);
+    }
+
+    result
+}
+
+/// The run to export as LiveSplit splits with `export_livesplit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitsSource {
+    /// The run with the given ID.
+    Run(i64),
+
+    /// The personal best of the given category.
+    PersonalBest(RunCategory),
+}
+
+/// Exports a run as a LiveSplit splits file (`.lss`).
+///
+/// The splits have a segment for the flight and one for each phase of the run, with the times of
+/// the run as the personal best splits. The best segment times, which LiveSplit calls "golds",
+/// come from the [`sum_of_best`] of the category of the run, and the attempt count is the number
+/// of runs in that category, including aborted ones.
+///
+/// Splits files never hold the names of players nor when the run was played, so there is no need
+/// to anonymize them.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `source` - The run to export.
+/// * `writer` - Where to write the splits file to.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists,
+/// [`DatabaseError::InvalidData`] if the category has no valid runs to take a personal best from,
+/// or another error if a query fails or the file cannot be written.
+pub fn export_livesplit(
+    conn: &Connection,
+    source: &SplitsSource,
+    mut writer: impl Write,
+) -> Result<()> {
+    let run = match source {
+        SplitsSource::Run(run_id) => fetch_run_by_id(conn, *run_id)?,
+        SplitsSource::PersonalBest(category) => fetch_pb(conn, category)?.ok_or_else(|| {
+            DatabaseError::InvalidData("the category has no valid runs".to_owned())
+        })?,
+    };
+    let category = match source {
+        SplitsSource::PersonalBest(category) => category.clone(),
+        SplitsSource::Run(_) => RunCategory {
+            solo: run.is_solo_run,
+            bugged: run.is_bugged_run,
+            game_version: None,
+            warframe: None,
+        },
+    };
+    let sum_of_best = sum_of_best(conn, &category)?;
+    let (condition, values) = RunFilter {
+        aborted: None,
+        ..category.to_filter()
+    }
+    .to_sql();
+    let attempt_count: u64 = conn
+        .prepare_cached(&format!("SELECT COUNT(*) FROM runs WHERE {condition}"))?
+        .query_row(params_from_iter(values), |row| row.get(0))?;
+
+    let category_name = if category.solo { "Solo" } else { "Squad" };
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<Run version="1.7.0">"#)?;
+    writeln!(writer, "  <GameIcon />")?;
+    writeln!(writer, "  <GameName>Warframe</GameName>")?;
+    writeln!(
+        writer,
+        "  <CategoryName>Profit-Taker ({category_name})</CategoryName>"
+    )?;
+    writeln!(writer, "  <Metadata>")?;
+    writeln!(writer, r#"    <Run id="" />"#)?;
+    match &run.platform {
+        Some(platform) => writeln!(
+            writer,
+            r#"    <Platform usesEmulator="False">{}</Platform>"#,
+            escape_xml(platform)
+        )?,
+        None => writeln!(writer, r#"    <Platform usesEmulator="False" />"#)?,
+    }
+    writeln!(writer, "    <Region />")?;
+    writeln!(writer, "    <Variables />")?;
+    writeln!(writer, "  </Metadata>")?;
+    writeln!(writer, "  <Offset>00:00:00</Offset>")?;
+    writeln!(writer, "  <AttemptCount>{attempt_count}</AttemptCount>")?;
+    writeln!(writer, "  <AttemptHistory />")?;
+    writeln!(writer, "  <Segments>")?;
+
+    let mut split_time = run.total_times.total_flight_time;
+    let flight_gold = sum_of_best
+        .as_ref()
+        .map(|best| best.flight_time.as_secs_f64());
+    write_segment(&mut writer, "Flight", split_time, flight_gold)?;
+    for phase in &run.phases {
+        split_time += phase.total_time;
+        let gold = sum_of_best.as_ref().and_then(|best| {
+            best.phases
+                .iter()
+                .find(|best_phase| best_phase.phase_number == phase.phase_number)
+                .map(|best_phase| best_phase.phase_time.as_secs_f64())
+        });
+        write_segment(
+            &mut writer,
+            &format!("Phase {}", phase.phase_number),
+            split_time,
+            gold,
+        )?;
+    }
+
+    writeln!(writer, "  </Segments>")?;
+    writeln!(writer, "  <AutoSplitterSettings />")?;
+    writeln!(writer, "</Run>")?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Writes a segment of a LiveSplit splits file.
+fn write_segment(
+    writer: &mut impl Write,
+    name: &str,
+    split_time: f64,
+    best_segment_time: Option<f64>,
+) -> io::Result<()> {
+    writeln!(writer, "    <Segment>")?;
+    writeln!(writer, "      <Name>{}</Name>", escape_xml(name))?;
+    writeln!(writer, "      <Icon />")?;
+    writeln!(writer, "      <SplitTimes>")?;
+    writeln!(writer, r#"        <SplitTime name="Personal Best">"#)?;
+    writeln!(
+        writer,
+        "          <RealTime>{}</RealTime>",
+        livesplit_time(split_time)
+    )?;
+    writeln!(writer, "        </SplitTime>")?;
+    writeln!(writer, "      </SplitTimes>")?;
+    match best_segment_time {
+        Some(time) => {
+            writeln!(writer, "      <BestSegmentTime>")?;
+            writeln!(
+                writer,
+                "        <RealTime>{}</RealTime>",
+                livesplit_time(time)
+            )?;
+            writeln!(writer, "      </BestSegmentTime>")?;
+        }
+        None => writeln!(writer, "      <BestSegmentTime />")?,
+    }
+    writeln!(writer, "      <SegmentHistory />")?;
+    writeln!(writer, "    </Segment>")?;
+
+    Ok(())
+}
+
+/// Formats a time in seconds the way LiveSplit does, as `HH:MM:SS.fffffff`.
+fn livesplit_time(seconds: f64) -> String {
+    // LiveSplit counts time in ticks of 100 nanoseconds
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "times are never negative, and never anywhere near `u64::MAX` ticks"
+    )]
+    let ticks = (seconds * 10_000_000.0).round() as u64;
+    let (whole_seconds, ticks) = (ticks / 10_000_000, ticks % 10_000_000);
+
+    format!(
+        "{:02}:{:02}:{:02}.{ticks:07}",
+        whole_seconds / 3600,
+        whole_seconds / 60 % 60,
+        whole_seconds % 60
+    )
+}
+
+/// Escapes the characters that cannot appear as they are in XML text or attribute values.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// The version of the stats card format written by `stats_card`.
+pub const STATS_CARD_VERSION: u32 = 1;
+
+/// A summary of every valid run, as written by `stats_card` for the frontend to render as a
+/// shareable image.
+///
+/// Valid runs are those that are neither bugged, aborted, nor in the trash. Times are in seconds,
+/// rounded to the millisecond.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsCard {
+    /// The version of the format the card was written in, which is [`STATS_CARD_VERSION`].
+    pub format_version: u32,
+
+    /// The name the player had in their most recent valid run, or `None` if there are no valid
+    /// runs or the card is anonymized.
+    pub player_name: Option<String>,
+
+    /// The number of valid runs.
+    pub run_count: usize,
+
+    /// The statistics of the valid solo runs, or `None` if there are none.
+    pub solo: Option<StatsCardCategory>,
+
+    /// The statistics of the valid squad runs, or `None` if there are none.
+    pub squad: Option<StatsCardCategory>,
+
+    /// The mean times of the valid runs, or `None` if there are no valid runs.
+    pub average_times: Option<StatsCardAverages>,
+
+    /// How many shields of the valid runs were broken with each status effect, most used first.
+    pub elements: Vec<StatsCardElement>,
+}
+
+/// The statistics of the solo or squad runs of a [`StatsCard`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsCardCategory {
+    /// The number of valid runs in the category.
+    pub run_count: usize,
+
+    /// The total time of the personal best of the category.
+    pub pb_time: f64,
+
+    /// The Unix timestamp of when the personal best was started, or `None` if the card is
+    /// anonymized.
+    pub pb_time_stamp: Option<i64>,
+
+    /// The [sum of best](crate::analytics::SumOfBest) of the category.
+    pub sum_of_best: f64,
+}
+
+/// The mean times of the runs of a [`StatsCard`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsCardAverages {
+    /// The mean total time.
+    pub total_time: f64,
+
+    /// The mean flight time.
+    pub flight_time: f64,
+
+    /// The mean time spent on shields, summed over the phases of each run.
+    pub shield_time: f64,
+
+    /// The mean time spent on legs, summed over the phases of each run.
+    pub leg_time: f64,
+
+    /// The mean time spent on body kills, summed over the phases of each run.
+    pub body_time: f64,
+
+    /// The mean time spent on pylons, summed over the phases of each run.
+    pub pylon_time: f64,
+}
+
+/// How often shields were broken with a status effect, in a [`StatsCard`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsCardElement {
+    /// The name of the [`StatusEffect`] variant.
+    pub status_effect: String,
+
+    /// The number of shields broken with the status effect.
+    pub count: usize,
+
+    /// The fraction of all shields broken with the status effect, between 0 and 1, rounded to four
+    /// decimal places.
+    pub share: f64,
+}
+
+/// Summarizes every valid run as a compact JSON [`StatsCard`], for the frontend to render as a
+/// shareable image.
+///
+/// The card holds the personal best, sum of best, and run count of solo and squad runs, the mean
+/// times of every valid run, and how often shields were broken with each status effect.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `options` - How to write the card. When anonymizing, the player name and the date of each
+///   personal best are left out.
+///
+/// # Returns
+/// * `Result<String>` - The card as JSON, without any whitespace.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or a row references an unknown status effect or leg
+/// position.
+pub fn stats_card(conn: &Connection, options: ExportOptions) -> Result<String> {
+    let player_name = if options.anonymize {
+        None
+    } else {
+        let (condition, values) = RunFilter {
+            bugged: Some(false),
+            aborted: Some(false),
+            ..RunFilter::default()
+        }
+        .to_sql();
+        conn.prepare_cached(&format!(
+            "SELECT player_name FROM runs WHERE {condition} ORDER BY time_stamp DESC LIMIT 1"
+        ))?
+        .query_row(params_from_iter(values), |row| row.get(0))
+        .optional()?
+    };
+
+    let overview = cached_overview(conn)?;
+    let average_times = overview.map(|overview| StatsCardAverages {
+        total_time: overview.total_time.mean.as_secs_f64(),
+        flight_time: overview.flight_time.mean.as_secs_f64(),
+        shield_time: overview.shield_time.mean.as_secs_f64(),
+        leg_time: overview.leg_time.mean.as_secs_f64(),
+        body_time: overview.body_time.mean.as_secs_f64(),
+        pylon_time: overview.pylon_time.mean.as_secs_f64(),
+    });
+
+    let mut elements: Vec<StatsCardElement> = Vec::new();
+    for stats in shield_element_stats(conn)? {
+        let status_effect = StatusEffect::to_string(&stats.status_effect);
+        match elements
+            .iter_mut()
+            .find(|element| element.status_effect == status_effect)
+        {
+            Some(element) => element.count += stats.times.count,
+            None => elements.push(StatsCardElement {
+                status_effect: status_effect.to_owned(),
+                count: stats.times.count,
+                share: 0.0,
+            }),
+        }
+    }
+    let shield_count: usize = elements.iter().map(|element| element.count).sum();
+    for element in &mut elements {
+        #[expect(
+            clippy::cast_precision_loss,
+            reason = "there will never be anywhere near 2^52 shields"
+        )]
+        let share = element.count as f64 / shield_count as f64;
+        element.share = (share * 10_000.0).round() / 10_000.0;
+    }
+    elements.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.status_effect.cmp(&b.status_effect))
+    });
+
+    let card = StatsCard {
+        format_version: STATS_CARD_VERSION,
+        player_name,
+        run_count: overview.map_or(0, |overview| overview.run_count),
+        solo: stats_card_category(conn, true, options)?,
+        squad: stats_card_category(conn, false, options)?,
+        average_times,
+        elements,
+    };
+
+    Ok(serde_json::to_string(&card)?)
+}
+
+/// Computes the statistics of the valid solo or squad runs for `stats_card`.
+fn stats_card_category(
+    conn: &Connection,
+    solo: bool,
+    options: ExportOptions,
+) -> Result<Option<StatsCardCategory>> {
+    let category = RunCategory {
+        solo,
+        bugged: false,
+        game_version: None,
+        warframe: None,
+    };
+    let (Some(pb), Some(sum_of_best)) = (fetch_pb(conn, &category)?, sum_of_best(conn, &category)?)
+    else {
+        return Ok(None);
+    };
+
+    let (condition, values) = category.to_filter().to_sql();
+    let run_count = conn
+        .prepare_cached(&format!("SELECT COUNT(*) FROM runs WHERE {condition}"))?
+        .query_row(params_from_iter(values), |row| row.get(0))?;
+
+    Ok(Some(StatsCardCategory {
+        run_count,
+        pb_time: round_seconds(pb.total_times.total_time),
+        pb_time_stamp: Some(pb.time_stamp).filter(|_| !options.anonymize),
+        sum_of_best: sum_of_best.total_time.as_secs_f64(),
+    }))
+}
+
+/// Rounds a time in seconds to the millisecond, so that sums and means are written without
+/// floating-point noise.
+fn round_seconds(seconds: f64) -> f64 {
+    Duration::from_secs_f64(seconds).as_secs_f64()
+}
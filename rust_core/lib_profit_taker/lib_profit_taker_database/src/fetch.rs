@@ -0,0 +1,995 @@
+//! This module provides functions for reading runs back out of the SQLite database.
+//!
+//! The `fetch_run_by_id` function hydrates a complete `Run`, including its notes, game version,
+//! total times, phases, shield changes, leg breaks, and squad members, so callers receive a single
+//! typed model instead of having to query each table and stitch the results together themselves.
+//!
+//! The `fetch_run_by_uuid` function does the same for a run identified by its UUID, which, unlike
+//! its ID, is the same in every database the run was exported to or merged into.
+//!
+//! The `fetch_runs_paged` function lists runs one page at a time, sorted by a [`SortBy`] and
+//! narrowed down by a [`RunFilter`], so the frontend never has to load every run at once. The
+//! `fetch_runs_after` function does the same from a [`RunCursor`] instead of a page number, which
+//! stays fast however deep into the list the user scrolls.
+//!
+//! The `fetch_run_summaries` function lists only the columns the run list shows, without reading
+//! any other table, so even a very long history can be listed at once.
+//!
+//! The `fetch_random_run` and `runs_on_date` functions pick out runs for the home screen, such as
+//! a run to review for practice or the runs from this day in earlier years.
+//!
+//! The `fetch_raw_log` function reads back the excerpt of `EE.log` a run was parsed from.
+//!
+//! The `fetch_in_progress_runs` function lists the runs that were stored while still in progress
+//! and never completed, so the app can recover them after a crash. These runs are left out of
+//! every other list.
+//!
+//! The `iter_runs` function walks through every run instead, hydrating each one only when it is
+//! reached, for code like exports that needs every run but only one at a time.
+//!
+//! Unlike the [`analytics`](crate::analytics) module, which returns every time as a
+//! [`Duration`], these functions return the models of `lib_profit_taker_core`, which measure
+//! times as seconds in `f64`. The same models are built by the parser and read by the frontend,
+//! and exported and sent through the bridge as seconds, so a run reads back in the same shape it
+//! was inserted in. The stored milliseconds are converted at this boundary only, and every whole
+//! millisecond converts back to the same `Duration` through `Duration::from_secs_f64`.
+
+use chrono::NaiveDate;
+use lib_profit_taker_core::{LegBreak, Phase, Run, ShieldChange, SquadMember, TotalTimes};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+
+use crate::dto::RunSummaryDto;
+use crate::duration::Duration;
+use crate::error::{DatabaseError, Result};
+use crate::local_time::{date_to_sql, RUN_LOCAL_TIME_SQL};
+use crate::lookup::{get_leg_position, get_status_effect};
+
+/// The columns of `runs` read by [`run_from_row`], in the order it expects them.
+pub(crate) const RUN_COLUMNS: &str = "id, time_stamp, run_name, player_name, bugged_run, \
+    aborted_run, solo_run, favorite, notes, game_version, platform, bugged_reason, aborted_reason, \
+    total_time, total_flight_time, total_shield_time, total_leg_time, total_body_time, \
+    total_pylon_time, run_uuid, utc_offset";
+
+/// Fetches a complete run by its ID.
+///
+/// Each child table is read with a single query for the whole run, and the rows are then
+/// distributed into their phases, so the number of queries does not grow with the number of
+/// phases.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to fetch.
+///
+/// # Returns
+/// * `Result<Run>` - The fully hydrated run.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// a query fails or a row references an unknown status effect or leg position.
+pub fn fetch_run_by_id(conn: &Connection, run_id: i64) -> Result<Run> {
+    let mut run = conn
+        .prepare_cached(&format!("SELECT {RUN_COLUMNS} FROM runs WHERE id = ?1"))?
+        .query_row([run_id], run_from_row)
+        .optional()?
+        .ok_or(DatabaseError::RunNotFound(run_id))?;
+
+    run.phases = fetch_phases(conn, run_id)?;
+    run.squad_members = fetch_squad_members(conn, run_id)?;
+
+    Ok(run)
+}
+
+/// Fetches a complete run by its UUID. See [`fetch_run_by_id`].
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_uuid` - The UUID of the run to fetch.
+///
+/// # Returns
+/// * `Result<Option<Run>>` - The fully hydrated run, or `None` if no run has the given UUID.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or a row references an unknown status effect or leg position.
+pub fn fetch_run_by_uuid(conn: &Connection, run_uuid: &str) -> Result<Option<Run>> {
+    let run = conn
+        .prepare_cached(&format!("SELECT {RUN_COLUMNS} FROM runs WHERE run_uuid = ?1"))?
+        .query_row([run_uuid], run_from_row)
+        .optional()?;
+
+    run.map(|mut run| {
+        run.phases = fetch_phases(conn, run.run_id)?;
+        run.squad_members = fetch_squad_members(conn, run.run_id)?;
+
+        Ok(run)
+    })
+    .transpose()
+}
+
+/// Fetches whether a run is still in progress, or how it ended.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run.
+///
+/// # Returns
+/// * `Result<RunStatus>` - The status of the run.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// the query fails.
+pub fn fetch_run_status(conn: &Connection, run_id: i64) -> Result<RunStatus> {
+    let status: String = conn
+        .prepare_cached("SELECT status FROM runs WHERE id = ?1")?
+        .query_row([run_id], |row| row.get(0))
+        .optional()?
+        .ok_or(DatabaseError::RunNotFound(run_id))?;
+
+    RunStatus::from_sql(&status)
+}
+
+/// Fetches every run that is still in progress, such as runs left behind when the app crashed
+/// before they ended.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Vec<Run>>` - The fully hydrated runs, oldest first, with their flags as they were last
+///   stored. Each is left out of analytics until it is completed with
+///   [`complete_run`](crate::update::complete_run).
+///
+/// # Errors
+///
+/// Returns an error if a query fails or a row references an unknown status effect or leg position.
+pub fn fetch_in_progress_runs(conn: &Connection) -> Result<Vec<Run>> {
+    let run_ids = conn
+        .prepare_cached(
+            "SELECT id FROM runs WHERE status = 'in_progress' AND deleted_at IS NULL
+            ORDER BY time_stamp, id",
+        )?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+    run_ids
+        .into_iter()
+        .map(|run_id| fetch_run_by_id(conn, run_id))
+        .collect()
+}
+
+/// The direction in which a sort is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// Smallest values first (fastest, oldest, or alphabetically first).
+    #[default]
+    Ascending,
+
+    /// Largest values first (slowest, newest, or alphabetically last).
+    Descending,
+}
+
+impl SortOrder {
+    /// Returns the SQL keyword for this sort order.
+    const fn to_sql(self) -> &'static str {
+        match self {
+            Self::Ascending => "ASC",
+            Self::Descending => "DESC",
+        }
+    }
+}
+
+/// The column by which a list of runs is sorted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    /// Sort by the total time of the run.
+    Time(SortOrder),
+
+    /// Sort by the timestamp of the run.
+    Date(SortOrder),
+
+    /// Sort by the name of the run.
+    Name(SortOrder),
+
+    /// Sort by the number of shield changes of the run, across all phases.
+    ShieldChanges(SortOrder),
+}
+
+impl Default for SortBy {
+    /// Newest runs first.
+    fn default() -> Self {
+        Self::Date(SortOrder::Descending)
+    }
+}
+
+impl SortBy {
+    /// Returns the `ORDER BY` expression for this sort, without the `ORDER BY` keyword.
+    ///
+    /// The run ID is always used as a tiebreaker so that pages are stable.
+    pub(crate) fn to_sql(self) -> String {
+        let (column, order) = self.column();
+        let order = order.to_sql();
+
+        format!("{column} {order}, id {order}")
+    }
+
+    /// Returns the column runs are sorted by, along with the direction.
+    const fn column(self) -> (&'static str, SortOrder) {
+        match self {
+            Self::Time(order) => ("total_time", order),
+            Self::Date(order) => ("time_stamp", order),
+            Self::Name(order) => ("run_name", order),
+            Self::ShieldChanges(order) => ("shield_change_count", order),
+        }
+    }
+
+    /// Returns the value of the sorted column for the given run, whose phases must be fetched.
+    fn key_of(self, run: &Run) -> Value {
+        match self {
+            Self::Time(_) => {
+                Value::Integer(Duration::from_secs_f64(run.total_times.total_time).as_millis())
+            }
+            Self::Date(_) => Value::Integer(run.time_stamp),
+            Self::Name(_) => Value::Text(run.run_name.clone()),
+            Self::ShieldChanges(_) => {
+                Value::Integer(i64::try_from(shield_change_count(run)).unwrap_or(i64::MAX))
+            }
+        }
+    }
+}
+
+/// Returns the number of shield changes of a run, across all phases, as kept in the
+/// `shield_change_count` column.
+pub(crate) fn shield_change_count(run: &Run) -> usize {
+    run.phases.iter().map(|phase| phase.shield_changes.len()).sum()
+}
+
+/// Whether a run is still in progress, or how it ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RunStatus {
+    /// The run was stored by [`insert_partial_run`](crate::insert::insert_partial_run) and has not
+    /// been completed yet.
+    InProgress,
+
+    /// The run ended with Profit-Taker being killed.
+    Completed,
+
+    /// The run ended before Profit-Taker was killed.
+    Aborted,
+}
+
+impl RunStatus {
+    /// Returns the status of a run that has ended, given by whether it was aborted.
+    pub(crate) const fn from_run(run: &Run) -> Self {
+        if run.is_aborted_run {
+            Self::Aborted
+        } else {
+            Self::Completed
+        }
+    }
+
+    /// Returns the value stored in the `status` column for this status.
+    pub(crate) const fn to_sql(self) -> &'static str {
+        match self {
+            Self::InProgress => "in_progress",
+            Self::Completed => "completed",
+            Self::Aborted => "aborted",
+        }
+    }
+
+    /// Parses a value of the `status` column.
+    fn from_sql(status: &str) -> Result<Self> {
+        match status {
+            "in_progress" => Ok(Self::InProgress),
+            "completed" => Ok(Self::Completed),
+            "aborted" => Ok(Self::Aborted),
+            _ => Err(DatabaseError::InvalidData(format!(
+                "unknown run status `{status}`"
+            ))),
+        }
+    }
+}
+
+/// Restricts which runs are included in a list of runs.
+///
+/// Each optional field either does not filter on that property at all (`None`), or only includes
+/// runs matching the given value (`Some(value)`). The default filter includes every run that is
+/// not in the trash. Runs that are still in progress are never included.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct RunFilter {
+    /// Only include solo runs (`Some(true)`) or squad runs (`Some(false)`).
+    pub solo: Option<bool>,
+
+    /// Only include bugged runs (`Some(true)`) or non-bugged runs (`Some(false)`).
+    pub bugged: Option<bool>,
+
+    /// Only include aborted runs (`Some(true)`) or non-aborted runs (`Some(false)`).
+    pub aborted: Option<bool>,
+
+    /// Only include favorite runs (`Some(true)`) or non-favorite runs (`Some(false)`).
+    pub favorite: Option<bool>,
+
+    /// Only include runs started at or after this Unix timestamp.
+    pub since: Option<i64>,
+
+    /// Only include runs started at or before this Unix timestamp.
+    pub until: Option<i64>,
+
+    /// Only include runs played on or after this day, in the local time where each run was played.
+    pub from_date: Option<NaiveDate>,
+
+    /// Only include runs played on or before this day, in the local time where each run was
+    /// played.
+    pub to_date: Option<NaiveDate>,
+
+    /// Only include runs played on this version of Warframe.
+    pub game_version: Option<String>,
+
+    /// Only include runs with this many players in the squad, including the host.
+    pub squad_size: Option<u32>,
+
+    /// Only include runs whose [loadout](crate::loadouts) has this Warframe, ignoring case.
+    pub warframe: Option<String>,
+
+    /// Only include runs whose [loadout](crate::loadouts) has this weapon in any slot, including
+    /// the arch-gun, ignoring case.
+    pub weapon: Option<String>,
+
+    /// List the runs in the trash instead of the regular runs.
+    pub trashed: bool,
+}
+
+impl RunFilter {
+    /// Returns the `WHERE` condition for this filter along with the values of its parameters.
+    ///
+    /// The condition uses positional `?` parameters, so it can be combined with other conditions
+    /// as long as their parameters are appended in order.
+    pub(crate) fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut conditions = vec![
+            if self.trashed {
+                "deleted_at IS NOT NULL".to_string()
+            } else {
+                "deleted_at IS NULL".to_string()
+            },
+            "status != 'in_progress'".to_string(),
+        ];
+        let mut values = Vec::new();
+
+        for (column, value) in [
+            ("solo_run", self.solo),
+            ("bugged_run", self.bugged),
+            ("aborted_run", self.aborted),
+            ("favorite", self.favorite),
+        ] {
+            if let Some(value) = value {
+                conditions.push(format!("{column} = ?"));
+                values.push(Value::Integer(value.into()));
+            }
+        }
+
+        for (condition, time_stamp) in [
+            ("time_stamp >= ?", self.since),
+            ("time_stamp <= ?", self.until),
+        ] {
+            if let Some(time_stamp) = time_stamp {
+                conditions.push(condition.to_string());
+                values.push(Value::Integer(time_stamp));
+            }
+        }
+
+        for (condition, date) in [
+            (format!("date({RUN_LOCAL_TIME_SQL}) >= ?"), self.from_date),
+            (format!("date({RUN_LOCAL_TIME_SQL}) <= ?"), self.to_date),
+        ] {
+            if let Some(date) = date {
+                conditions.push(condition);
+                values.push(Value::Text(date_to_sql(date)));
+            }
+        }
+
+        if let Some(squad_size) = self.squad_size {
+            conditions.push("squad_size = ?".to_string());
+            values.push(Value::Integer(squad_size.into()));
+        }
+
+        if let Some(game_version) = &self.game_version {
+            conditions.push("game_version = ?".to_string());
+            values.push(Value::Text(game_version.clone()));
+        }
+
+        for (condition, name) in [
+            (
+                "id IN (SELECT run_id FROM loadouts WHERE warframe = ? COLLATE NOCASE)",
+                &self.warframe,
+            ),
+            (
+                "id IN (
+                    SELECT run_id FROM loadouts WHERE ? COLLATE NOCASE
+                        IN (primary_weapon, secondary_weapon, melee_weapon, arch_gun)
+                )",
+                &self.weapon,
+            ),
+        ] {
+            if let Some(name) = name {
+                conditions.push(condition.to_string());
+                values.push(Value::Text(name.trim().to_owned()));
+            }
+        }
+
+        (conditions.join(" AND "), values)
+    }
+}
+
+/// Fetches a single page of complete runs.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `page` - The zero-based index of the page to fetch.
+/// * `page_size` - The maximum number of runs per page.
+/// * `sort` - The order in which runs are listed.
+/// * `filter` - Restricts which runs are listed.
+///
+/// # Returns
+/// * `Result<Vec<Run>>` - The fully hydrated runs on the requested page. This is empty if the
+///   page is past the last run.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or a row references an unknown status effect or leg
+/// position.
+pub fn fetch_runs_paged(
+    conn: &Connection,
+    page: u32,
+    page_size: u32,
+    sort: SortBy,
+    filter: &RunFilter,
+) -> Result<Vec<Run>> {
+    let (condition, mut values) = filter.to_sql();
+    values.push(Value::Integer(page_size.into()));
+    values.push(Value::Integer(i64::from(page) * i64::from(page_size)));
+
+    let mut runs = conn
+        .prepare_cached(&format!(
+            "SELECT {RUN_COLUMNS} FROM runs WHERE {condition} ORDER BY {} LIMIT ? OFFSET ?",
+            sort.to_sql()
+        ))?
+        .query_map(params_from_iter(values), run_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for run in &mut runs {
+        run.phases = fetch_phases(conn, run.run_id)?;
+        run.squad_members = fetch_squad_members(conn, run.run_id)?;
+    }
+
+    Ok(runs)
+}
+
+/// A position in a sorted and filtered list of runs, from which `fetch_runs_after` continues.
+///
+/// A cursor remembers the sort and filter of the list along with the last run already listed, so
+/// its contents are deliberately private: the only ways to get one are to start a new list with
+/// [`RunCursor::new`], or to take the cursor of the next page from a [`RunPage`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunCursor {
+    /// The order in which runs are listed.
+    sort: SortBy,
+
+    /// Restricts which runs are listed.
+    filter: RunFilter,
+
+    /// The value of the sorted column and the ID of the last run listed, or `None` at the start
+    /// of the list.
+    after: Option<(Value, i64)>,
+}
+
+impl RunCursor {
+    /// Creates a cursor at the start of a list of runs.
+    ///
+    /// # Arguments
+    /// * `sort` - The order in which runs are listed.
+    /// * `filter` - Restricts which runs are listed.
+    #[must_use]
+    pub const fn new(sort: SortBy, filter: RunFilter) -> Self {
+        Self {
+            sort,
+            filter,
+            after: None,
+        }
+    }
+}
+
+/// A page of runs fetched by `fetch_runs_after`.
+#[derive(Debug)]
+pub struct RunPage {
+    /// The fully hydrated runs of the page.
+    pub runs: Vec<Run>,
+
+    /// The cursor from which the next page continues, or `None` if this is the last page.
+    pub next: Option<RunCursor>,
+}
+
+/// Fetches the page of complete runs that follows a cursor.
+///
+/// Unlike `fetch_runs_paged`, which has SQLite count past every run on the earlier pages, this
+/// finds where the page starts from the last run of the previous one, so deep pages are as cheap
+/// to fetch as the first. Runs inserted or deleted between pages also do not cause runs to be
+/// skipped or listed twice.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `cursor` - Where the page starts, either a new cursor or the `next` cursor of the previous
+///   page.
+/// * `limit` - The maximum number of runs on the page.
+///
+/// # Returns
+/// * `Result<RunPage>` - The fully hydrated runs of the page, along with the cursor of the next
+///   page, if there is one.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or a row references an unknown status effect or leg
+/// position.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_database::connection::open_in_memory;
+/// use lib_profit_taker_database::fetch::{fetch_runs_after, RunCursor, RunFilter, SortBy};
+///
+/// let conn = open_in_memory()?;
+/// let mut cursor = Some(RunCursor::new(SortBy::default(), RunFilter::default()));
+/// while let Some(current) = cursor {
+///     let page = fetch_runs_after(&conn, &current, 50)?;
+///     for run in &page.runs {
+///         println!("{}", run.run_name);
+///     }
+///     cursor = page.next;
+/// }
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+pub fn fetch_runs_after(conn: &Connection, cursor: &RunCursor, limit: u32) -> Result<RunPage> {
+    let (mut condition, mut values) = cursor.filter.to_sql();
+    let (column, order) = cursor.sort.column();
+    if let Some((key, run_id)) = &cursor.after {
+        let comparison = match order {
+            SortOrder::Ascending => ">",
+            SortOrder::Descending => "<",
+        };
+        condition = format!("{condition} AND ({column}, id) {comparison} (?, ?)");
+        values.push(key.clone());
+        values.push(Value::Integer(*run_id));
+    }
+    // One more run than needed tells whether there is a next page
+    values.push(Value::Integer(i64::from(limit) + 1));
+
+    let mut runs = conn
+        .prepare_cached(&format!(
+            "SELECT {RUN_COLUMNS} FROM runs WHERE {condition} ORDER BY {} LIMIT ?",
+            cursor.sort.to_sql()
+        ))?
+        .query_map(params_from_iter(values), run_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+    let has_next = runs.len() > limit;
+    runs.truncate(limit);
+
+    // Before building the cursor, since sort keys such as the shield change count need them
+    for run in &mut runs {
+        run.phases = fetch_phases(conn, run.run_id)?;
+        run.squad_members = fetch_squad_members(conn, run.run_id)?;
+    }
+
+    let next = match runs.last() {
+        Some(last) if has_next => Some(RunCursor {
+            sort: cursor.sort,
+            filter: cursor.filter.clone(),
+            after: Some((cursor.sort.key_of(last), last.run_id)),
+        }),
+        _ => None,
+    };
+
+    Ok(RunPage { runs, next })
+}
+
+/// Fetches the summaries of the runs matching a filter, newest first.
+///
+/// Only the columns of `runs` shown in the run list are read, without joining or querying any
+/// other table, so this stays fast even with thousands of runs.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `filter` - Restricts which runs are listed.
+/// * `limit` - The largest number of runs to list, or `None` to list every matching run.
+///
+/// # Returns
+/// * `Result<Vec<RunSummaryDto>>` - The ID, name, timestamp, player name, total time, flags, and
+///   counts of phases, shield changes, and players of each run.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn fetch_run_summaries(
+    conn: &Connection,
+    filter: &RunFilter,
+    limit: Option<u32>,
+) -> Result<Vec<RunSummaryDto>> {
+    let (condition, mut values) = filter.to_sql();
+    // A negative limit is no limit at all
+    values.push(Value::Integer(limit.map_or(-1, i64::from)));
+
+    let summaries = conn
+        .prepare_cached(&format!(
+            "SELECT id, run_name, time_stamp, player_name, total_time,
+                bugged_run, aborted_run, solo_run, favorite,
+                phase_count, shield_change_count, squad_size
+            FROM runs WHERE {condition} ORDER BY {} LIMIT ?",
+            SortBy::default().to_sql()
+        ))?
+        .query_map(params_from_iter(values), |row| {
+            Ok(RunSummaryDto {
+                run_id: row.get(0)?,
+                run_name: row.get(1)?,
+                time_stamp: row.get(2)?,
+                player_name: row.get(3)?,
+                total_time: get_seconds(row, 4)?,
+                is_bugged_run: row.get(5)?,
+                is_aborted_run: row.get(6)?,
+                is_solo_run: row.get(7)?,
+                is_favorite: row.get(8)?,
+                phase_count: row.get(9)?,
+                shield_change_count: row.get(10)?,
+                squad_size: row.get(11)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(summaries)
+}
+
+/// Fetches a complete run picked at random, for features like reviewing an old run for practice.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `filter` - Restricts which runs can be picked.
+///
+/// # Returns
+/// * `Result<Option<Run>>` - The fully hydrated run, or `None` if no run matches `filter`.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or a row references an unknown status effect or leg
+/// position.
+pub fn fetch_random_run(conn: &Connection, filter: &RunFilter) -> Result<Option<Run>> {
+    let (condition, values) = filter.to_sql();
+
+    let run = conn
+        .prepare_cached(&format!(
+            "SELECT {RUN_COLUMNS} FROM runs WHERE {condition} ORDER BY random() LIMIT 1"
+        ))?
+        .query_row(params_from_iter(values), run_from_row)
+        .optional()?;
+
+    run.map(|mut run| {
+        run.phases = fetch_phases(conn, run.run_id)?;
+        run.squad_members = fetch_squad_members(conn, run.run_id)?;
+
+        Ok(run)
+    })
+    .transpose()
+}
+
+/// Fetches every complete run that is not in the trash and was started on the given day of the
+/// year, in any year, for cards like "one year ago today, you ran 52 seconds".
+///
+/// Days are in the local time where each run was played, as that is the calendar the user
+/// remembers their runs by.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `month` - The month, from 1 for January to 12 for December.
+/// * `day` - The day of the month, from 1 to 31.
+///
+/// # Returns
+/// * `Result<Vec<Run>>` - The fully hydrated runs, newest first.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if `month` or `day` is out of range, or another error
+/// if a query fails or a row references an unknown status effect or leg position.
+pub fn runs_on_date(conn: &Connection, month: u32, day: u32) -> Result<Vec<Run>> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(DatabaseError::InvalidData(format!(
+            "{month}/{day} is not a valid month and day"
+        )));
+    }
+
+    let mut runs = conn
+        .prepare_cached(&format!(
+            "SELECT {RUN_COLUMNS} FROM runs
+            WHERE deleted_at IS NULL AND status != 'in_progress'
+                AND strftime('%m-%d', {RUN_LOCAL_TIME_SQL}) = ?1
+            ORDER BY time_stamp DESC, id DESC"
+        ))?
+        .query_map([format!("{month:02}-{day:02}")], run_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for run in &mut runs {
+        run.phases = fetch_phases(conn, run.run_id)?;
+        run.squad_members = fetch_squad_members(conn, run.run_id)?;
+    }
+
+    Ok(runs)
+}
+
+/// How many runs [`RunIter`] reads from the `runs` table at once.
+const ITER_BATCH_SIZE: u32 = 100;
+
+/// Returns an iterator over every complete run that is not in the trash or in progress, oldest
+/// first.
+///
+/// Unlike `fetch_runs_paged`, runs are hydrated one at a time as the iterator advances, so memory
+/// usage does not grow with the size of the database.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `RunIter` - An iterator over the fully hydrated runs. If a query fails, it yields the error
+///   and then ends.
+#[must_use]
+pub fn iter_runs(conn: &Connection) -> RunIter<'_> {
+    RunIter {
+        conn,
+        batch: Vec::new().into_iter(),
+        last_key: None,
+        finished: false,
+    }
+}
+
+/// An iterator over every complete run that is not in the trash, oldest first.
+///
+/// Created by [`iter_runs`].
+#[derive(Debug)]
+pub struct RunIter<'conn> {
+    /// The connection runs are read from.
+    conn: &'conn Connection,
+
+    /// The runs of the current batch not yet yielded, without their phases and squad members.
+    batch: std::vec::IntoIter<Run>,
+
+    /// The `(time_stamp, id)` of the last run read, which the next batch starts after.
+    last_key: Option<(i64, i64)>,
+
+    /// Whether every run has been yielded or an error has ended the iteration.
+    finished: bool,
+}
+
+impl RunIter<'_> {
+    /// Reads the top-level rows of the next batch of runs.
+    ///
+    /// Batches are found by their position after the last run read rather than by an offset, so
+    /// each batch is as cheap to find as the first.
+    fn next_batch(&self) -> Result<Vec<Run>> {
+        let (time_stamp, id) = self.last_key.unwrap_or((i64::MIN, i64::MIN));
+
+        self.conn
+            .prepare_cached(&format!(
+                "SELECT {RUN_COLUMNS} FROM runs
+                WHERE deleted_at IS NULL AND status != 'in_progress'
+                    AND (time_stamp, id) > (?1, ?2)
+                ORDER BY time_stamp, id LIMIT ?3"
+            ))?
+            .query_map(params![time_stamp, id, ITER_BATCH_SIZE], run_from_row)?
+            .collect::<rusqlite::Result<_>>()
+            .map_err(Into::into)
+    }
+
+    /// Reads the next run, along with its phases and squad members.
+    fn next_run(&mut self) -> Result<Option<Run>> {
+        if self.batch.len() == 0 {
+            self.batch = self.next_batch()?.into_iter();
+        }
+        let Some(mut run) = self.batch.next() else {
+            return Ok(None);
+        };
+        self.last_key = Some((run.time_stamp, run.run_id));
+
+        run.phases = fetch_phases(self.conn, run.run_id)?;
+        run.squad_members = fetch_squad_members(self.conn, run.run_id)?;
+
+        Ok(Some(run))
+    }
+}
+
+impl Iterator for RunIter<'_> {
+    type Item = Result<Run>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let next = self.next_run().transpose();
+        if !matches!(next, Some(Ok(_))) {
+            self.finished = true;
+        }
+
+        next
+    }
+}
+
+/// Fetches the excerpt of `EE.log` a run was parsed from, as stored by
+/// [`store_raw_log`](crate::insert::store_raw_log).
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run whose excerpt to fetch.
+///
+/// # Returns
+/// * `Result<Option<String>>` - The lines of the log, or `None` if no excerpt was stored for the
+///   run.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the stored excerpt cannot be decompressed, or
+/// another error if the query fails.
+pub fn fetch_raw_log(conn: &Connection, run_id: i64) -> Result<Option<String>> {
+    let compressed: Option<Vec<u8>> = conn
+        .prepare_cached("SELECT log FROM raw_logs WHERE run_id = ?1")?
+        .query_row([run_id], |row| row.get(0))
+        .optional()?;
+
+    compressed
+        .map(|compressed| {
+            let log = miniz_oxide::inflate::decompress_to_vec(&compressed).map_err(|error| {
+                DatabaseError::InvalidData(format!("the log of run {run_id} is corrupted: {error}"))
+            })?;
+
+            String::from_utf8(log).map_err(|_| {
+                DatabaseError::InvalidData(format!("the log of run {run_id} is not valid UTF-8"))
+            })
+        })
+        .transpose()
+}
+
+/// Lists every game version the complete runs that are not in the trash were played on.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Vec<String>>` - The game versions, most recently played first. Runs whose game version
+///   is not known are left out.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn fetch_game_versions(conn: &Connection) -> Result<Vec<String>> {
+    conn.prepare_cached(
+        "SELECT game_version FROM runs
+        WHERE deleted_at IS NULL AND status != 'in_progress' AND game_version IS NOT NULL
+        GROUP BY game_version ORDER BY MAX(time_stamp) DESC",
+    )?
+    .query_map([], |row| row.get(0))?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
+
+/// Builds a `Run` without phases or squad members from a row of the `runs` table.
+///
+/// The row must contain the columns listed in [`RUN_COLUMNS`], in that order.
+pub(crate) fn run_from_row(row: &Row) -> rusqlite::Result<Run> {
+    let mut run = Run::new(row.get(0)?, row.get(1)?, "", "");
+    run.run_name = row.get(2)?;
+    run.player_name = row.get(3)?;
+    run.is_bugged_run = row.get(4)?;
+    run.is_aborted_run = row.get(5)?;
+    run.is_solo_run = row.get(6)?;
+    run.is_favorite = row.get(7)?;
+    run.notes = row.get(8)?;
+    run.game_version = row.get(9)?;
+    run.platform = row.get(10)?;
+    run.bugged_reason = row.get(11)?;
+    run.aborted_reason = row.get(12)?;
+    run.total_times = TotalTimes::new(
+        get_seconds(row, 13)?,
+        get_seconds(row, 14)?,
+        get_seconds(row, 15)?,
+        get_seconds(row, 16)?,
+        get_seconds(row, 17)?,
+        get_seconds(row, 18)?,
+    );
+    run.run_uuid = row.get(19)?;
+    run.utc_offset = Some(row.get(20)?);
+
+    Ok(run)
+}
+
+/// Fetches all phases of a run, ordered by phase number, along with their shield changes and
+/// leg breaks.
+pub(crate) fn fetch_phases(conn: &Connection, run_id: i64) -> Result<Vec<Phase>> {
+    let mut phases = conn
+        .prepare_cached(
+            "SELECT phase_number, phase_time, shield_time, leg_time, body_kill_time, pylon_time
+            FROM phases WHERE run_id = ?1 ORDER BY phase_number",
+        )?
+        .query_map([run_id], |row| {
+            let mut phase = Phase::new(row.get(0)?);
+            phase.total_time = get_seconds(row, 1)?;
+            phase.total_shield_time = get_optional_seconds(row, 2)?;
+            phase.total_leg_time = get_seconds(row, 3)?;
+            phase.total_body_kill_time = get_seconds(row, 4)?;
+            phase.total_pylon_time = get_optional_seconds(row, 5)?;
+            Ok(phase)
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut shield_changes = conn.prepare_cached(
+        "SELECT phase_number, shield_time, status_effect_id, is_overshield
+        FROM shield_changes WHERE run_id = ?1 ORDER BY phase_number, id",
+    )?;
+    let shield_changes = shield_changes.query_map([run_id], |row| {
+        let mut shield_change =
+            ShieldChange::new(get_seconds(row, 1)?, get_status_effect(row, 2)?);
+        shield_change.is_overshield = row.get(3)?;
+
+        Ok((row.get(0)?, shield_change))
+    })?;
+    for shield_change in shield_changes {
+        let (phase_number, shield_change) = shield_change?;
+        if let Some(phase) = find_phase(&mut phases, phase_number) {
+            phase.shield_changes.push(shield_change);
+        }
+    }
+
+    let mut leg_breaks = conn.prepare_cached(
+        "SELECT phase_number, break_time, leg_position_id, break_order
+        FROM leg_breaks WHERE run_id = ?1 ORDER BY phase_number, break_order",
+    )?;
+    let leg_breaks = leg_breaks.query_map([run_id], |row| {
+        Ok((
+            row.get(0)?,
+            LegBreak::new(get_seconds(row, 1)?, get_leg_position(row, 2)?, row.get(3)?),
+        ))
+    })?;
+    for leg_break in leg_breaks {
+        let (phase_number, leg_break) = leg_break?;
+        if let Some(phase) = find_phase(&mut phases, phase_number) {
+            phase.leg_breaks.push(leg_break);
+        }
+    }
+
+    Ok(phases)
+}
+
+/// Reads a duration column of milliseconds as seconds, as measured by the models of the parser.
+fn get_seconds(row: &Row, index: usize) -> rusqlite::Result<f64> {
+    row.get::<_, Duration>(index).map(Duration::as_secs_f64)
+}
+
+/// Reads a duration column that may be `NULL`, counting `NULL` as no time at all.
+fn get_optional_seconds(row: &Row, index: usize) -> rusqlite::Result<f64> {
+    Ok(row.get::<_, Option<Duration>>(index)?.unwrap_or_default().as_secs_f64())
+}
+
+/// Finds the phase with the given phase number.
+fn find_phase(phases: &mut [Phase], phase_number: i32) -> Option<&mut Phase> {
+    phases
+        .iter_mut()
+        .find(|phase| phase.phase_number == phase_number)
+}
+
+/// Fetches all squad members of a run, ordered by name.
+pub(crate) fn fetch_squad_members(conn: &Connection, run_id: i64) -> Result<Vec<SquadMember>> {
+    conn.prepare_cached(
+        "SELECT member_name FROM squad_members WHERE run_id = ?1 ORDER BY member_name",
+    )?
+    .query_map([run_id], |row| {
+        Ok(SquadMember::new(&row.get::<_, String>(0)?))
+    })?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
@@ -0,0 +1,125 @@
+//! This module provides functions for setting target times, such as "a solo run under a minute"
+//! or "phase 1 in under 12 seconds".
+//!
+//! Solo and squad runs have separate goals, and each can have a goal for the whole run and for
+//! each phase. How close the user is to each goal is reported by
+//! [`goal_progress`](crate::analytics::goal_progress).
+
+use rusqlite::{params, Connection, Row};
+
+use crate::connection::execute_retrying;
+use crate::duration::Duration;
+use crate::error::{DatabaseError, Result};
+
+/// A target time for solo or squad runs, or for one of their phases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Goal {
+    /// Whether the goal is for solo runs rather than squad runs.
+    pub solo: bool,
+
+    /// The phase the goal is for, or `None` if it is for the whole run.
+    pub phase_number: Option<i32>,
+
+    /// The time to beat.
+    pub target_time: Duration,
+}
+
+/// Sets a goal, replacing any goal set before for the same runs and phase.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `goal` - The goal to set.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the phase number is not positive or the target time
+/// is not positive, or another error if the query fails.
+pub fn set_goal(conn: &Connection, goal: &Goal) -> Result<()> {
+    if goal.target_time <= Duration::ZERO {
+        return Err(DatabaseError::InvalidData(format!(
+            "{} is not a valid target time",
+            goal.target_time
+        )));
+    }
+
+    execute_retrying(
+        conn,
+        "INSERT INTO goals (solo_run, phase_number, target_time) VALUES (?1, ?2, ?3)
+        ON CONFLICT (solo_run, phase_number) DO UPDATE SET target_time = excluded.target_time",
+        params![
+            goal.solo,
+            goal_phase_number(goal.phase_number)?,
+            goal.target_time
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Removes a goal.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `solo` - Whether the goal is for solo runs rather than squad runs.
+/// * `phase_number` - The phase the goal is for, or `None` if it is for the whole run.
+///
+/// # Returns
+/// * `Result<bool>` - Whether the goal had been set.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the phase number is not positive, or another error
+/// if the query fails.
+pub fn remove_goal(conn: &Connection, solo: bool, phase_number: Option<i32>) -> Result<bool> {
+    let removed = execute_retrying(
+        conn,
+        "DELETE FROM goals WHERE solo_run = ?1 AND phase_number = ?2",
+        params![solo, goal_phase_number(phase_number)?],
+    )?;
+
+    Ok(removed > 0)
+}
+
+/// Fetches every goal that has been set.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Vec<Goal>>` - The goals, solo goals first, each starting with the goal for the whole
+///   run followed by the goals for each phase in order.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn fetch_goals(conn: &Connection) -> Result<Vec<Goal>> {
+    conn.prepare_cached(
+        "SELECT solo_run, phase_number, target_time FROM goals
+        ORDER BY solo_run DESC, phase_number",
+    )?
+    .query_map([], goal_from_row)?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
+
+/// Returns the phase number a goal is stored with, where 0 stands for the whole run.
+fn goal_phase_number(phase_number: Option<i32>) -> Result<i32> {
+    match phase_number {
+        None => Ok(0),
+        Some(phase_number) if phase_number >= 1 => Ok(phase_number),
+        Some(phase_number) => Err(DatabaseError::InvalidData(format!(
+            "phase number {phase_number} is not positive"
+        ))),
+    }
+}
+
+/// Reads a goal from a row of `solo_run`, `phase_number`, and `target_time`.
+fn goal_from_row(row: &Row) -> rusqlite::Result<Goal> {
+    let phase_number: i32 = row.get(1)?;
+
+    Ok(Goal {
+        solo: row.get(0)?,
+        phase_number: (phase_number != 0).then_some(phase_number),
+        target_time: row.get(2)?,
+    })
+}
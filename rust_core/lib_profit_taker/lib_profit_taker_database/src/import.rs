@@ -0,0 +1,846 @@
+//! This module provides functions for importing runs into the SQLite database, such as runs
+//! exported by a friend or restored from a shared file.
+//!
+//! The `import_json` function reads the JSON format written by the `export` module. Since an
+//! imported run may already exist in the database, an [`ImportStrategy`] decides what happens to
+//! runs whose UUID, timestamp, or [checksum](crate::checksum) matches a stored run.
+//!
+//! The `decode_share_code` function reads a single run back from the sharing code written by
+//! `encode_share_code`, such as one pasted from a chat message.
+//!
+//! The `merge_database` function copies runs from another database created by this library, such
+//! as one from a second computer, skipping runs that are already stored.
+//!
+//! The `import_legacy` function reads the run files written by the earlier, Python-based version
+//! of the app, so that long-time users keep their history. That format records less than this
+//! one, and anything that cannot be mapped onto the current schema is listed in the returned
+//! [`LegacyImportReport`] instead of being silently dropped.
+//!
+//! Both `import_json` and `merge_database` keep the UUIDs of the runs they copy, so a run has the
+//! same UUID in every database it ends up in, except for runs imported with
+//! [`ImportStrategy::Duplicate`], which are given a new one.
+
+use chrono::{Local, NaiveDateTime};
+use lib_profit_taker_core::{LegBreak, Phase, Run, ShieldChange, SquadMember, TotalTimes};
+use rusqlite::{params, Connection};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::base64;
+use crate::checksum::run_checksum;
+use crate::connection::with_savepoint;
+use crate::delete::delete_run;
+use crate::error::{DatabaseError, Result};
+use crate::export::{ExportFile, ExportedPhase, ExportedRun, FORMAT_VERSION, SHARE_CODE_VERSION};
+use crate::insert::insert_run;
+use crate::migrations::LATEST_VERSION;
+use crate::players::sync_players;
+
+/// What to do with an imported run whose UUID, timestamp, or checksum matches a run already in the
+/// database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportStrategy {
+    /// Keep the stored run and do not import the new one.
+    Skip,
+
+    /// Delete the stored run and import the new one in its place.
+    Overwrite,
+
+    /// Keep the stored run and import the new one alongside it, with a new UUID.
+    Duplicate,
+}
+
+/// A summary of what happened to the runs of an imported file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImportReport {
+    /// The number of runs that did not match a stored run and were imported.
+    pub imported: usize,
+
+    /// The number of runs that matched a stored run and were not imported.
+    pub skipped: usize,
+
+    /// The number of runs that matched a stored run and replaced it.
+    pub overwritten: usize,
+
+    /// The number of runs that matched a stored run and were imported alongside it.
+    pub duplicated: usize,
+}
+
+/// A summary of what happened to the runs of a merged database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MergeReport {
+    /// The number of runs that were not already stored and were copied over.
+    pub merged: usize,
+
+    /// The number of runs that were already stored and were not copied over.
+    pub skipped: usize,
+}
+
+/// The name the other database is attached under by `merge_database`.
+const MERGE_SCHEMA: &str = "merge_source";
+
+/// Imports runs from JSON in the format written by the `export` module.
+///
+/// Either every run in the file is imported or, if an error occurs, none of them are.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `reader` - Where to read the JSON from.
+/// * `strategy` - What to do with runs whose UUID, timestamp, or checksum matches a stored run.
+///
+/// # Returns
+/// * `Result<ImportReport>` - How many runs were imported, skipped, overwritten, or duplicated.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Json`] if the file is not valid JSON in the expected format,
+/// [`DatabaseError::UnsupportedFormatVersion`] if it was written by a newer version of this
+/// library, [`DatabaseError::InvalidData`] if it contains an unknown status effect or leg
+/// position, or another error if a run fails to insert.
+pub fn import_json(
+    conn: &Connection,
+    reader: impl Read,
+    strategy: ImportStrategy,
+) -> Result<ImportReport> {
+    let file: ExportFile = serde_json::from_reader(reader)?;
+    if file.format_version > FORMAT_VERSION {
+        return Err(DatabaseError::UnsupportedFormatVersion(file.format_version));
+    }
+
+    // Convert everything up front, so an invalid run is reported before anything is written
+    let mut runs = file
+        .runs
+        .into_iter()
+        .map(Run::try_from)
+        .collect::<Result<Vec<_>>>()?;
+
+    with_savepoint(conn, "import_json", |conn| {
+        let mut report = ImportReport::default();
+
+        for run in &mut runs {
+            let existing = fetch_matching_run_ids(conn, run)?;
+
+            if existing.is_empty() {
+                report.imported += 1;
+            } else {
+                match strategy {
+                    ImportStrategy::Skip => {
+                        report.skipped += 1;
+                        continue;
+                    }
+                    ImportStrategy::Overwrite => {
+                        for run_id in existing {
+                            delete_run(conn, run_id)?;
+                        }
+                        report.overwritten += 1;
+                    }
+                    ImportStrategy::Duplicate => {
+                        run.run_uuid = None;
+                        report.duplicated += 1;
+                    }
+                }
+            }
+
+            insert_run(conn, run)?;
+        }
+
+        Ok(report)
+    })
+}
+
+/// The largest size, in bytes, that the JSON of a sharing code is decompressed to.
+///
+/// Real runs take a few kilobytes, so this only stops a crafted code from exhausting memory.
+const MAX_SHARE_CODE_JSON_SIZE: usize = 1 << 20;
+
+/// Decodes a sharing code written by [`encode_share_code`](crate::export::encode_share_code)
+/// back into a run.
+///
+/// Nothing is written to the database; the run can be stored with
+/// [`insert_run_if_absent`](crate::insert::insert_run_if_absent), which recognizes a run that was
+/// already imported from the same code. Whitespace around the code, as chat apps tend to add, is
+/// ignored.
+///
+/// # Arguments
+/// * `code` - The sharing code.
+///
+/// # Returns
+/// * `Result<Run>` - The decoded run, with an ID of 0.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::UnsupportedFormatVersion`] if the code is not of the
+/// [`SHARE_CODE_VERSION`] this library writes, such as one written by a newer version,
+/// [`DatabaseError::InvalidData`] if it is not a sharing code or is truncated or corrupted, or
+/// [`DatabaseError::Json`] if it does not hold a run in the expected format.
+pub fn decode_share_code(code: &str) -> Result<Run> {
+    let invalid = || DatabaseError::InvalidData("not a valid sharing code".to_string());
+
+    let (version, data) = code
+        .trim()
+        .strip_prefix("PTA")
+        .and_then(|code| code.split_once(':'))
+        .ok_or_else(invalid)?;
+    let version: u32 = version.parse().map_err(|_| invalid())?;
+    if version != SHARE_CODE_VERSION {
+        return Err(DatabaseError::UnsupportedFormatVersion(version));
+    }
+
+    let compressed = base64::decode(data).ok_or_else(invalid)?;
+    let json = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(
+        &compressed,
+        MAX_SHARE_CODE_JSON_SIZE,
+    )
+    .map_err(|_| invalid())?;
+    let mut run = Run::try_from(serde_json::from_slice::<ExportedRun>(&json)?)?;
+    run.run_id = 0;
+
+    Ok(run)
+}
+
+/// Copies the runs of another database created by this library into the database.
+///
+/// A run is considered already stored, and is skipped, if a stored run (including one in the
+/// trash) has the same UUID, the same checksum, or the same timestamp and total time. Runs in the
+/// other database's trash are not copied. Either every new run is copied or, if an error occurs,
+/// none of them are. The other database is only read from, never modified.
+///
+/// Since the other database is attached to `conn`, this must not be called while a transaction is
+/// open on `conn`.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `other_db_path` - The file path of the database to copy runs from.
+///
+/// # Returns
+/// * `Result<MergeReport>` - How many runs were merged or skipped.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Io`] if there is no file at `other_db_path`,
+/// [`DatabaseError::InvalidData`] if it is not a database created by this library,
+/// [`DatabaseError::SchemaVersionMismatch`] if its schema is not at [`LATEST_VERSION`] (in which
+/// case it should first be opened with this version of the library, so it is migrated), or
+/// another error if a run fails to copy.
+pub fn merge_database(conn: &Connection, other_db_path: &str) -> Result<MergeReport> {
+    // `ATTACH` would otherwise create an empty database at the path
+    if !Path::new(other_db_path).is_file() {
+        return Err(DatabaseError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no database at `{other_db_path}`"),
+        )));
+    }
+
+    conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {MERGE_SCHEMA}"),
+        [other_db_path],
+    )?;
+
+    let result = validate_merge_source(conn)
+        .and_then(|()| with_savepoint(conn, "merge_database", merge_attached_runs));
+
+    // Detach even if merging failed, but report the merge's error over the detach's
+    let detached = conn.execute(&format!("DETACH DATABASE {MERGE_SCHEMA}"), []);
+    let report = result?;
+    detached?;
+
+    Ok(report)
+}
+
+/// Checks that the attached database was created by this library and is at [`LATEST_VERSION`].
+fn validate_merge_source(conn: &Connection) -> Result<()> {
+    let table_exists = |name: &str| -> Result<bool> {
+        conn.query_row(
+            &format!(
+                "SELECT EXISTS (SELECT 1 FROM {MERGE_SCHEMA}.sqlite_master \
+                 WHERE type = 'table' AND name = ?1)"
+            ),
+            [name],
+            |row| row.get(0),
+        )
+        .map_err(Into::into)
+    };
+
+    if !table_exists("runs")? {
+        return Err(DatabaseError::InvalidData(
+            "the database to merge is not a Profit-Taker Analytics database".to_string(),
+        ));
+    }
+
+    let version = if table_exists("schema_version")? {
+        conn.query_row(
+            &format!("SELECT COALESCE(MAX(version), 0) FROM {MERGE_SCHEMA}.schema_version"),
+            [],
+            |row| row.get(0),
+        )?
+    } else {
+        0
+    };
+    if version != LATEST_VERSION {
+        return Err(DatabaseError::SchemaVersionMismatch {
+            expected: LATEST_VERSION,
+            found: version,
+        });
+    }
+
+    Ok(())
+}
+
+/// Copies every run of the attached database that is not already stored, with its children.
+fn merge_attached_runs(conn: &Connection) -> Result<MergeReport> {
+    let candidates: Vec<(i64, bool)> = conn
+        .prepare(&format!(
+            "SELECT other.id, EXISTS (
+                SELECT 1 FROM main.runs AS stored
+                WHERE stored.run_uuid = other.run_uuid
+                    OR stored.checksum = other.checksum
+                    OR (stored.time_stamp = other.time_stamp
+                        AND stored.total_time = other.total_time)
+            )
+            FROM {MERGE_SCHEMA}.runs AS other
+            WHERE other.deleted_at IS NULL
+            ORDER BY other.id"
+        ))?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut report = MergeReport::default();
+
+    for (other_id, already_stored) in candidates {
+        if already_stored {
+            report.skipped += 1;
+            continue;
+        }
+
+        copy_attached_run(conn, other_id)?;
+        report.merged += 1;
+    }
+
+    // Squad members are copied as rows, so their names have to be linked to players separately
+    sync_players(conn)?;
+
+    Ok(report)
+}
+
+/// Copies the run with the given ID from the attached database, assigning it a new ID but keeping
+/// its UUID.
+fn copy_attached_run(conn: &Connection, other_id: i64) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO main.runs (
+                time_stamp, run_name, player_name, bugged_run, aborted_run, solo_run, favorite,
+                notes, game_version, platform, bugged_reason, aborted_reason, total_time,
+                total_flight_time, total_shield_time, total_leg_time, total_body_time,
+                total_pylon_time, run_uuid, checksum, status, utc_offset
+            )
+            SELECT
+                time_stamp, run_name, player_name, bugged_run, aborted_run, solo_run, favorite,
+                notes, game_version, platform, bugged_reason, aborted_reason, total_time,
+                total_flight_time, total_shield_time, total_leg_time, total_body_time,
+                total_pylon_time, run_uuid, checksum, status, utc_offset
+            FROM {MERGE_SCHEMA}.runs WHERE id = ?1"
+        ),
+        [other_id],
+    )?;
+    let run_id = conn.last_insert_rowid();
+
+    let child_tables = [
+        (
+            "phases",
+            "phase_number, phase_time, shield_time, leg_time, body_kill_time, pylon_time",
+        ),
+        (
+            "shield_changes",
+            "phase_number, shield_time, status_effect_id, is_overshield",
+        ),
+        (
+            "leg_breaks",
+            "phase_number, break_time, break_order, leg_position_id",
+        ),
+        ("squad_members", "member_name"),
+        ("tags", "tag"),
+        ("raw_logs", "log"),
+        ("media", "location, added_at"),
+        (
+            "loadouts",
+            "warframe, primary_weapon, secondary_weapon, melee_weapon, arch_gun",
+        ),
+    ];
+    for (table, columns) in child_tables {
+        conn.execute(
+            &format!(
+                "INSERT INTO main.{table} (run_id, {columns})
+                SELECT ?1, {columns} FROM {MERGE_SCHEMA}.{table} WHERE run_id = ?2"
+            ),
+            [run_id, other_id],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// A summary of what happened to the files of a legacy import.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LegacyImportReport {
+    /// The number of files that were imported as runs.
+    pub imported: usize,
+
+    /// The number of files whose timestamp matched a stored run, and were not imported again.
+    pub skipped: usize,
+
+    /// The number of files that could not be read as a run at all, and were not imported.
+    pub failed: usize,
+
+    /// Everything that could not be mapped onto the current schema, in the order it was found.
+    pub issues: Vec<LegacyIssue>,
+}
+
+/// Something in a legacy run file that could not be mapped onto the current schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyIssue {
+    /// The name of the file the issue was found in.
+    pub file_name: String,
+
+    /// What could not be mapped, and what was done about it.
+    pub message: String,
+}
+
+/// Imports the run files written by the earlier, Python-based version of the app.
+///
+/// Those files are JSON objects stored one per run in the `storage` directory of the app, and
+/// named after the local time the run started, as in `20240131_204512.json`. Their fields are
+/// mapped as follows:
+///
+/// - `pretty_name` becomes the name of the run, falling back to the file name without its
+///   extension, as the old app displayed it.
+/// - `player_name` and `squad_members` become the player and squad members of the run.
+/// - `total_duration`, `flight_duration`, `total_shield`, `total_leg`, `total_body`, and
+///   `total_pylon` become the total times of the run. `total_duration` is required.
+/// - `bugged_run`, `aborted_run`, `solo_run`, and `favorite` become the flags of the run.
+/// - `phase_1` to `phase_4` become its phases, from their `total_time`, `total_shield`,
+///   `total_leg`, `total_body_kill`, and `total_pylon` fields, and the shield changes and leg
+///   breaks given by the parallel lists `shield_change_times` and `shield_change_types`, and
+///   `leg_break_times` and `leg_break_positions`.
+///
+/// Unknown fields, values of the wrong type, and unknown status effects or leg positions are
+/// ignored and reported as [`LegacyIssue`]s, as are files that are not valid JSON or lack a
+/// timestamp or total duration, which are not imported at all. Runs whose timestamp matches a
+/// stored run are skipped, so importing the same directory twice does not duplicate anything.
+/// Either every run is imported or, if a database error occurs, none of them are.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `path` - The path of a single legacy run file, or of a directory whose `.json` files should
+///   all be imported.
+///
+/// # Returns
+/// * `Result<LegacyImportReport>` - How many files were imported, skipped, or failed, and what
+///   could not be mapped.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Io`] if `path` or one of the files cannot be read, or another error
+/// if a run fails to insert.
+pub fn import_legacy(conn: &Connection, path: &str) -> Result<LegacyImportReport> {
+    let path = Path::new(path);
+    let mut files = if path.is_dir() {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .filter(|path| {
+                path.as_ref().map_or(true, |path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "json")
+                })
+            })
+            .collect::<io::Result<Vec<PathBuf>>>()?
+    } else {
+        vec![path.to_path_buf()]
+    };
+    files.sort();
+
+    let mut report = LegacyImportReport::default();
+    let mut runs = Vec::new();
+    for file in files {
+        let file_name = file
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let contents = fs::read_to_string(&file)?;
+
+        let mut issues = Vec::new();
+        match legacy_run(&file, &contents, &mut issues) {
+            Some(run) => runs.push(run),
+            None => report.failed += 1,
+        }
+        report
+            .issues
+            .extend(issues.into_iter().map(|message| LegacyIssue {
+                file_name: file_name.clone(),
+                message,
+            }));
+    }
+
+    with_savepoint(conn, "import_legacy", |conn| {
+        for run in &runs {
+            if fetch_matching_run_ids(conn, run)?.is_empty() {
+                insert_run(conn, run)?;
+                report.imported += 1;
+            } else {
+                report.skipped += 1;
+            }
+        }
+
+        Ok(report)
+    })
+}
+
+/// Converts the contents of a legacy run file into a run, or returns `None` if it is not a run at
+/// all. Everything that could not be mapped is added to `issues`.
+fn legacy_run(file: &Path, contents: &str, issues: &mut Vec<String>) -> Option<Run> {
+    let file_stem = file
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    let Some((time_stamp, utc_offset)) = file_stem
+        .get(..15)
+        .and_then(|date| NaiveDateTime::parse_from_str(date, "%Y%m%d_%H%M%S").ok())
+        .and_then(|date| date.and_local_timezone(Local).earliest())
+        .map(|date| (date.timestamp(), date.offset().local_minus_utc()))
+    else {
+        issues.push("the file name does not start with the time the run started".to_string());
+        return None;
+    };
+
+    let mut fields = match serde_json::from_str(contents) {
+        Ok(Value::Object(fields)) => fields,
+        Ok(_) => {
+            issues.push("the file is not a JSON object".to_string());
+            return None;
+        }
+        Err(error) => {
+            issues.push(format!("the file is not valid JSON: {error}"));
+            return None;
+        }
+    };
+
+    let mut legacy = LegacyFields {
+        fields: &mut fields,
+        issues,
+        context: String::new(),
+    };
+    let Some(total_time) = legacy.f64("total_duration") else {
+        legacy
+            .issues
+            .push("the file has no `total_duration`".to_string());
+        return None;
+    };
+
+    let run_name = legacy
+        .string("pretty_name")
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| file_stem.clone());
+    let player_name = legacy.string("player_name").unwrap_or_default();
+    let mut run = Run::new(0, time_stamp, &run_name, &player_name);
+    run.utc_offset = Some(utc_offset);
+    run.total_times = TotalTimes::new(
+        total_time,
+        legacy.f64("flight_duration").unwrap_or_default(),
+        legacy.f64("total_shield").unwrap_or_default(),
+        legacy.f64("total_leg").unwrap_or_default(),
+        legacy.f64("total_body").unwrap_or_default(),
+        legacy.f64("total_pylon").unwrap_or_default(),
+    );
+    run.is_bugged_run = legacy.bool("bugged_run").unwrap_or_default();
+    run.is_aborted_run = legacy.bool("aborted_run").unwrap_or_default();
+    run.is_favorite = legacy.bool("favorite").unwrap_or_default();
+    run.squad_members = legacy
+        .list("squad_members")
+        .into_iter()
+        .filter_map(|member| legacy.item_string(member, "squad_members"))
+        .map(|name| SquadMember::new(&name))
+        .collect();
+    run.is_solo_run = legacy
+        .bool("solo_run")
+        .unwrap_or(run.squad_members.is_empty());
+
+    for phase_number in 1..=4 {
+        let key = format!("phase_{phase_number}");
+        match legacy.fields.remove(&key) {
+            Some(Value::Object(mut fields)) => {
+                let mut phase_fields = LegacyFields {
+                    fields: &mut fields,
+                    issues: &mut *legacy.issues,
+                    context: format!("`{key}`: "),
+                };
+                run.phases
+                    .push(legacy_phase(&mut phase_fields, phase_number));
+                phase_fields.report_unknown();
+            }
+            Some(_) => legacy.issues.push(format!(
+                "`{key}` is not an object, so the phase was ignored"
+            )),
+            None => {}
+        }
+    }
+
+    legacy.report_unknown();
+
+    Some(run)
+}
+
+/// Converts a phase of a legacy run file.
+fn legacy_phase(legacy: &mut LegacyFields, phase_number: i32) -> Phase {
+    let mut phase = Phase::new(phase_number);
+    phase.total_time = legacy.f64("total_time").unwrap_or_default();
+    phase.total_shield_time = legacy.f64("total_shield").unwrap_or_default();
+    phase.total_leg_time = legacy.f64("total_leg").unwrap_or_default();
+    phase.total_body_kill_time = legacy.f64("total_body_kill").unwrap_or_default();
+    phase.total_pylon_time = legacy.f64("total_pylon").unwrap_or_default();
+
+    let shield_times = legacy.list("shield_change_times");
+    let shield_types = legacy.list("shield_change_types");
+    legacy.check_lengths(
+        "shield_change_times",
+        &shield_times,
+        "shield_change_types",
+        &shield_types,
+    );
+    for (time, status_effect) in shield_times.into_iter().zip(shield_types) {
+        let time = legacy.item_f64(&time, "shield_change_times");
+        let status_effect = legacy
+            .item_string(status_effect, "shield_change_types")
+            .and_then(|name| {
+                let status_effect = name.parse().ok();
+                if status_effect.is_none() {
+                    legacy.issue(&format!(
+                        "unknown status effect `{name}`, so the shield change was ignored"
+                    ));
+                }
+                status_effect
+            });
+        if let (Some(time), Some(status_effect)) = (time, status_effect) {
+            phase
+                .shield_changes
+                .push(ShieldChange::new(time, status_effect));
+        }
+    }
+
+    let leg_times = legacy.list("leg_break_times");
+    let leg_positions = legacy.list("leg_break_positions");
+    legacy.check_lengths(
+        "leg_break_times",
+        &leg_times,
+        "leg_break_positions",
+        &leg_positions,
+    );
+    let mut leg_order = 0;
+    for (time, leg_position) in leg_times.into_iter().zip(leg_positions) {
+        let time = legacy.item_f64(&time, "leg_break_times");
+        let leg_position = legacy
+            .item_string(leg_position, "leg_break_positions")
+            .and_then(|name| {
+                let leg_position = name.parse().ok();
+                if leg_position.is_none() {
+                    legacy.issue(&format!(
+                        "unknown leg position `{name}`, so the leg break was ignored"
+                    ));
+                }
+                leg_position
+            });
+        if let (Some(time), Some(leg_position)) = (time, leg_position) {
+            leg_order += 1;
+            phase
+                .leg_breaks
+                .push(LegBreak::new(time, leg_position, leg_order));
+        }
+    }
+
+    phase
+}
+
+/// The fields of a legacy JSON object that have not been mapped yet, and the issues found so far.
+struct LegacyFields<'a> {
+    /// The fields left to map. Each field is removed as it is mapped.
+    fields: &'a mut Map<String, Value>,
+
+    /// Everything that could not be mapped so far.
+    issues: &'a mut Vec<String>,
+
+    /// Where the object is in the file, prepended to each issue.
+    context: String,
+}
+
+impl LegacyFields<'_> {
+    /// Records an issue with the object.
+    fn issue(&mut self, message: &str) {
+        self.issues.push(format!("{}{message}", self.context));
+    }
+
+    /// Takes a field, reporting it if it has the wrong type.
+    fn take<T>(&mut self, key: &str, type_name: &str, get: fn(&Value) -> Option<T>) -> Option<T> {
+        let value = self.fields.remove(key)?;
+        if value.is_null() {
+            return None;
+        }
+
+        let converted = get(&value);
+        if converted.is_none() {
+            self.issue(&format!("`{key}` is not {type_name}, so it was ignored"));
+        }
+        converted
+    }
+
+    /// Takes a number field.
+    fn f64(&mut self, key: &str) -> Option<f64> {
+        self.take(key, "a number", Value::as_f64)
+    }
+
+    /// Takes a boolean field.
+    fn bool(&mut self, key: &str) -> Option<bool> {
+        self.take(key, "a boolean", Value::as_bool)
+    }
+
+    /// Takes a string field.
+    fn string(&mut self, key: &str) -> Option<String> {
+        self.take(key, "a string", |value| {
+            value.as_str().map(ToString::to_string)
+        })
+    }
+
+    /// Takes a list field, treating a missing list as empty.
+    fn list(&mut self, key: &str) -> Vec<Value> {
+        self.take(key, "a list", |value| value.as_array().cloned())
+            .unwrap_or_default()
+    }
+
+    /// Converts an item of a list to a number, reporting it if it is not one.
+    fn item_f64(&mut self, item: &Value, key: &str) -> Option<f64> {
+        let converted = item.as_f64();
+        if converted.is_none() {
+            self.issue(&format!("`{key}` contains {item}, which is not a number"));
+        }
+        converted
+    }
+
+    /// Converts an item of a list to a string, reporting it if it is not one.
+    fn item_string(&mut self, item: Value, key: &str) -> Option<String> {
+        match item {
+            Value::String(string) => Some(string),
+            item => {
+                self.issue(&format!("`{key}` contains {item}, which is not a string"));
+                None
+            }
+        }
+    }
+
+    /// Reports two parallel lists that do not have the same length.
+    fn check_lengths(&mut self, key_a: &str, list_a: &[Value], key_b: &str, list_b: &[Value]) {
+        if list_a.len() != list_b.len() {
+            self.issue(&format!(
+                "`{key_a}` has {} items but `{key_b}` has {}, so the extra items were ignored",
+                list_a.len(),
+                list_b.len()
+            ));
+        }
+    }
+
+    /// Reports every field that was not mapped.
+    fn report_unknown(&mut self) {
+        let keys: Vec<String> = self.fields.keys().cloned().collect();
+        for key in keys {
+            self.issue(&format!("unknown field `{key}` was ignored"));
+        }
+    }
+}
+
+/// Fetches the IDs of every stored run, including those in the trash, with the same UUID,
+/// timestamp, or checksum as `run`.
+fn fetch_matching_run_ids(conn: &Connection, run: &Run) -> Result<Vec<i64>> {
+    conn.prepare_cached(
+        "SELECT id FROM runs WHERE run_uuid = ?1 OR time_stamp = ?2 OR checksum = ?3",
+    )?
+    .query_map(
+        params![run.run_uuid, run.time_stamp, run_checksum(run)],
+        |row| row.get(0),
+    )?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
+
+impl TryFrom<ExportedRun> for Run {
+    type Error = DatabaseError;
+
+    fn try_from(exported: ExportedRun) -> Result<Self> {
+        let mut run = Self::new(
+            exported.run_id,
+            exported.time_stamp,
+            &exported.run_name,
+            &exported.player_name,
+        );
+        run.run_uuid = exported.run_uuid;
+        run.utc_offset = exported.utc_offset;
+        run.is_bugged_run = exported.is_bugged_run;
+        run.bugged_reason = exported.bugged_reason;
+        run.is_aborted_run = exported.is_aborted_run;
+        run.aborted_reason = exported.aborted_reason;
+        run.is_solo_run = exported.is_solo_run;
+        run.is_favorite = exported.is_favorite;
+        run.notes = exported.notes;
+        run.game_version = exported.game_version;
+        run.platform = exported.platform;
+
+        let times = exported.total_times;
+        run.total_times = TotalTimes::new(
+            times.total_time,
+            times.total_flight_time,
+            times.total_shield_time,
+            times.total_leg_time,
+            times.total_body_time,
+            times.total_pylon_time,
+        );
+
+        run.phases = exported
+            .phases
+            .into_iter()
+            .map(Phase::try_from)
+            .collect::<Result<_>>()?;
+        run.squad_members = exported
+            .squad_members
+            .iter()
+            .map(|name| SquadMember::new(name))
+            .collect();
+
+        Ok(run)
+    }
+}
+
+impl TryFrom<ExportedPhase> for Phase {
+    type Error = DatabaseError;
+
+    fn try_from(exported: ExportedPhase) -> Result<Self> {
+        let mut phase = Self::new(exported.phase_number);
+        phase.total_time = exported.total_time;
+        phase.total_shield_time = exported.total_shield_time;
+        phase.total_leg_time = exported.total_leg_time;
+        phase.total_body_kill_time = exported.total_body_kill_time;
+        phase.total_pylon_time = exported.total_pylon_time;
+
+        for shield_change in exported.shield_changes {
+            let status_effect = shield_change.status_effect.parse()?;
+            let mut imported = ShieldChange::new(shield_change.shield_time, status_effect);
+            imported.is_overshield = shield_change.is_overshield;
+            phase.shield_changes.push(imported);
+        }
+
+        for leg_break in exported.leg_breaks {
+            let leg_position = leg_break.leg_position.parse()?;
+            phase.leg_breaks.push(LegBreak::new(
+                leg_break.leg_break_time,
+                leg_position,
+                leg_break.leg_order,
+            ));
+        }
+
+        Ok(phase)
+    }
+}
@@ -0,0 +1,521 @@
+//! This module provides functions for writing runs into the SQLite database.
+//!
+//! The `insert_run` function persists a complete `Run`, including its total times, phases,
+//! shield changes, leg breaks, and squad members. All rows belonging to a run are written
+//! atomically: if any of them fails to insert, nothing from that run is kept.
+//!
+//! The `insert_runs_batch` function does the same for many runs at once, such as when importing
+//! a whole `EE.log` archive, and reports its progress as it goes.
+//!
+//! The `insert_run_if_absent` function only inserts a run if the same run is not already stored,
+//! so that reading the same `EE.log` twice does not store its runs twice.
+//!
+//! The `insert_validated_run` function checks a run with `validate_run` before inserting it, so
+//! that runs the parser got wrong can be rejected or kept out of the statistics.
+//!
+//! The `update_run` function overwrites a run that was already inserted with a newer version of
+//! it, for when the parser writes a run while it is still in progress and completes it later.
+//!
+//! The `store_raw_log` function keeps the excerpt of `EE.log` a run was parsed from alongside it.
+//!
+//! The `insert_partial_run` function stores a run that is still in progress, so that it can be
+//! recovered if the app closes before the run ends. It is then kept up to date with `update_run`
+//! and finished with [`complete_run`](crate::update::complete_run).
+//!
+//! Every duration is rounded to the nearest millisecond as it is written, as described in
+//! [`duration`](crate::duration).
+
+use lib_profit_taker_core::{LegBreak, Phase, Run, ShieldChange, SquadMember};
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+use crate::checksum::run_checksum;
+use crate::connection::{execute_retrying, with_cached_stmt, with_savepoint};
+use crate::duration::Duration;
+use crate::error::{DatabaseError, Result};
+use crate::fetch::{fetch_run_by_id, RunStatus};
+use crate::local_time::local_utc_offset;
+use crate::players::link_name;
+use crate::validation::{validate_run, InvalidRunPolicy, ValidationWarning};
+
+/// Inserts a complete run into the database and returns its newly assigned ID.
+///
+/// The `run_id` field of the provided run is ignored, as the database assigns IDs itself. The
+/// `run_uuid` field is kept if it is set, such as for a run imported from another database, and is
+/// generated otherwise. Likewise, the `utc_offset` field is kept if it is set, and is otherwise
+/// the offset of the current time zone at the time the run was played. The run and all of its
+/// child rows are written inside a savepoint, so either everything is persisted or nothing is.
+/// This also means it can safely be called inside a larger transaction.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run` - The run to insert.
+///
+/// # Returns
+/// * `Result<i64>` - The ID of the newly inserted run.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::ConstraintViolation`] if a row violates a constraint, such as two leg
+/// breaks sharing a position in the same phase or the UUID of the run already being in use, or
+/// another error if any row fails to insert. In either case, the whole run is rolled back.
+pub fn insert_run(conn: &Connection, run: &Run) -> Result<i64> {
+    with_savepoint(conn, "insert_run", |conn| {
+        insert_run_rows(conn, run, RunStatus::from_run(run))
+    })
+}
+
+/// Whether `insert_run_if_absent` stored a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertOutcome {
+    /// The run was inserted with the given ID.
+    Inserted(i64),
+
+    /// The run was already stored with the given ID, so nothing was inserted.
+    AlreadyExists(i64),
+}
+
+impl InsertOutcome {
+    /// Returns the ID the run is stored with, whether it was just inserted or not.
+    #[must_use]
+    pub const fn run_id(self) -> i64 {
+        match self {
+            Self::Inserted(run_id) | Self::AlreadyExists(run_id) => run_id,
+        }
+    }
+}
+
+/// Inserts a complete run into the database, unless the same run is already stored.
+///
+/// A run is already stored if a stored run, including one in the trash, has the same timestamp
+/// and the same [checksum](crate::checksum) of its timing data. This way, the live parser and the
+/// importer of historical logs can both read the same `EE.log` without storing its runs twice,
+/// and a run the user moved to the trash is not brought back by reading its log again.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run` - The run to insert. Its `run_id` field is ignored.
+///
+/// # Returns
+/// * `Result<InsertOutcome>` - The ID of the newly inserted run, or of the run that was already
+///   stored.
+///
+/// # Errors
+///
+/// Returns the same errors as [`insert_run`], or an error if a stored run with the same timestamp
+/// cannot be read to compute its checksum.
+pub fn insert_run_if_absent(conn: &Connection, run: &Run) -> Result<InsertOutcome> {
+    with_savepoint(conn, "insert_run_if_absent", |conn| {
+        let checksum = run_checksum(run);
+        let candidates = conn
+            .prepare_cached("SELECT id, checksum FROM runs WHERE time_stamp = ?1 ORDER BY id")?
+            .query_map([run.time_stamp], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(i64, Option<String>)>>>()?;
+
+        for (run_id, stored_checksum) in candidates {
+            // Checksums are cleared by some migrations until they are backfilled
+            let stored_checksum = match stored_checksum {
+                Some(stored_checksum) => stored_checksum,
+                None => run_checksum(&fetch_run_by_id(conn, run_id)?),
+            };
+            if stored_checksum == checksum {
+                return Ok(InsertOutcome::AlreadyExists(run_id));
+            }
+        }
+
+        insert_run_rows(conn, run, RunStatus::from_run(run)).map(InsertOutcome::Inserted)
+    })
+}
+
+/// Inserts a run that is still in progress into the database and returns its newly assigned ID.
+///
+/// The run is stored with the [`RunStatus::InProgress`] status, which leaves it out of analytics
+/// and of lists of runs until it is completed with [`complete_run`](crate::update::complete_run).
+/// Its flags, such as whether it was aborted, are stored as they are. Runs that were never completed, such as because the app
+/// crashed, are listed by [`fetch_in_progress_runs`](crate::fetch::fetch_in_progress_runs).
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run` - The run so far, such as with only its first phases. Its `run_id` field is ignored.
+///
+/// # Returns
+/// * `Result<i64>` - The ID of the newly inserted run.
+///
+/// # Errors
+///
+/// Returns the same errors as [`insert_run`].
+pub fn insert_partial_run(conn: &Connection, run: &Run) -> Result<i64> {
+    with_savepoint(conn, "insert_partial_run", |conn| {
+        insert_run_rows(conn, run, RunStatus::InProgress)
+    })
+}
+
+/// Inserts many complete runs into the database and returns their newly assigned IDs.
+///
+/// This is much faster than calling `insert_run` for each run, as every run is written inside a
+/// single savepoint, reusing the same cached prepared statements, and foreign key checks are
+/// deferred until the end of the transaction instead of being performed for every row.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `runs` - The runs to insert. Their `run_id` fields are ignored.
+/// * `progress` - Called after each run is inserted with the number of runs inserted so far and
+///   the total number of runs, for example to drive a progress bar.
+///
+/// # Returns
+/// * `Result<Vec<i64>>` - The IDs of the newly inserted runs, in the same order as `runs`.
+///
+/// # Errors
+///
+/// Returns an error if any row of any run fails to insert. In that case, none of the runs are
+/// kept.
+pub fn insert_runs_batch(
+    conn: &Connection,
+    runs: &[Run],
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Vec<i64>> {
+    with_savepoint(conn, "insert_runs_batch", |conn| {
+        // Automatically turned back off when the outermost transaction ends
+        conn.pragma_update(None, "defer_foreign_keys", true)?;
+
+        let mut run_ids = Vec::with_capacity(runs.len());
+        for run in runs {
+            run_ids.push(insert_run_rows(conn, run, RunStatus::from_run(run))?);
+            progress(run_ids.len(), runs.len());
+        }
+
+        Ok(run_ids)
+    })
+}
+
+/// Checks a run for internal consistency, then inserts it unless the policy says otherwise.
+///
+/// Runs with no warnings are inserted as they are. What happens to the others depends on
+/// `policy`: they are either rejected, or inserted but marked as bugged, with the warnings as the
+/// reason unless the run was already marked as bugged with a reason of its own.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run` - The run to insert. Its `run_id` field is ignored.
+/// * `policy` - What to do if the run fails validation.
+///
+/// # Returns
+/// * `Result<(i64, Vec<ValidationWarning>)>` - The ID of the newly inserted run, and everything
+///   that looked wrong with it.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidRun`] if the run fails validation and `policy` is
+/// [`InvalidRunPolicy::Reject`], or the same errors as `insert_run` otherwise. In particular, a run
+/// breaking the same leg twice in a phase is always rejected, as the database cannot store it.
+pub fn insert_validated_run(
+    conn: &Connection,
+    run: &Run,
+    policy: InvalidRunPolicy,
+) -> Result<(i64, Vec<ValidationWarning>)> {
+    let warnings = validate_run(run);
+    if warnings.is_empty() {
+        return Ok((insert_run(conn, run)?, warnings));
+    }
+
+    match policy {
+        InvalidRunPolicy::Reject => Err(DatabaseError::InvalidRun(warnings)),
+        InvalidRunPolicy::MarkBugged => {
+            let reason = warnings
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; ");
+
+            let run_id = with_savepoint(conn, "insert_validated_run", |conn| {
+                let run_id = insert_run_rows(conn, run, RunStatus::from_run(run))?;
+                conn.prepare_cached(
+                    "UPDATE runs SET bugged_run = TRUE, bugged_reason = COALESCE(bugged_reason, ?2)
+                    WHERE id = ?1",
+                )?
+                .execute(params![run_id, reason])?;
+
+                Ok(run_id)
+            })?;
+
+            Ok((run_id, warnings))
+        }
+    }
+}
+
+/// Overwrites the recorded details of a run that was already inserted.
+///
+/// The time stamp, player, game version, platform, flags and their reasons, total times, phases,
+/// and squad members of the stored run are replaced with those of `run`. The UUID of the run is
+/// kept, as are the details edited from the frontend, namely the name, whether the run is a
+/// favorite, its notes, its tags, and its media. Like `insert_run`, everything is written inside
+/// a savepoint.
+///
+/// A run inserted with `insert_partial_run` stays in progress, whatever the flags of `run`, until
+/// it is completed with [`complete_run`](crate::update::complete_run).
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to overwrite.
+/// * `run` - The new version of the run. Its `run_id` and `run_uuid` fields are ignored, as is
+///   its `utc_offset` field if it is `None`.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// any row fails to be written. In either case, the stored run is left unchanged.
+pub fn update_run(conn: &Connection, run_id: i64, run: &Run) -> Result<()> {
+    with_savepoint(conn, "update_run", |conn| {
+        let times = &run.total_times;
+        let updated = with_cached_stmt(
+            conn,
+            "UPDATE runs SET
+                time_stamp = ?2, player_name = ?3, game_version = ?4, platform = ?5,
+                bugged_run = ?6, bugged_reason = ?7,
+                aborted_run = ?8, aborted_reason = ?9,
+                status = CASE
+                    WHEN status = 'in_progress' THEN status
+                    WHEN ?8 THEN 'aborted'
+                    ELSE 'completed'
+                END,
+                solo_run = ?10, total_time = ?11, total_flight_time = ?12,
+                total_shield_time = ?13, total_leg_time = ?14, total_body_time = ?15,
+                total_pylon_time = ?16, checksum = ?17, utc_offset = COALESCE(?18, utc_offset)
+            WHERE id = ?1",
+            |stmt| {
+                stmt.execute(params![
+                    run_id,
+                    run.time_stamp,
+                    run.player_name,
+                    run.game_version,
+                    run.platform,
+                    run.is_bugged_run,
+                    run.bugged_reason,
+                    run.is_aborted_run,
+                    run.aborted_reason,
+                    run.is_solo_run,
+                    Duration::from_secs_f64(times.total_time),
+                    Duration::from_secs_f64(times.total_flight_time),
+                    Duration::from_secs_f64(times.total_shield_time),
+                    Duration::from_secs_f64(times.total_leg_time),
+                    Duration::from_secs_f64(times.total_body_time),
+                    Duration::from_secs_f64(times.total_pylon_time),
+                    run_checksum(run),
+                    run.utc_offset,
+                ])
+            },
+        )?;
+        if updated == 0 {
+            return Err(DatabaseError::RunNotFound(run_id));
+        }
+
+        for table in ["shield_changes", "leg_breaks", "phases", "squad_members"] {
+            conn.prepare_cached(&format!("DELETE FROM {table} WHERE run_id = ?1"))?
+                .execute([run_id])?;
+        }
+
+        insert_run_children(conn, run_id, run)
+    })
+}
+
+/// How hard log excerpts are compressed, on `miniz_oxide`'s scale from 0 to 10.
+const RAW_LOG_COMPRESSION_LEVEL: u8 = 6;
+
+/// Stores the excerpt of `EE.log` a run was parsed from, replacing any excerpt stored before.
+///
+/// The excerpt is compressed before it is stored, which shrinks the repetitive lines of the log
+/// to a fraction of their size. Keeping it means suspicious timings can be checked against the
+/// log, and runs can be parsed again after a parser fix, long after the log file itself is gone.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run the excerpt belongs to.
+/// * `log` - The lines of the log the run was parsed from.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// the query fails.
+pub fn store_raw_log(conn: &Connection, run_id: i64, log: &str) -> Result<()> {
+    let compressed =
+        miniz_oxide::deflate::compress_to_vec(log.as_bytes(), RAW_LOG_COMPRESSION_LEVEL);
+
+    let stored = execute_retrying(
+        conn,
+        "INSERT INTO raw_logs (run_id, log) SELECT id, ?2 FROM runs WHERE id = ?1
+        ON CONFLICT (run_id) DO UPDATE SET log = excluded.log",
+        params![run_id, compressed],
+    )?;
+    if stored == 0 {
+        return Err(DatabaseError::RunNotFound(run_id));
+    }
+
+    Ok(())
+}
+
+/// Inserts a run and all of its child rows with the given status, without wrapping them in a
+/// savepoint.
+fn insert_run_rows(conn: &Connection, run: &Run, status: RunStatus) -> Result<i64> {
+    let run_id = insert_run_row(conn, run, status)?;
+    insert_run_children(conn, run_id, run)?;
+
+    Ok(run_id)
+}
+
+/// Inserts the phases and squad members of a run whose top-level row already exists.
+fn insert_run_children(conn: &Connection, run_id: i64, run: &Run) -> Result<()> {
+    for phase in &run.phases {
+        insert_phase(conn, run_id, phase)?;
+    }
+
+    for squad_member in &run.squad_members {
+        insert_squad_member(conn, run_id, squad_member)?;
+    }
+
+    Ok(())
+}
+
+/// Inserts the top-level row of a run into the `runs` table and returns its ID.
+fn insert_run_row(conn: &Connection, run: &Run, status: RunStatus) -> Result<i64> {
+    let times = &run.total_times;
+    let run_uuid = run
+        .run_uuid
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    with_cached_stmt(
+        conn,
+        "INSERT INTO runs (
+            time_stamp, run_name, player_name, bugged_run, aborted_run, solo_run, favorite,
+            notes, game_version, platform, bugged_reason, aborted_reason, total_time,
+            total_flight_time, total_shield_time, total_leg_time, total_body_time,
+            total_pylon_time, run_uuid, checksum, status, utc_offset
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19,
+            ?20, ?21, ?22
+        )",
+        |stmt| {
+            stmt.execute(params![
+                run.time_stamp,
+                run.run_name,
+                run.player_name,
+                run.is_bugged_run,
+                run.is_aborted_run,
+                run.is_solo_run,
+                run.is_favorite,
+                run.notes,
+                run.game_version,
+                run.platform,
+                run.bugged_reason,
+                run.aborted_reason,
+                Duration::from_secs_f64(times.total_time),
+                Duration::from_secs_f64(times.total_flight_time),
+                Duration::from_secs_f64(times.total_shield_time),
+                Duration::from_secs_f64(times.total_leg_time),
+                Duration::from_secs_f64(times.total_body_time),
+                Duration::from_secs_f64(times.total_pylon_time),
+                run_uuid,
+                run_checksum(run),
+                status.to_sql(),
+                run.utc_offset
+                    .unwrap_or_else(|| local_utc_offset(run.time_stamp)),
+            ])
+        },
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Inserts a phase along with its shield changes and leg breaks.
+fn insert_phase(conn: &Connection, run_id: i64, phase: &Phase) -> Result<()> {
+    with_cached_stmt(
+        conn,
+        "INSERT INTO phases (
+            run_id, phase_number, phase_time, shield_time, leg_time, body_kill_time, pylon_time
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        |stmt| {
+            stmt.execute(params![
+                run_id,
+                phase.phase_number,
+                Duration::from_secs_f64(phase.total_time),
+                Duration::from_secs_f64(phase.total_shield_time),
+                Duration::from_secs_f64(phase.total_leg_time),
+                Duration::from_secs_f64(phase.total_body_kill_time),
+                Duration::from_secs_f64(phase.total_pylon_time),
+            ])
+        },
+    )?;
+
+    for shield_change in &phase.shield_changes {
+        insert_shield_change(conn, run_id, phase.phase_number, shield_change)?;
+    }
+
+    for leg_break in &phase.leg_breaks {
+        insert_leg_break(conn, run_id, phase.phase_number, leg_break)?;
+    }
+
+    Ok(())
+}
+
+/// Inserts a single shield change belonging to a phase.
+fn insert_shield_change(
+    conn: &Connection,
+    run_id: i64,
+    phase_number: i32,
+    shield_change: &ShieldChange,
+) -> Result<()> {
+    with_cached_stmt(
+        conn,
+        "INSERT INTO shield_changes
+            (shield_time, status_effect_id, run_id, phase_number, is_overshield)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        |stmt| {
+            stmt.execute(params![
+                Duration::from_secs_f64(shield_change.shield_time),
+                i64::from(shield_change.status_effect),
+                run_id,
+                phase_number,
+                shield_change.is_overshield,
+            ])
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Inserts a single leg break belonging to a phase.
+fn insert_leg_break(
+    conn: &Connection,
+    run_id: i64,
+    phase_number: i32,
+    leg_break: &LegBreak,
+) -> Result<()> {
+    with_cached_stmt(
+        conn,
+        "INSERT INTO leg_breaks (run_id, phase_number, break_time, break_order, leg_position_id)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        |stmt| {
+            stmt.execute(params![
+                run_id,
+                phase_number,
+                Duration::from_secs_f64(leg_break.leg_break_time),
+                leg_break.leg_order,
+                i64::from(leg_break.leg_position),
+            ])
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Inserts a squad member participating in a run.
+fn insert_squad_member(conn: &Connection, run_id: i64, squad_member: &SquadMember) -> Result<()> {
+    with_cached_stmt(
+        conn,
+        "INSERT INTO squad_members (run_id, member_name) VALUES (?1, ?2)",
+        |stmt| stmt.execute(params![run_id, squad_member.member_name]),
+    )?;
+    link_name(conn, &squad_member.member_name)?;
+
+    Ok(())
+}
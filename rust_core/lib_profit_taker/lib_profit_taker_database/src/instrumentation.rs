@@ -0,0 +1,77 @@
+//! This module provides query timings, so that performance regressions in the analytics queries
+//! show up in the logs of users instead of only as a vague feeling that the app got slower.
+//!
+//! It is only available with the `tracing` feature enabled. Connections opened through
+//! [`ConnectionOptions`](crate::connection::ConnectionOptions) or a [`Pool`](crate::pool::Pool)
+//! are then instrumented automatically, and other connections can be instrumented with
+//! `instrument`. Every statement run on an instrumented connection is logged at the `trace` level
+//! with how long it took, and statements slower than the slow query threshold are also logged at
+//! the `warn` level. SQLite only measures these durations to the millisecond, so quick statements
+//! are usually reported as taking no time at all.
+//!
+//! Records are emitted through the `log` crate under the [`QUERY_LOG_TARGET`] target, so any
+//! logger can collect them, including `tracing` subscribers through `tracing-log`.
+
+use rusqlite::Connection;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The target of the log records of query timings.
+pub const QUERY_LOG_TARGET: &str = "lib_profit_taker_database::query";
+
+/// The slow query threshold used until `set_slow_query_threshold` is called.
+pub const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// The slow query threshold, in microseconds.
+///
+/// This is global rather than per connection because SQLite's profiling callback cannot carry any
+/// state of its own.
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "the default threshold is far below 2^64 microseconds"
+)]
+static SLOW_QUERY_THRESHOLD_MICROS: AtomicU64 =
+    AtomicU64::new(DEFAULT_SLOW_QUERY_THRESHOLD.as_micros() as u64);
+
+/// Sets how long a statement can take before it is logged as a slow query, for every
+/// instrumented connection.
+///
+/// # Arguments
+/// * `threshold` - The new slow query threshold.
+pub fn set_slow_query_threshold(threshold: Duration) {
+    let micros = u64::try_from(threshold.as_micros()).unwrap_or(u64::MAX);
+    SLOW_QUERY_THRESHOLD_MICROS.store(micros, Ordering::Relaxed);
+}
+
+/// Returns how long a statement can take before it is logged as a slow query.
+#[must_use]
+pub fn slow_query_threshold() -> Duration {
+    Duration::from_micros(SLOW_QUERY_THRESHOLD_MICROS.load(Ordering::Relaxed))
+}
+
+/// Starts logging the duration of every statement run on `conn`.
+///
+/// A connection can only have one profiler, so this replaces any profiler registered before.
+///
+/// # Arguments
+/// * `conn` - The connection to instrument.
+pub fn instrument(conn: &mut Connection) {
+    conn.profile(Some(log_query));
+}
+
+/// Stops logging the duration of the statements run on `conn`.
+///
+/// # Arguments
+/// * `conn` - The connection to stop instrumenting.
+pub fn uninstrument(conn: &mut Connection) {
+    conn.profile(None);
+}
+
+/// Logs a statement that finished running, and warns about it if it was slow.
+fn log_query(sql: &str, duration: Duration) {
+    log::trace!(target: QUERY_LOG_TARGET, "query took {duration:?}: {sql}");
+
+    if duration > slow_query_threshold() {
+        log::warn!(target: QUERY_LOG_TARGET, "slow query took {duration:?}: {sql}");
+    }
+}
@@ -4,7 +4,46 @@
 
 #![warn(clippy::nursery, clippy::pedantic)]
 
+pub mod analytics;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+mod base64;
+pub mod cache;
+pub mod checksum;
 pub mod connection;
+pub mod delete;
+pub mod dto;
+pub mod duration;
+pub mod error;
+pub mod events;
+pub mod export;
+pub mod fetch;
+pub mod goals;
+pub mod import;
+pub mod insert;
+#[cfg(feature = "tracing")]
+pub mod instrumentation;
+pub mod loadouts;
+pub mod local_time;
+mod lookup;
+pub mod maintenance;
+pub mod manager;
+pub mod media;
+pub mod migrations;
+pub mod players;
+pub mod pool;
+pub mod profiles;
+pub mod query;
 pub mod schema;
-
-// TODO: include more modules here for additional functionality like inserts, fetches, etc
+pub mod search;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod settings;
+pub mod storage;
+pub mod tags;
+#[cfg(feature = "test-utils")]
+pub mod testing;
+pub mod undo;
+pub mod update;
+pub mod validation;
+pub mod writer;
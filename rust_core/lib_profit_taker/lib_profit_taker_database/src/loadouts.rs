@@ -0,0 +1,181 @@
+//! This module provides functions for recording what a run was played with, such as the Warframe,
+//! the weapons, and the arch-gun, so players can compare, for example, their Volt and Titania
+//! flight times.
+//!
+//! Loadouts are optional, as the parser cannot tell what a run was played with, so each run has
+//! at most one loadout, entered by the user. Names are stored as they are entered, and compared
+//! without regard to case by [`RunFilter`](crate::fetch::RunFilter) and
+//! [`RunCategory`](crate::analytics::RunCategory), which can both narrow runs down to a Warframe or
+//! a weapon. Kitguns and other modular weapons are recorded in the slot they are used in.
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::connection::execute_retrying;
+use crate::error::Result;
+use crate::tags::ensure_run_exists;
+
+/// What a run was played with.
+///
+/// Each field is the name of what was used in that slot, or `None` if it is not known or the slot
+/// was empty.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Loadout {
+    /// The Warframe, such as `Volt Prime`.
+    pub warframe: Option<String>,
+
+    /// The primary weapon.
+    pub primary_weapon: Option<String>,
+
+    /// The secondary weapon, such as a kitgun.
+    pub secondary_weapon: Option<String>,
+
+    /// The melee weapon.
+    pub melee_weapon: Option<String>,
+
+    /// The arch-gun, used against the pylons.
+    pub arch_gun: Option<String>,
+}
+
+impl Loadout {
+    /// Returns whether no slot of this loadout is known.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.warframe.is_none()
+            && self.primary_weapon.is_none()
+            && self.secondary_weapon.is_none()
+            && self.melee_weapon.is_none()
+            && self.arch_gun.is_none()
+    }
+}
+
+/// Records what a run was played with, replacing any loadout recorded for it before.
+///
+/// Leading and trailing whitespace is removed from each name, and names that are then empty are
+/// treated as unknown. Setting a loadout with no known slot removes the loadout instead.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to record the loadout of.
+/// * `loadout` - What the run was played with.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`](crate::error::DatabaseError::RunNotFound) if no run with
+/// the given ID exists, or another error if a query fails.
+pub fn set_loadout(conn: &Connection, run_id: i64, loadout: &Loadout) -> Result<()> {
+    ensure_run_exists(conn, run_id)?;
+
+    let name = |name: &Option<String>| {
+        name.as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_owned)
+    };
+    let loadout = Loadout {
+        warframe: name(&loadout.warframe),
+        primary_weapon: name(&loadout.primary_weapon),
+        secondary_weapon: name(&loadout.secondary_weapon),
+        melee_weapon: name(&loadout.melee_weapon),
+        arch_gun: name(&loadout.arch_gun),
+    };
+    if loadout.is_empty() {
+        remove_loadout(conn, run_id)?;
+        return Ok(());
+    }
+
+    execute_retrying(
+        conn,
+        "INSERT INTO loadouts (
+            run_id, warframe, primary_weapon, secondary_weapon, melee_weapon, arch_gun
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ON CONFLICT (run_id) DO UPDATE SET
+            warframe = excluded.warframe,
+            primary_weapon = excluded.primary_weapon,
+            secondary_weapon = excluded.secondary_weapon,
+            melee_weapon = excluded.melee_weapon,
+            arch_gun = excluded.arch_gun",
+        params![
+            run_id,
+            loadout.warframe,
+            loadout.primary_weapon,
+            loadout.secondary_weapon,
+            loadout.melee_weapon,
+            loadout.arch_gun,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Removes the loadout recorded for a run.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run whose loadout to remove.
+///
+/// # Returns
+/// * `Result<bool>` - Whether the run had a loadout.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn remove_loadout(conn: &Connection, run_id: i64) -> Result<bool> {
+    let removed = execute_retrying(conn, "DELETE FROM loadouts WHERE run_id = ?1", [run_id])?;
+
+    Ok(removed > 0)
+}
+
+/// Fetches what a run was played with.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run whose loadout to fetch.
+///
+/// # Returns
+/// * `Result<Option<Loadout>>` - The loadout of the run, or `None` if none was recorded.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`](crate::error::DatabaseError::RunNotFound) if no run with
+/// the given ID exists, or another error if the query fails.
+pub fn fetch_loadout(conn: &Connection, run_id: i64) -> Result<Option<Loadout>> {
+    ensure_run_exists(conn, run_id)?;
+
+    conn.prepare_cached(
+        "SELECT warframe, primary_weapon, secondary_weapon, melee_weapon, arch_gun
+        FROM loadouts WHERE run_id = ?1",
+    )?
+    .query_row([run_id], |row| {
+        Ok(Loadout {
+            warframe: row.get(0)?,
+            primary_weapon: row.get(1)?,
+            secondary_weapon: row.get(2)?,
+            melee_weapon: row.get(3)?,
+            arch_gun: row.get(4)?,
+        })
+    })
+    .optional()
+    .map_err(Into::into)
+}
+
+/// Lists every Warframe that runs were recorded with, for the choices of a loadout filter.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Vec<String>>` - The names of the Warframes, sorted without regard to case. Names
+///   that only differ in case are listed once.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn fetch_warframes(conn: &Connection) -> Result<Vec<String>> {
+    conn.prepare_cached(
+        "SELECT MIN(warframe) FROM loadouts WHERE warframe IS NOT NULL
+        GROUP BY warframe COLLATE NOCASE ORDER BY warframe COLLATE NOCASE",
+    )?
+    .query_map([], |row| row.get(0))?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
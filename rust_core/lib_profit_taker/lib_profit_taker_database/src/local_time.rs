@@ -0,0 +1,69 @@
+//! This module provides functions for working out the local time at which runs were played.
+//!
+//! The timestamp of a run is a Unix timestamp, which is the same wherever it is read. Alongside
+//! it, each run stores the offset from UTC of the local time where it was played, so that the
+//! day, hour, and weekday a run counts towards are the ones on the player's clock when it was
+//! played. They do not shift when the player travels or changes the time zone of their computer,
+//! which reading timestamps in the current time zone would do.
+//!
+//! Runs stored without an offset, such as those from the parser, are given the offset of the
+//! current time zone at the time they were played by `local_utc_offset`.
+
+use chrono::{FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone};
+
+/// The local time at which a run was played, relative to the Unix epoch, as the arguments of an
+/// SQLite date and time function such as `date()` or `strftime()`.
+///
+/// The `runs` table must be in scope, without an alias.
+pub(crate) const RUN_LOCAL_TIME_SQL: &str = "time_stamp + utc_offset, 'unixepoch'";
+
+/// Returns the offset from UTC of the current time zone at the given time.
+///
+/// This follows daylight saving time, so a run played in the summer may have a different offset
+/// than one played in the winter in the same place.
+///
+/// # Arguments
+/// * `time_stamp` - The Unix timestamp at which to look up the offset.
+///
+/// # Returns
+/// * `i32` - The offset, in seconds east of UTC, or 0 if the time is out of range.
+#[must_use]
+pub fn local_utc_offset(time_stamp: i64) -> i32 {
+    Local
+        .timestamp_opt(time_stamp, 0)
+        .earliest()
+        .map_or(0, |time| time.offset().fix().local_minus_utc())
+}
+
+/// Returns the local date and time at which a run was played.
+///
+/// # Arguments
+/// * `time_stamp` - The Unix timestamp at which the run was started.
+/// * `utc_offset` - The offset from UTC, in seconds, of the local time where the run was played.
+///
+/// # Returns
+/// * `Option<NaiveDateTime>` - The local date and time, or `None` if the offset is more than a day
+///   or the time is out of range.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use lib_profit_taker_database::local_time::local_date_time;
+///
+/// // 23:30 UTC is already the next day at UTC+1
+/// let played = local_date_time(1_700_004_600, 3600).unwrap();
+/// assert_eq!(played.date(), NaiveDate::from_ymd_opt(2023, 11, 15).unwrap());
+/// ```
+#[must_use]
+pub fn local_date_time(time_stamp: i64, utc_offset: i32) -> Option<NaiveDateTime> {
+    FixedOffset::east_opt(utc_offset)?
+        .timestamp_opt(time_stamp, 0)
+        .single()
+        .map(|time| time.naive_local())
+}
+
+/// Formats a date the way SQLite's `date()` function does, for comparing with local dates of runs.
+pub(crate) fn date_to_sql(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
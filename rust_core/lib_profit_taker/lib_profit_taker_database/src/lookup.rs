@@ -0,0 +1,27 @@
+//! This module reads the model enums from the IDs of the lookup tables seeded by the schema.
+//!
+//! The `leg_position` and `status_effects` tables are populated with fixed IDs in `SCHEMA_SQL`,
+//! which must be kept in sync with the integer IDs of `LegPosition` and `StatusEffect`. Writing
+//! an enum only takes `i64::from`, but reading one can fail, so the conversions here turn an
+//! unknown ID into a `rusqlite` error.
+
+use lib_profit_taker_core::{LegPosition, StatusEffect};
+use rusqlite::Row;
+
+/// Reads a `status_effects.id` from a column of a row and converts it to a status effect.
+///
+/// Fails with [`rusqlite::Error::IntegralValueOutOfRange`] if the ID is unknown.
+pub fn get_status_effect(row: &Row, index: usize) -> rusqlite::Result<StatusEffect> {
+    let id = row.get(index)?;
+
+    StatusEffect::try_from(id).map_err(|_| rusqlite::Error::IntegralValueOutOfRange(index, id))
+}
+
+/// Reads a `leg_position.id` from a column of a row and converts it to a leg position.
+///
+/// Fails with [`rusqlite::Error::IntegralValueOutOfRange`] if the ID is unknown.
+pub fn get_leg_position(row: &Row, index: usize) -> rusqlite::Result<LegPosition> {
+    let id = row.get(index)?;
+
+    LegPosition::try_from(id).map_err(|_| rusqlite::Error::IntegralValueOutOfRange(index, id))
+}
@@ -0,0 +1,1374 @@
+//! This module provides maintenance operations on the SQLite database as a whole, as opposed to
+//! operations on individual runs.
+//!
+//! The `backup_to` and `restore_from` functions use SQLite's online backup API, which copies the
+//! database page by page while it remains usable, so users can safeguard their run history (for
+//! example, before an app update) without closing the app.
+//!
+//! The `find_duplicates` and `remove_duplicates` functions clean up copies of the same run, which
+//! can be left behind by repeated imports.
+//!
+//! The `snapshot_for_analysis` function copies the database into memory with the same API, so
+//! that heavy analytics can run against the copy without holding up run inserts.
+//!
+//! The `check_integrity` and `repair` functions look for and clean up damage, such as rows left
+//! behind by a crash, that would otherwise cause confusing errors elsewhere.
+//!
+//! The `move_database` function relocates the database file, for example into a folder synced to
+//! the cloud.
+//!
+//! The `optimize` function compacts the database file and refreshes the statistics SQLite uses to
+//! plan queries, for the "Compact database" button of the settings screen.
+//!
+//! The `db_stats` function reports how large the database is and what takes up the space, for
+//! the settings screen and for deciding whether old runs are worth archiving.
+//!
+//! The `explain` function reports how SQLite plans to run a query, to check that a query uses the
+//! indexes it is meant to instead of scanning whole tables.
+//!
+//! The `archive_runs` function moves old runs into a separate archive database, keeping the
+//! main one small and fast, and `attach_archive` makes them visible again when the user asks.
+//!
+//! The `reparse_runs` function parses the stored log excerpts of runs again and updates their
+//! timings, so that a fix to the parser also corrects the runs recorded before it.
+//!
+//! The `apply_retention` function deletes the runs that a [`RetentionPolicy`] does not keep, such
+//! as all but the fastest few runs of each month, so the database does not grow without bound.
+//!
+//! The `audit` function looks for runs whose stored data does not make sense, such as a negative
+//! time or a squad of five, for the diagnostics screen and for checking runs after an import, and
+//! `fix_anomalies` marks those runs as bugged so they stop counting towards the statistics.
+
+use lib_profit_taker_core::Run;
+use rusqlite::backup::Backup;
+use rusqlite::{params, Connection, DatabaseName, OpenFlags};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::connection::{remove_database_files, with_savepoint, ConnectionOptions};
+use crate::delete::{delete_run, purge_trashed};
+use crate::duration;
+use crate::error::{DatabaseError, Result};
+use crate::fetch::fetch_raw_log;
+use crate::insert::update_run;
+use crate::local_time::RUN_LOCAL_TIME_SQL;
+use crate::migrations::{self, LATEST_VERSION};
+use crate::validation::{MAX_SQUAD_SIZE, PHASE_COUNT, TIME_TOLERANCE};
+
+/// Writes a copy of the database to the given path.
+///
+/// If a file already exists at the path, it is overwritten. The directory structure is created
+/// if needed.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `path` - The file path to write the backup to.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Io`] if the directory structure cannot be created, or another error
+/// if the backup fails.
+pub fn backup_to(conn: &Connection, path: &str) -> Result<()> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    conn.backup(DatabaseName::Main, path, None)?;
+
+    Ok(())
+}
+
+/// Moves the database to a new location, returning a connection to it there.
+///
+/// The database is copied with the same online backup API as `backup_to`, so everything
+/// committed so far is included even if it is still in the write-ahead log. The copy is checked
+/// for corruption and broken references before the original files are removed, and if it fails
+/// the check, the copy is removed instead and the original is left untouched.
+///
+/// Any other connections to the database, such as those of a pool, must be closed first, or the
+/// original files cannot be removed on Windows.
+///
+/// # Arguments
+/// * `conn` - The connection to the database to move, which is closed.
+/// * `new_path` - The file path to move the database to. The directory structure is created if
+///   needed.
+///
+/// # Returns
+/// * `Result<Connection>` - A connection to the database at its new location, configured with
+///   the default [`ConnectionOptions`].
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the database is not stored in a file or the copy
+/// fails the integrity check, [`DatabaseError::Io`] if a file already exists at `new_path` or the
+/// original files cannot be removed, or another error if the copy fails.
+pub fn move_database(conn: Connection, new_path: &str) -> Result<Connection> {
+    let old_path = match conn.path() {
+        Some(path) if !path.is_empty() => path.to_owned(),
+        _ => {
+            return Err(DatabaseError::InvalidData(
+                "only databases stored in a file can be moved".to_owned(),
+            ))
+        }
+    };
+    if Path::new(new_path).exists() {
+        return Err(DatabaseError::Io(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("a file already exists at `{new_path}`"),
+        )));
+    }
+
+    let verified = backup_to(&conn, new_path).and_then(|()| {
+        let copy = Connection::open(new_path).map_err(DatabaseError::ConnectionFailed)?;
+        let report = check_integrity(&copy)?;
+        if !(report.integrity_errors.is_empty() && report.foreign_key_violations.is_empty()) {
+            return Err(DatabaseError::InvalidData(format!(
+                "the copy at `{new_path}` failed the integrity check"
+            )));
+        }
+
+        Ok(())
+    });
+    if let Err(e) = verified {
+        // The original error is the one worth reporting, so a failure here is ignored
+        let _ = remove_database_files(new_path);
+        return Err(e);
+    }
+
+    drop(conn);
+    remove_database_files(&old_path)?;
+
+    ConnectionOptions::default().open(new_path)
+}
+
+/// Replaces the contents of the database with a backup made by `backup_to`.
+///
+/// The backup is validated before anything is replaced: it must be a database created by this
+/// library, with a schema version no newer than [`LATEST_VERSION`]. Backups with an older schema
+/// are migrated to the latest version after being restored.
+///
+/// # Arguments
+/// * `conn` - A mutable reference to the active SQLite database connection to restore into.
+/// * `path` - The file path of the backup to restore.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::ConnectionFailed`] if the backup cannot be opened,
+/// [`DatabaseError::InvalidData`] if it is not a database created by this library,
+/// [`DatabaseError::SchemaVersionMismatch`] if it was created by a newer version of this library,
+/// or another error if the restore or migration fails.
+pub fn restore_from(conn: &mut Connection, path: &str) -> Result<()> {
+    validate_backup(path)?;
+
+    conn.restore(DatabaseName::Main, path, None::<fn(_)>)?;
+    migrations::migrate(conn)?;
+
+    Ok(())
+}
+
+/// Copies the database into memory, for running expensive analytics against without touching the
+/// database itself.
+///
+/// Queries that read many runs, such as histograms and trends, keep the database locked for
+/// reading while they run. Running them against a snapshot instead means they never delay run
+/// inserts, and only the copy itself briefly reads the database. The snapshot does not see
+/// anything committed after it was taken.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Connection>` - A connection to the copy, which is discarded when the connection is
+///   closed. The copy is read-only, and its schema is that of the original database.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::ConnectionFailed`] if the in-memory database cannot be opened, or
+/// another error if the copy fails.
+pub fn snapshot_for_analysis(conn: &Connection) -> Result<Connection> {
+    let mut snapshot = Connection::open_in_memory().map_err(DatabaseError::ConnectionFailed)?;
+
+    // Copying every page in a single step reads a consistent state of the database, which steps
+    // interleaved with writes would have to restart to do. The pause is only taken while another
+    // connection holds a lock that keeps the step from starting
+    Backup::new(conn, &mut snapshot)?.run_to_completion(
+        i32::MAX,
+        Duration::from_millis(10),
+        None,
+    )?;
+    snapshot.pragma_update(None, "query_only", true)?;
+
+    Ok(snapshot)
+}
+
+/// Checks that the file at the given path is a database this library can restore.
+fn validate_backup(path: &str) -> Result<()> {
+    let backup = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(DatabaseError::ConnectionFailed)?;
+
+    let version = migrations::current_version(&backup)?;
+    if version > LATEST_VERSION {
+        return Err(DatabaseError::SchemaVersionMismatch {
+            expected: LATEST_VERSION,
+            found: version,
+        });
+    }
+
+    // Databases from before migrations existed have no version, but do have the initial tables
+    if version == 0 && !migrations::table_exists(&backup, "runs")? {
+        return Err(DatabaseError::InvalidData(format!(
+            "`{path}` is not a Profit-Taker Analytics database"
+        )));
+    }
+
+    Ok(())
+}
+
+/// A set of stored runs that are copies of the same run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// The ID of the copy that `remove_duplicates` keeps.
+    pub kept_run_id: i64,
+
+    /// The IDs of the other copies, which `remove_duplicates` deletes.
+    pub duplicate_run_ids: Vec<i64>,
+}
+
+/// Finds every set of stored runs, including those in the trash, that have the same timestamp
+/// and total times.
+///
+/// In each group, the copy to keep is the first one that is not in the trash, then the first
+/// favorite, then the one stored first. This way, a copy that the user has favorited or renamed is
+/// kept over the untouched ones.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Vec<DuplicateGroup>>` - The groups of copies, ordered by the ID of the kept copy.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn find_duplicates(conn: &Connection) -> Result<Vec<DuplicateGroup>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT kept_id, id FROM (
+            SELECT
+                id,
+                FIRST_VALUE(id) OVER copies AS kept_id,
+                COUNT(*) OVER copies_unordered AS copy_count
+            FROM runs
+            WINDOW
+                copies_unordered AS (
+                    PARTITION BY time_stamp, total_time, total_flight_time, total_shield_time,
+                        total_leg_time, total_body_time, total_pylon_time
+                ),
+                copies AS (
+                    copies_unordered
+                    ORDER BY deleted_at IS NOT NULL, favorite DESC, id
+                    ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING
+                )
+        )
+        WHERE copy_count > 1 AND id != kept_id
+        ORDER BY kept_id, id",
+    )?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<(i64, i64)>>>()?;
+
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for (kept_run_id, duplicate_run_id) in rows {
+        match groups.last_mut() {
+            Some(group) if group.kept_run_id == kept_run_id => {
+                group.duplicate_run_ids.push(duplicate_run_id);
+            }
+            _ => groups.push(DuplicateGroup {
+                kept_run_id,
+                duplicate_run_ids: vec![duplicate_run_id],
+            }),
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Permanently deletes every copy found by `find_duplicates` except the kept one.
+///
+/// If any copy in a group is a favorite, the kept copy is marked as a favorite. It is also given
+/// the tags and media of every copy, and the notes of the first copy with notes if it has none of
+/// its own. Either every duplicate is removed or, if an error occurs, none of them are.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<usize>` - The number of runs deleted.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn remove_duplicates(conn: &Connection) -> Result<usize> {
+    with_savepoint(conn, "remove_duplicates", |conn| {
+        let mut removed = 0;
+
+        for group in find_duplicates(conn)? {
+            for run_id in group.duplicate_run_ids {
+                // Only needed when the favorited copies are all in the trash, since a favorite
+                // outside the trash would have been kept
+                conn.prepare_cached(
+                    "UPDATE runs SET favorite = TRUE
+                    WHERE id = ?1 AND (SELECT favorite FROM runs WHERE id = ?2)",
+                )?
+                .execute([group.kept_run_id, run_id])?;
+                conn.prepare_cached(
+                    "UPDATE runs SET notes = (SELECT notes FROM runs WHERE id = ?2)
+                    WHERE id = ?1 AND notes IS NULL",
+                )?
+                .execute([group.kept_run_id, run_id])?;
+                conn.prepare_cached(
+                    "INSERT OR IGNORE INTO tags (run_id, tag)
+                    SELECT ?1, tag FROM tags WHERE run_id = ?2",
+                )?
+                .execute([group.kept_run_id, run_id])?;
+                conn.prepare_cached("UPDATE OR IGNORE media SET run_id = ?1 WHERE run_id = ?2")?
+                    .execute([group.kept_run_id, run_id])?;
+
+                delete_run(conn, run_id)?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    })
+}
+
+/// Child tables along with the condition matching their rows that no longer belong to anything.
+///
+/// Phases come before shield changes and leg breaks, so that repairing also removes the children
+/// of the orphaned phases it deletes.
+const ORPHAN_CONDITIONS: [(&str, &str); 9] = [
+    ("phases", "run_id NOT IN (SELECT id FROM runs)"),
+    (
+        "shield_changes",
+        "(run_id, phase_number) NOT IN (SELECT run_id, phase_number FROM phases)",
+    ),
+    (
+        "leg_breaks",
+        "(run_id, phase_number) NOT IN (SELECT run_id, phase_number FROM phases)",
+    ),
+    ("squad_members", "run_id NOT IN (SELECT id FROM runs)"),
+    ("tags", "run_id NOT IN (SELECT id FROM runs)"),
+    ("raw_logs", "run_id NOT IN (SELECT id FROM runs)"),
+    ("media", "run_id NOT IN (SELECT id FROM runs)"),
+    ("loadouts", "run_id NOT IN (SELECT id FROM runs)"),
+    ("run_search", "rowid NOT IN (SELECT id FROM runs)"),
+];
+
+/// The problems found by `check_integrity`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IntegrityReport {
+    /// The problems reported by SQLite's `PRAGMA integrity_check`, such as corrupted pages or
+    /// indexes. These cannot be fixed by `repair`; restoring a backup is the only remedy.
+    pub integrity_errors: Vec<String>,
+
+    /// The rows that reference a row missing from another table, as reported by SQLite's
+    /// `PRAGMA foreign_key_check`.
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
+
+    /// The tables that contain rows belonging to a run or phase that no longer exists, which
+    /// `repair` deletes.
+    pub orphaned_rows: Vec<OrphanedRows>,
+}
+
+impl IntegrityReport {
+    /// Returns whether no problems were found.
+    #[must_use]
+    pub const fn is_healthy(&self) -> bool {
+        self.integrity_errors.is_empty()
+            && self.foreign_key_violations.is_empty()
+            && self.orphaned_rows.is_empty()
+    }
+}
+
+/// A row that references a row missing from another table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKeyViolation {
+    /// The table containing the row.
+    pub table: String,
+
+    /// The row ID of the row, or `None` if the table has no row IDs.
+    pub row_id: Option<i64>,
+
+    /// The table the missing row should be in.
+    pub parent: String,
+}
+
+/// The number of rows of a table that belong to a run or phase that no longer exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrphanedRows {
+    /// The table containing the rows.
+    pub table: &'static str,
+
+    /// The number of orphaned rows.
+    pub count: usize,
+}
+
+/// Checks the database for corruption, broken references, and orphaned rows.
+///
+/// This only reads from the database, so it is safe to run at any time, but it reads every page
+/// of the database and can take a while for large ones.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<IntegrityReport>` - Every problem found.
+///
+/// # Errors
+///
+/// Returns an error if a check fails to run.
+pub fn check_integrity(conn: &Connection) -> Result<IntegrityReport> {
+    let integrity_errors = conn
+        .prepare("PRAGMA integrity_check")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .filter(|message| !matches!(message.as_deref(), Ok("ok")))
+        .collect::<rusqlite::Result<_>>()?;
+
+    let foreign_key_violations = conn
+        .prepare("PRAGMA foreign_key_check")?
+        .query_map([], |row| {
+            Ok(ForeignKeyViolation {
+                table: row.get(0)?,
+                row_id: row.get(1)?,
+                parent: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut orphaned_rows = Vec::new();
+    for (table, condition) in ORPHAN_CONDITIONS {
+        let count = conn.query_row(
+            &format!("SELECT COUNT(*) FROM {table} WHERE {condition}"),
+            [],
+            |row| row.get(0),
+        )?;
+        if count > 0 {
+            orphaned_rows.push(OrphanedRows { table, count });
+        }
+    }
+
+    Ok(IntegrityReport {
+        integrity_errors,
+        foreign_key_violations,
+        orphaned_rows,
+    })
+}
+
+/// Deletes every row that belongs to a run or phase that no longer exists.
+///
+/// Either every orphaned row is deleted or, if an error occurs, none of them are. Corruption
+/// reported in [`IntegrityReport::integrity_errors`] is left as is.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<usize>` - The number of rows deleted.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn repair(conn: &Connection) -> Result<usize> {
+    with_savepoint(conn, "repair", |conn| {
+        let mut deleted = 0;
+        for (table, condition) in ORPHAN_CONDITIONS {
+            deleted += conn.execute(&format!("DELETE FROM {table} WHERE {condition}"), [])?;
+        }
+
+        Ok(deleted)
+    })
+}
+
+/// The size of the database before and after `optimize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeReport {
+    /// The size of the database before optimizing, in bytes.
+    pub before_bytes: u64,
+
+    /// The size of the database after optimizing, in bytes.
+    pub after_bytes: u64,
+}
+
+/// Compacts the database and refreshes its query planner statistics.
+///
+/// This runs `ANALYZE`, `VACUUM`, and `PRAGMA optimize`, then checkpoints the write-ahead log, if
+/// any, so that the space freed by deleted runs is returned to the operating system. `VACUUM`
+/// rewrites the whole database, so this can take a while for large ones, and other connections
+/// cannot write in the meantime.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection. No transaction may be open on
+///   it, as SQLite cannot vacuum inside a transaction.
+///
+/// # Returns
+/// * `Result<SizeReport>` - The size of the database before and after optimizing.
+///
+/// # Errors
+///
+/// Returns an error if a transaction is open on `conn`, or if a step fails.
+pub fn optimize(conn: &Connection) -> Result<SizeReport> {
+    let before_bytes = database_size(conn)?;
+
+    conn.execute_batch("ANALYZE; VACUUM; PRAGMA optimize;")?;
+    // Reports how much of the log was checkpointed, which is not needed here
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+
+    Ok(SizeReport {
+        before_bytes,
+        after_bytes: database_size(conn)?,
+    })
+}
+
+/// Returns the size of the database, in bytes.
+fn database_size(conn: &Connection) -> Result<u64> {
+    conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(Into::into)
+}
+
+/// The size of the database and of everything in it, as reported by `db_stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseStats {
+    /// The size of the database file, in bytes, not counting the write-ahead log.
+    pub file_bytes: u64,
+
+    /// The number of pages in the database file.
+    pub page_count: u64,
+
+    /// The size of each page, in bytes.
+    pub page_size: u64,
+
+    /// The number of unused pages, which `optimize` would return to the operating system.
+    pub free_pages: u64,
+
+    /// The size of the write-ahead log, in bytes, or 0 if there is none.
+    pub wal_bytes: u64,
+
+    /// The number of runs outside the trash.
+    pub run_count: u64,
+
+    /// The tables of the database, by name.
+    pub tables: Vec<TableStats>,
+
+    /// The indexes of the database, by name.
+    pub indexes: Vec<IndexStats>,
+}
+
+/// The size of a table, as part of `DatabaseStats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableStats {
+    /// The name of the table.
+    pub name: String,
+
+    /// The number of rows in the table.
+    pub row_count: u64,
+
+    /// The space taken up by the rows of the table, in bytes, not counting its indexes.
+    pub bytes: u64,
+}
+
+/// The size of an index, as part of `DatabaseStats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexStats {
+    /// The name of the index.
+    pub name: String,
+
+    /// The name of the table the index is on.
+    pub table: String,
+
+    /// The space taken up by the index, in bytes.
+    pub bytes: u64,
+}
+
+/// Reports the size of the database, the number of rows in each table, and the size of each
+/// table and index.
+///
+/// Virtual tables, such as the full-text search index, are not listed themselves, but the tables
+/// that store their contents are.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<DatabaseStats>` - The sizes and row counts.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Io`] if the size of the write-ahead log cannot be read, or another
+/// error if a query fails.
+pub fn db_stats(conn: &Connection) -> Result<DatabaseStats> {
+    let (page_count, page_size, free_pages) = conn.query_row(
+        "SELECT page_count, page_size, freelist_count
+        FROM pragma_page_count(), pragma_page_size(), pragma_freelist_count()",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+
+    // The log does not exist until the first write, and never for in-memory databases
+    let wal_bytes = match conn.path().filter(|path| !path.is_empty()) {
+        Some(path) => match fs::metadata(format!("{path}-wal")) {
+            Ok(metadata) => metadata.len(),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(error) => return Err(error.into()),
+        },
+        None => 0,
+    };
+
+    let run_count = conn
+        .prepare_cached("SELECT COUNT(*) FROM runs WHERE deleted_at IS NULL")?
+        .query_row([], |row| row.get(0))?;
+
+    let mut tables = conn
+        .prepare(
+            "SELECT schema.name, COALESCE(SUM(dbstat.pgsize), 0)
+            FROM sqlite_schema AS schema LEFT JOIN dbstat ON dbstat.name = schema.name
+            WHERE schema.type = 'table' AND schema.name NOT LIKE 'sqlite_%'
+                AND schema.sql NOT LIKE 'CREATE VIRTUAL TABLE%'
+            GROUP BY schema.name ORDER BY schema.name",
+        )?
+        .query_map([], |row| {
+            Ok(TableStats {
+                name: row.get(0)?,
+                row_count: 0,
+                bytes: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for table in &mut tables {
+        let name = table.name.replace('"', "\"\"");
+        table.row_count =
+            conn.query_row(&format!("SELECT COUNT(*) FROM \"{name}\""), [], |row| {
+                row.get(0)
+            })?;
+    }
+
+    let indexes = conn
+        .prepare(
+            "SELECT schema.name, schema.tbl_name, COALESCE(SUM(dbstat.pgsize), 0)
+            FROM sqlite_schema AS schema LEFT JOIN dbstat ON dbstat.name = schema.name
+            WHERE schema.type = 'index'
+            GROUP BY schema.name ORDER BY schema.name",
+        )?
+        .query_map([], |row| {
+            Ok(IndexStats {
+                name: row.get(0)?,
+                table: row.get(1)?,
+                bytes: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(DatabaseStats {
+        file_bytes: page_count * page_size,
+        page_count,
+        page_size,
+        free_pages,
+        wal_bytes,
+        run_count,
+        tables,
+        indexes,
+    })
+}
+
+/// A step of the plan SQLite chose for a query, as reported by `explain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPlanStep {
+    /// The ID of the step, unique within the plan.
+    pub id: i64,
+
+    /// The ID of the step this step is part of, or 0 if it is a top-level step.
+    pub parent: i64,
+
+    /// What the step does, such as `SEARCH runs USING INTEGER PRIMARY KEY (rowid=?)`.
+    pub detail: String,
+}
+
+impl QueryPlanStep {
+    /// Returns whether the step reads every row of a table without the help of an index.
+    #[must_use]
+    pub fn is_full_scan(&self) -> bool {
+        self.detail.starts_with("SCAN ") && !self.detail.contains(" USING ")
+    }
+}
+
+/// Reports how SQLite plans to run a query, using `EXPLAIN QUERY PLAN`.
+///
+/// The query is only planned, not run. Its parameters, if any, do not need to be bound, and are
+/// planned as though they were `NULL`.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `query` - The SQL of a single statement.
+///
+/// # Returns
+/// * `Result<Vec<QueryPlanStep>>` - The steps of the plan, in the order SQLite reports them.
+///
+/// # Errors
+///
+/// Returns an error if the query is not valid SQL.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_database::connection::open_in_memory;
+/// use lib_profit_taker_database::maintenance::{explain, QueryPlanStep};
+///
+/// let conn = open_in_memory()?;
+/// let plan = explain(&conn, "SELECT id FROM runs ORDER BY time_stamp DESC, id DESC")?;
+/// assert!(!plan.iter().any(QueryPlanStep::is_full_scan));
+///
+/// let plan = explain(&conn, "SELECT * FROM shield_changes WHERE run_id = ?1")?;
+/// assert!(!plan.iter().any(QueryPlanStep::is_full_scan));
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+pub fn explain(conn: &Connection, query: &str) -> Result<Vec<QueryPlanStep>> {
+    let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {query}"))?;
+    // Unlike `query`, `raw_query` does not insist on every parameter being bound
+    let steps = stmt
+        .raw_query()
+        .mapped(|row| {
+            Ok(QueryPlanStep {
+                id: row.get(0)?,
+                parent: row.get(1)?,
+                detail: row.get(3)?,
+            })
+        })
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(steps)
+}
+
+/// The outcome of `reparse_runs`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReparseReport {
+    /// The number of runs that were parsed again and updated.
+    pub reparsed: usize,
+
+    /// The IDs of the runs whose log excerpts the parser could not make a run from, which were
+    /// left unchanged.
+    pub failed_run_ids: Vec<i64>,
+}
+
+/// Parses the stored log excerpts of runs again, overwriting the runs with the results.
+///
+/// Every run with a log excerpt stored by [`store_raw_log`](crate::insert::store_raw_log) is
+/// passed to `parser`, and the run it returns replaces the stored one as with
+/// [`update_run`]. This means the details edited from the frontend, such as the name, notes, and
+/// tags, are kept, but everything recorded by the parser is replaced. Runs without a log excerpt
+/// are left alone.
+///
+/// Everything is written inside a single savepoint, so either every run is updated or, if an
+/// error occurs, none of them are.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `parser` - Called with the log excerpt of each run, returning the run parsed from it, or
+///   `None` if it could not be parsed.
+///
+/// # Returns
+/// * `Result<ReparseReport>` - How many runs were updated, and which could not be parsed.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if a stored log excerpt cannot be decompressed, or
+/// another error if a query fails.
+pub fn reparse_runs(
+    conn: &Connection,
+    mut parser: impl FnMut(&str) -> Option<Run>,
+) -> Result<ReparseReport> {
+    with_savepoint(conn, "reparse_runs", |conn| {
+        let run_ids = conn
+            .prepare("SELECT run_id FROM raw_logs ORDER BY run_id")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+        let mut report = ReparseReport::default();
+        for run_id in run_ids {
+            let Some(log) = fetch_raw_log(conn, run_id)? else {
+                continue;
+            };
+
+            match parser(&log) {
+                Some(run) => {
+                    update_run(conn, run_id, &run)?;
+                    report.reparsed += 1;
+                }
+                None => report.failed_run_ids.push(run_id),
+            }
+        }
+
+        Ok(report)
+    })
+}
+
+/// The name the archive database is attached under by `archive_runs` and `attach_archive`.
+const ARCHIVE_SCHEMA: &str = "archive";
+
+/// The tables holding runs and everything belonging to them, with `runs` first, which are moved by
+/// `archive_runs`.
+pub(crate) const RUN_TABLES: [&str; 9] = [
+    "runs",
+    "phases",
+    "shield_changes",
+    "leg_breaks",
+    "squad_members",
+    "tags",
+    "raw_logs",
+    "media",
+    "loadouts",
+];
+
+/// Moves the runs older than a given age into an archive database, keeping the main database
+/// small and fast for large histories.
+///
+/// The archive is a database of its own, created at `archive_path` if needed, and each run is
+/// moved with its phases, squad members, tags, media, loadout, and log excerpt, keeping its ID.
+/// Runs in the trash are left alone, as they are removed by
+/// [`purge_trashed`](crate::delete::purge_trashed) anyway.
+///
+/// The moved runs are taken out of the totals of
+/// [`cached_overview`](crate::analytics::cached_overview) like any deleted run, as the cache only
+/// covers the main database, and every other query only sees them while the archive is attached
+/// with `attach_archive`.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection. No transaction may be open on
+///   it, as SQLite cannot attach a database inside a transaction.
+/// * `older_than` - How long ago a run must have been started to be archived.
+/// * `archive_path` - The file path of the archive database. The directory structure is created
+///   if needed.
+///
+/// # Returns
+/// * `Result<usize>` - The number of runs moved into the archive.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::SchemaVersionMismatch`] if the archive was created by a newer version
+/// of this library, or another error if the archive cannot be opened or a query fails. In every
+/// case, no run is removed from the main database unless it was written to the archive.
+pub fn archive_runs(conn: &Connection, older_than: Duration, archive_path: &str) -> Result<usize> {
+    if let Some(parent) = Path::new(archive_path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Creates the archive, or brings its schema up to date
+    drop(ConnectionOptions::default().open(archive_path)?);
+
+    let older_than = i64::try_from(older_than.as_secs()).unwrap_or(i64::MAX);
+
+    conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {ARCHIVE_SCHEMA}"),
+        [archive_path],
+    )?;
+    let result = with_savepoint(conn, "archive_runs", |conn| {
+        let run_ids = conn
+            .prepare(
+                "SELECT id FROM main.runs
+                WHERE deleted_at IS NULL AND time_stamp <= unixepoch() - ?1",
+            )?
+            .query_map([older_than], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+        for &run_id in &run_ids {
+            copy_to_archive(conn, run_id)?;
+            delete_run(conn, run_id)?;
+        }
+
+        Ok(run_ids.len())
+    });
+
+    // Detach even if archiving failed, but report the archiving's error over the detach's
+    let detached = conn.execute(&format!("DETACH DATABASE {ARCHIVE_SCHEMA}"), []);
+    let archived = result?;
+    detached?;
+
+    Ok(archived)
+}
+
+/// Copies a run and everything belonging to it from the main database to the attached archive,
+/// unless the archive already has it from an earlier, interrupted attempt.
+fn copy_to_archive(conn: &Connection, run_id: i64) -> Result<()> {
+    let already_archived: bool = conn.query_row(
+        &format!(
+            "SELECT EXISTS (
+                SELECT 1 FROM {ARCHIVE_SCHEMA}.runs AS archived, main.runs AS run
+                WHERE run.id = ?1 AND (archived.id = run.id OR archived.run_uuid = run.run_uuid)
+            )"
+        ),
+        [run_id],
+        |row| row.get(0),
+    )?;
+    if already_archived {
+        return Ok(());
+    }
+
+    for table in RUN_TABLES {
+        let columns = table_columns(conn, table)?;
+        let key = if table == "runs" { "id" } else { "run_id" };
+        conn.execute(
+            &format!(
+                "INSERT INTO {ARCHIVE_SCHEMA}.{table} ({columns})
+                SELECT {columns} FROM main.{table} WHERE {key} = ?1"
+            ),
+            [run_id],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Returns the columns of a table of the main database, separated by commas.
+///
+/// The columns are listed by name rather than with `*`, as tables that were migrated can have
+/// their columns in a different order than the same tables created from scratch.
+fn table_columns(conn: &Connection, table: &str) -> Result<String> {
+    conn.query_row(
+        "SELECT group_concat(name, ', ') FROM pragma_table_info(?1, 'main')",
+        [table],
+        |row| row.get(0),
+    )
+    .map_err(Into::into)
+}
+
+/// Attaches an archive created by `archive_runs`, so that queries see the archived runs alongside
+/// the others.
+///
+/// Each table of runs is replaced by a temporary view combining the runs of the main database
+/// with those of the archive, so every function reading runs includes the archived ones without
+/// any changes, except for search and `cached_overview`, which only cover the main database. As
+/// views cannot be written to, runs cannot be inserted, updated, or deleted, nor any more
+/// archived, until the archive is detached again with `detach_archive`.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection. No transaction may be open on
+///   it, as SQLite cannot attach a database inside a transaction.
+/// * `archive_path` - The file path of the archive database.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Io`] if there is no file at `archive_path`, or another error if it
+/// cannot be attached or an archive is already attached.
+pub fn attach_archive(conn: &Connection, archive_path: &str) -> Result<()> {
+    // `ATTACH` would otherwise create an empty database at the path
+    if !Path::new(archive_path).is_file() {
+        return Err(DatabaseError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("no archive at `{archive_path}`"),
+        )));
+    }
+
+    conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {ARCHIVE_SCHEMA}"),
+        [archive_path],
+    )?;
+    let result = with_savepoint(conn, "attach_archive", |conn| {
+        for table in RUN_TABLES {
+            let columns = table_columns(conn, table)?;
+            conn.execute_batch(&format!(
+                "CREATE TEMP VIEW {table} AS
+                SELECT {columns} FROM main.{table}
+                UNION ALL SELECT {columns} FROM {ARCHIVE_SCHEMA}.{table}"
+            ))?;
+        }
+
+        Ok(())
+    });
+
+    if result.is_err() {
+        // The original error is the one worth reporting, so a failure here is ignored
+        let _ = conn.execute(&format!("DETACH DATABASE {ARCHIVE_SCHEMA}"), []);
+    }
+
+    result
+}
+
+/// Detaches the archive attached with `attach_archive`, so queries only see the runs of the main
+/// database again.
+///
+/// Detaching when no archive is attached does nothing.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection. No transaction may be open on
+///   it, as SQLite cannot detach a database inside a transaction.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn detach_archive(conn: &Connection) -> Result<()> {
+    let attached: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_database_list WHERE name = ?1)",
+        [ARCHIVE_SCHEMA],
+        |row| row.get(0),
+    )?;
+    if !attached {
+        return Ok(());
+    }
+
+    for table in RUN_TABLES {
+        conn.execute_batch(&format!("DROP VIEW IF EXISTS temp.{table}"))?;
+    }
+    conn.execute(&format!("DETACH DATABASE {ARCHIVE_SCHEMA}"), [])?;
+
+    Ok(())
+}
+
+/// Which runs `apply_retention` deletes, so that the database of a casual user does not grow
+/// without bound.
+///
+/// Every rule is off by default, keeping every run. Favorites and runs still in progress are never
+/// deleted by any rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RetentionPolicy {
+    /// How many runs to keep of each month, or `None` to keep every run.
+    ///
+    /// The fastest runs of each month are kept separately for solo and squad runs, and for bugged
+    /// and normal runs, so a personal best is never deleted for being slower than a run of another
+    /// category. Aborted runs are only kept if a month has fewer completed runs than this. Months
+    /// follow the local time the runs were played at, and the current month is left alone until
+    /// it is over.
+    pub keep_best_per_month: Option<u32>,
+
+    /// How long ago a bugged run must have been started to be deleted, or `None` to keep bugged
+    /// runs.
+    pub delete_bugged_after: Option<Duration>,
+
+    /// How long ago an aborted run must have been started to be deleted, or `None` to keep
+    /// aborted runs.
+    pub delete_aborted_after: Option<Duration>,
+
+    /// How long a run must have been in the trash to be purged, as with
+    /// [`purge_trashed`](crate::delete::purge_trashed), or `None` to leave the trash to the user.
+    pub purge_trash_after: Option<Duration>,
+}
+
+/// Permanently deletes the runs that a retention policy does not keep.
+///
+/// This is meant to be run at startup, right after opening the database, with the policy the user
+/// chose in the settings screen. Runs in the trash are only purged by
+/// [`RetentionPolicy::purge_trash_after`], and are otherwise left alone.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `policy` - Which runs to delete.
+///
+/// # Returns
+/// * `Result<usize>` - The number of runs deleted, including those purged from the trash.
+///
+/// # Errors
+///
+/// Returns an error if a query fails, in which case nothing is deleted.
+pub fn apply_retention(conn: &Connection, policy: &RetentionPolicy) -> Result<usize> {
+    let seconds = |age: Duration| i64::try_from(age.as_secs()).unwrap_or(i64::MAX);
+
+    with_savepoint(conn, "apply_retention", |conn| {
+        let mut run_ids = Vec::new();
+
+        if let Some(keep) = policy.keep_best_per_month {
+            let mut stmt = conn.prepare_cached(&format!(
+                "SELECT id FROM (
+                    SELECT
+                        id,
+                        favorite,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY month, solo_run, bugged_run
+                            ORDER BY aborted_run, total_time, id
+                        ) AS place
+                    FROM (
+                        SELECT *, strftime('%Y-%m', {RUN_LOCAL_TIME_SQL}) AS month FROM runs
+                        WHERE deleted_at IS NULL AND status != 'in_progress'
+                    )
+                    WHERE month < strftime('%Y-%m', 'now', 'localtime')
+                )
+                WHERE place > ?1 AND NOT favorite"
+            ))?;
+            let rows = stmt.query_map([keep], |row| row.get(0))?;
+            for run_id in rows {
+                run_ids.push(run_id?);
+            }
+        }
+
+        for (flag, age) in [
+            ("bugged_run", policy.delete_bugged_after),
+            ("aborted_run", policy.delete_aborted_after),
+        ] {
+            let Some(age) = age else {
+                continue;
+            };
+            let mut stmt = conn.prepare_cached(&format!(
+                "SELECT id FROM runs
+                WHERE {flag} AND NOT favorite AND deleted_at IS NULL
+                    AND status != 'in_progress' AND time_stamp <= unixepoch() - ?1"
+            ))?;
+            let rows = stmt.query_map([seconds(age)], |row| row.get(0))?;
+            for run_id in rows {
+                run_ids.push(run_id?);
+            }
+        }
+
+        // A run can be deleted by more than one rule
+        run_ids.sort_unstable();
+        run_ids.dedup();
+        for &run_id in &run_ids {
+            delete_run(conn, run_id)?;
+        }
+
+        let purged = match policy.purge_trash_after {
+            Some(age) => purge_trashed(conn, age)?,
+            None => 0,
+        };
+
+        Ok(run_ids.len() + purged)
+    })
+}
+
+/// The condition on `runs` matching the runs checked by `audit`.
+const AUDITED_RUNS: &str = "deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run";
+
+/// A run whose stored data does not make sense, as found by `audit`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    /// The ID of the run.
+    pub run_id: i64,
+
+    /// What is wrong with the run.
+    pub kind: AnomalyKind,
+}
+
+/// What is wrong with a run found by `audit`.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum AnomalyKind {
+    /// A run that was not aborted does not have exactly four phases.
+    #[error("the run has {0} phases instead of {PHASE_COUNT}")]
+    PhaseCount(usize),
+
+    /// A time of the run is negative.
+    #[error("the {segment} time {time} is negative")]
+    NegativeDuration {
+        /// The segment the time belongs to, such as `phase 2 shield`, named as in
+        /// [`ValidationWarning::InvalidTime`](crate::validation::ValidationWarning::InvalidTime).
+        segment: String,
+
+        /// The negative time.
+        time: duration::Duration,
+    },
+
+    /// The shield changes of a phase add up to more time than the whole phase took.
+    #[error(
+        "phase {phase_number} has {}s of shield changes in {}s",
+        shield_time.format_seconds(),
+        phase_time.format_seconds()
+    )]
+    ShieldChangesOutsidePhase {
+        /// The number of the phase.
+        phase_number: i32,
+
+        /// The sum of the times of the shield changes of the phase.
+        shield_time: duration::Duration,
+
+        /// The total time of the phase.
+        phase_time: duration::Duration,
+    },
+
+    /// The squad has more players than Profit-Taker allows.
+    #[error("the squad has {0} players, more than {MAX_SQUAD_SIZE}")]
+    SquadSize(usize),
+}
+
+/// The anomalies found by `audit`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AuditReport {
+    /// Every anomaly found, ordered by run ID. A run can have several.
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl AuditReport {
+    /// Returns whether no anomalies were found.
+    #[must_use]
+    pub const fn is_clean(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+}
+
+/// Looks for runs whose phases, shield changes, leg breaks, or squad do not make sense together.
+///
+/// Runs in the trash, runs still in progress, and runs already marked as bugged are not checked,
+/// as they do not count towards the statistics anyway. Shield changes and leg breaks of phases
+/// that do not exist at all are reported by `check_integrity` instead. Like `check_integrity`,
+/// this only reads from the database, so it is safe to run at any time.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<AuditReport>` - Every anomaly found.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn audit(conn: &Connection) -> Result<AuditReport> {
+    let mut anomalies = Vec::new();
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT id, phase_count FROM runs
+        WHERE {AUDITED_RUNS} AND NOT aborted_run AND phase_count != ?1"
+    ))?;
+    let rows = stmt.query_map([PHASE_COUNT], |row| {
+        Ok(Anomaly {
+            run_id: row.get(0)?,
+            kind: AnomalyKind::PhaseCount(row.get(1)?),
+        })
+    })?;
+    for anomaly in rows {
+        anomalies.push(anomaly?);
+    }
+
+    negative_durations(conn, &mut anomalies)?;
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT run_id, phase_number, SUM(shield_changes.shield_time), MAX(phase_time)
+        FROM shield_changes JOIN phases USING (run_id, phase_number)
+        WHERE run_id IN (SELECT id FROM runs WHERE {AUDITED_RUNS})
+        GROUP BY run_id, phase_number
+        HAVING SUM(shield_changes.shield_time) > MAX(phase_time) + ?1"
+    ))?;
+    let tolerance = duration::Duration::from_secs_f64(TIME_TOLERANCE);
+    let rows = stmt.query_map([tolerance], |row| {
+        Ok(Anomaly {
+            run_id: row.get(0)?,
+            kind: AnomalyKind::ShieldChangesOutsidePhase {
+                phase_number: row.get(1)?,
+                shield_time: row.get(2)?,
+                phase_time: row.get(3)?,
+            },
+        })
+    })?;
+    for anomaly in rows {
+        anomalies.push(anomaly?);
+    }
+
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT id, squad_size FROM runs WHERE {AUDITED_RUNS} AND squad_size > ?1"
+    ))?;
+    let rows = stmt.query_map([MAX_SQUAD_SIZE], |row| {
+        Ok(Anomaly {
+            run_id: row.get(0)?,
+            kind: AnomalyKind::SquadSize(row.get(1)?),
+        })
+    })?;
+    for anomaly in rows {
+        anomalies.push(anomaly?);
+    }
+
+    // Stable, so the anomalies of each run stay in the order they were checked in
+    anomalies.sort_by_key(|anomaly| anomaly.run_id);
+
+    Ok(AuditReport { anomalies })
+}
+
+/// Adds an anomaly for every negative time of a run, a phase, a shield change, or a leg break.
+fn negative_durations(conn: &Connection, anomalies: &mut Vec<Anomaly>) -> Result<()> {
+    let run_segments = [
+        ("total", "total_time"),
+        ("flight", "total_flight_time"),
+        ("shield", "total_shield_time"),
+        ("leg", "total_leg_time"),
+        ("body", "total_body_time"),
+        ("pylon", "total_pylon_time"),
+    ]
+    .map(|(segment, column)| {
+        format!("SELECT id, '{segment}', {column} FROM runs WHERE {AUDITED_RUNS} AND {column} < 0")
+    });
+    let phase_segments = [
+        ("", "phase_time", "phases"),
+        (" shield", "shield_time", "phases"),
+        (" leg", "leg_time", "phases"),
+        (" body kill", "body_kill_time", "phases"),
+        (" pylon", "pylon_time", "phases"),
+        (" shield change", "shield_time", "shield_changes"),
+        (" leg break", "break_time", "leg_breaks"),
+    ]
+    .map(|(segment, column, table)| {
+        format!(
+            "SELECT run_id, 'phase ' || phase_number || '{segment}', {column} FROM {table}
+            WHERE {column} < 0 AND run_id IN (SELECT id FROM runs WHERE {AUDITED_RUNS})"
+        )
+    });
+
+    for sql in run_segments.iter().chain(&phase_segments) {
+        let mut stmt = conn.prepare_cached(sql)?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Anomaly {
+                run_id: row.get(0)?,
+                kind: AnomalyKind::NegativeDuration {
+                    segment: row.get(1)?,
+                    time: row.get(2)?,
+                },
+            })
+        })?;
+        for anomaly in rows {
+            anomalies.push(anomaly?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Marks every run with an anomaly found by `audit` as bugged, with the anomalies as the reason,
+/// so that they are left out of the statistics of valid runs.
+///
+/// This is the same treatment [`InvalidRunPolicy::MarkBugged`] gives runs that fail validation
+/// when they are inserted. Nothing else about the runs is changed, since there is no telling
+/// which of their times is the wrong one, and a run that already has a bugged reason keeps it.
+/// Either every run is marked or, if an error occurs, none of them are.
+///
+/// [`InvalidRunPolicy::MarkBugged`]: crate::validation::InvalidRunPolicy::MarkBugged
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<usize>` - The number of runs marked as bugged.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn fix_anomalies(conn: &Connection) -> Result<usize> {
+    with_savepoint(conn, "fix_anomalies", |conn| {
+        let report = audit(conn)?;
+
+        let mut reasons: Vec<(i64, Vec<String>)> = Vec::new();
+        for anomaly in &report.anomalies {
+            match reasons.last_mut() {
+                Some((run_id, reasons)) if *run_id == anomaly.run_id => {
+                    reasons.push(anomaly.kind.to_string());
+                }
+                _ => reasons.push((anomaly.run_id, vec![anomaly.kind.to_string()])),
+            }
+        }
+
+        let mut stmt = conn.prepare_cached(
+            "UPDATE runs SET bugged_run = TRUE, bugged_reason = COALESCE(bugged_reason, ?2)
+            WHERE id = ?1",
+        )?;
+        for (run_id, reasons) in &reasons {
+            stmt.execute(params![run_id, reasons.join("; ")])?;
+        }
+
+        Ok(reasons.len())
+    })
+}
@@ -0,0 +1,142 @@
+//! This module provides a process-wide home for the connection pool of the app, for bindings
+//! such as `flutter_rust_bridge` that cannot hold on to a pool between calls.
+//!
+//! Calls from several Dart isolates can reach the library at the same time. [`ConnectionManager`]
+//! makes sure only one of them opens the database and migrates it, and that the others wait for
+//! it to finish rather than opening the database a second time.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::error::{DatabaseError, Result};
+use crate::pool::{create_pool, Pool, PooledConnection};
+
+/// The connection manager shared by the whole process, as returned by
+/// [`ConnectionManager::global`].
+static GLOBAL: ConnectionManager = ConnectionManager::new();
+
+/// The database opened by a [`ConnectionManager`].
+#[derive(Debug)]
+struct OpenDatabase {
+    /// The file path the database was opened from.
+    path: String,
+
+    /// The pool of connections to the database.
+    pool: Pool,
+}
+
+/// Owns the connection pool of a single database, which can be opened and closed from any thread.
+///
+/// # Examples
+///
+/// ```no_run
+/// use lib_profit_taker_database::fetch::fetch_game_versions;
+/// use lib_profit_taker_database::manager::ConnectionManager;
+///
+/// ConnectionManager::global().initialize("runs.sqlite")?;
+///
+/// let conn = ConnectionManager::global().get()?;
+/// let game_versions = fetch_game_versions(&conn)?;
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct ConnectionManager {
+    /// The open database, or `None` before `initialize` and after `close`.
+    database: Mutex<Option<OpenDatabase>>,
+}
+
+impl ConnectionManager {
+    /// Creates a manager with no database open.
+    ///
+    /// Most callers want the process-wide manager returned by [`ConnectionManager::global`]
+    /// instead.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            database: Mutex::new(None),
+        }
+    }
+
+    /// Returns the connection manager shared by the whole process.
+    #[must_use]
+    pub fn global() -> &'static Self {
+        &GLOBAL
+    }
+
+    /// Opens the database at the given path, creating it and applying any pending migrations as
+    /// with [`create_pool`].
+    ///
+    /// Other calls to this manager wait until the database is open and migrated. Initializing the
+    /// manager again with the same path does nothing, so every caller that needs the database can
+    /// safely initialize it.
+    ///
+    /// # Arguments
+    /// * `path` - The file path of the SQLite database.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::AlreadyInitialized`] if a database at a different path is already
+    /// open, or the same errors as [`create_pool`] if the database cannot be opened.
+    pub fn initialize(&self, path: &str) -> Result<()> {
+        let mut database = self.lock();
+        if let Some(open) = database.as_ref() {
+            if open.path == path {
+                return Ok(());
+            }
+
+            return Err(DatabaseError::AlreadyInitialized(open.path.clone()));
+        }
+
+        *database = Some(OpenDatabase {
+            path: path.to_owned(),
+            pool: create_pool(path)?,
+        });
+        drop(database);
+
+        Ok(())
+    }
+
+    /// Borrows a connection to the open database.
+    ///
+    /// The manager is not locked while waiting for a free connection, so a slow query on one
+    /// thread does not hold up other threads.
+    ///
+    /// # Returns
+    /// * `Result<PooledConnection>` - A connection to the database, which is returned to the pool
+    ///   when dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::NotInitialized`] if no database is open, or
+    /// [`DatabaseError::PoolFailed`] if no connection becomes available in time.
+    pub fn get(&self) -> Result<PooledConnection> {
+        let pool = self
+            .lock()
+            .as_ref()
+            .map(|open| open.pool.clone())
+            .ok_or(DatabaseError::NotInitialized)?;
+
+        Ok(pool.get()?)
+    }
+
+    /// Returns the file path of the open database, or `None` if no database is open.
+    #[must_use]
+    pub fn path(&self) -> Option<String> {
+        self.lock().as_ref().map(|open| open.path.clone())
+    }
+
+    /// Closes the open database, so that another one can be opened with `initialize`.
+    ///
+    /// Connections already borrowed with `get` keep working, and are closed as they are dropped.
+    /// Closing a manager with no database open does nothing.
+    pub fn close(&self) {
+        let database = self.lock().take();
+        // Dropped only after the lock is released, as closing the connections can take a moment
+        drop(database);
+    }
+
+    /// Locks the open database, ignoring poisoning, as the database is only ever replaced whole
+    /// and so cannot be left half-updated by a panic.
+    fn lock(&self) -> MutexGuard<'_, Option<OpenDatabase>> {
+        self.database.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,112 @@
+//! This module provides functions for attaching recordings and screenshots to runs, such as the
+//! video of a personal best.
+//!
+//! Only where the media can be found is stored, as a file path or a URL, never the media itself,
+//! so attaching a recording does not grow the database. Nothing checks that the media is still
+//! there when it is opened later.
+
+use rusqlite::{params, Connection};
+
+use crate::connection::execute_retrying;
+use crate::error::{DatabaseError, Result};
+use crate::tags::ensure_run_exists;
+
+/// A recording, screenshot, or other media attached to a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Media {
+    /// The ID of the attachment.
+    pub media_id: i64,
+
+    /// The ID of the run the media is attached to.
+    pub run_id: i64,
+
+    /// The file path or URL of the media.
+    pub location: String,
+
+    /// The Unix timestamp of when the media was attached.
+    pub added_at: i64,
+}
+
+/// Attaches media to a run.
+///
+/// Leading and trailing whitespace is removed from the location. Attaching the same location to
+/// the same run again does nothing.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to attach the media to.
+/// * `location` - The file path or URL of the media.
+///
+/// # Returns
+/// * `Result<i64>` - The ID of the attachment.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the location is empty, [`DatabaseError::RunNotFound`]
+/// if no run with the given ID exists, or another error if a query fails.
+pub fn add_media(conn: &Connection, run_id: i64, location: &str) -> Result<i64> {
+    let location = location.trim();
+    if location.is_empty() {
+        return Err(DatabaseError::InvalidData(
+            "media locations cannot be empty".to_string(),
+        ));
+    }
+    ensure_run_exists(conn, run_id)?;
+
+    execute_retrying(
+        conn,
+        "INSERT INTO media (run_id, location, added_at) VALUES (?1, ?2, unixepoch())
+        ON CONFLICT (run_id, location) DO NOTHING",
+        params![run_id, location],
+    )?;
+
+    conn.prepare_cached("SELECT id FROM media WHERE run_id = ?1 AND location = ?2")?
+        .query_row(params![run_id, location], |row| row.get(0))
+        .map_err(Into::into)
+}
+
+/// Detaches media from the run it was attached to.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `media_id` - The ID of the attachment to remove.
+///
+/// # Returns
+/// * `Result<bool>` - Whether the attachment existed.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn remove_media(conn: &Connection, media_id: i64) -> Result<bool> {
+    let removed = execute_retrying(conn, "DELETE FROM media WHERE id = ?1", [media_id])?;
+
+    Ok(removed > 0)
+}
+
+/// Fetches the media attached to a run, in the order it was attached.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run whose media to fetch.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// a query fails.
+pub fn fetch_media(conn: &Connection, run_id: i64) -> Result<Vec<Media>> {
+    ensure_run_exists(conn, run_id)?;
+
+    conn.prepare_cached(
+        "SELECT id, run_id, location, added_at FROM media WHERE run_id = ?1 ORDER BY added_at, id",
+    )?
+    .query_map([run_id], |row| {
+        Ok(Media {
+            media_id: row.get(0)?,
+            run_id: row.get(1)?,
+            location: row.get(2)?,
+            added_at: row.get(3)?,
+        })
+    })?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
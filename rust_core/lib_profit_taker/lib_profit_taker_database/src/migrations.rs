@@ -0,0 +1,2014 @@
+//! This module provides a versioned migration system for the database schema.
+//!
+//! Each [`Migration`] has a version number, an `up` script that upgrades the schema from the
+//! previous version, and a `down` script that reverts it. Applied migrations are recorded in the
+//! `schema_version` table, so existing user databases can be brought up to date on startup
+//! without losing any data when new columns or tables are added.
+//!
+//! Migrations are applied in order inside a single savepoint: if any of them fails, the database
+//! is left exactly as it was before migrating.
+//!
+//! Some data cannot be computed in SQL, such as the checksums of runs. Once the database is up to
+//! date, `migrate` fills it in for every run that is missing it, in the same savepoint.
+//!
+//! Before migrating on startup, `plan` can be used to preview which migrations would be applied,
+//! and `apply_with_backup` to back the database up first if any of them are pending.
+//!
+//! To change the schema, append a new migration to [`MIGRATIONS`] rather than editing an
+//! existing one, as existing migrations may already have been applied to user databases.
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::checksum::backfill_checksums;
+use crate::connection::with_savepoint;
+use crate::error::{DatabaseError, Result};
+use crate::maintenance::backup_to;
+use crate::schema::SCHEMA_SQL;
+
+/// A single, reversible change to the database schema.
+#[derive(Debug)]
+pub struct Migration {
+    /// The schema version this migration upgrades to. Versions start at 1 and are contiguous.
+    pub version: u32,
+
+    /// A short, human-readable description of the change.
+    pub description: &'static str,
+
+    /// Whether the `up` script can lose data, such as by dropping a column that holds user data,
+    /// so that the database should be backed up before it is applied.
+    pub destructive: bool,
+
+    /// The SQL that upgrades the schema from `version - 1` to `version`.
+    pub up: &'static str,
+
+    /// The SQL that reverts the schema from `version` to `version - 1`.
+    pub down: &'static str,
+}
+
+/// Every migration, ordered by version.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create the initial schema",
+        destructive: false,
+        up: SCHEMA_SQL,
+        down: "
+            DROP TABLE shield_changes;
+            DROP TABLE status_effects;
+            DROP TABLE leg_breaks;
+            DROP TABLE leg_position;
+            DROP TABLE squad_members;
+            DROP TABLE phases;
+            DROP TABLE runs;
+        ",
+    },
+    Migration {
+        version: 2,
+        description: "Add soft deletion of runs",
+        destructive: false,
+        up: "
+            -- Unix timestamp of when the run was moved to the trash, or NULL if it is not trashed
+            ALTER TABLE runs ADD COLUMN deleted_at INTEGER;
+        ",
+        down: "
+            ALTER TABLE runs DROP COLUMN deleted_at;
+        ",
+    },
+    Migration {
+        version: 3,
+        description: "Add favorite runs",
+        destructive: false,
+        up: "
+            ALTER TABLE runs ADD COLUMN favorite BOOLEAN NOT NULL DEFAULT FALSE;
+        ",
+        down: "
+            ALTER TABLE runs DROP COLUMN favorite;
+        ",
+    },
+    Migration {
+        version: 4,
+        description: "Add run tags",
+        destructive: false,
+        up: "
+            CREATE TABLE tags (
+                run_id INTEGER NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (run_id, tag),
+                FOREIGN KEY (run_id) REFERENCES runs (id) ON DELETE CASCADE
+            );
+            CREATE INDEX tags_by_tag ON tags (tag);
+        ",
+        down: "
+            DROP TABLE tags;
+        ",
+    },
+    Migration {
+        version: 5,
+        description: "Add full-text search of runs",
+        destructive: false,
+        up: "
+            -- The row ID of each entry is the ID of its run
+            CREATE VIRTUAL TABLE run_search USING fts5 (run_name, squad_members);
+
+            INSERT INTO run_search (rowid, run_name, squad_members)
+            SELECT id, run_name, (
+                SELECT COALESCE(group_concat(member_name, ' '), '')
+                FROM squad_members WHERE run_id = runs.id
+            )
+            FROM runs;
+
+            CREATE TRIGGER run_search_insert_run AFTER INSERT ON runs BEGIN
+                INSERT INTO run_search (rowid, run_name, squad_members)
+                VALUES (new.id, new.run_name, '');
+            END;
+            CREATE TRIGGER run_search_rename_run AFTER UPDATE OF run_name ON runs BEGIN
+                UPDATE run_search SET run_name = new.run_name WHERE rowid = new.id;
+            END;
+            CREATE TRIGGER run_search_delete_run AFTER DELETE ON runs BEGIN
+                DELETE FROM run_search WHERE rowid = old.id;
+            END;
+            CREATE TRIGGER run_search_insert_squad_member AFTER INSERT ON squad_members BEGIN
+                UPDATE run_search SET squad_members = (
+                    SELECT group_concat(member_name, ' ')
+                    FROM squad_members WHERE run_id = new.run_id
+                )
+                WHERE rowid = new.run_id;
+            END;
+            CREATE TRIGGER run_search_delete_squad_member AFTER DELETE ON squad_members BEGIN
+                UPDATE run_search SET squad_members = (
+                    SELECT COALESCE(group_concat(member_name, ' '), '')
+                    FROM squad_members WHERE run_id = old.run_id
+                )
+                WHERE rowid = old.run_id;
+            END;
+        ",
+        down: "
+            DROP TRIGGER run_search_delete_squad_member;
+            DROP TRIGGER run_search_insert_squad_member;
+            DROP TRIGGER run_search_delete_run;
+            DROP TRIGGER run_search_rename_run;
+            DROP TRIGGER run_search_insert_run;
+            DROP TABLE run_search;
+        ",
+    },
+    Migration {
+        version: 6,
+        description: "Add notes to runs",
+        destructive: false,
+        up: "
+            -- FTS5 tables cannot gain columns, so the search index is rebuilt to include notes.
+            -- SQLite fails to drop an FTS5 table that was created before an `ALTER TABLE` in the
+            -- same transaction, so the old index is dropped before `runs` is altered.
+            DROP TRIGGER run_search_rename_run;
+            DROP TRIGGER run_search_insert_run;
+            DROP TABLE run_search;
+
+            ALTER TABLE runs ADD COLUMN notes TEXT;
+
+            CREATE VIRTUAL TABLE run_search USING fts5 (run_name, notes, squad_members);
+
+            INSERT INTO run_search (rowid, run_name, notes, squad_members)
+            SELECT id, run_name, notes, (
+                SELECT COALESCE(group_concat(member_name, ' '), '')
+                FROM squad_members WHERE run_id = runs.id
+            )
+            FROM runs;
+
+            CREATE TRIGGER run_search_insert_run AFTER INSERT ON runs BEGIN
+                INSERT INTO run_search (rowid, run_name, notes, squad_members)
+                VALUES (new.id, new.run_name, new.notes, '');
+            END;
+            CREATE TRIGGER run_search_edit_run AFTER UPDATE OF run_name, notes ON runs BEGIN
+                UPDATE run_search SET run_name = new.run_name, notes = new.notes
+                WHERE rowid = new.id;
+            END;
+        ",
+        down: "
+            -- Dropping a column fails while any trigger refers to it or to a missing table, so the
+            -- triggers using `notes` are dropped first and the index is only rebuilt afterwards
+            DROP TRIGGER run_search_edit_run;
+            DROP TRIGGER run_search_insert_run;
+
+            ALTER TABLE runs DROP COLUMN notes;
+
+            DROP TABLE run_search;
+
+            CREATE VIRTUAL TABLE run_search USING fts5 (run_name, squad_members);
+
+            INSERT INTO run_search (rowid, run_name, squad_members)
+            SELECT id, run_name, (
+                SELECT COALESCE(group_concat(member_name, ' '), '')
+                FROM squad_members WHERE run_id = runs.id
+            )
+            FROM runs;
+
+            CREATE TRIGGER run_search_insert_run AFTER INSERT ON runs BEGIN
+                INSERT INTO run_search (rowid, run_name, squad_members)
+                VALUES (new.id, new.run_name, '');
+            END;
+            CREATE TRIGGER run_search_rename_run AFTER UPDATE OF run_name ON runs BEGIN
+                UPDATE run_search SET run_name = new.run_name WHERE rowid = new.id;
+            END;
+        ",
+    },
+    Migration {
+        version: 7,
+        description: "Add app settings",
+        destructive: false,
+        up: "
+            -- Values are stored with whichever type they were set with
+            CREATE TABLE settings (
+                key TEXT PRIMARY KEY,
+                value NOT NULL
+            );
+        ",
+        down: "
+            DROP TABLE settings;
+        ",
+    },
+    Migration {
+        version: 8,
+        description: "Add the game version and platform of runs",
+        destructive: false,
+        up: "
+            ALTER TABLE runs ADD COLUMN game_version TEXT;
+            ALTER TABLE runs ADD COLUMN platform TEXT;
+            CREATE INDEX runs_by_game_version ON runs (game_version);
+        ",
+        down: "
+            DROP INDEX runs_by_game_version;
+            ALTER TABLE runs DROP COLUMN platform;
+            ALTER TABLE runs DROP COLUMN game_version;
+        ",
+    },
+    Migration {
+        version: 9,
+        description: "Add the reasons runs are bugged or aborted",
+        destructive: false,
+        up: "
+            ALTER TABLE runs ADD COLUMN bugged_reason TEXT;
+            ALTER TABLE runs ADD COLUMN aborted_reason TEXT;
+        ",
+        down: "
+            ALTER TABLE runs DROP COLUMN aborted_reason;
+            ALTER TABLE runs DROP COLUMN bugged_reason;
+        ",
+    },
+    Migration {
+        version: 10,
+        description: "Add globally unique run IDs",
+        destructive: false,
+        up: "
+            ALTER TABLE runs ADD COLUMN run_uuid TEXT;
+            -- Random version 4 UUIDs, formatted the same way as those generated on insert
+            UPDATE runs SET run_uuid =
+                lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4'
+                || substr(lower(hex(randomblob(2))), 2) || '-'
+                || substr('89ab', 1 + abs(random() % 4), 1)
+                || substr(lower(hex(randomblob(2))), 2) || '-' || lower(hex(randomblob(6)));
+            CREATE UNIQUE INDEX runs_by_uuid ON runs (run_uuid);
+        ",
+        down: "
+            DROP INDEX runs_by_uuid;
+            ALTER TABLE runs DROP COLUMN run_uuid;
+        ",
+    },
+    Migration {
+        version: 11,
+        description: "Add a cache of overall statistics",
+        destructive: false,
+        up: "
+            -- A single row of running totals over the valid runs, kept up to date by triggers so
+            -- that reading it takes the same time however many runs there are. Best times cannot
+            -- be updated incrementally when a run stops counting, so they are recomputed then, but
+            -- only if that run held one of them.
+            CREATE TABLE stats_cache (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                run_count INTEGER NOT NULL,
+                total_time_sum REAL NOT NULL,
+                flight_time_sum REAL NOT NULL,
+                shield_time_sum REAL NOT NULL,
+                leg_time_sum REAL NOT NULL,
+                body_time_sum REAL NOT NULL,
+                pylon_time_sum REAL NOT NULL,
+                total_time_best REAL,
+                flight_time_best REAL,
+                shield_time_best REAL,
+                leg_time_best REAL,
+                body_time_best REAL,
+                pylon_time_best REAL
+            );
+
+            INSERT INTO stats_cache
+            SELECT
+                1,
+                COUNT(*),
+                COALESCE(SUM(total_time), 0),
+                COALESCE(SUM(total_flight_time), 0),
+                COALESCE(SUM(total_shield_time), 0),
+                COALESCE(SUM(total_leg_time), 0),
+                COALESCE(SUM(total_body_time), 0),
+                COALESCE(SUM(total_pylon_time), 0),
+                MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+            FROM runs WHERE deleted_at IS NULL AND NOT bugged_run AND NOT aborted_run;
+
+            CREATE TRIGGER stats_cache_insert_run AFTER INSERT ON runs
+            WHEN new.deleted_at IS NULL AND NOT new.bugged_run AND NOT new.aborted_run
+            BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count + 1,
+                    total_time_sum = total_time_sum + new.total_time,
+                    flight_time_sum = flight_time_sum + new.total_flight_time,
+                    shield_time_sum = shield_time_sum + new.total_shield_time,
+                    leg_time_sum = leg_time_sum + new.total_leg_time,
+                    body_time_sum = body_time_sum + new.total_body_time,
+                    pylon_time_sum = pylon_time_sum + new.total_pylon_time,
+                    total_time_best =
+                        IFNULL(MIN(total_time_best, new.total_time), new.total_time),
+                    flight_time_best =
+                        IFNULL(MIN(flight_time_best, new.total_flight_time), new.total_flight_time),
+                    shield_time_best =
+                        IFNULL(MIN(shield_time_best, new.total_shield_time), new.total_shield_time),
+                    leg_time_best =
+                        IFNULL(MIN(leg_time_best, new.total_leg_time), new.total_leg_time),
+                    body_time_best =
+                        IFNULL(MIN(body_time_best, new.total_body_time), new.total_body_time),
+                    pylon_time_best =
+                        IFNULL(MIN(pylon_time_best, new.total_pylon_time), new.total_pylon_time);
+            END;
+            CREATE TRIGGER stats_cache_delete_run AFTER DELETE ON runs
+            WHEN old.deleted_at IS NULL AND NOT old.bugged_run AND NOT old.aborted_run
+            BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count - 1,
+                    total_time_sum = total_time_sum - old.total_time,
+                    flight_time_sum = flight_time_sum - old.total_flight_time,
+                    shield_time_sum = shield_time_sum - old.total_shield_time,
+                    leg_time_sum = leg_time_sum - old.total_leg_time,
+                    body_time_sum = body_time_sum - old.total_body_time,
+                    pylon_time_sum = pylon_time_sum - old.total_pylon_time;
+
+                UPDATE stats_cache SET (
+                    total_time_best, flight_time_best, shield_time_best, leg_time_best,
+                    body_time_best, pylon_time_best
+                ) = (
+                    SELECT
+                        MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                        MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+                    FROM runs WHERE deleted_at IS NULL AND NOT bugged_run AND NOT aborted_run
+                )
+                WHERE (
+                    old.total_time <= total_time_best
+                    OR old.total_flight_time <= flight_time_best
+                    OR old.total_shield_time <= shield_time_best
+                    OR old.total_leg_time <= leg_time_best
+                    OR old.total_body_time <= body_time_best
+                    OR old.total_pylon_time <= pylon_time_best
+                );
+            END;
+            CREATE TRIGGER stats_cache_update_run AFTER UPDATE OF
+                deleted_at, bugged_run, aborted_run, total_time, total_flight_time,
+                total_shield_time, total_leg_time, total_body_time, total_pylon_time
+            ON runs BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count - 1,
+                    total_time_sum = total_time_sum - old.total_time,
+                    flight_time_sum = flight_time_sum - old.total_flight_time,
+                    shield_time_sum = shield_time_sum - old.total_shield_time,
+                    leg_time_sum = leg_time_sum - old.total_leg_time,
+                    body_time_sum = body_time_sum - old.total_body_time,
+                    pylon_time_sum = pylon_time_sum - old.total_pylon_time
+                WHERE old.deleted_at IS NULL AND NOT old.bugged_run AND NOT old.aborted_run;
+
+                UPDATE stats_cache SET (
+                    total_time_best, flight_time_best, shield_time_best, leg_time_best,
+                    body_time_best, pylon_time_best
+                ) = (
+                    SELECT
+                        MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                        MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+                    FROM runs WHERE deleted_at IS NULL AND NOT bugged_run AND NOT aborted_run
+                )
+                WHERE old.deleted_at IS NULL AND NOT old.bugged_run AND NOT old.aborted_run AND (
+                    old.total_time <= total_time_best
+                    OR old.total_flight_time <= flight_time_best
+                    OR old.total_shield_time <= shield_time_best
+                    OR old.total_leg_time <= leg_time_best
+                    OR old.total_body_time <= body_time_best
+                    OR old.total_pylon_time <= pylon_time_best
+                );
+
+                UPDATE stats_cache SET
+                    run_count = run_count + 1,
+                    total_time_sum = total_time_sum + new.total_time,
+                    flight_time_sum = flight_time_sum + new.total_flight_time,
+                    shield_time_sum = shield_time_sum + new.total_shield_time,
+                    leg_time_sum = leg_time_sum + new.total_leg_time,
+                    body_time_sum = body_time_sum + new.total_body_time,
+                    pylon_time_sum = pylon_time_sum + new.total_pylon_time,
+                    total_time_best =
+                        IFNULL(MIN(total_time_best, new.total_time), new.total_time),
+                    flight_time_best =
+                        IFNULL(MIN(flight_time_best, new.total_flight_time), new.total_flight_time),
+                    shield_time_best =
+                        IFNULL(MIN(shield_time_best, new.total_shield_time), new.total_shield_time),
+                    leg_time_best =
+                        IFNULL(MIN(leg_time_best, new.total_leg_time), new.total_leg_time),
+                    body_time_best =
+                        IFNULL(MIN(body_time_best, new.total_body_time), new.total_body_time),
+                    pylon_time_best =
+                        IFNULL(MIN(pylon_time_best, new.total_pylon_time), new.total_pylon_time)
+                WHERE new.deleted_at IS NULL AND NOT new.bugged_run AND NOT new.aborted_run;
+            END;
+        ",
+        down: "
+            DROP TRIGGER stats_cache_update_run;
+            DROP TRIGGER stats_cache_delete_run;
+            DROP TRIGGER stats_cache_insert_run;
+            DROP TABLE stats_cache;
+        ",
+    },
+    Migration {
+        version: 12,
+        description: "Add indexes for sorting runs and looking up shield changes",
+        destructive: false,
+        up: "
+            -- Sorting by date or time otherwise scans and sorts every run, and the run ID
+            -- tiebreaker is implied by both indexes, as it is the rowid
+            CREATE INDEX runs_by_time_stamp ON runs (time_stamp);
+            CREATE INDEX runs_by_validity ON runs (bugged_run, aborted_run, total_time);
+            -- The other child tables are covered by their primary keys, which start with run_id
+            CREATE INDEX shield_changes_by_phase ON shield_changes (run_id, phase_number);
+        ",
+        down: "
+            DROP INDEX shield_changes_by_phase;
+            DROP INDEX runs_by_validity;
+            DROP INDEX runs_by_time_stamp;
+        ",
+    },
+    Migration {
+        version: 13,
+        description: "Add whether shields were stripped with overshields",
+        destructive: false,
+        up: "
+            ALTER TABLE shield_changes ADD COLUMN is_overshield BOOLEAN NOT NULL DEFAULT FALSE;
+        ",
+        down: "
+            ALTER TABLE shield_changes DROP COLUMN is_overshield;
+        ",
+    },
+    Migration {
+        version: 14,
+        description: "Add the log excerpts runs were parsed from",
+        destructive: false,
+        up: "
+            -- Kept out of the runs table, so that listing runs never reads the logs
+            CREATE TABLE raw_logs (
+                run_id INTEGER PRIMARY KEY,
+                log BLOB NOT NULL,
+                FOREIGN KEY (run_id) REFERENCES runs (id) ON DELETE CASCADE
+            );
+        ",
+        down: "
+            DROP TABLE raw_logs;
+        ",
+    },
+    Migration {
+        version: 15,
+        description: "Add target times",
+        destructive: false,
+        up: "
+            -- Phase 0 stands for the whole run
+            CREATE TABLE goals (
+                solo_run BOOLEAN NOT NULL,
+                phase_number INTEGER NOT NULL CHECK (phase_number >= 0),
+                target_time REAL NOT NULL CHECK (target_time > 0),
+                PRIMARY KEY (solo_run, phase_number)
+            );
+        ",
+        down: "
+            DROP TABLE goals;
+        ",
+    },
+    Migration {
+        version: 16,
+        description: "Add recordings and screenshots of runs",
+        destructive: false,
+        up: "
+            CREATE TABLE media (
+                id INTEGER PRIMARY KEY,
+                run_id INTEGER NOT NULL,
+                location TEXT NOT NULL,
+                added_at INTEGER NOT NULL,
+                UNIQUE (run_id, location),
+                FOREIGN KEY (run_id) REFERENCES runs (id) ON DELETE CASCADE
+            );
+        ",
+        down: "
+            DROP TABLE media;
+        ",
+    },
+    Migration {
+        version: 17,
+        description: "Add a journal of operations that can be undone",
+        destructive: false,
+        up: "
+            -- The states are JSON snapshots of every row of the affected runs
+            CREATE TABLE undo_journal (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                description TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                before_state TEXT NOT NULL,
+                after_state TEXT NOT NULL,
+                undone BOOLEAN NOT NULL DEFAULT 0
+            );
+        ",
+        down: "
+            DROP TABLE undo_journal;
+        ",
+    },
+    Migration {
+        version: 18,
+        description: "Remove rows left behind by runs deleted without foreign keys",
+        destructive: false,
+        up: "
+            -- Every child table cascades deletions from its run, but only on connections that
+            -- enforce foreign keys. Rows left behind by other connections are removed once, so
+            -- that deleting a run is all it takes to clean up after it from now on.
+            DELETE FROM phases WHERE run_id NOT IN (SELECT id FROM runs);
+            DELETE FROM shield_changes
+            WHERE (run_id, phase_number) NOT IN (SELECT run_id, phase_number FROM phases);
+            DELETE FROM leg_breaks
+            WHERE (run_id, phase_number) NOT IN (SELECT run_id, phase_number FROM phases);
+            DELETE FROM squad_members WHERE run_id NOT IN (SELECT id FROM runs);
+            DELETE FROM tags WHERE run_id NOT IN (SELECT id FROM runs);
+            DELETE FROM raw_logs WHERE run_id NOT IN (SELECT id FROM runs);
+            DELETE FROM media WHERE run_id NOT IN (SELECT id FROM runs);
+        ",
+        // The removed rows were unreachable, so there is nothing to bring back
+        down: "",
+    },
+    Migration {
+        version: 19,
+        description: "Add players grouping the names of squad members",
+        destructive: false,
+        up: "
+            CREATE TABLE players (
+                id INTEGER PRIMARY KEY,
+                display_name TEXT NOT NULL
+            );
+
+            -- Not a foreign key to squad_members, as a name can belong to many runs
+            CREATE TABLE player_names (
+                member_name TEXT PRIMARY KEY,
+                normalized_name TEXT NOT NULL,
+                player_id INTEGER NOT NULL,
+                FOREIGN KEY (player_id) REFERENCES players (id) ON DELETE CASCADE
+            );
+            CREATE INDEX player_names_by_normalized_name ON player_names (normalized_name);
+            CREATE INDEX player_names_by_player ON player_names (player_id);
+        ",
+        down: "
+            DROP TABLE player_names;
+            DROP TABLE players;
+        ",
+    },
+    Migration {
+        version: 20,
+        description: "Add a checksum of the timing data of each run",
+        destructive: false,
+        // Existing runs are given a checksum by `checksum::backfill_checksums` once the database is
+        // up to date, as computing one needs Rust
+        up: "
+            ALTER TABLE runs ADD COLUMN checksum TEXT;
+            CREATE INDEX runs_by_checksum ON runs (checksum);
+        ",
+        down: "
+            DROP INDEX runs_by_checksum;
+            ALTER TABLE runs DROP COLUMN checksum;
+        ",
+    },
+    Migration {
+        version: 21,
+        description: "Add the status of runs, to store runs that are still in progress",
+        destructive: false,
+        up: "
+            ALTER TABLE runs ADD COLUMN status TEXT NOT NULL DEFAULT 'completed'
+                CHECK (status IN ('in_progress', 'completed', 'aborted'));
+            UPDATE runs SET status = 'aborted' WHERE aborted_run;
+            CREATE INDEX runs_in_progress ON runs (status) WHERE status = 'in_progress';
+
+            -- Runs in progress are left out of the statistics cache until they are completed
+            DROP TRIGGER stats_cache_update_run;
+            DROP TRIGGER stats_cache_delete_run;
+            DROP TRIGGER stats_cache_insert_run;
+            CREATE TRIGGER stats_cache_insert_run AFTER INSERT ON runs
+            WHEN new.deleted_at IS NULL AND new.status != 'in_progress'
+                AND NOT new.bugged_run AND NOT new.aborted_run
+            BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count + 1,
+                    total_time_sum = total_time_sum + new.total_time,
+                    flight_time_sum = flight_time_sum + new.total_flight_time,
+                    shield_time_sum = shield_time_sum + new.total_shield_time,
+                    leg_time_sum = leg_time_sum + new.total_leg_time,
+                    body_time_sum = body_time_sum + new.total_body_time,
+                    pylon_time_sum = pylon_time_sum + new.total_pylon_time,
+                    total_time_best =
+                        IFNULL(MIN(total_time_best, new.total_time), new.total_time),
+                    flight_time_best =
+                        IFNULL(MIN(flight_time_best, new.total_flight_time), new.total_flight_time),
+                    shield_time_best =
+                        IFNULL(MIN(shield_time_best, new.total_shield_time), new.total_shield_time),
+                    leg_time_best =
+                        IFNULL(MIN(leg_time_best, new.total_leg_time), new.total_leg_time),
+                    body_time_best =
+                        IFNULL(MIN(body_time_best, new.total_body_time), new.total_body_time),
+                    pylon_time_best =
+                        IFNULL(MIN(pylon_time_best, new.total_pylon_time), new.total_pylon_time);
+            END;
+            CREATE TRIGGER stats_cache_delete_run AFTER DELETE ON runs
+            WHEN old.deleted_at IS NULL AND old.status != 'in_progress'
+                AND NOT old.bugged_run AND NOT old.aborted_run
+            BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count - 1,
+                    total_time_sum = total_time_sum - old.total_time,
+                    flight_time_sum = flight_time_sum - old.total_flight_time,
+                    shield_time_sum = shield_time_sum - old.total_shield_time,
+                    leg_time_sum = leg_time_sum - old.total_leg_time,
+                    body_time_sum = body_time_sum - old.total_body_time,
+                    pylon_time_sum = pylon_time_sum - old.total_pylon_time;
+
+                UPDATE stats_cache SET (
+                    total_time_best, flight_time_best, shield_time_best, leg_time_best,
+                    body_time_best, pylon_time_best
+                ) = (
+                    SELECT
+                        MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                        MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+                    FROM runs
+                    WHERE deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run
+                        AND NOT aborted_run
+                )
+                WHERE (
+                    old.total_time <= total_time_best
+                    OR old.total_flight_time <= flight_time_best
+                    OR old.total_shield_time <= shield_time_best
+                    OR old.total_leg_time <= leg_time_best
+                    OR old.total_body_time <= body_time_best
+                    OR old.total_pylon_time <= pylon_time_best
+                );
+            END;
+            CREATE TRIGGER stats_cache_update_run AFTER UPDATE OF
+                deleted_at, status, bugged_run, aborted_run, total_time, total_flight_time,
+                total_shield_time, total_leg_time, total_body_time, total_pylon_time
+            ON runs BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count - 1,
+                    total_time_sum = total_time_sum - old.total_time,
+                    flight_time_sum = flight_time_sum - old.total_flight_time,
+                    shield_time_sum = shield_time_sum - old.total_shield_time,
+                    leg_time_sum = leg_time_sum - old.total_leg_time,
+                    body_time_sum = body_time_sum - old.total_body_time,
+                    pylon_time_sum = pylon_time_sum - old.total_pylon_time
+                WHERE old.deleted_at IS NULL AND old.status != 'in_progress'
+                    AND NOT old.bugged_run AND NOT old.aborted_run;
+
+                UPDATE stats_cache SET (
+                    total_time_best, flight_time_best, shield_time_best, leg_time_best,
+                    body_time_best, pylon_time_best
+                ) = (
+                    SELECT
+                        MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                        MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+                    FROM runs
+                    WHERE deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run
+                        AND NOT aborted_run
+                )
+                WHERE old.deleted_at IS NULL AND old.status != 'in_progress'
+                    AND NOT old.bugged_run AND NOT old.aborted_run
+                    AND (
+                        old.total_time <= total_time_best
+                        OR old.total_flight_time <= flight_time_best
+                        OR old.total_shield_time <= shield_time_best
+                        OR old.total_leg_time <= leg_time_best
+                        OR old.total_body_time <= body_time_best
+                        OR old.total_pylon_time <= pylon_time_best
+                    );
+
+                UPDATE stats_cache SET
+                    run_count = run_count + 1,
+                    total_time_sum = total_time_sum + new.total_time,
+                    flight_time_sum = flight_time_sum + new.total_flight_time,
+                    shield_time_sum = shield_time_sum + new.total_shield_time,
+                    leg_time_sum = leg_time_sum + new.total_leg_time,
+                    body_time_sum = body_time_sum + new.total_body_time,
+                    pylon_time_sum = pylon_time_sum + new.total_pylon_time,
+                    total_time_best =
+                        IFNULL(MIN(total_time_best, new.total_time), new.total_time),
+                    flight_time_best =
+                        IFNULL(MIN(flight_time_best, new.total_flight_time), new.total_flight_time),
+                    shield_time_best =
+                        IFNULL(MIN(shield_time_best, new.total_shield_time), new.total_shield_time),
+                    leg_time_best =
+                        IFNULL(MIN(leg_time_best, new.total_leg_time), new.total_leg_time),
+                    body_time_best =
+                        IFNULL(MIN(body_time_best, new.total_body_time), new.total_body_time),
+                    pylon_time_best =
+                        IFNULL(MIN(pylon_time_best, new.total_pylon_time), new.total_pylon_time)
+                WHERE new.deleted_at IS NULL AND new.status != 'in_progress'
+                    AND NOT new.bugged_run AND NOT new.aborted_run;
+            END;
+        ",
+        down: "
+            -- Without a status, runs in progress can only be left out of statistics as aborted
+            UPDATE runs SET aborted_run = TRUE WHERE status = 'in_progress';
+
+            DROP TRIGGER stats_cache_update_run;
+            DROP TRIGGER stats_cache_delete_run;
+            DROP TRIGGER stats_cache_insert_run;
+            CREATE TRIGGER stats_cache_insert_run AFTER INSERT ON runs
+            WHEN new.deleted_at IS NULL AND NOT new.bugged_run AND NOT new.aborted_run
+            BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count + 1,
+                    total_time_sum = total_time_sum + new.total_time,
+                    flight_time_sum = flight_time_sum + new.total_flight_time,
+                    shield_time_sum = shield_time_sum + new.total_shield_time,
+                    leg_time_sum = leg_time_sum + new.total_leg_time,
+                    body_time_sum = body_time_sum + new.total_body_time,
+                    pylon_time_sum = pylon_time_sum + new.total_pylon_time,
+                    total_time_best =
+                        IFNULL(MIN(total_time_best, new.total_time), new.total_time),
+                    flight_time_best =
+                        IFNULL(MIN(flight_time_best, new.total_flight_time), new.total_flight_time),
+                    shield_time_best =
+                        IFNULL(MIN(shield_time_best, new.total_shield_time), new.total_shield_time),
+                    leg_time_best =
+                        IFNULL(MIN(leg_time_best, new.total_leg_time), new.total_leg_time),
+                    body_time_best =
+                        IFNULL(MIN(body_time_best, new.total_body_time), new.total_body_time),
+                    pylon_time_best =
+                        IFNULL(MIN(pylon_time_best, new.total_pylon_time), new.total_pylon_time);
+            END;
+            CREATE TRIGGER stats_cache_delete_run AFTER DELETE ON runs
+            WHEN old.deleted_at IS NULL AND NOT old.bugged_run AND NOT old.aborted_run
+            BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count - 1,
+                    total_time_sum = total_time_sum - old.total_time,
+                    flight_time_sum = flight_time_sum - old.total_flight_time,
+                    shield_time_sum = shield_time_sum - old.total_shield_time,
+                    leg_time_sum = leg_time_sum - old.total_leg_time,
+                    body_time_sum = body_time_sum - old.total_body_time,
+                    pylon_time_sum = pylon_time_sum - old.total_pylon_time;
+
+                UPDATE stats_cache SET (
+                    total_time_best, flight_time_best, shield_time_best, leg_time_best,
+                    body_time_best, pylon_time_best
+                ) = (
+                    SELECT
+                        MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                        MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+                    FROM runs WHERE deleted_at IS NULL AND NOT bugged_run AND NOT aborted_run
+                )
+                WHERE (
+                    old.total_time <= total_time_best
+                    OR old.total_flight_time <= flight_time_best
+                    OR old.total_shield_time <= shield_time_best
+                    OR old.total_leg_time <= leg_time_best
+                    OR old.total_body_time <= body_time_best
+                    OR old.total_pylon_time <= pylon_time_best
+                );
+            END;
+            CREATE TRIGGER stats_cache_update_run AFTER UPDATE OF
+                deleted_at, bugged_run, aborted_run, total_time, total_flight_time,
+                total_shield_time, total_leg_time, total_body_time, total_pylon_time
+            ON runs BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count - 1,
+                    total_time_sum = total_time_sum - old.total_time,
+                    flight_time_sum = flight_time_sum - old.total_flight_time,
+                    shield_time_sum = shield_time_sum - old.total_shield_time,
+                    leg_time_sum = leg_time_sum - old.total_leg_time,
+                    body_time_sum = body_time_sum - old.total_body_time,
+                    pylon_time_sum = pylon_time_sum - old.total_pylon_time
+                WHERE old.deleted_at IS NULL AND NOT old.bugged_run AND NOT old.aborted_run;
+
+                UPDATE stats_cache SET (
+                    total_time_best, flight_time_best, shield_time_best, leg_time_best,
+                    body_time_best, pylon_time_best
+                ) = (
+                    SELECT
+                        MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                        MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+                    FROM runs WHERE deleted_at IS NULL AND NOT bugged_run AND NOT aborted_run
+                )
+                WHERE old.deleted_at IS NULL AND NOT old.bugged_run AND NOT old.aborted_run AND (
+                    old.total_time <= total_time_best
+                    OR old.total_flight_time <= flight_time_best
+                    OR old.total_shield_time <= shield_time_best
+                    OR old.total_leg_time <= leg_time_best
+                    OR old.total_body_time <= body_time_best
+                    OR old.total_pylon_time <= pylon_time_best
+                );
+
+                UPDATE stats_cache SET
+                    run_count = run_count + 1,
+                    total_time_sum = total_time_sum + new.total_time,
+                    flight_time_sum = flight_time_sum + new.total_flight_time,
+                    shield_time_sum = shield_time_sum + new.total_shield_time,
+                    leg_time_sum = leg_time_sum + new.total_leg_time,
+                    body_time_sum = body_time_sum + new.total_body_time,
+                    pylon_time_sum = pylon_time_sum + new.total_pylon_time,
+                    total_time_best =
+                        IFNULL(MIN(total_time_best, new.total_time), new.total_time),
+                    flight_time_best =
+                        IFNULL(MIN(flight_time_best, new.total_flight_time), new.total_flight_time),
+                    shield_time_best =
+                        IFNULL(MIN(shield_time_best, new.total_shield_time), new.total_shield_time),
+                    leg_time_best =
+                        IFNULL(MIN(leg_time_best, new.total_leg_time), new.total_leg_time),
+                    body_time_best =
+                        IFNULL(MIN(body_time_best, new.total_body_time), new.total_body_time),
+                    pylon_time_best =
+                        IFNULL(MIN(pylon_time_best, new.total_pylon_time), new.total_pylon_time)
+                WHERE new.deleted_at IS NULL AND NOT new.bugged_run AND NOT new.aborted_run;
+            END;
+
+            DROP INDEX runs_in_progress;
+            ALTER TABLE runs DROP COLUMN status;
+        ",
+    },
+    Migration {
+        version: 22,
+        description: "Add the offset from UTC of the local time each run was played at",
+        // Early versions of the app stored the local time a run started as text, which SQLite
+        // kept as is in the timestamp column. The 'utc' modifier reads it in the current time
+        // zone, which is the best guess of where it was played, as is the offset of existing runs
+        destructive: false,
+        up: "
+            UPDATE runs SET time_stamp = CAST(strftime('%s', time_stamp, 'utc') AS INTEGER)
+            WHERE typeof(time_stamp) = 'text' AND strftime('%s', time_stamp, 'utc') IS NOT NULL;
+            ALTER TABLE runs ADD COLUMN utc_offset INTEGER NOT NULL DEFAULT 0;
+            UPDATE runs SET utc_offset = CAST(ROUND(86400 * (
+                julianday(time_stamp, 'unixepoch', 'localtime')
+                    - julianday(time_stamp, 'unixepoch')
+            )) AS INTEGER);
+        ",
+        down: "
+            ALTER TABLE runs DROP COLUMN utc_offset;
+        ",
+    },
+    Migration {
+        version: 23,
+        description: "Add the loadouts runs were played with",
+        destructive: false,
+        up: "
+            CREATE TABLE loadouts (
+                run_id INTEGER PRIMARY KEY,
+                warframe TEXT,
+                primary_weapon TEXT,
+                secondary_weapon TEXT,
+                melee_weapon TEXT,
+                arch_gun TEXT,
+                FOREIGN KEY (run_id) REFERENCES runs (id) ON DELETE CASCADE
+            );
+            CREATE INDEX loadouts_by_warframe ON loadouts (warframe COLLATE NOCASE);
+        ",
+        down: "
+            DROP TABLE loadouts;
+        ",
+    },
+    Migration {
+        version: 24,
+        description: "Keep the counts and segment totals of each run in sync with its phases",
+        destructive: false,
+        up: "
+            -- Kept up to date by triggers, so that runs can be listed, sorted, and filtered by
+            -- them without joining the child tables. The triggers count the rows again instead of
+            -- adding or subtracting one, so that copying a run along with its counts, as archiving
+            -- and importing do, does not count its children twice.
+            ALTER TABLE runs ADD COLUMN phase_count INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN shield_change_count INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN squad_size INTEGER NOT NULL DEFAULT 1;
+
+            UPDATE runs SET
+                phase_count = (SELECT COUNT(*) FROM phases WHERE run_id = runs.id),
+                shield_change_count = (SELECT COUNT(*) FROM shield_changes WHERE run_id = runs.id),
+                squad_size = 1 + (SELECT COUNT(*) FROM squad_members WHERE run_id = runs.id);
+
+            CREATE TRIGGER run_counts_insert_phase AFTER INSERT ON phases BEGIN
+                UPDATE runs SET phase_count =
+                    (SELECT COUNT(*) FROM phases WHERE run_id = new.run_id)
+                WHERE id = new.run_id;
+            END;
+            CREATE TRIGGER run_counts_delete_phase AFTER DELETE ON phases BEGIN
+                UPDATE runs SET phase_count =
+                    (SELECT COUNT(*) FROM phases WHERE run_id = old.run_id)
+                WHERE id = old.run_id;
+            END;
+            CREATE TRIGGER run_counts_insert_shield_change AFTER INSERT ON shield_changes BEGIN
+                UPDATE runs SET shield_change_count =
+                    (SELECT COUNT(*) FROM shield_changes WHERE run_id = new.run_id)
+                WHERE id = new.run_id;
+            END;
+            CREATE TRIGGER run_counts_delete_shield_change AFTER DELETE ON shield_changes BEGIN
+                UPDATE runs SET shield_change_count =
+                    (SELECT COUNT(*) FROM shield_changes WHERE run_id = old.run_id)
+                WHERE id = old.run_id;
+            END;
+            CREATE TRIGGER run_counts_insert_squad_member AFTER INSERT ON squad_members BEGIN
+                UPDATE runs SET squad_size =
+                    1 + (SELECT COUNT(*) FROM squad_members WHERE run_id = new.run_id)
+                WHERE id = new.run_id;
+            END;
+            CREATE TRIGGER run_counts_delete_squad_member AFTER DELETE ON squad_members BEGIN
+                UPDATE runs SET squad_size =
+                    1 + (SELECT COUNT(*) FROM squad_members WHERE run_id = old.run_id)
+                WHERE id = old.run_id;
+            END;
+
+            -- The times each run spent on shields, legs, the body, and pylons are the sums of
+            -- those of its phases, and are recounted in the same way. Runs without any phases,
+            -- such as some imported ones, keep the totals they were stored with.
+            UPDATE runs SET (
+                total_shield_time, total_leg_time, total_body_time, total_pylon_time
+            ) = (
+                SELECT IFNULL(SUM(shield_time), 0), IFNULL(SUM(leg_time), 0),
+                    IFNULL(SUM(body_kill_time), 0), IFNULL(SUM(pylon_time), 0)
+                FROM phases WHERE run_id = runs.id
+            )
+            WHERE id IN (SELECT run_id FROM phases);
+
+            CREATE TRIGGER run_totals_insert_phase AFTER INSERT ON phases BEGIN
+                UPDATE runs SET (
+                    total_shield_time, total_leg_time, total_body_time, total_pylon_time
+                ) = (
+                    SELECT IFNULL(SUM(shield_time), 0), IFNULL(SUM(leg_time), 0),
+                        IFNULL(SUM(body_kill_time), 0), IFNULL(SUM(pylon_time), 0)
+                    FROM phases WHERE run_id = new.run_id
+                )
+                WHERE id = new.run_id;
+            END;
+            CREATE TRIGGER run_totals_update_phase AFTER UPDATE OF
+                shield_time, leg_time, body_kill_time, pylon_time
+            ON phases BEGIN
+                UPDATE runs SET (
+                    total_shield_time, total_leg_time, total_body_time, total_pylon_time
+                ) = (
+                    SELECT IFNULL(SUM(shield_time), 0), IFNULL(SUM(leg_time), 0),
+                        IFNULL(SUM(body_kill_time), 0), IFNULL(SUM(pylon_time), 0)
+                    FROM phases WHERE run_id = new.run_id
+                )
+                WHERE id = new.run_id;
+            END;
+            CREATE TRIGGER run_totals_delete_phase AFTER DELETE ON phases BEGIN
+                UPDATE runs SET (
+                    total_shield_time, total_leg_time, total_body_time, total_pylon_time
+                ) = (
+                    SELECT IFNULL(SUM(shield_time), 0), IFNULL(SUM(leg_time), 0),
+                        IFNULL(SUM(body_kill_time), 0), IFNULL(SUM(pylon_time), 0)
+                    FROM phases WHERE run_id = old.run_id
+                )
+                WHERE id = old.run_id
+                    AND EXISTS (SELECT 1 FROM phases WHERE run_id = old.run_id);
+            END;
+        ",
+        down: "
+            DROP TRIGGER run_totals_delete_phase;
+            DROP TRIGGER run_totals_update_phase;
+            DROP TRIGGER run_totals_insert_phase;
+            DROP TRIGGER run_counts_delete_squad_member;
+            DROP TRIGGER run_counts_insert_squad_member;
+            DROP TRIGGER run_counts_delete_shield_change;
+            DROP TRIGGER run_counts_insert_shield_change;
+            DROP TRIGGER run_counts_delete_phase;
+            DROP TRIGGER run_counts_insert_phase;
+            ALTER TABLE runs DROP COLUMN squad_size;
+            ALTER TABLE runs DROP COLUMN shield_change_count;
+            ALTER TABLE runs DROP COLUMN phase_count;
+        ",
+    },
+    Migration {
+        version: 25,
+        description: "Add a counter of changes to the runs",
+        destructive: false,
+        up: "
+            -- A single row counting every change to a run or to its phases, shield changes, leg
+            -- breaks, squad members, or loadout, so that results computed from them can be cached
+            -- until the count moves on. Triggers bump it even for changes that would not affect
+            -- a given result, which only costs a cache miss.
+            CREATE TABLE generation (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                generation INTEGER NOT NULL
+            );
+            INSERT INTO generation VALUES (1, 0);
+
+            CREATE TRIGGER generation_insert_run AFTER INSERT ON runs BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_update_run AFTER UPDATE ON runs BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_delete_run AFTER DELETE ON runs BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_insert_phase AFTER INSERT ON phases BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_update_phase AFTER UPDATE ON phases BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_delete_phase AFTER DELETE ON phases BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_insert_shield_change AFTER INSERT ON shield_changes BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_update_shield_change AFTER UPDATE ON shield_changes BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_delete_shield_change AFTER DELETE ON shield_changes BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_insert_leg_break AFTER INSERT ON leg_breaks BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_update_leg_break AFTER UPDATE ON leg_breaks BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_delete_leg_break AFTER DELETE ON leg_breaks BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_insert_squad_member AFTER INSERT ON squad_members BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_update_squad_member AFTER UPDATE ON squad_members BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_delete_squad_member AFTER DELETE ON squad_members BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_insert_loadout AFTER INSERT ON loadouts BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_update_loadout AFTER UPDATE ON loadouts BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+            CREATE TRIGGER generation_delete_loadout AFTER DELETE ON loadouts BEGIN
+                UPDATE generation SET generation = generation + 1;
+            END;
+        ",
+        down: "
+            DROP TRIGGER generation_delete_loadout;
+            DROP TRIGGER generation_update_loadout;
+            DROP TRIGGER generation_insert_loadout;
+            DROP TRIGGER generation_delete_squad_member;
+            DROP TRIGGER generation_update_squad_member;
+            DROP TRIGGER generation_insert_squad_member;
+            DROP TRIGGER generation_delete_leg_break;
+            DROP TRIGGER generation_update_leg_break;
+            DROP TRIGGER generation_insert_leg_break;
+            DROP TRIGGER generation_delete_shield_change;
+            DROP TRIGGER generation_update_shield_change;
+            DROP TRIGGER generation_insert_shield_change;
+            DROP TRIGGER generation_delete_phase;
+            DROP TRIGGER generation_update_phase;
+            DROP TRIGGER generation_insert_phase;
+            DROP TRIGGER generation_delete_run;
+            DROP TRIGGER generation_update_run;
+            DROP TRIGGER generation_insert_run;
+            DROP TABLE generation;
+        ",
+    },
+    Migration {
+        version: 26,
+        description: "Store every duration as a whole number of milliseconds",
+        // Times are rounded to the nearest millisecond, and the dropped fractions cannot be
+        // restored. Checksums are of the rounded times already, so they stay the same.
+        destructive: true,
+        up: "
+            -- Dropping a column fails while any trigger or index refers to it, so the triggers
+            -- summing the times of phases, the statistics cache, and the index of total times are
+            -- dropped first and rebuilt afterwards, with the cache counting milliseconds as well
+            DROP TRIGGER run_totals_delete_phase;
+            DROP TRIGGER run_totals_update_phase;
+            DROP TRIGGER run_totals_insert_phase;
+            DROP TRIGGER stats_cache_update_run;
+            DROP TRIGGER stats_cache_delete_run;
+            DROP TRIGGER stats_cache_insert_run;
+            DROP TABLE stats_cache;
+            DROP INDEX runs_by_validity;
+
+            -- SQLite cannot change the type of a column, and columns declared REAL turn integers
+            -- back into floating-point numbers, so each duration column is replaced by a new
+            -- INTEGER column of the same name
+            ALTER TABLE runs ADD COLUMN total_time_millis INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN total_flight_time_millis INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN total_shield_time_millis INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN total_leg_time_millis INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN total_body_time_millis INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN total_pylon_time_millis INTEGER NOT NULL DEFAULT 0;
+            UPDATE runs SET
+                total_time_millis = CAST(ROUND(total_time * 1000) AS INTEGER),
+                total_flight_time_millis = CAST(ROUND(total_flight_time * 1000) AS INTEGER),
+                total_shield_time_millis = CAST(ROUND(total_shield_time * 1000) AS INTEGER),
+                total_leg_time_millis = CAST(ROUND(total_leg_time * 1000) AS INTEGER),
+                total_body_time_millis = CAST(ROUND(total_body_time * 1000) AS INTEGER),
+                total_pylon_time_millis = CAST(ROUND(total_pylon_time * 1000) AS INTEGER);
+            ALTER TABLE runs DROP COLUMN total_time;
+            ALTER TABLE runs DROP COLUMN total_flight_time;
+            ALTER TABLE runs DROP COLUMN total_shield_time;
+            ALTER TABLE runs DROP COLUMN total_leg_time;
+            ALTER TABLE runs DROP COLUMN total_body_time;
+            ALTER TABLE runs DROP COLUMN total_pylon_time;
+            ALTER TABLE runs RENAME COLUMN total_time_millis TO total_time;
+            ALTER TABLE runs RENAME COLUMN total_flight_time_millis TO total_flight_time;
+            ALTER TABLE runs RENAME COLUMN total_shield_time_millis TO total_shield_time;
+            ALTER TABLE runs RENAME COLUMN total_leg_time_millis TO total_leg_time;
+            ALTER TABLE runs RENAME COLUMN total_body_time_millis TO total_body_time;
+            ALTER TABLE runs RENAME COLUMN total_pylon_time_millis TO total_pylon_time;
+
+            ALTER TABLE phases ADD COLUMN phase_time_millis INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE phases ADD COLUMN shield_time_millis INTEGER;
+            ALTER TABLE phases ADD COLUMN leg_time_millis INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE phases ADD COLUMN body_kill_time_millis INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE phases ADD COLUMN pylon_time_millis INTEGER;
+            UPDATE phases SET
+                phase_time_millis = CAST(ROUND(phase_time * 1000) AS INTEGER),
+                shield_time_millis = CAST(ROUND(shield_time * 1000) AS INTEGER),
+                leg_time_millis = CAST(ROUND(leg_time * 1000) AS INTEGER),
+                body_kill_time_millis = CAST(ROUND(body_kill_time * 1000) AS INTEGER),
+                pylon_time_millis = CAST(ROUND(pylon_time * 1000) AS INTEGER);
+            ALTER TABLE phases DROP COLUMN phase_time;
+            ALTER TABLE phases DROP COLUMN shield_time;
+            ALTER TABLE phases DROP COLUMN leg_time;
+            ALTER TABLE phases DROP COLUMN body_kill_time;
+            ALTER TABLE phases DROP COLUMN pylon_time;
+            ALTER TABLE phases RENAME COLUMN phase_time_millis TO phase_time;
+            ALTER TABLE phases RENAME COLUMN shield_time_millis TO shield_time;
+            ALTER TABLE phases RENAME COLUMN leg_time_millis TO leg_time;
+            ALTER TABLE phases RENAME COLUMN body_kill_time_millis TO body_kill_time;
+            ALTER TABLE phases RENAME COLUMN pylon_time_millis TO pylon_time;
+
+            -- These columns were always declared INTEGER, so they only held seconds because SQLite
+            -- stores floating-point numbers in them as is
+            UPDATE shield_changes SET shield_time = CAST(ROUND(shield_time * 1000) AS INTEGER);
+            UPDATE leg_breaks SET break_time = CAST(ROUND(break_time * 1000) AS INTEGER);
+
+            -- The target time is part of a CHECK constraint, so the table is rebuilt instead
+            CREATE TABLE goals_millis (
+                solo_run BOOLEAN NOT NULL,
+                phase_number INTEGER NOT NULL CHECK (phase_number >= 0),
+                target_time INTEGER NOT NULL CHECK (target_time > 0),
+                PRIMARY KEY (solo_run, phase_number)
+            );
+            INSERT INTO goals_millis
+            SELECT solo_run, phase_number, MAX(CAST(ROUND(target_time * 1000) AS INTEGER), 1)
+            FROM goals;
+            DROP TABLE goals;
+            ALTER TABLE goals_millis RENAME TO goals;
+
+            -- The snapshots of rows in the undo journal are converted too, so that undoing an
+            -- operation restores them in the new unit
+            WITH
+                durations (table_name, column_name) AS (VALUES
+                    ('runs', 'total_time'), ('runs', 'total_flight_time'),
+                    ('runs', 'total_shield_time'), ('runs', 'total_leg_time'),
+                    ('runs', 'total_body_time'), ('runs', 'total_pylon_time'),
+                    ('phases', 'phase_time'), ('phases', 'shield_time'), ('phases', 'leg_time'),
+                    ('phases', 'body_kill_time'), ('phases', 'pylon_time'),
+                    ('shield_changes', 'shield_time'), ('leg_breaks', 'break_time')
+                ),
+                states (entry_id, kind, state) AS (
+                    SELECT id, 'before', before_state FROM undo_journal
+                    UNION ALL
+                    SELECT id, 'after', after_state FROM undo_journal
+                ),
+                converted (entry_id, kind, state) AS (
+                    SELECT entry_id, kind, (
+                        SELECT json_group_array(json_object(
+                            'run_id', json_extract(run.value, '$.run_id'),
+                            'tables', (
+                                SELECT json_group_array(json_object(
+                                    'table', json_extract(snapshot.value, '$.table'),
+                                    'columns', json(json_extract(snapshot.value, '$.columns')),
+                                    'rows', (
+                                        SELECT json_group_array((
+                                            SELECT json_group_array(CASE
+                                                WHEN cell.type != 'object' THEN cell.value
+                                                WHEN EXISTS (
+                                                    SELECT 1 FROM durations
+                                                    WHERE table_name =
+                                                            json_extract(snapshot.value, '$.table')
+                                                        AND column_name = json_extract(
+                                                            snapshot.value,
+                                                            '$.columns[' || cell.key || ']'
+                                                        )
+                                                ) THEN json_object(
+                                                    'Integer',
+                                                    CAST(ROUND(COALESCE(
+                                                        json_extract(cell.value, '$.Real'),
+                                                        json_extract(cell.value, '$.Integer')
+                                                    ) * 1000) AS INTEGER)
+                                                )
+                                                ELSE json(cell.value)
+                                            END)
+                                            FROM json_each(snapshot_row.value) AS cell
+                                        ))
+                                        FROM json_each(snapshot.value, '$.rows') AS snapshot_row
+                                    )
+                                ))
+                                FROM json_each(run.value, '$.tables') AS snapshot
+                            )
+                        ))
+                        FROM json_each(state) AS run
+                    )
+                    FROM states
+                )
+            UPDATE undo_journal SET
+                before_state = (
+                    SELECT state FROM converted WHERE entry_id = id AND kind = 'before'
+                ),
+                after_state = (
+                    SELECT state FROM converted WHERE entry_id = id AND kind = 'after'
+                );
+
+            CREATE INDEX runs_by_validity ON runs (bugged_run, aborted_run, total_time);
+
+            CREATE TABLE stats_cache (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                run_count INTEGER NOT NULL,
+                total_time_sum INTEGER NOT NULL,
+                flight_time_sum INTEGER NOT NULL,
+                shield_time_sum INTEGER NOT NULL,
+                leg_time_sum INTEGER NOT NULL,
+                body_time_sum INTEGER NOT NULL,
+                pylon_time_sum INTEGER NOT NULL,
+                total_time_best INTEGER,
+                flight_time_best INTEGER,
+                shield_time_best INTEGER,
+                leg_time_best INTEGER,
+                body_time_best INTEGER,
+                pylon_time_best INTEGER
+            );
+
+            INSERT INTO stats_cache
+            SELECT
+                1,
+                COUNT(*),
+                COALESCE(SUM(total_time), 0),
+                COALESCE(SUM(total_flight_time), 0),
+                COALESCE(SUM(total_shield_time), 0),
+                COALESCE(SUM(total_leg_time), 0),
+                COALESCE(SUM(total_body_time), 0),
+                COALESCE(SUM(total_pylon_time), 0),
+                MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+            FROM runs
+            WHERE deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run
+                AND NOT aborted_run;
+
+            CREATE TRIGGER stats_cache_insert_run AFTER INSERT ON runs
+            WHEN new.deleted_at IS NULL AND new.status != 'in_progress'
+                AND NOT new.bugged_run AND NOT new.aborted_run
+            BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count + 1,
+                    total_time_sum = total_time_sum + new.total_time,
+                    flight_time_sum = flight_time_sum + new.total_flight_time,
+                    shield_time_sum = shield_time_sum + new.total_shield_time,
+                    leg_time_sum = leg_time_sum + new.total_leg_time,
+                    body_time_sum = body_time_sum + new.total_body_time,
+                    pylon_time_sum = pylon_time_sum + new.total_pylon_time,
+                    total_time_best =
+                        IFNULL(MIN(total_time_best, new.total_time), new.total_time),
+                    flight_time_best =
+                        IFNULL(MIN(flight_time_best, new.total_flight_time), new.total_flight_time),
+                    shield_time_best =
+                        IFNULL(MIN(shield_time_best, new.total_shield_time), new.total_shield_time),
+                    leg_time_best =
+                        IFNULL(MIN(leg_time_best, new.total_leg_time), new.total_leg_time),
+                    body_time_best =
+                        IFNULL(MIN(body_time_best, new.total_body_time), new.total_body_time),
+                    pylon_time_best =
+                        IFNULL(MIN(pylon_time_best, new.total_pylon_time), new.total_pylon_time);
+            END;
+            CREATE TRIGGER stats_cache_delete_run AFTER DELETE ON runs
+            WHEN old.deleted_at IS NULL AND old.status != 'in_progress'
+                AND NOT old.bugged_run AND NOT old.aborted_run
+            BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count - 1,
+                    total_time_sum = total_time_sum - old.total_time,
+                    flight_time_sum = flight_time_sum - old.total_flight_time,
+                    shield_time_sum = shield_time_sum - old.total_shield_time,
+                    leg_time_sum = leg_time_sum - old.total_leg_time,
+                    body_time_sum = body_time_sum - old.total_body_time,
+                    pylon_time_sum = pylon_time_sum - old.total_pylon_time;
+
+                UPDATE stats_cache SET (
+                    total_time_best, flight_time_best, shield_time_best, leg_time_best,
+                    body_time_best, pylon_time_best
+                ) = (
+                    SELECT
+                        MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                        MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+                    FROM runs
+                    WHERE deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run
+                        AND NOT aborted_run
+                )
+                WHERE (
+                    old.total_time <= total_time_best
+                    OR old.total_flight_time <= flight_time_best
+                    OR old.total_shield_time <= shield_time_best
+                    OR old.total_leg_time <= leg_time_best
+                    OR old.total_body_time <= body_time_best
+                    OR old.total_pylon_time <= pylon_time_best
+                );
+            END;
+            CREATE TRIGGER stats_cache_update_run AFTER UPDATE OF
+                deleted_at, status, bugged_run, aborted_run, total_time, total_flight_time,
+                total_shield_time, total_leg_time, total_body_time, total_pylon_time
+            ON runs BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count - 1,
+                    total_time_sum = total_time_sum - old.total_time,
+                    flight_time_sum = flight_time_sum - old.total_flight_time,
+                    shield_time_sum = shield_time_sum - old.total_shield_time,
+                    leg_time_sum = leg_time_sum - old.total_leg_time,
+                    body_time_sum = body_time_sum - old.total_body_time,
+                    pylon_time_sum = pylon_time_sum - old.total_pylon_time
+                WHERE old.deleted_at IS NULL AND old.status != 'in_progress'
+                    AND NOT old.bugged_run AND NOT old.aborted_run;
+
+                UPDATE stats_cache SET (
+                    total_time_best, flight_time_best, shield_time_best, leg_time_best,
+                    body_time_best, pylon_time_best
+                ) = (
+                    SELECT
+                        MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                        MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+                    FROM runs
+                    WHERE deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run
+                        AND NOT aborted_run
+                )
+                WHERE old.deleted_at IS NULL AND old.status != 'in_progress'
+                    AND NOT old.bugged_run AND NOT old.aborted_run
+                    AND (
+                        old.total_time <= total_time_best
+                        OR old.total_flight_time <= flight_time_best
+                        OR old.total_shield_time <= shield_time_best
+                        OR old.total_leg_time <= leg_time_best
+                        OR old.total_body_time <= body_time_best
+                        OR old.total_pylon_time <= pylon_time_best
+                    );
+
+                UPDATE stats_cache SET
+                    run_count = run_count + 1,
+                    total_time_sum = total_time_sum + new.total_time,
+                    flight_time_sum = flight_time_sum + new.total_flight_time,
+                    shield_time_sum = shield_time_sum + new.total_shield_time,
+                    leg_time_sum = leg_time_sum + new.total_leg_time,
+                    body_time_sum = body_time_sum + new.total_body_time,
+                    pylon_time_sum = pylon_time_sum + new.total_pylon_time,
+                    total_time_best =
+                        IFNULL(MIN(total_time_best, new.total_time), new.total_time),
+                    flight_time_best =
+                        IFNULL(MIN(flight_time_best, new.total_flight_time), new.total_flight_time),
+                    shield_time_best =
+                        IFNULL(MIN(shield_time_best, new.total_shield_time), new.total_shield_time),
+                    leg_time_best =
+                        IFNULL(MIN(leg_time_best, new.total_leg_time), new.total_leg_time),
+                    body_time_best =
+                        IFNULL(MIN(body_time_best, new.total_body_time), new.total_body_time),
+                    pylon_time_best =
+                        IFNULL(MIN(pylon_time_best, new.total_pylon_time), new.total_pylon_time)
+                WHERE new.deleted_at IS NULL AND new.status != 'in_progress'
+                    AND NOT new.bugged_run AND NOT new.aborted_run;
+            END;
+
+            CREATE TRIGGER run_totals_insert_phase AFTER INSERT ON phases BEGIN
+                UPDATE runs SET (
+                    total_shield_time, total_leg_time, total_body_time, total_pylon_time
+                ) = (
+                    SELECT IFNULL(SUM(shield_time), 0), IFNULL(SUM(leg_time), 0),
+                        IFNULL(SUM(body_kill_time), 0), IFNULL(SUM(pylon_time), 0)
+                    FROM phases WHERE run_id = new.run_id
+                )
+                WHERE id = new.run_id;
+            END;
+            CREATE TRIGGER run_totals_update_phase AFTER UPDATE OF
+                shield_time, leg_time, body_kill_time, pylon_time
+            ON phases BEGIN
+                UPDATE runs SET (
+                    total_shield_time, total_leg_time, total_body_time, total_pylon_time
+                ) = (
+                    SELECT IFNULL(SUM(shield_time), 0), IFNULL(SUM(leg_time), 0),
+                        IFNULL(SUM(body_kill_time), 0), IFNULL(SUM(pylon_time), 0)
+                    FROM phases WHERE run_id = new.run_id
+                )
+                WHERE id = new.run_id;
+            END;
+            CREATE TRIGGER run_totals_delete_phase AFTER DELETE ON phases BEGIN
+                UPDATE runs SET (
+                    total_shield_time, total_leg_time, total_body_time, total_pylon_time
+                ) = (
+                    SELECT IFNULL(SUM(shield_time), 0), IFNULL(SUM(leg_time), 0),
+                        IFNULL(SUM(body_kill_time), 0), IFNULL(SUM(pylon_time), 0)
+                    FROM phases WHERE run_id = old.run_id
+                )
+                WHERE id = old.run_id
+                    AND EXISTS (SELECT 1 FROM phases WHERE run_id = old.run_id);
+            END;
+        ",
+        down: "
+            DROP TRIGGER run_totals_delete_phase;
+            DROP TRIGGER run_totals_update_phase;
+            DROP TRIGGER run_totals_insert_phase;
+            DROP TRIGGER stats_cache_update_run;
+            DROP TRIGGER stats_cache_delete_run;
+            DROP TRIGGER stats_cache_insert_run;
+            DROP TABLE stats_cache;
+            DROP INDEX runs_by_validity;
+
+            ALTER TABLE runs ADD COLUMN total_time_seconds REAL NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN total_flight_time_seconds REAL NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN total_shield_time_seconds REAL NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN total_leg_time_seconds REAL NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN total_body_time_seconds REAL NOT NULL DEFAULT 0;
+            ALTER TABLE runs ADD COLUMN total_pylon_time_seconds REAL NOT NULL DEFAULT 0;
+            UPDATE runs SET
+                total_time_seconds = total_time / 1000.0,
+                total_flight_time_seconds = total_flight_time / 1000.0,
+                total_shield_time_seconds = total_shield_time / 1000.0,
+                total_leg_time_seconds = total_leg_time / 1000.0,
+                total_body_time_seconds = total_body_time / 1000.0,
+                total_pylon_time_seconds = total_pylon_time / 1000.0;
+            ALTER TABLE runs DROP COLUMN total_time;
+            ALTER TABLE runs DROP COLUMN total_flight_time;
+            ALTER TABLE runs DROP COLUMN total_shield_time;
+            ALTER TABLE runs DROP COLUMN total_leg_time;
+            ALTER TABLE runs DROP COLUMN total_body_time;
+            ALTER TABLE runs DROP COLUMN total_pylon_time;
+            ALTER TABLE runs RENAME COLUMN total_time_seconds TO total_time;
+            ALTER TABLE runs RENAME COLUMN total_flight_time_seconds TO total_flight_time;
+            ALTER TABLE runs RENAME COLUMN total_shield_time_seconds TO total_shield_time;
+            ALTER TABLE runs RENAME COLUMN total_leg_time_seconds TO total_leg_time;
+            ALTER TABLE runs RENAME COLUMN total_body_time_seconds TO total_body_time;
+            ALTER TABLE runs RENAME COLUMN total_pylon_time_seconds TO total_pylon_time;
+
+            ALTER TABLE phases ADD COLUMN phase_time_seconds REAL NOT NULL DEFAULT 0;
+            ALTER TABLE phases ADD COLUMN shield_time_seconds REAL;
+            ALTER TABLE phases ADD COLUMN leg_time_seconds REAL NOT NULL DEFAULT 0;
+            ALTER TABLE phases ADD COLUMN body_kill_time_seconds REAL NOT NULL DEFAULT 0;
+            ALTER TABLE phases ADD COLUMN pylon_time_seconds REAL;
+            UPDATE phases SET
+                phase_time_seconds = phase_time / 1000.0,
+                shield_time_seconds = shield_time / 1000.0,
+                leg_time_seconds = leg_time / 1000.0,
+                body_kill_time_seconds = body_kill_time / 1000.0,
+                pylon_time_seconds = pylon_time / 1000.0;
+            ALTER TABLE phases DROP COLUMN phase_time;
+            ALTER TABLE phases DROP COLUMN shield_time;
+            ALTER TABLE phases DROP COLUMN leg_time;
+            ALTER TABLE phases DROP COLUMN body_kill_time;
+            ALTER TABLE phases DROP COLUMN pylon_time;
+            ALTER TABLE phases RENAME COLUMN phase_time_seconds TO phase_time;
+            ALTER TABLE phases RENAME COLUMN shield_time_seconds TO shield_time;
+            ALTER TABLE phases RENAME COLUMN leg_time_seconds TO leg_time;
+            ALTER TABLE phases RENAME COLUMN body_kill_time_seconds TO body_kill_time;
+            ALTER TABLE phases RENAME COLUMN pylon_time_seconds TO pylon_time;
+
+            UPDATE shield_changes SET shield_time = shield_time / 1000.0;
+            UPDATE leg_breaks SET break_time = break_time / 1000.0;
+
+            CREATE TABLE goals_seconds (
+                solo_run BOOLEAN NOT NULL,
+                phase_number INTEGER NOT NULL CHECK (phase_number >= 0),
+                target_time REAL NOT NULL CHECK (target_time > 0),
+                PRIMARY KEY (solo_run, phase_number)
+            );
+            INSERT INTO goals_seconds
+            SELECT solo_run, phase_number, target_time / 1000.0 FROM goals;
+            DROP TABLE goals;
+            ALTER TABLE goals_seconds RENAME TO goals;
+
+            -- Converts the snapshots of the undo journal back, as when converting to milliseconds
+            WITH
+                durations (table_name, column_name) AS (VALUES
+                    ('runs', 'total_time'), ('runs', 'total_flight_time'),
+                    ('runs', 'total_shield_time'), ('runs', 'total_leg_time'),
+                    ('runs', 'total_body_time'), ('runs', 'total_pylon_time'),
+                    ('phases', 'phase_time'), ('phases', 'shield_time'), ('phases', 'leg_time'),
+                    ('phases', 'body_kill_time'), ('phases', 'pylon_time'),
+                    ('shield_changes', 'shield_time'), ('leg_breaks', 'break_time')
+                ),
+                states (entry_id, kind, state) AS (
+                    SELECT id, 'before', before_state FROM undo_journal
+                    UNION ALL
+                    SELECT id, 'after', after_state FROM undo_journal
+                ),
+                converted (entry_id, kind, state) AS (
+                    SELECT entry_id, kind, (
+                        SELECT json_group_array(json_object(
+                            'run_id', json_extract(run.value, '$.run_id'),
+                            'tables', (
+                                SELECT json_group_array(json_object(
+                                    'table', json_extract(snapshot.value, '$.table'),
+                                    'columns', json(json_extract(snapshot.value, '$.columns')),
+                                    'rows', (
+                                        SELECT json_group_array((
+                                            SELECT json_group_array(CASE
+                                                WHEN cell.type != 'object' THEN cell.value
+                                                WHEN EXISTS (
+                                                    SELECT 1 FROM durations
+                                                    WHERE table_name =
+                                                            json_extract(snapshot.value, '$.table')
+                                                        AND column_name = json_extract(
+                                                            snapshot.value,
+                                                            '$.columns[' || cell.key || ']'
+                                                        )
+                                                ) THEN json_object(
+                                                    'Real',
+                                                    json_extract(cell.value, '$.Integer') / 1000.0
+                                                )
+                                                ELSE json(cell.value)
+                                            END)
+                                            FROM json_each(snapshot_row.value) AS cell
+                                        ))
+                                        FROM json_each(snapshot.value, '$.rows') AS snapshot_row
+                                    )
+                                ))
+                                FROM json_each(run.value, '$.tables') AS snapshot
+                            )
+                        ))
+                        FROM json_each(state) AS run
+                    )
+                    FROM states
+                )
+            UPDATE undo_journal SET
+                before_state = (
+                    SELECT state FROM converted WHERE entry_id = id AND kind = 'before'
+                ),
+                after_state = (
+                    SELECT state FROM converted WHERE entry_id = id AND kind = 'after'
+                );
+
+            CREATE INDEX runs_by_validity ON runs (bugged_run, aborted_run, total_time);
+
+            CREATE TABLE stats_cache (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                run_count INTEGER NOT NULL,
+                total_time_sum REAL NOT NULL,
+                flight_time_sum REAL NOT NULL,
+                shield_time_sum REAL NOT NULL,
+                leg_time_sum REAL NOT NULL,
+                body_time_sum REAL NOT NULL,
+                pylon_time_sum REAL NOT NULL,
+                total_time_best REAL,
+                flight_time_best REAL,
+                shield_time_best REAL,
+                leg_time_best REAL,
+                body_time_best REAL,
+                pylon_time_best REAL
+            );
+
+            INSERT INTO stats_cache
+            SELECT
+                1,
+                COUNT(*),
+                COALESCE(SUM(total_time), 0),
+                COALESCE(SUM(total_flight_time), 0),
+                COALESCE(SUM(total_shield_time), 0),
+                COALESCE(SUM(total_leg_time), 0),
+                COALESCE(SUM(total_body_time), 0),
+                COALESCE(SUM(total_pylon_time), 0),
+                MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+            FROM runs
+            WHERE deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run
+                AND NOT aborted_run;
+
+            CREATE TRIGGER stats_cache_insert_run AFTER INSERT ON runs
+            WHEN new.deleted_at IS NULL AND new.status != 'in_progress'
+                AND NOT new.bugged_run AND NOT new.aborted_run
+            BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count + 1,
+                    total_time_sum = total_time_sum + new.total_time,
+                    flight_time_sum = flight_time_sum + new.total_flight_time,
+                    shield_time_sum = shield_time_sum + new.total_shield_time,
+                    leg_time_sum = leg_time_sum + new.total_leg_time,
+                    body_time_sum = body_time_sum + new.total_body_time,
+                    pylon_time_sum = pylon_time_sum + new.total_pylon_time,
+                    total_time_best =
+                        IFNULL(MIN(total_time_best, new.total_time), new.total_time),
+                    flight_time_best =
+                        IFNULL(MIN(flight_time_best, new.total_flight_time), new.total_flight_time),
+                    shield_time_best =
+                        IFNULL(MIN(shield_time_best, new.total_shield_time), new.total_shield_time),
+                    leg_time_best =
+                        IFNULL(MIN(leg_time_best, new.total_leg_time), new.total_leg_time),
+                    body_time_best =
+                        IFNULL(MIN(body_time_best, new.total_body_time), new.total_body_time),
+                    pylon_time_best =
+                        IFNULL(MIN(pylon_time_best, new.total_pylon_time), new.total_pylon_time);
+            END;
+            CREATE TRIGGER stats_cache_delete_run AFTER DELETE ON runs
+            WHEN old.deleted_at IS NULL AND old.status != 'in_progress'
+                AND NOT old.bugged_run AND NOT old.aborted_run
+            BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count - 1,
+                    total_time_sum = total_time_sum - old.total_time,
+                    flight_time_sum = flight_time_sum - old.total_flight_time,
+                    shield_time_sum = shield_time_sum - old.total_shield_time,
+                    leg_time_sum = leg_time_sum - old.total_leg_time,
+                    body_time_sum = body_time_sum - old.total_body_time,
+                    pylon_time_sum = pylon_time_sum - old.total_pylon_time;
+
+                UPDATE stats_cache SET (
+                    total_time_best, flight_time_best, shield_time_best, leg_time_best,
+                    body_time_best, pylon_time_best
+                ) = (
+                    SELECT
+                        MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                        MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+                    FROM runs
+                    WHERE deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run
+                        AND NOT aborted_run
+                )
+                WHERE (
+                    old.total_time <= total_time_best
+                    OR old.total_flight_time <= flight_time_best
+                    OR old.total_shield_time <= shield_time_best
+                    OR old.total_leg_time <= leg_time_best
+                    OR old.total_body_time <= body_time_best
+                    OR old.total_pylon_time <= pylon_time_best
+                );
+            END;
+            CREATE TRIGGER stats_cache_update_run AFTER UPDATE OF
+                deleted_at, status, bugged_run, aborted_run, total_time, total_flight_time,
+                total_shield_time, total_leg_time, total_body_time, total_pylon_time
+            ON runs BEGIN
+                UPDATE stats_cache SET
+                    run_count = run_count - 1,
+                    total_time_sum = total_time_sum - old.total_time,
+                    flight_time_sum = flight_time_sum - old.total_flight_time,
+                    shield_time_sum = shield_time_sum - old.total_shield_time,
+                    leg_time_sum = leg_time_sum - old.total_leg_time,
+                    body_time_sum = body_time_sum - old.total_body_time,
+                    pylon_time_sum = pylon_time_sum - old.total_pylon_time
+                WHERE old.deleted_at IS NULL AND old.status != 'in_progress'
+                    AND NOT old.bugged_run AND NOT old.aborted_run;
+
+                UPDATE stats_cache SET (
+                    total_time_best, flight_time_best, shield_time_best, leg_time_best,
+                    body_time_best, pylon_time_best
+                ) = (
+                    SELECT
+                        MIN(total_time), MIN(total_flight_time), MIN(total_shield_time),
+                        MIN(total_leg_time), MIN(total_body_time), MIN(total_pylon_time)
+                    FROM runs
+                    WHERE deleted_at IS NULL AND status != 'in_progress' AND NOT bugged_run
+                        AND NOT aborted_run
+                )
+                WHERE old.deleted_at IS NULL AND old.status != 'in_progress'
+                    AND NOT old.bugged_run AND NOT old.aborted_run
+                    AND (
+                        old.total_time <= total_time_best
+                        OR old.total_flight_time <= flight_time_best
+                        OR old.total_shield_time <= shield_time_best
+                        OR old.total_leg_time <= leg_time_best
+                        OR old.total_body_time <= body_time_best
+                        OR old.total_pylon_time <= pylon_time_best
+                    );
+
+                UPDATE stats_cache SET
+                    run_count = run_count + 1,
+                    total_time_sum = total_time_sum + new.total_time,
+                    flight_time_sum = flight_time_sum + new.total_flight_time,
+                    shield_time_sum = shield_time_sum + new.total_shield_time,
+                    leg_time_sum = leg_time_sum + new.total_leg_time,
+                    body_time_sum = body_time_sum + new.total_body_time,
+                    pylon_time_sum = pylon_time_sum + new.total_pylon_time,
+                    total_time_best =
+                        IFNULL(MIN(total_time_best, new.total_time), new.total_time),
+                    flight_time_best =
+                        IFNULL(MIN(flight_time_best, new.total_flight_time), new.total_flight_time),
+                    shield_time_best =
+                        IFNULL(MIN(shield_time_best, new.total_shield_time), new.total_shield_time),
+                    leg_time_best =
+                        IFNULL(MIN(leg_time_best, new.total_leg_time), new.total_leg_time),
+                    body_time_best =
+                        IFNULL(MIN(body_time_best, new.total_body_time), new.total_body_time),
+                    pylon_time_best =
+                        IFNULL(MIN(pylon_time_best, new.total_pylon_time), new.total_pylon_time)
+                WHERE new.deleted_at IS NULL AND new.status != 'in_progress'
+                    AND NOT new.bugged_run AND NOT new.aborted_run;
+            END;
+
+            CREATE TRIGGER run_totals_insert_phase AFTER INSERT ON phases BEGIN
+                UPDATE runs SET (
+                    total_shield_time, total_leg_time, total_body_time, total_pylon_time
+                ) = (
+                    SELECT IFNULL(SUM(shield_time), 0), IFNULL(SUM(leg_time), 0),
+                        IFNULL(SUM(body_kill_time), 0), IFNULL(SUM(pylon_time), 0)
+                    FROM phases WHERE run_id = new.run_id
+                )
+                WHERE id = new.run_id;
+            END;
+            CREATE TRIGGER run_totals_update_phase AFTER UPDATE OF
+                shield_time, leg_time, body_kill_time, pylon_time
+            ON phases BEGIN
+                UPDATE runs SET (
+                    total_shield_time, total_leg_time, total_body_time, total_pylon_time
+                ) = (
+                    SELECT IFNULL(SUM(shield_time), 0), IFNULL(SUM(leg_time), 0),
+                        IFNULL(SUM(body_kill_time), 0), IFNULL(SUM(pylon_time), 0)
+                    FROM phases WHERE run_id = new.run_id
+                )
+                WHERE id = new.run_id;
+            END;
+            CREATE TRIGGER run_totals_delete_phase AFTER DELETE ON phases BEGIN
+                UPDATE runs SET (
+                    total_shield_time, total_leg_time, total_body_time, total_pylon_time
+                ) = (
+                    SELECT IFNULL(SUM(shield_time), 0), IFNULL(SUM(leg_time), 0),
+                        IFNULL(SUM(body_kill_time), 0), IFNULL(SUM(pylon_time), 0)
+                    FROM phases WHERE run_id = old.run_id
+                )
+                WHERE id = old.run_id
+                    AND EXISTS (SELECT 1 FROM phases WHERE run_id = old.run_id);
+            END;
+        ",
+    },
+];
+
+/// The schema version reached after applying every migration.
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "there will never be more than `u32::MAX` migrations"
+)]
+pub const LATEST_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Creates the table recording which migrations have been applied.
+const CREATE_SCHEMA_VERSION_SQL: &str = "
+CREATE TABLE IF NOT EXISTS schema_version (
+    version INTEGER PRIMARY KEY,
+    description TEXT NOT NULL,
+    applied_at INTEGER NOT NULL  -- Store as Unix timestamp
+);
+";
+
+/// Returns the schema version of the database, or 0 if no migrations have been applied.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<u32>` - The version of the most recently applied migration.
+///
+/// # Errors
+///
+/// Returns an error if the `schema_version` table exists but cannot be read.
+pub fn current_version(conn: &Connection) -> Result<u32> {
+    if !table_exists(conn, "schema_version")? {
+        return Ok(0);
+    }
+
+    let version = conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )?;
+
+    Ok(version)
+}
+
+/// Upgrades the database to [`LATEST_VERSION`], applying every pending migration, then gives a
+/// checksum to every run that does not have one.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::SchemaVersionMismatch`] if the database was created by a newer
+/// version of this library, [`DatabaseError::MigrationFailed`] if a migration fails, or another
+/// error if a run cannot be given a checksum. In any case, none of the pending migrations are
+/// applied.
+pub fn migrate(conn: &Connection) -> Result<()> {
+    migrate_to(conn, LATEST_VERSION)
+}
+
+/// The migrations `migrate` would apply to a database.
+#[derive(Debug, Clone)]
+pub struct MigrationPlan {
+    /// The current schema version of the database.
+    pub current_version: u32,
+
+    /// The schema version the database would be migrated to, which is always [`LATEST_VERSION`].
+    pub target_version: u32,
+
+    /// The migrations that would be applied, in the order they would be applied in.
+    pub pending: Vec<&'static Migration>,
+}
+
+impl MigrationPlan {
+    /// Returns whether the database is already up to date.
+    #[must_use]
+    pub const fn is_up_to_date(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns whether any pending migration can lose data.
+    #[must_use]
+    pub fn is_destructive(&self) -> bool {
+        self.pending.iter().any(|migration| migration.destructive)
+    }
+}
+
+/// Previews what `migrate` would do to the database, without changing anything.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<MigrationPlan>` - The migrations that are pending.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::SchemaVersionMismatch`] if the database was created by a newer
+/// version of this library, or another error if the schema version cannot be read.
+pub fn plan(conn: &Connection) -> Result<MigrationPlan> {
+    let mut current = current_version(conn)?;
+    if current > LATEST_VERSION {
+        return Err(DatabaseError::SchemaVersionMismatch {
+            expected: LATEST_VERSION,
+            found: current,
+        });
+    }
+    // `migrate` would adopt such a database instead of applying the initial schema
+    if current == 0 && table_exists(conn, "runs")? {
+        current = MIGRATIONS[0].version;
+    }
+
+    Ok(MigrationPlan {
+        current_version: current,
+        target_version: LATEST_VERSION,
+        pending: MIGRATIONS
+            .iter()
+            .filter(|migration| migration.version > current)
+            .collect(),
+    })
+}
+
+/// Upgrades the database to [`LATEST_VERSION`] like `migrate`, but first writes a backup of it to
+/// `backup_path` if any migration is pending.
+///
+/// If migrating fails, the database is left unchanged anyway, but the backup is kept. It can be
+/// restored with [`restore_from`](crate::maintenance::restore_from).
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `backup_path` - The file path to write the backup to. If a file already exists at the path,
+///   it is overwritten.
+///
+/// # Returns
+/// * `Result<MigrationPlan>` - The migrations that were applied. If there were none, no backup
+///   was written.
+///
+/// # Errors
+///
+/// Returns the same errors as `plan` and `migrate`, or an error if the backup fails, in which
+/// case no migration is applied.
+pub fn apply_with_backup(conn: &Connection, backup_path: &str) -> Result<MigrationPlan> {
+    let plan = plan(conn)?;
+    if !plan.is_up_to_date() {
+        backup_to(conn, backup_path)?;
+        migrate(conn)?;
+    }
+
+    Ok(plan)
+}
+
+/// Upgrades or downgrades the database to the given schema version.
+///
+/// Upgrading applies the `up` script of each migration after the current version, in ascending
+/// order. Downgrading applies the `down` script of each migration down to (but excluding) the
+/// target version, in descending order. Downgrading may drop data stored in the reverted
+/// columns or tables.
+///
+/// Once the database reaches [`LATEST_VERSION`], every run without a checksum is given one, as the
+/// code computing them only supports the latest schema.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `target` - The schema version to migrate to. `0` reverts every migration.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::UnknownSchemaVersion`] if `target` is newer than [`LATEST_VERSION`],
+/// [`DatabaseError::SchemaVersionMismatch`] if the database was created by a newer version of
+/// this library, [`DatabaseError::MigrationFailed`] if a migration fails, or another error if a
+/// run cannot be given a checksum. In any case, the database is left unchanged.
+pub fn migrate_to(conn: &Connection, target: u32) -> Result<()> {
+    if target > LATEST_VERSION {
+        return Err(DatabaseError::UnknownSchemaVersion(target));
+    }
+
+    with_savepoint(conn, "migrate", |conn| {
+        conn.execute_batch(CREATE_SCHEMA_VERSION_SQL)
+            .map_err(DatabaseError::MigrationFailed)?;
+        adopt_unversioned_schema(conn)?;
+
+        let current = current_version(conn)?;
+        if current > LATEST_VERSION {
+            return Err(DatabaseError::SchemaVersionMismatch {
+                expected: LATEST_VERSION,
+                found: current,
+            });
+        }
+
+        if target >= current {
+            for migration in MIGRATIONS
+                .iter()
+                .filter(|m| m.version > current && m.version <= target)
+            {
+                apply_up(conn, migration).map_err(DatabaseError::MigrationFailed)?;
+            }
+        } else {
+            for migration in MIGRATIONS
+                .iter()
+                .rev()
+                .filter(|m| m.version <= current && m.version > target)
+            {
+                apply_down(conn, migration).map_err(DatabaseError::MigrationFailed)?;
+            }
+        }
+
+        if target == LATEST_VERSION {
+            backfill_checksums(conn)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Applies the `up` script of a migration and records it as applied.
+fn apply_up(conn: &Connection, migration: &Migration) -> rusqlite::Result<()> {
+    conn.execute_batch(migration.up)?;
+    conn.execute(
+        "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, unixepoch())",
+        params![migration.version, migration.description],
+    )?;
+
+    Ok(())
+}
+
+/// Applies the `down` script of a migration and removes its record.
+fn apply_down(conn: &Connection, migration: &Migration) -> rusqlite::Result<()> {
+    conn.execute_batch(migration.down)?;
+    conn.execute(
+        "DELETE FROM schema_version WHERE version = ?1",
+        [migration.version],
+    )?;
+
+    Ok(())
+}
+
+/// Records the initial schema as applied for databases created before migrations existed.
+///
+/// Such databases already contain the tables of the first migration, only without any record of
+/// it, so running that migration again would fail.
+fn adopt_unversioned_schema(conn: &Connection) -> Result<()> {
+    if current_version(conn)? == 0 && table_exists(conn, "runs")? {
+        let initial = &MIGRATIONS[0];
+        conn.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, unixepoch())",
+            params![initial.version, initial.description],
+        )
+        .map_err(DatabaseError::MigrationFailed)?;
+    }
+
+    Ok(())
+}
+
+/// Returns whether a table with the given name exists.
+pub(crate) fn table_exists(conn: &Connection, name: &str) -> Result<bool> {
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [name],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+
+    Ok(exists)
+}
@@ -0,0 +1,289 @@
+//! This module groups the names squad members were recorded under into players.
+//!
+//! A friend who shows up under several names is then counted as one person by
+//! [`squad_member_stats`](crate::analytics::squad_member_stats).
+//!
+//! Names are matched automatically once normalized by `normalize_name`, which ignores case, the
+//! platform icons Warframe shows next to crossplay names, and number suffixes such as `#123`.
+//! Names that differ in any other way, such as after the player renamed themselves, can be
+//! grouped by hand with `add_player_name` or `merge_players`.
+//!
+//! Names are linked to a player as runs are inserted. Names stored before players existed, or
+//! copied in by [`merge_database`](crate::import::merge_database), are linked by `sync_players`.
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::connection::{execute_retrying, with_savepoint};
+use crate::error::{DatabaseError, Result};
+
+/// A person the user played with, along with every name they were recorded under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Player {
+    /// The ID of the player.
+    pub player_id: i64,
+
+    /// The name the player is shown under.
+    pub display_name: String,
+
+    /// The names of squad members that belong to the player, in alphabetical order.
+    pub names: Vec<String>,
+}
+
+/// Returns the form of a squad member name used to match it against other names.
+///
+/// Leading and trailing whitespace, platform icons (which Warframe writes as characters from the
+/// Unicode private use area), and a trailing `#` followed by digits are removed, and the rest is
+/// converted to lowercase. A name made of nothing else is only trimmed and lowercased.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_database::players::normalize_name;
+///
+/// assert_eq!(normalize_name(" Player\u{e000}"), "player");
+/// assert_eq!(normalize_name("PLAYER#123"), "player");
+/// ```
+#[must_use]
+pub fn normalize_name(name: &str) -> String {
+    base_name(name).to_lowercase()
+}
+
+/// Returns a name without whitespace, platform icons, or a number suffix, keeping its case.
+fn base_name(name: &str) -> String {
+    let without_icons: String = name
+        .chars()
+        .filter(|&c| !matches!(c, '\u{e000}'..='\u{f8ff}'))
+        .collect();
+    let trimmed = without_icons.trim();
+
+    let base = match trimmed.rsplit_once('#') {
+        Some((base, suffix))
+            if !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            base.trim_end()
+        }
+        _ => trimmed,
+    };
+
+    if base.is_empty() {
+        name.trim().to_string()
+    } else {
+        base.to_string()
+    }
+}
+
+/// Links a squad member name to a player, creating a player for it if no other name matches it.
+///
+/// # Returns
+/// * `Result<i64>` - The ID of the player the name belongs to.
+pub(crate) fn link_name(conn: &Connection, name: &str) -> Result<i64> {
+    with_savepoint(conn, "link_name", |conn| {
+        if let Some(player_id) = conn
+            .prepare_cached("SELECT player_id FROM player_names WHERE member_name = ?1")?
+            .query_row([name], |row| row.get(0))
+            .optional()?
+        {
+            return Ok(player_id);
+        }
+
+        let normalized_name = normalize_name(name);
+        let existing: Option<i64> = conn
+            .prepare_cached(
+                "SELECT player_id FROM player_names WHERE normalized_name = ?1 LIMIT 1",
+            )?
+            .query_row([&normalized_name], |row| row.get(0))
+            .optional()?;
+        let player_id = if let Some(player_id) = existing {
+            player_id
+        } else {
+            conn.prepare_cached("INSERT INTO players (display_name) VALUES (?1)")?
+                .execute([base_name(name)])?;
+            conn.last_insert_rowid()
+        };
+
+        conn.prepare_cached(
+            "INSERT INTO player_names (member_name, normalized_name, player_id)
+            VALUES (?1, ?2, ?3)",
+        )?
+        .execute(params![name, normalized_name, player_id])?;
+
+        Ok(player_id)
+    })
+}
+
+/// Links every squad member name that does not belong to a player yet.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<usize>` - The number of names that were linked.
+///
+/// # Errors
+///
+/// Returns an error if a query fails, in which case no name is linked.
+pub fn sync_players(conn: &Connection) -> Result<usize> {
+    with_savepoint(conn, "sync_players", |conn| {
+        let names = conn
+            .prepare_cached(
+                "SELECT DISTINCT member_name FROM squad_members
+                WHERE member_name NOT IN (SELECT member_name FROM player_names)
+                ORDER BY member_name",
+            )?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        for name in &names {
+            link_name(conn, name)?;
+        }
+
+        Ok(names.len())
+    })
+}
+
+/// Fetches every player, along with the names that belong to them.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Vec<Player>>` - The players, ordered by display name.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn fetch_players(conn: &Connection) -> Result<Vec<Player>> {
+    let mut players = conn
+        .prepare_cached("SELECT id, display_name FROM players ORDER BY display_name, id")?
+        .query_map([], |row| {
+            Ok(Player {
+                player_id: row.get(0)?,
+                display_name: row.get(1)?,
+                names: Vec::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for player in &mut players {
+        player.names = conn
+            .prepare_cached(
+                "SELECT member_name FROM player_names WHERE player_id = ?1 ORDER BY member_name",
+            )?
+            .query_map([player.player_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+    }
+
+    Ok(players)
+}
+
+/// Changes the name a player is shown under.
+///
+/// Leading and trailing whitespace is removed from the name.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `player_id` - The ID of the player to rename.
+/// * `display_name` - The new name of the player.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the name is empty, [`DatabaseError::PlayerNotFound`]
+/// if no player with the given ID exists, or another error if the query fails.
+pub fn rename_player(conn: &Connection, player_id: i64, display_name: &str) -> Result<()> {
+    let display_name = display_name.trim();
+    if display_name.is_empty() {
+        return Err(DatabaseError::InvalidData(
+            "player names cannot be empty".to_string(),
+        ));
+    }
+
+    let updated = execute_retrying(
+        conn,
+        "UPDATE players SET display_name = ?2 WHERE id = ?1",
+        params![player_id, display_name],
+    )?;
+    if updated == 0 {
+        return Err(DatabaseError::PlayerNotFound(player_id));
+    }
+
+    Ok(())
+}
+
+/// Adds a name to a player, such as the new name of a player who renamed themselves.
+///
+/// If the name already belongs to another player, it is moved to this one, and the other player
+/// is removed if it has no names left.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `player_id` - The ID of the player to add the name to.
+/// * `name` - The name, exactly as squad members are recorded under it.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::PlayerNotFound`] if no player with the given ID exists, or another
+/// error if a query fails.
+pub fn add_player_name(conn: &Connection, player_id: i64, name: &str) -> Result<()> {
+    with_savepoint(conn, "add_player_name", |conn| {
+        ensure_player_exists(conn, player_id)?;
+
+        conn.prepare_cached(
+            "INSERT INTO player_names (member_name, normalized_name, player_id)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (member_name) DO UPDATE SET player_id = excluded.player_id",
+        )?
+        .execute(params![name, normalize_name(name), player_id])?;
+        remove_empty_players(conn)
+    })
+}
+
+/// Merges one player into another, so that every name of the former belongs to the latter.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `into_id` - The ID of the player to keep.
+/// * `from_id` - The ID of the player to merge into it, which is removed.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if both IDs are the same,
+/// [`DatabaseError::PlayerNotFound`] if no player with one of the IDs exists, or another error if
+/// a query fails.
+pub fn merge_players(conn: &Connection, into_id: i64, from_id: i64) -> Result<()> {
+    if into_id == from_id {
+        return Err(DatabaseError::InvalidData(
+            "a player cannot be merged into itself".to_string(),
+        ));
+    }
+
+    with_savepoint(conn, "merge_players", |conn| {
+        ensure_player_exists(conn, into_id)?;
+        ensure_player_exists(conn, from_id)?;
+
+        conn.prepare_cached("UPDATE player_names SET player_id = ?1 WHERE player_id = ?2")?
+            .execute([into_id, from_id])?;
+        remove_empty_players(conn)
+    })
+}
+
+/// Returns [`DatabaseError::PlayerNotFound`] if no player with the given ID exists.
+fn ensure_player_exists(conn: &Connection, player_id: i64) -> Result<()> {
+    let exists: bool = conn
+        .prepare_cached("SELECT EXISTS (SELECT 1 FROM players WHERE id = ?1)")?
+        .query_row([player_id], |row| row.get(0))?;
+    if !exists {
+        return Err(DatabaseError::PlayerNotFound(player_id));
+    }
+
+    Ok(())
+}
+
+/// Removes the players that no name belongs to anymore.
+fn remove_empty_players(conn: &Connection) -> Result<()> {
+    conn.prepare_cached(
+        "DELETE FROM players WHERE id NOT IN (SELECT player_id FROM player_names)",
+    )?
+    .execute([])?;
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! This module provides a pool of SQLite connections for concurrent access to the database.
+//!
+//! A single connection can only be used by one thread at a time, which would force the Flutter UI,
+//! the log watcher, and background analytics to wait on each other. The pool instead hands out
+//! separate connections to the same database file, and enables write-ahead logging so readers
+//! can keep reading while a writer commits.
+//!
+//! Pooled connections dereference to [`rusqlite::Connection`], so they can be passed directly to
+//! the query functions of this library.
+
+use r2d2_sqlite::SqliteConnectionManager;
+use std::fs;
+use std::path::Path;
+
+use crate::connection::ConnectionOptions;
+use crate::error::Result;
+use crate::migrations;
+
+/// A pool of connections to a single database file.
+pub type Pool = r2d2::Pool<SqliteConnectionManager>;
+
+/// A connection borrowed from a [`Pool`], which is returned to the pool when dropped.
+pub type PooledConnection = r2d2::PooledConnection<SqliteConnectionManager>;
+
+/// Creates a connection pool for the database at the given path.
+///
+/// Like `create_database`, this creates the database file and its directory structure if they do
+/// not exist, and applies any pending migrations before the pool is returned.
+///
+/// # Arguments
+/// * `path` - The file path of the SQLite database.
+///
+/// # Returns
+/// * `Result<Pool>` - A pool of connections to the database, each configured with the default
+///   [`ConnectionOptions`].
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Io`](crate::error::DatabaseError::Io) if the directory structure
+/// cannot be created, [`DatabaseError::PoolFailed`](crate::error::DatabaseError::PoolFailed) if
+/// the pool cannot open its connections, or a migration error if the schema cannot be brought
+/// up to date.
+pub fn create_pool(path: &str) -> Result<Pool> {
+    create_pool_with_options(path, ConnectionOptions::default())
+}
+
+/// Creates a connection pool for the database at the given path, configuring each connection
+/// with the given options.
+///
+/// # Arguments
+/// * `path` - The file path of the SQLite database.
+/// * `options` - The settings applied to each connection when it is opened.
+///
+/// # Returns
+/// * `Result<Pool>` - A pool of connections to the database.
+///
+/// # Errors
+///
+/// Returns the same errors as [`create_pool`].
+pub fn create_pool_with_options(path: &str, options: ConnectionOptions) -> Result<Pool> {
+    // Ensure the directory exists
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let manager = SqliteConnectionManager::file(path).with_init(move |conn| {
+        #[cfg(feature = "tracing")]
+        crate::instrumentation::instrument(conn);
+
+        options.configure(conn)
+    });
+    let pool = r2d2::Pool::new(manager)?;
+
+    // Migrate once up front, so no caller ever sees an outdated schema
+    let conn = pool.get()?;
+    migrations::migrate(&conn)?;
+    drop(conn);
+
+    Ok(pool)
+}
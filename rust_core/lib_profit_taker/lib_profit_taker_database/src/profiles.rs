@@ -0,0 +1,225 @@
+//! This module manages profiles, separate databases kept side by side for players who want to
+//! keep their runs apart, such as those of a main account, an alt account, and runs played with a
+//! controller.
+//!
+//! Each profile is a database file named after the profile, in a directory shared by all of them.
+//! The directory also records which profile is active, which is the one
+//! [`open_active_profile`](crate::connection::open_active_profile) opens. Until another profile
+//! is switched to, [`DEFAULT_PROFILE`] is active.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::connection::{create_database, remove_database_files};
+use crate::error::{DatabaseError, Result};
+
+/// The name of the profile that is active until another profile is switched to.
+pub const DEFAULT_PROFILE: &str = "main";
+
+/// The file extension of the database file of each profile.
+const PROFILE_EXTENSION: &str = "sqlite";
+
+/// The name of the file recording which profile is active.
+const ACTIVE_PROFILE_FILE: &str = "active_profile";
+
+/// Characters that cannot appear in file names on at least one supported platform.
+const FORBIDDEN_CHARACTERS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// The profiles kept in a directory.
+///
+/// # Examples
+///
+/// ```no_run
+/// use lib_profit_taker_database::connection::open_active_profile;
+/// use lib_profit_taker_database::profiles::Profiles;
+///
+/// let profiles = Profiles::new("profiles");
+/// profiles.create("controller runs")?;
+/// profiles.switch("controller runs")?;
+///
+/// let conn = open_active_profile(&profiles)?;
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profiles {
+    /// The directory holding the database file of each profile.
+    dir: PathBuf,
+}
+
+impl Profiles {
+    /// Manages the profiles in the given directory, which is created once a profile is.
+    ///
+    /// # Arguments
+    /// * `dir` - The path of the directory holding the profiles.
+    #[must_use]
+    pub fn new(dir: &str) -> Self {
+        Self {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    /// Lists the names of the profiles, in alphabetical order.
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>>` - The names of the profiles, which is empty if the directory does
+    ///   not exist yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::Io`] if the directory cannot be read.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == PROFILE_EXTENSION)
+            {
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    names.push(name.to_owned());
+                }
+            }
+        }
+        names.sort_unstable();
+
+        Ok(names)
+    }
+
+    /// Creates a new, empty profile.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the profile, such as "alt". Leading and trailing whitespace is
+    ///   removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::InvalidData`] if the name is empty or cannot be used as a file
+    /// name, [`DatabaseError::ProfileExists`] if a profile with the same name already exists, or
+    /// the same errors as [`create_database`] if its database cannot be created.
+    pub fn create(&self, name: &str) -> Result<()> {
+        let name = normalize_name(name)?;
+        let path = self.path_of(name);
+        if Path::new(&path).exists() {
+            return Err(DatabaseError::ProfileExists(name.to_owned()));
+        }
+
+        create_database(&path)
+    }
+
+    /// Returns the name of the active profile.
+    ///
+    /// # Returns
+    /// * `Result<String>` - The name of the profile last switched to, or [`DEFAULT_PROFILE`] if
+    ///   none was.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::Io`] if the record of the active profile cannot be read.
+    pub fn active(&self) -> Result<String> {
+        match fs::read_to_string(self.dir.join(ACTIVE_PROFILE_FILE)) {
+            Ok(name) => Ok(name.trim().to_owned()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(DEFAULT_PROFILE.to_owned()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the path of the database file of the active profile.
+    ///
+    /// # Returns
+    /// * `Result<String>` - The path of the database file, which may not exist yet if the active
+    ///   profile is [`DEFAULT_PROFILE`] and it was never opened.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::Io`] if the record of the active profile cannot be read.
+    pub fn active_path(&self) -> Result<String> {
+        Ok(self.path_of(&self.active()?))
+    }
+
+    /// Makes a profile the active one.
+    ///
+    /// Connections already open to the previously active profile are not affected.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the profile to switch to.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::ProfileNotFound`] if no profile with the given name exists, or
+    /// [`DatabaseError::Io`] if the active profile cannot be recorded.
+    pub fn switch(&self, name: &str) -> Result<()> {
+        let name = self.existing(name)?;
+        fs::write(self.dir.join(ACTIVE_PROFILE_FILE), name)?;
+
+        Ok(())
+    }
+
+    /// Deletes a profile along with every run in it.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the profile to delete.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::ProfileNotFound`] if no profile with the given name exists,
+    /// [`DatabaseError::InvalidData`] if it is the active profile, or [`DatabaseError::Io`] if its
+    /// files cannot be removed.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let name = self.existing(name)?;
+        if name == self.active()? {
+            return Err(DatabaseError::InvalidData(format!(
+                "\"{name}\" is the active profile and cannot be deleted"
+            )));
+        }
+
+        remove_database_files(&self.path_of(name))?;
+
+        Ok(())
+    }
+
+    /// Returns the normalized name of a profile, checking that it exists.
+    fn existing<'a>(&self, name: &'a str) -> Result<&'a str> {
+        let name = normalize_name(name)?;
+        if !Path::new(&self.path_of(name)).is_file() {
+            return Err(DatabaseError::ProfileNotFound(name.to_owned()));
+        }
+
+        Ok(name)
+    }
+
+    /// Returns the path of the database file of a profile.
+    fn path_of(&self, name: &str) -> String {
+        self.dir
+            .join(format!("{name}.{PROFILE_EXTENSION}"))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Trims a profile name, checking that it can be used as a file name.
+fn normalize_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(DatabaseError::InvalidData(
+            "profile names cannot be empty".to_owned(),
+        ));
+    }
+    if name.starts_with('.')
+        || name
+            .chars()
+            .any(|c| c.is_control() || FORBIDDEN_CHARACTERS.contains(&c))
+    {
+        return Err(DatabaseError::InvalidData(format!(
+            "\"{name}\" cannot be used as a profile name"
+        )));
+    }
+
+    Ok(name)
+}
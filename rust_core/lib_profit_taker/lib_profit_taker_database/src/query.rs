@@ -0,0 +1,121 @@
+//! This module runs arbitrary SQL written by the user, for power users who want to answer
+//! questions that the built-in statistics do not cover from an SQL console.
+//!
+//! Queries are limited to reading data: an authorizer rejects anything but `SELECT` statements
+//! while a query is prepared and run, so a mistyped or malicious query cannot modify runs.
+
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params_from_iter, Batch, Connection, ErrorCode};
+use serde::Serialize;
+
+use crate::error::{DatabaseError, Result};
+
+/// The result of a query run from the SQL console.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueryResult {
+    /// The names of the columns of the result, in order.
+    pub columns: Vec<String>,
+
+    /// The rows of the result, each with one value per column.
+    ///
+    /// Integers, reals, and text are converted to the equivalent JSON values, blobs to arrays of
+    /// bytes, and `NULL` as well as non-finite reals to `null`.
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// Runs a single read-only query written by the user.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `sql` - The query, which must be a single `SELECT` statement. It can use `WITH` clauses and
+///   any built-in function, but cannot use `PRAGMA`, `ATTACH`, or transactions.
+/// * `params` - The values bound to the parameters of the query, in order.
+///
+/// # Returns
+/// * `Result<QueryResult>` - The column names and every row of the result.
+///
+/// # Errors
+///
+/// Returns `DatabaseError::InvalidData` if the query is empty, contains more than one statement,
+/// or would do anything other than read data. Returns an error if the query is otherwise invalid
+/// or fails.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_database::connection::open_in_memory;
+/// use lib_profit_taker_database::query::execute_readonly;
+/// use rusqlite::types::Value;
+///
+/// let conn = open_in_memory().unwrap();
+///
+/// let result = execute_readonly(&conn, "SELECT ?1 + 1 AS answer", &[Value::Integer(41)])
+///     .unwrap();
+/// assert_eq!(result.columns, ["answer"]);
+/// assert_eq!(result.rows, [[serde_json::json!(42)]]);
+///
+/// assert!(execute_readonly(&conn, "DELETE FROM runs", &[]).is_err());
+/// ```
+pub fn execute_readonly(conn: &Connection, sql: &str, params: &[Value]) -> Result<QueryResult> {
+    conn.authorizer(Some(authorize_read));
+    let result = run_query(conn, sql, params);
+    conn.authorizer(None::<fn(AuthContext<'_>) -> Authorization>);
+
+    result.map_err(|error| match error {
+        DatabaseError::Sqlite(error)
+            if error.sqlite_error_code() == Some(ErrorCode::AuthorizationForStatementDenied) =>
+        {
+            DatabaseError::InvalidData(format!("only SELECT queries can be run: {error}"))
+        }
+        error => error,
+    })
+}
+
+/// Allows only the actions needed to read data.
+const fn authorize_read(context: AuthContext<'_>) -> Authorization {
+    match context.action {
+        AuthAction::Select
+        | AuthAction::Read { .. }
+        | AuthAction::Function { .. }
+        | AuthAction::Recursive => Authorization::Allow,
+        _ => Authorization::Deny,
+    }
+}
+
+/// Prepares and runs the query once the authorizer is in place.
+fn run_query(conn: &Connection, sql: &str, params: &[Value]) -> Result<QueryResult> {
+    let mut batch = Batch::new(conn, sql);
+    let Some(mut stmt) = batch.next()? else {
+        return Err(DatabaseError::InvalidData("the query is empty".to_string()));
+    };
+    if batch.next()?.is_some() {
+        return Err(DatabaseError::InvalidData(
+            "only a single statement can be run at a time".to_string(),
+        ));
+    }
+
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = Vec::new();
+    let mut query = stmt.query(params_from_iter(params))?;
+    while let Some(row) = query.next()? {
+        rows.push(
+            (0..columns.len())
+                .map(|i| row.get_ref(i).map(json_value))
+                .collect::<rusqlite::Result<_>>()?,
+        );
+    }
+
+    Ok(QueryResult { columns, rows })
+}
+
+/// Converts an SQLite value to the equivalent JSON value.
+fn json_value(value: ValueRef<'_>) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(integer) => integer.into(),
+        ValueRef::Real(real) => real.into(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into(),
+        ValueRef::Blob(blob) => blob.into(),
+    }
+}
@@ -1,4 +1,5 @@
 //! This module contains the SQL schema used to set up the SQLite database for the application.
+//!
 //! It includes SQL statements for creating and initializing the necessary database tables, 
 //! along with inserting default values for specific tables like `leg_position` and `status_effects`.
 //!
@@ -11,8 +12,19 @@
 //! - `status_effects`: Contains predefined status effects that can be applied during runs.
 //! - `shield_changes`: Tracks the shield time changes during a run, linked to status effects and phases.
 //!
-//! The SQL statements in this module are stored as a constant string (`SCHEMA_SQL`), which is later 
-//! executed to initialize the database schema.
+//! The SQL statements in this module are stored as a constant string (`SCHEMA_SQL`), which is
+//! applied as the first migration in `migrations::MIGRATIONS`. Later schema changes are made by
+//! adding new migrations, not by editing this string.
+//!
+//! The `describe` function reports the schema a database actually has, including every migration
+//! applied to it, for the about screen of the app and for bug reports.
+
+use rusqlite::Connection;
+use std::fmt;
+
+use crate::error::Result;
+pub use crate::migrations::current_version;
+use crate::migrations::{table_exists, LATEST_VERSION};
 
 pub const SCHEMA_SQL: &str = "
 -- Create runs table
@@ -111,3 +123,185 @@ CREATE TABLE shield_changes (
     FOREIGN KEY (run_id, phase_number) REFERENCES phases (run_id, phase_number) ON DELETE CASCADE
 );
 ";
+
+/// The schema of a database, as reported by `describe`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaDescription {
+    /// The schema version of the database, or 0 if no migrations have been applied.
+    pub version: u32,
+
+    /// The schema version this version of the library migrates databases to.
+    pub latest_version: u32,
+
+    /// Every migration applied to the database, oldest first.
+    pub migrations: Vec<AppliedMigration>,
+
+    /// Every table of the database, ordered by name.
+    pub tables: Vec<TableDescription>,
+}
+
+/// A migration that has been applied to a database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedMigration {
+    /// The schema version the migration upgraded to.
+    pub version: u32,
+
+    /// The description of the migration.
+    pub description: String,
+
+    /// The Unix timestamp of when the migration was applied.
+    pub applied_at: i64,
+}
+
+/// The layout of a single table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDescription {
+    /// The name of the table.
+    pub name: String,
+
+    /// The columns of the table, in order.
+    pub columns: Vec<ColumnDescription>,
+
+    /// The names of the indexes created on the table, ordered by name. Indexes SQLite creates
+    /// for primary keys and unique constraints are not included.
+    pub indexes: Vec<String>,
+}
+
+/// A single column of a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDescription {
+    /// The name of the column.
+    pub name: String,
+
+    /// The declared type of the column, such as `INTEGER`, or an empty string if it has none.
+    pub type_name: String,
+
+    /// Whether the column has a `NOT NULL` constraint.
+    pub not_null: bool,
+
+    /// Whether the column is part of the primary key of the table.
+    pub primary_key: bool,
+
+    /// The SQL of the default value of the column, if it has one.
+    pub default: Option<String>,
+}
+
+/// Describes the schema of a database: its version, the migrations applied to it, and the layout
+/// of its tables.
+///
+/// The internal tables of SQLite and of the full-text search index are left out.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<SchemaDescription>` - The schema of the database. Its [`fmt::Display`]
+///   implementation formats it as plain text, for pasting into a bug report.
+///
+/// # Errors
+///
+/// Returns an error if a query fails.
+pub fn describe(conn: &Connection) -> Result<SchemaDescription> {
+    let migrations = if table_exists(conn, "schema_version")? {
+        conn.prepare(
+            "SELECT version, description, applied_at FROM schema_version ORDER BY version",
+        )?
+        .query_map([], |row| {
+            Ok(AppliedMigration {
+                version: row.get(0)?,
+                description: row.get(1)?,
+                applied_at: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?
+    } else {
+        Vec::new()
+    };
+
+    let table_names: Vec<String> = conn
+        .prepare(
+            "SELECT name FROM pragma_table_list
+            WHERE schema = 'main' AND type IN ('table', 'virtual') AND name NOT LIKE 'sqlite_%'
+            ORDER BY name",
+        )?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut tables = Vec::with_capacity(table_names.len());
+    for name in table_names {
+        let columns = conn
+            .prepare(
+                "SELECT name, type, \"notnull\", pk > 0, dflt_value FROM pragma_table_info(?1)",
+            )?
+            .query_map([&name], |row| {
+                Ok(ColumnDescription {
+                    name: row.get(0)?,
+                    type_name: row.get(1)?,
+                    not_null: row.get(2)?,
+                    primary_key: row.get(3)?,
+                    default: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let indexes = conn
+            .prepare("SELECT name FROM pragma_index_list(?1) WHERE origin = 'c' ORDER BY name")?
+            .query_map([&name], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        tables.push(TableDescription {
+            name,
+            columns,
+            indexes,
+        });
+    }
+
+    Ok(SchemaDescription {
+        version: current_version(conn)?,
+        latest_version: LATEST_VERSION,
+        migrations,
+        tables,
+    })
+}
+
+impl fmt::Display for SchemaDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Schema version {} (latest {})",
+            self.version, self.latest_version
+        )?;
+
+        for migration in &self.migrations {
+            writeln!(
+                f,
+                "  Migration {}: {} (applied at {})",
+                migration.version, migration.description, migration.applied_at
+            )?;
+        }
+
+        for table in &self.tables {
+            writeln!(f, "Table {}", table.name)?;
+            for column in &table.columns {
+                write!(f, "  {}", column.name)?;
+                if !column.type_name.is_empty() {
+                    write!(f, " {}", column.type_name)?;
+                }
+                if column.primary_key {
+                    write!(f, " PRIMARY KEY")?;
+                }
+                if column.not_null {
+                    write!(f, " NOT NULL")?;
+                }
+                if let Some(default) = &column.default {
+                    write!(f, " DEFAULT {default}")?;
+                }
+                writeln!(f)?;
+            }
+            for index in &table.indexes {
+                writeln!(f, "  Index {index}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+//! This module provides full-text search of runs, for the search box of the frontend.
+//!
+//! Runs are indexed by name, notes, and the names of their squad members in the `run_search` FTS5
+//! table, which triggers keep in sync with the `runs` and `squad_members` tables.
+
+use lib_profit_taker_core::Run;
+use rusqlite::Connection;
+
+use crate::error::Result;
+use crate::fetch::{fetch_phases, fetch_squad_members, run_from_row, RUN_COLUMNS};
+
+/// Searches the complete runs outside the trash by name, notes, and squad member names.
+///
+/// The query is treated as plain text rather than FTS5 query syntax: it is split into words, and
+/// a run matches if each word is the start of a word in its name, notes, or squad member names,
+/// ignoring case. For example, `"tri warm"` matches a run named "Tridolon warmup". Words can be
+/// matched in different fields, such as one in the name and another in the notes.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `query` - The text typed by the user.
+///
+/// # Returns
+/// * `Result<Vec<Run>>` - The fully hydrated matching runs, best matches first. This is empty if
+///   the query contains no words.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or a row references an unknown status effect or leg
+/// position.
+pub fn search_runs(conn: &Connection, query: &str) -> Result<Vec<Run>> {
+    let Some(fts_query) = to_fts_query(query) else {
+        return Ok(Vec::new());
+    };
+
+    let mut runs = conn
+        .prepare_cached(&format!(
+            "SELECT {RUN_COLUMNS} FROM runs
+            JOIN (
+                SELECT rowid AS run_id, rank FROM run_search WHERE run_search MATCH ?1
+            ) AS matches ON matches.run_id = runs.id
+            WHERE deleted_at IS NULL AND status != 'in_progress'
+            ORDER BY matches.rank, time_stamp DESC, id DESC"
+        ))?
+        .query_map([fts_query], run_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for run in &mut runs {
+        run.phases = fetch_phases(conn, run.run_id)?;
+        run.squad_members = fetch_squad_members(conn, run.run_id)?;
+    }
+
+    Ok(runs)
+}
+
+/// Turns plain text into an FTS5 query matching every word as a prefix, or `None` if the text
+/// has no words.
+fn to_fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
@@ -0,0 +1,554 @@
+//! This module provides a small local HTTP API serving runs and statistics as JSON.
+//!
+//! Stream overlays, such as OBS browser sources, can then show the latest run or the personal
+//! best without going through the app.
+//!
+//! A [`ServerHandle`] listens on a thread of its own and answers each request on a new thread,
+//! with a connection borrowed from a [`Pool`]. The API is read-only, and every response allows
+//! cross-origin requests, since overlays are usually loaded from a local file. It only supports
+//! `GET` requests to these endpoints:
+//!
+//! - `/runs?limit=20` lists the summaries of the latest runs outside the trash, newest first.
+//! - `/runs/latest` returns every field of the latest run, or `null` if there are none.
+//! - `/runs/{id}` returns every field of the run with the given ID.
+//! - `/stats/overview` returns the overview of every valid run.
+//! - `/stats/pb?solo=true&bugged=false` returns the personal best of a category, or `null`.
+//! - `/stats/average?n=5` returns the best average of `n` consecutive valid runs, or `null`.
+//! - `/stats/streaks?threshold=60` returns the current and longest streaks, of runs under
+//!   `threshold` seconds for streaks of fast runs.
+//! - `/events` streams an event whenever a run is inserted, as described below.
+//!
+//! Runs are serialized as the structs of the [`dto`](crate::dto) module. Errors are reported with
+//! the matching status code and a body of the form `{"error": "..."}`. Connections sending
+//! overly long lines or too many headers are closed without a response.
+//!
+//! `/events` is a stream of [server-sent events], which browsers read with an `EventSource`, so
+//! overlays can update as soon as a run finishes instead of polling. Each inserted run is sent as
+//! a `run_inserted` event whose data is its `RunSummaryDto`. Since [`events`](crate::events) only
+//! sees the changes of a single connection, the connection runs are inserted through has to be
+//! watched with the listener returned by `ServerHandle::run_listener`.
+//!
+//! [server-sent events]: https://html.spec.whatwg.org/multipage/server-sent-events.html
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::analytics::{best_average_of, cached_overview, fetch_pb, streaks, RunCategory};
+use crate::dto::{OverviewStatsDto, RunDetailDto, RunSummaryDto};
+use crate::duration;
+use crate::error::{DatabaseError, Result};
+use crate::events::RunEvent;
+use crate::fetch::{fetch_run_by_id, fetch_run_summaries, fetch_runs_paged, RunFilter, SortBy};
+use crate::pool::Pool;
+
+/// The number of runs listed by `/runs` when no limit is given.
+pub const DEFAULT_RUN_LIMIT: u32 = 20;
+
+/// The longest time a client can take to send its request before the connection is closed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The longest request line or header a client can send, in bytes, before the connection is
+/// closed. Requests to the API need far less.
+const MAX_LINE_LENGTH: u64 = 8 * 1024;
+
+/// The largest number of headers a client can send before the connection is closed.
+const MAX_HEADERS: usize = 100;
+
+/// How long the server first waits before accepting connections again after failing to, such as
+/// when the process runs out of file descriptors.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// The longest the server waits before accepting connections again, however many times in a row
+/// it failed to.
+const MAX_ACCEPT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The longest time sending an event to a client of `/events` can take before the client is
+/// dropped, so that a client that stopped reading cannot hold up the others.
+const EVENT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often the server tries to read a run it was told was inserted, before giving up on it.
+const FETCH_ATTEMPTS: u32 = 10;
+
+/// How long the server waits between attempts to read an inserted run.
+const FETCH_RETRY_DELAY: Duration = Duration::from_millis(20);
+
+/// What the server threads share.
+struct Shared {
+    /// The pool to borrow a connection from for each request.
+    pool: Pool,
+
+    /// The connections of the clients of `/events`.
+    subscribers: Mutex<Vec<TcpStream>>,
+}
+
+/// A message for the thread sending events to the clients of `/events`.
+enum Broadcast {
+    /// The run with the given ID was inserted.
+    Inserted(i64),
+
+    /// The server is stopping.
+    Stop,
+}
+
+/// A handle to a running HTTP server.
+///
+/// Dropping the handle stops the server and waits for it to exit. Requests that are already being
+/// answered are finished on their own threads.
+#[derive(Debug)]
+pub struct ServerHandle {
+    /// The address the server is listening on.
+    local_addr: SocketAddr,
+
+    /// Whether the server should keep accepting connections.
+    running: Arc<AtomicBool>,
+
+    /// The thread accepting connections. Only `None` once the handle is being closed.
+    thread: Option<JoinHandle<()>>,
+
+    /// The queue of the thread sending events to the clients of `/events`.
+    broadcasts: Sender<Broadcast>,
+
+    /// The thread sending events. Only `None` once the handle is being closed.
+    broadcast_thread: Option<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    /// Starts serving the database of the given pool on the given address.
+    ///
+    /// # Arguments
+    /// * `pool` - The pool to borrow a connection from for each request.
+    /// * `addr` - The address to listen on, such as `"127.0.0.1:8080"`. Port `0` picks any free
+    ///   port, which `local_addr` then reports. Binding to a loopback address keeps the API
+    ///   private to the computer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::Io`] if the address cannot be bound or the thread cannot be
+    /// started.
+    pub fn spawn(pool: Pool, addr: impl ToSocketAddrs) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let running = Arc::new(AtomicBool::new(true));
+        let shared = Arc::new(Shared {
+            pool,
+            subscribers: Mutex::new(Vec::new()),
+        });
+
+        let (broadcasts, receiver) = mpsc::channel();
+        let broadcast_shared = Arc::clone(&shared);
+        let broadcast_thread = thread::Builder::new()
+            .name("profit-taker-events".to_string())
+            .spawn(move || broadcast_events(&broadcast_shared, &receiver))?;
+
+        let still_running = Arc::clone(&running);
+        let thread = thread::Builder::new()
+            .name("profit-taker-server".to_string())
+            .spawn(move || accept_connections(&listener, &shared, &still_running))?;
+
+        Ok(Self {
+            local_addr,
+            running,
+            thread: Some(thread),
+            broadcasts,
+            broadcast_thread: Some(broadcast_thread),
+        })
+    }
+
+    /// Returns a listener that sends an event to the clients of `/events` for every run inserted,
+    /// to be registered with [`watch_runs`](crate::events::watch_runs) on the connection runs are
+    /// inserted through.
+    ///
+    /// Updates and deletions are ignored. Once the server is stopped, the listener does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use lib_profit_taker_database::connection::ConnectionOptions;
+    /// use lib_profit_taker_database::events::watch_runs;
+    /// use lib_profit_taker_database::pool::create_pool;
+    /// use lib_profit_taker_database::server::ServerHandle;
+    /// use lib_profit_taker_database::writer::WriterHandle;
+    ///
+    /// let server = ServerHandle::spawn(create_pool("runs.db")?, "127.0.0.1:8080")?;
+    ///
+    /// let conn = ConnectionOptions::default().open("runs.db")?;
+    /// watch_runs(&conn, server.run_listener());
+    /// let writer = WriterHandle::spawn(conn)?;
+    /// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+    /// ```
+    pub fn run_listener(&self) -> impl FnMut(RunEvent) + Send + 'static {
+        let broadcasts = self.broadcasts.clone();
+
+        move |event| {
+            if let RunEvent::Inserted(run_id) = event {
+                // The server having stopped just means nobody is listening anymore
+                let _ = broadcasts.send(Broadcast::Inserted(run_id));
+            }
+        }
+    }
+
+    /// Returns the address the server is listening on.
+    #[must_use]
+    pub const fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops accepting connections and waits for the server thread to exit.
+    ///
+    /// This is what dropping the handle does, except that it reports whether the thread exited
+    /// cleanly.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::TaskFailed`] if the server thread panicked.
+    pub fn close(mut self) -> Result<()> {
+        self.stop()
+    }
+
+    /// Tells the server thread to stop, and waits for it to exit.
+    fn stop(&mut self) -> Result<()> {
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+
+        self.running.store(false, Ordering::SeqCst);
+        // The thread is blocked waiting for a connection, so wake it up with one
+        let mut wake_addr = self.local_addr;
+        if wake_addr.ip().is_unspecified() {
+            wake_addr.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        }
+        let _ = TcpStream::connect(wake_addr);
+        let _ = self.broadcasts.send(Broadcast::Stop);
+
+        let joined = thread.join();
+        let broadcast_joined = self.broadcast_thread.take().map(JoinHandle::join);
+        if joined.is_err() || broadcast_joined.is_some_and(|joined| joined.is_err()) {
+            return Err(DatabaseError::TaskFailed);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        // There is no way to report a panic from here, and `close` exists for those who care
+        let _ = self.stop();
+    }
+}
+
+/// Answers every connection on a thread of its own, until the server is stopped.
+fn accept_connections(listener: &TcpListener, shared: &Arc<Shared>, running: &AtomicBool) {
+    let mut retry_delay = ACCEPT_RETRY_DELAY;
+    for stream in listener.incoming() {
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+        // Failures such as running out of file descriptors tend to last a while, so the server
+        // waits for them to clear up instead of failing again straight away
+        let Ok(stream) = stream else {
+            thread::sleep(retry_delay);
+            retry_delay = (retry_delay * 2).min(MAX_ACCEPT_RETRY_DELAY);
+            continue;
+        };
+        retry_delay = ACCEPT_RETRY_DELAY;
+
+        let shared = Arc::clone(shared);
+        // A request that cannot be answered only fails for its own client
+        let _ = thread::Builder::new()
+            .name("profit-taker-request".to_string())
+            .spawn(move || handle_connection(stream, &shared));
+    }
+}
+
+/// A response to a request.
+struct Response {
+    /// The HTTP status code.
+    status: u16,
+
+    /// The JSON body.
+    body: Value,
+}
+
+impl Response {
+    /// Creates a successful response with the given body.
+    const fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    /// Creates an error response with the given status code and message.
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+impl From<DatabaseError> for Response {
+    fn from(error: DatabaseError) -> Self {
+        let status = match error {
+            DatabaseError::RunNotFound(_) => 404,
+            DatabaseError::InvalidData(_) => 400,
+            _ => 500,
+        };
+
+        Self::error(status, &error.to_string())
+    }
+}
+
+/// Reads a request from a connection and writes the response to it.
+fn handle_connection(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+
+    let mut request_line = String::new();
+    read_line(&mut reader, &mut request_line)?;
+    // The headers are not used, but have to be read before responding
+    for headers in 0.. {
+        if headers == MAX_HEADERS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many headers",
+            ));
+        }
+
+        let mut header = String::new();
+        if read_line(&mut reader, &mut header)? == 0 || header.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/events")) => return subscribe(stream, shared),
+        (Some("GET"), Some(target)) => route(&shared.pool, target),
+        (Some(_), Some(_)) => Response::error(405, "only GET requests are supported"),
+        _ => Response::error(400, "malformed request"),
+    };
+
+    write_response(&stream, &response)
+}
+
+/// Reads a line of a request, including its line ending, returning its length.
+///
+/// Returns an error if the line is longer than [`MAX_LINE_LENGTH`], so that a client cannot make
+/// the server buffer an endless line.
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    let mut limited = reader.take(MAX_LINE_LENGTH);
+    let length = limited.read_line(line)?;
+    if limited.limit() == 0 && !line.ends_with('\n') {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+    }
+
+    Ok(length)
+}
+
+/// Starts streaming events to a client of `/events`, keeping its connection open.
+fn subscribe(mut stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    stream.set_write_timeout(Some(EVENT_WRITE_TIMEOUT))?;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\n\
+        Content-Type: text/event-stream\r\n\
+        Access-Control-Allow-Origin: *\r\n\
+        Cache-Control: no-store\r\n\
+        \r\n"
+    )?;
+    stream.flush()?;
+
+    shared
+        .subscribers
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(stream);
+
+    Ok(())
+}
+
+/// Sends an event to every client of `/events` for each inserted run, until the server is
+/// stopped.
+fn broadcast_events(shared: &Shared, receiver: &Receiver<Broadcast>) {
+    while let Ok(Broadcast::Inserted(run_id)) = receiver.recv() {
+        let Some(summary) = fetch_inserted_summary(&shared.pool, run_id) else {
+            continue;
+        };
+        let event = format!("event: run_inserted\ndata: {}\n\n", json!(summary));
+
+        // The clients are taken out of the lock while writing, so that new clients can subscribe
+        // in the meantime
+        let mut subscribers = mem::take(
+            &mut *shared
+                .subscribers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        // Clients that closed the stream or stopped reading it fail to be written to, and are
+        // forgotten
+        subscribers.retain_mut(|stream| {
+            stream
+                .write_all(event.as_bytes())
+                .and_then(|()| stream.flush())
+                .is_ok()
+        });
+
+        let mut current = shared
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        subscribers.append(&mut current);
+        *current = subscribers;
+    }
+
+    // Dropping the connections ends the streams
+    shared
+        .subscribers
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+/// Reads the summary of a run that was just inserted, or `None` if it cannot be read.
+fn fetch_inserted_summary(pool: &Pool, run_id: i64) -> Option<RunSummaryDto> {
+    let conn = pool.get().ok()?;
+
+    // Listeners are called while the transaction inserting the run is still committing, so the
+    // run may not be visible to other connections for a moment
+    for _ in 0..FETCH_ATTEMPTS {
+        match fetch_run_by_id(&conn, run_id) {
+            Ok(run) => return Some(RunSummaryDto::from(&run)),
+            Err(DatabaseError::RunNotFound(_)) => thread::sleep(FETCH_RETRY_DELAY),
+            Err(_) => return None,
+        }
+    }
+
+    None
+}
+
+/// Writes a response, closing the connection after it.
+fn write_response(mut stream: &TcpStream, response: &Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let body = response.body.to_string();
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {reason}\r\n\
+        Content-Type: application/json\r\n\
+        Content-Length: {}\r\n\
+        Access-Control-Allow-Origin: *\r\n\
+        Cache-Control: no-store\r\n\
+        Connection: close\r\n\
+        \r\n\
+        {body}",
+        response.status,
+        body.len(),
+    )?;
+    stream.flush()
+}
+
+/// Answers a request for the given path and query string.
+fn route(pool: &Pool, target: &str) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let conn = match pool.get() {
+        Ok(conn) => conn,
+        Err(error) => return DatabaseError::from(error).into(),
+    };
+
+    let body =
+        match segments.as_slice() {
+            ["runs"] => query_param(query, "limit")
+                .map(|limit| limit.unwrap_or(DEFAULT_RUN_LIMIT))
+                .and_then(|limit| {
+                    to_json(&fetch_run_summaries(
+                        &conn,
+                        &RunFilter::default(),
+                        Some(limit),
+                    )?)
+                }),
+            ["runs", "latest"] => {
+                fetch_runs_paged(&conn, 0, 1, SortBy::default(), &RunFilter::default())
+                    .and_then(|runs| to_json(&runs.first().map(RunDetailDto::from)))
+            }
+            ["runs", run_id] => match run_id.parse() {
+                Ok(run_id) => fetch_run_by_id(&conn, run_id)
+                    .and_then(|run| to_json(&RunDetailDto::from(&run))),
+                Err(_) => return Response::error(404, "no such endpoint"),
+            },
+            ["stats", "overview"] => cached_overview(&conn)
+                .and_then(|overview| to_json(&OverviewStatsDto::from(overview))),
+            ["stats", "pb"] => query_param(query, "solo")
+                .and_then(|solo| Ok((solo, query_param(query, "bugged")?)))
+                .and_then(|(solo, bugged)| {
+                    let category = RunCategory {
+                        solo: solo.unwrap_or(true),
+                        bugged: bugged.unwrap_or(false),
+                        game_version: None,
+                        warframe: None,
+                    };
+                    to_json(&fetch_pb(&conn, &category)?.as_ref().map(RunDetailDto::from))
+                }),
+            ["stats", "average"] => query_param(query, "n").and_then(|n| {
+                let average = best_average_of(&conn, n.unwrap_or(5))?;
+                Ok(average.map_or(Value::Null, |average| {
+                    json!({
+                        "average": average.average.as_secs_f64(),
+                        "run_ids": average.runs.iter().map(|run| run.run_id).collect::<Vec<_>>(),
+                    })
+                }))
+            }),
+            ["stats", "streaks"] => query_param(query, "threshold").and_then(|threshold| {
+                let threshold = threshold.ok_or_else(|| {
+                    DatabaseError::InvalidData("missing query parameter `threshold`".to_string())
+                })?;
+                let streaks = streaks(&conn, duration::Duration::from_secs_f64(threshold))?;
+                Ok(json!({
+                    "current_daily": streaks.current_daily,
+                    "longest_daily": streaks.longest_daily,
+                    "current_under_threshold": streaks.current_under_threshold,
+                    "longest_under_threshold": streaks.longest_under_threshold,
+                }))
+            }),
+            _ => return Response::error(404, "no such endpoint"),
+        };
+
+    body.map_or_else(Response::from, Response::ok)
+}
+
+/// Converts a value to JSON.
+fn to_json(value: &impl Serialize) -> Result<Value> {
+    Ok(serde_json::to_value(value)?)
+}
+
+/// Parses the value of a parameter of a query string, such as `limit` in `limit=20&n=5`, or
+/// returns `None` if it is missing.
+///
+/// Values are not percent-decoded, since every parameter of the API is a number or a boolean.
+fn query_param<T: std::str::FromStr>(query: &str, name: &str) -> Result<Option<T>> {
+    let Some(value) = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find_map(|(key, value)| (key == name).then_some(value))
+    else {
+        return Ok(None);
+    };
+
+    value.parse().map(Some).map_err(|_| {
+        DatabaseError::InvalidData(format!("invalid value of query parameter `{name}`"))
+    })
+}
@@ -0,0 +1,181 @@
+//! This module provides a key-value store for the preferences of the app, such as the path of the
+//! selected `EE.log` file or the options of the overlay.
+//!
+//! Keeping the settings in the database instead of a separate configuration file means they are
+//! included in backups along with the runs. Each setting is stored with the type it was last set
+//! with, and reading it back as a different type is an error rather than a silent conversion.
+
+use rusqlite::types::{FromSql, ToSql};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::connection::execute_retrying;
+use crate::error::{DatabaseError, Result};
+
+/// Reads a setting as a string.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `key` - The name of the setting.
+///
+/// # Returns
+/// * `Result<Option<String>>` - The value of the setting, or `None` if it has not been set.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the setting was set with a different type, or
+/// another error if the query fails.
+pub fn get_string(conn: &Connection, key: &str) -> Result<Option<String>> {
+    get(conn, key, "string")
+}
+
+/// Reads a setting as a boolean.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `key` - The name of the setting.
+///
+/// # Returns
+/// * `Result<Option<bool>>` - The value of the setting, or `None` if it has not been set.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the setting was set with a different type, or
+/// another error if the query fails.
+pub fn get_bool(conn: &Connection, key: &str) -> Result<Option<bool>> {
+    get(conn, key, "boolean")
+}
+
+/// Reads a setting as an integer.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `key` - The name of the setting.
+///
+/// # Returns
+/// * `Result<Option<i64>>` - The value of the setting, or `None` if it has not been set.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the setting was set with a different type, or
+/// another error if the query fails.
+pub fn get_i64(conn: &Connection, key: &str) -> Result<Option<i64>> {
+    get(conn, key, "integer")
+}
+
+/// Reads a setting as a floating point number.
+///
+/// Settings set as integers can also be read as floating point numbers.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `key` - The name of the setting.
+///
+/// # Returns
+/// * `Result<Option<f64>>` - The value of the setting, or `None` if it has not been set.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the setting was set with a different type, or
+/// another error if the query fails.
+pub fn get_f64(conn: &Connection, key: &str) -> Result<Option<f64>> {
+    get(conn, key, "number")
+}
+
+/// Sets a setting to a string, replacing any previous value.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `key` - The name of the setting.
+/// * `value` - The new value of the setting.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn set_string(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    set(conn, key, value)
+}
+
+/// Sets a setting to a boolean, replacing any previous value.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `key` - The name of the setting.
+/// * `value` - The new value of the setting.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn set_bool(conn: &Connection, key: &str, value: bool) -> Result<()> {
+    set(conn, key, value)
+}
+
+/// Sets a setting to an integer, replacing any previous value.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `key` - The name of the setting.
+/// * `value` - The new value of the setting.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn set_i64(conn: &Connection, key: &str, value: i64) -> Result<()> {
+    set(conn, key, value)
+}
+
+/// Sets a setting to a floating point number, replacing any previous value.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `key` - The name of the setting.
+/// * `value` - The new value of the setting.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn set_f64(conn: &Connection, key: &str, value: f64) -> Result<()> {
+    set(conn, key, value)
+}
+
+/// Removes a setting, so that reading it returns `None` until it is set again.
+///
+/// Removing a setting that has not been set does nothing.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `key` - The name of the setting.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn remove_setting(conn: &Connection, key: &str) -> Result<()> {
+    execute_retrying(conn, "DELETE FROM settings WHERE key = ?1", [key])?;
+
+    Ok(())
+}
+
+/// Reads a setting, reporting a value of the wrong type as invalid data.
+fn get<T: FromSql>(conn: &Connection, key: &str, type_name: &str) -> Result<Option<T>> {
+    conn.prepare_cached("SELECT value FROM settings WHERE key = ?1")?
+        .query_row([key], |row| row.get(0))
+        .optional()
+        .map_err(|error| match error {
+            rusqlite::Error::InvalidColumnType(..)
+            | rusqlite::Error::FromSqlConversionFailure(..) => {
+                DatabaseError::InvalidData(format!("setting `{key}` is not a {type_name}"))
+            }
+            error => error.into(),
+        })
+}
+
+/// Sets a setting, replacing any previous value.
+fn set(conn: &Connection, key: &str, value: impl ToSql) -> Result<()> {
+    execute_retrying(
+        conn,
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+        ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?;
+
+    Ok(())
+}
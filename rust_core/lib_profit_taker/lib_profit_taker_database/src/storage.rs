@@ -0,0 +1,150 @@
+//! This module provides [`Storage`], the operations on runs that code built on this library needs
+//! from wherever the runs are stored.
+//!
+//! The query functions of this library take a SQLite [`Connection`], which makes code calling them
+//! hard to test without a database file and ties it to SQLite. Code that only needs to store runs,
+//! read them back, and show their statistics can instead be generic over a [`Storage`], so tests
+//! can pass a mock, and a server backed by another database can provide its own implementation.
+//!
+//! SQLite is the default implementation: [`Storage`] is implemented for [`Connection`], and for
+//! [`Pool`], which borrows a connection for each call.
+
+use lib_profit_taker_core::Run;
+use rusqlite::Connection;
+
+use crate::analytics::{self, RunCategory, StatsOverview};
+use crate::error::Result;
+use crate::fetch::{self, RunFilter, SortBy};
+use crate::insert;
+use crate::pool::Pool;
+
+/// Somewhere runs can be stored in and read back from.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_core::Run;
+/// use lib_profit_taker_database::connection::ConnectionOptions;
+/// use lib_profit_taker_database::error::Result;
+/// use lib_profit_taker_database::storage::Storage;
+///
+/// /// Stores a run, and returns the name it was stored under.
+/// fn store(storage: &impl Storage, run: &Run) -> Result<String> {
+///     let run_id = storage.insert_run(run)?;
+///     Ok(storage.fetch_run(run_id)?.run_name)
+/// }
+///
+/// let conn = ConnectionOptions::default().open(":memory:")?;
+/// let run = Run::new(0, 1_700_000_000, "Run #1", "Player1");
+/// assert_eq!(store(&conn, &run)?, "Run #1");
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+pub trait Storage {
+    /// Stores a complete run and returns its newly assigned ID, as with
+    /// [`insert_run`](crate::insert::insert_run).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the run cannot be stored, in which case nothing from it is kept.
+    fn insert_run(&self, run: &Run) -> Result<i64>;
+
+    /// Reads back a complete run by its ID, as with
+    /// [`fetch_run_by_id`](crate::fetch::fetch_run_by_id).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::RunNotFound`](crate::error::DatabaseError::RunNotFound) if no run
+    /// with the given ID is stored, or another error if it cannot be read.
+    fn fetch_run(&self, run_id: i64) -> Result<Run>;
+
+    /// Lists a single page of complete runs, as with
+    /// [`fetch_runs_paged`](crate::fetch::fetch_runs_paged).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runs cannot be read.
+    fn list_runs(
+        &self,
+        page: u32,
+        page_size: u32,
+        sort: SortBy,
+        filter: &RunFilter,
+    ) -> Result<Vec<Run>>;
+
+    /// Returns the fastest valid run of a category, as with
+    /// [`fetch_pb`](crate::analytics::fetch_pb).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runs cannot be read.
+    fn fetch_pb(&self, category: &RunCategory) -> Result<Option<Run>>;
+
+    /// Returns the statistics of every valid run, as with
+    /// [`cached_overview`](crate::analytics::cached_overview).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the statistics cannot be read.
+    fn overview(&self) -> Result<Option<StatsOverview>>;
+}
+
+impl Storage for Connection {
+    fn insert_run(&self, run: &Run) -> Result<i64> {
+        insert::insert_run(self, run)
+    }
+
+    fn fetch_run(&self, run_id: i64) -> Result<Run> {
+        fetch::fetch_run_by_id(self, run_id)
+    }
+
+    fn list_runs(
+        &self,
+        page: u32,
+        page_size: u32,
+        sort: SortBy,
+        filter: &RunFilter,
+    ) -> Result<Vec<Run>> {
+        fetch::fetch_runs_paged(self, page, page_size, sort, filter)
+    }
+
+    fn fetch_pb(&self, category: &RunCategory) -> Result<Option<Run>> {
+        analytics::fetch_pb(self, category)
+    }
+
+    fn overview(&self) -> Result<Option<StatsOverview>> {
+        analytics::cached_overview(self)
+    }
+}
+
+/// Borrows a connection from the pool for each call, waiting for one to become free if needed.
+///
+/// Each call can also fail with
+/// [`DatabaseError::PoolFailed`](crate::error::DatabaseError::PoolFailed) if no connection
+/// becomes free in time.
+impl Storage for Pool {
+    fn insert_run(&self, run: &Run) -> Result<i64> {
+        self.get()?.insert_run(run)
+    }
+
+    fn fetch_run(&self, run_id: i64) -> Result<Run> {
+        self.get()?.fetch_run(run_id)
+    }
+
+    fn list_runs(
+        &self,
+        page: u32,
+        page_size: u32,
+        sort: SortBy,
+        filter: &RunFilter,
+    ) -> Result<Vec<Run>> {
+        self.get()?.list_runs(page, page_size, sort, filter)
+    }
+
+    fn fetch_pb(&self, category: &RunCategory) -> Result<Option<Run>> {
+        self.get()?.fetch_pb(category)
+    }
+
+    fn overview(&self) -> Result<Option<StatsOverview>> {
+        self.get()?.overview()
+    }
+}
@@ -0,0 +1,160 @@
+//! This module provides functions for labeling runs with user-defined tags, such as "new strat"
+//! or "controller".
+//!
+//! A run can have any number of tags, and the run list can be narrowed down to the runs with a
+//! given tag using `fetch_runs_by_tag`.
+
+use lib_profit_taker_core::Run;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::connection::execute_retrying;
+use crate::error::{DatabaseError, Result};
+use crate::fetch::{fetch_phases, fetch_squad_members, run_from_row, SortBy, RUN_COLUMNS};
+
+/// Adds a tag to a run.
+///
+/// Leading and trailing whitespace is removed from the tag. Adding a tag the run already has does
+/// nothing.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to tag.
+/// * `tag` - The tag to add.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::InvalidData`] if the tag is empty, [`DatabaseError::RunNotFound`] if
+/// no run with the given ID exists, or another error if a query fails.
+pub fn add_tag(conn: &Connection, run_id: i64, tag: &str) -> Result<()> {
+    let tag = normalize_tag(tag)?;
+    ensure_run_exists(conn, run_id)?;
+
+    execute_retrying(
+        conn,
+        "INSERT OR IGNORE INTO tags (run_id, tag) VALUES (?1, ?2)",
+        params![run_id, tag],
+    )?;
+
+    Ok(())
+}
+
+/// Removes a tag from a run.
+///
+/// Leading and trailing whitespace is removed from the tag. Removing a tag the run does not have
+/// does nothing.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to untag.
+/// * `tag` - The tag to remove.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// a query fails.
+pub fn remove_tag(conn: &Connection, run_id: i64, tag: &str) -> Result<()> {
+    ensure_run_exists(conn, run_id)?;
+
+    execute_retrying(
+        conn,
+        "DELETE FROM tags WHERE run_id = ?1 AND tag = ?2",
+        params![run_id, tag.trim()],
+    )?;
+
+    Ok(())
+}
+
+/// Fetches the tags of a run, in alphabetical order.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run whose tags to fetch.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// a query fails.
+pub fn fetch_tags(conn: &Connection, run_id: i64) -> Result<Vec<String>> {
+    ensure_run_exists(conn, run_id)?;
+
+    conn.prepare_cached("SELECT tag FROM tags WHERE run_id = ?1 ORDER BY tag")?
+        .query_map([run_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()
+        .map_err(Into::into)
+}
+
+/// Fetches every tag used by at least one complete run outside the trash, in alphabetical order.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn fetch_all_tags(conn: &Connection) -> Result<Vec<String>> {
+    conn.prepare_cached(
+        "SELECT DISTINCT tags.tag FROM tags
+        JOIN runs ON runs.id = tags.run_id
+        WHERE runs.deleted_at IS NULL AND runs.status != 'in_progress'
+        ORDER BY tags.tag",
+    )?
+    .query_map([], |row| row.get(0))?
+    .collect::<rusqlite::Result<_>>()
+    .map_err(Into::into)
+}
+
+/// Fetches every complete run outside the trash with the given tag.
+///
+/// Leading and trailing whitespace is removed from the tag.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `tag` - The tag runs must have.
+/// * `sort` - The order in which runs are listed.
+///
+/// # Returns
+/// * `Result<Vec<Run>>` - The fully hydrated runs with the tag.
+///
+/// # Errors
+///
+/// Returns an error if a query fails or a row references an unknown status effect or leg
+/// position.
+pub fn fetch_runs_by_tag(conn: &Connection, tag: &str, sort: SortBy) -> Result<Vec<Run>> {
+    let mut runs = conn
+        .prepare_cached(&format!(
+            "SELECT {RUN_COLUMNS} FROM runs
+            WHERE deleted_at IS NULL AND status != 'in_progress'
+                AND id IN (SELECT run_id FROM tags WHERE tag = ?1)
+            ORDER BY {}",
+            sort.to_sql()
+        ))?
+        .query_map([tag.trim()], run_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for run in &mut runs {
+        run.phases = fetch_phases(conn, run.run_id)?;
+        run.squad_members = fetch_squad_members(conn, run.run_id)?;
+    }
+
+    Ok(runs)
+}
+
+/// Trims the tag, rejecting it if nothing is left.
+fn normalize_tag(tag: &str) -> Result<&str> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(DatabaseError::InvalidData(
+            "tags cannot be empty".to_string(),
+        ));
+    }
+
+    Ok(tag)
+}
+
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists.
+pub(crate) fn ensure_run_exists(conn: &Connection, run_id: i64) -> Result<()> {
+    conn.prepare_cached("SELECT 1 FROM runs WHERE id = ?1")?
+        .query_row([run_id], |_| Ok(()))
+        .optional()?
+        .ok_or(DatabaseError::RunNotFound(run_id))
+}
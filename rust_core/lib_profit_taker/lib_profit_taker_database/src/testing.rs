@@ -0,0 +1,446 @@
+//! This module provides helpers for property testing the persistence of runs.
+//!
+//! Code built on this library, and this library itself, can use them to check that runs survive
+//! being stored against many generated runs, instead of a few written by hand.
+//!
+//! The `random_run` function generates a run that passes
+//! [`validate_run`](crate::validation::validate_run), and `round_trip` inserts a run, reads it
+//! back, and lists everything that did not survive the trip, as compared by `run_differences`.
+//!
+//! This module is only available with the `test-utils` feature, so that `rand` is not a
+//! dependency of the app itself.
+
+use lib_profit_taker_core::{
+    LegBreak, LegPosition, Phase, Run, ShieldChange, SquadMember, StatusEffect, TotalTimes,
+};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rusqlite::Connection;
+use std::fmt::Debug;
+use std::ops::RangeInclusive;
+use uuid::Uuid;
+
+use crate::duration::Duration;
+use crate::error::Result;
+use crate::fetch::fetch_run_by_id;
+use crate::insert::insert_run;
+use crate::validation::{LEG_BREAKS_PER_PHASE, PHASE_COUNT};
+
+/// The legs of the Profit-Taker, in the order they are usually broken.
+const LEG_POSITIONS: [LegPosition; LEG_BREAKS_PER_PHASE] = [
+    LegPosition::FrontLeft,
+    LegPosition::FrontRight,
+    LegPosition::BackLeft,
+    LegPosition::BackRight,
+];
+
+/// The platforms Warframe can be played on.
+const PLATFORMS: [&str; 4] = ["PC", "PlayStation", "Xbox", "Switch"];
+
+/// Every status effect a shield can be weak to.
+const STATUS_EFFECTS: [StatusEffect; 13] = [
+    StatusEffect::Impact,
+    StatusEffect::Puncture,
+    StatusEffect::Slash,
+    StatusEffect::Heat,
+    StatusEffect::Cold,
+    StatusEffect::Electric,
+    StatusEffect::Toxin,
+    StatusEffect::Blast,
+    StatusEffect::Radiation,
+    StatusEffect::Gas,
+    StatusEffect::Magnetic,
+    StatusEffect::Viral,
+    StatusEffect::Corrosive,
+];
+
+/// Generates a random complete run that passes
+/// [`validate_run`](crate::validation::validate_run).
+///
+/// Every time is a whole number of milliseconds, as it would be once stored, and the total times
+/// add up from the times of the phases. The run has no ID, but has a UUID, a UTC offset, and
+/// sometimes a game version, platform, notes, or squad, so that every column is exercised.
+///
+/// # Arguments
+/// * `rng` - The source of randomness. Seeding it makes the generated run reproducible.
+///
+/// # Returns
+/// * `Run` - The generated run.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_database::connection::open_in_memory;
+/// use lib_profit_taker_database::testing::{random_run, round_trip};
+/// use rand::rngs::StdRng;
+/// use lib_profit_taker_database::validation::validate_run;
+/// use rand::SeedableRng;
+///
+/// let conn = open_in_memory()?;
+/// let mut rng = StdRng::seed_from_u64(1);
+/// for _ in 0..100 {
+///     let run = random_run(&mut rng);
+///     assert_eq!(validate_run(&run), []);
+///
+///     let differences = round_trip(&conn, &run)?;
+///     assert!(differences.is_empty(), "{differences:?}");
+/// }
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+pub fn random_run<R: Rng + ?Sized>(rng: &mut R) -> Run {
+    let mut run = Run::new(
+        0,
+        rng.gen_range(1_600_000_000..=1_900_000_000),
+        &format!("Run #{}", rng.gen_range(1..=10_000)),
+        &random_name(rng),
+    );
+    run.run_uuid = Some(Uuid::from_u128(rng.gen()).to_string());
+    // Whole quarter hours, from UTC-12:00 to UTC+14:00
+    run.utc_offset = Some(rng.gen_range(-48..=56) * 15 * 60);
+    run.game_version = rng
+        .gen_bool(0.5)
+        .then(|| format!("{}.{}", rng.gen_range(35..=40), rng.gen_range(0..=9)));
+    run.platform = rng
+        .gen_bool(0.5)
+        .then(|| PLATFORMS[rng.gen_range(0..PLATFORMS.len())].to_string());
+    run.is_favorite = rng.gen_bool(0.1);
+    run.notes = rng.gen_bool(0.2).then(|| "Some notes".to_string());
+    if rng.gen_bool(0.1) {
+        run.is_bugged_run = true;
+        run.bugged_reason = Some("Marked as bugged".to_string());
+    }
+
+    run.is_solo_run = rng.gen_bool(0.5);
+    if !run.is_solo_run {
+        let squad_size = rng.gen_range(1..=3);
+        while run.squad_members.len() < squad_size {
+            let name = random_name(rng);
+            if run
+                .squad_members
+                .iter()
+                .all(|member| member.member_name != name)
+            {
+                run.squad_members.push(SquadMember::new(&name));
+            }
+        }
+    }
+
+    run.phases = (1..=PHASE_COUNT)
+        .map(|phase_number| random_phase(rng, phase_number))
+        .collect();
+
+    let flight_time = random_time(rng, 1_000..=20_000);
+    let sum = |time: fn(&Phase) -> f64| run.phases.iter().map(time).sum::<f64>();
+    run.total_times = TotalTimes::new(
+        flight_time + sum(|phase| phase.total_time),
+        flight_time,
+        sum(|phase| phase.total_shield_time),
+        sum(|phase| phase.total_leg_time),
+        sum(|phase| phase.total_body_kill_time),
+        sum(|phase| phase.total_pylon_time),
+    );
+
+    run
+}
+
+/// Generates a random phase with four leg breaks, whose total time adds up from its segments.
+///
+/// Only the first and third phases have pylons, as in the fight.
+fn random_phase<R: Rng + ?Sized>(rng: &mut R, phase_number: i32) -> Phase {
+    let mut phase = Phase::new(phase_number);
+
+    for _ in 0..rng.gen_range(1..=8) {
+        let mut shield_change = ShieldChange::new(
+            random_time(rng, 500..=5_000),
+            STATUS_EFFECTS[rng.gen_range(0..STATUS_EFFECTS.len())],
+        );
+        shield_change.is_overshield = rng.gen_bool(0.1);
+        phase.shield_changes.push(shield_change);
+    }
+
+    let mut leg_positions = LEG_POSITIONS;
+    leg_positions.shuffle(rng);
+    for (leg_order, leg_position) in (1..).zip(leg_positions) {
+        phase.leg_breaks.push(LegBreak::new(
+            random_time(rng, 200..=3_000),
+            leg_position,
+            leg_order,
+        ));
+    }
+
+    phase.total_shield_time = phase
+        .shield_changes
+        .iter()
+        .map(|shield_change| shield_change.shield_time)
+        .sum();
+    phase.total_leg_time = phase
+        .leg_breaks
+        .iter()
+        .map(|leg_break| leg_break.leg_break_time)
+        .sum();
+    phase.total_body_kill_time = random_time(rng, 500..=5_000);
+    phase.total_pylon_time = if phase_number % 2 == 1 {
+        random_time(rng, 5_000..=30_000)
+    } else {
+        0.0
+    };
+    phase.total_time = phase.total_shield_time
+        + phase.total_leg_time
+        + phase.total_body_kill_time
+        + phase.total_pylon_time;
+
+    phase
+}
+
+/// Generates a random time, in seconds, from a range of whole milliseconds.
+fn random_time<R: Rng + ?Sized>(rng: &mut R, millis: RangeInclusive<i64>) -> f64 {
+    Duration::from_millis(rng.gen_range(millis)).as_secs_f64()
+}
+
+/// Generates a random player name.
+fn random_name<R: Rng + ?Sized>(rng: &mut R) -> String {
+    format!("Player{}", rng.gen_range(1..=1_000))
+}
+
+/// Inserts a run, reads it back, and compares the two.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run` - The run to insert.
+///
+/// # Returns
+/// * `Result<Vec<String>>` - Everything that differs between the run and the one read back, as
+///   listed by `run_differences`, or nothing if the run survived the trip.
+///
+/// # Errors
+///
+/// Returns an error if the run cannot be inserted or read back.
+pub fn round_trip(conn: &Connection, run: &Run) -> Result<Vec<String>> {
+    let run_id = insert_run(conn, run)?;
+    let fetched = fetch_run_by_id(conn, run_id)?;
+
+    Ok(run_differences(run, &fetched))
+}
+
+/// Lists everything that differs between a run and a copy of it read back from the database.
+///
+/// Times are compared by the milliseconds they are stored as, so a time only differs if it was
+/// changed by more than rounding, and squad members are compared regardless of their order. The
+/// ID is never compared, as the copy has one assigned by the database, and neither are the UUID
+/// and UTC offset if the run has none, as they are then filled in when it is inserted.
+///
+/// # Arguments
+/// * `expected` - The run before it was stored.
+/// * `actual` - The run read back from the database.
+///
+/// # Returns
+/// * `Vec<String>` - A description of each difference, naming the field, or nothing if the runs
+///   match.
+#[must_use]
+pub fn run_differences(expected: &Run, actual: &Run) -> Vec<String> {
+    let mut differences = Differences(Vec::new());
+
+    if expected.run_uuid.is_some() {
+        differences.check("run_uuid", &expected.run_uuid, &actual.run_uuid);
+    }
+    if expected.utc_offset.is_some() {
+        differences.check("utc_offset", &expected.utc_offset, &actual.utc_offset);
+    }
+    differences.check("time_stamp", &expected.time_stamp, &actual.time_stamp);
+    differences.check("run_name", &expected.run_name, &actual.run_name);
+    differences.check("player_name", &expected.player_name, &actual.player_name);
+    differences.check("game_version", &expected.game_version, &actual.game_version);
+    differences.check("platform", &expected.platform, &actual.platform);
+    differences.check(
+        "is_bugged_run",
+        &expected.is_bugged_run,
+        &actual.is_bugged_run,
+    );
+    differences.check(
+        "bugged_reason",
+        &expected.bugged_reason,
+        &actual.bugged_reason,
+    );
+    differences.check(
+        "is_aborted_run",
+        &expected.is_aborted_run,
+        &actual.is_aborted_run,
+    );
+    differences.check(
+        "aborted_reason",
+        &expected.aborted_reason,
+        &actual.aborted_reason,
+    );
+    differences.check("is_solo_run", &expected.is_solo_run, &actual.is_solo_run);
+    differences.check("is_favorite", &expected.is_favorite, &actual.is_favorite);
+    differences.check("notes", &expected.notes, &actual.notes);
+
+    let (expected_times, actual_times) = (&expected.total_times, &actual.total_times);
+    for (field, expected_time, actual_time) in [
+        (
+            "total_time",
+            expected_times.total_time,
+            actual_times.total_time,
+        ),
+        (
+            "total_flight_time",
+            expected_times.total_flight_time,
+            actual_times.total_flight_time,
+        ),
+        (
+            "total_shield_time",
+            expected_times.total_shield_time,
+            actual_times.total_shield_time,
+        ),
+        (
+            "total_leg_time",
+            expected_times.total_leg_time,
+            actual_times.total_leg_time,
+        ),
+        (
+            "total_body_time",
+            expected_times.total_body_time,
+            actual_times.total_body_time,
+        ),
+        (
+            "total_pylon_time",
+            expected_times.total_pylon_time,
+            actual_times.total_pylon_time,
+        ),
+    ] {
+        differences.check_time(&format!("total_times.{field}"), expected_time, actual_time);
+    }
+
+    // Squad members are read back sorted by name
+    let squad = |run: &Run| {
+        let mut names = run
+            .squad_members
+            .iter()
+            .map(|member| member.member_name.clone())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    };
+    differences.check("squad_members", &squad(expected), &squad(actual));
+
+    differences.check("phases.len()", &expected.phases.len(), &actual.phases.len());
+    for (index, (expected, actual)) in expected.phases.iter().zip(&actual.phases).enumerate() {
+        differences.check_phase(&format!("phases[{index}]"), expected, actual);
+    }
+
+    differences.0
+}
+
+/// The differences found so far by `run_differences`.
+struct Differences(Vec<String>);
+
+impl Differences {
+    /// Records a difference if two values of a field are not equal.
+    fn check<T: PartialEq + Debug + ?Sized>(&mut self, field: &str, expected: &T, actual: &T) {
+        if expected != actual {
+            self.0
+                .push(format!("{field}: expected {expected:?}, found {actual:?}"));
+        }
+    }
+
+    /// Records a difference if two times of a field are not the same number of milliseconds.
+    fn check_time(&mut self, field: &str, expected: f64, actual: f64) {
+        self.check(
+            field,
+            &Duration::from_secs_f64(expected),
+            &Duration::from_secs_f64(actual),
+        );
+    }
+
+    /// Records the differences between two phases.
+    fn check_phase(&mut self, field: &str, expected: &Phase, actual: &Phase) {
+        self.check(
+            &format!("{field}.phase_number"),
+            &expected.phase_number,
+            &actual.phase_number,
+        );
+        for (segment, expected_time, actual_time) in [
+            ("total_time", expected.total_time, actual.total_time),
+            (
+                "total_shield_time",
+                expected.total_shield_time,
+                actual.total_shield_time,
+            ),
+            (
+                "total_leg_time",
+                expected.total_leg_time,
+                actual.total_leg_time,
+            ),
+            (
+                "total_body_kill_time",
+                expected.total_body_kill_time,
+                actual.total_body_kill_time,
+            ),
+            (
+                "total_pylon_time",
+                expected.total_pylon_time,
+                actual.total_pylon_time,
+            ),
+        ] {
+            self.check_time(&format!("{field}.{segment}"), expected_time, actual_time);
+        }
+
+        self.check(
+            &format!("{field}.shield_changes.len()"),
+            &expected.shield_changes.len(),
+            &actual.shield_changes.len(),
+        );
+        for (index, (expected, actual)) in expected
+            .shield_changes
+            .iter()
+            .zip(&actual.shield_changes)
+            .enumerate()
+        {
+            let field = format!("{field}.shield_changes[{index}]");
+            self.check_time(
+                &format!("{field}.shield_time"),
+                expected.shield_time,
+                actual.shield_time,
+            );
+            self.check(
+                &format!("{field}.status_effect"),
+                &expected.status_effect,
+                &actual.status_effect,
+            );
+            self.check(
+                &format!("{field}.is_overshield"),
+                &expected.is_overshield,
+                &actual.is_overshield,
+            );
+        }
+
+        self.check(
+            &format!("{field}.leg_breaks.len()"),
+            &expected.leg_breaks.len(),
+            &actual.leg_breaks.len(),
+        );
+        for (index, (expected, actual)) in expected
+            .leg_breaks
+            .iter()
+            .zip(&actual.leg_breaks)
+            .enumerate()
+        {
+            let field = format!("{field}.leg_breaks[{index}]");
+            self.check_time(
+                &format!("{field}.leg_break_time"),
+                expected.leg_break_time,
+                actual.leg_break_time,
+            );
+            self.check(
+                &format!("{field}.leg_position"),
+                &expected.leg_position,
+                &actual.leg_position,
+            );
+            self.check(
+                &format!("{field}.leg_order"),
+                &expected.leg_order,
+                &actual.leg_order,
+            );
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! This module provides undo and redo for destructive operations on runs, such as deleting a run
+//! or changing many runs at once with [`bulk_set`](crate::update::bulk_set).
+//!
+//! An operation is run through `record`, which stores the state of the runs it affects before and
+//! after it in the `undo_journal` table. `undo_last` then puts the runs back the way they were
+//! before the latest operation, and `redo_last` applies the latest undone operation again. Unlike
+//! restoring a backup, this leaves every other run untouched.
+//!
+//! As in a text editor, recording a new operation discards the operations that were undone, so
+//! they can no longer be redone.
+
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::connection::with_savepoint;
+use crate::error::Result;
+use crate::maintenance::RUN_TABLES;
+
+/// The number of operations kept in the journal, after which the oldest ones can no longer be
+/// undone.
+pub const UNDO_JOURNAL_LIMIT: u32 = 50;
+
+/// An operation recorded in the journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// The ID of the entry.
+    pub entry_id: i64,
+
+    /// What the operation did, such as "Delete run", for the frontend to show.
+    pub description: String,
+
+    /// The Unix timestamp of when the operation was recorded.
+    pub created_at: i64,
+}
+
+/// Every row belonging to a run, as stored in the journal.
+#[derive(Debug, Serialize, Deserialize)]
+struct RunSnapshot {
+    /// The ID of the run.
+    run_id: i64,
+
+    /// The rows of each table, in the order of [`RUN_TABLES`].
+    tables: Vec<TableSnapshot>,
+}
+
+/// The rows of a table belonging to a run, as part of a [`RunSnapshot`].
+#[derive(Debug, Serialize, Deserialize)]
+struct TableSnapshot {
+    /// The name of the table.
+    table: String,
+
+    /// The names of the columns of the rows.
+    columns: Vec<String>,
+
+    /// The values of each row, one per column.
+    rows: Vec<Vec<SnapshotValue>>,
+}
+
+/// A value of a column, as part of a [`TableSnapshot`], with one variant per SQLite storage class.
+#[derive(Debug, Serialize, Deserialize)]
+enum SnapshotValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl From<ValueRef<'_>> for SnapshotValue {
+    fn from(value: ValueRef<'_>) -> Self {
+        match value {
+            ValueRef::Null => Self::Null,
+            ValueRef::Integer(integer) => Self::Integer(integer),
+            ValueRef::Real(real) => Self::Real(real),
+            ValueRef::Text(text) => Self::Text(String::from_utf8_lossy(text).into_owned()),
+            ValueRef::Blob(blob) => Self::Blob(blob.to_vec()),
+        }
+    }
+}
+
+impl From<SnapshotValue> for Value {
+    fn from(value: SnapshotValue) -> Self {
+        match value {
+            SnapshotValue::Null => Self::Null,
+            SnapshotValue::Integer(integer) => Self::Integer(integer),
+            SnapshotValue::Real(real) => Self::Real(real),
+            SnapshotValue::Text(text) => Self::Text(text),
+            SnapshotValue::Blob(blob) => Self::Blob(blob),
+        }
+    }
+}
+
+/// Runs an operation on some runs, recording it in the journal so it can be undone.
+///
+/// The state of the runs is stored before and after the operation, so `operation` can change
+/// them in any way, including deleting them. Changes it makes to other runs are not recorded, and
+/// are not undone. Only the latest [`UNDO_JOURNAL_LIMIT`] operations are kept.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `description` - What the operation does, such as "Delete run", for the frontend to show.
+/// * `run_ids` - The IDs of the runs the operation changes.
+/// * `operation` - The operation itself, such as a call to
+///   [`delete_run`](crate::delete::delete_run).
+///
+/// # Returns
+/// * `Result<T>` - The value returned by `operation`.
+///
+/// # Errors
+///
+/// Returns the error of `operation` if it fails, in which case nothing is recorded, or another
+/// error if a query fails.
+///
+/// # Examples
+///
+/// ```
+/// use lib_profit_taker_core::Run;
+/// use lib_profit_taker_database::connection::open_in_memory;
+/// use lib_profit_taker_database::delete::delete_run;
+/// use lib_profit_taker_database::fetch::fetch_run_by_id;
+/// use lib_profit_taker_database::insert::insert_run;
+/// use lib_profit_taker_database::undo::{record, undo_last};
+///
+/// let conn = open_in_memory()?;
+/// let run_id = insert_run(&conn, &Run::new(0, 1_700_000_000, "Run", "Player"))?;
+///
+/// record(&conn, "Delete run", &[run_id], |conn| delete_run(conn, run_id))?;
+/// assert!(fetch_run_by_id(&conn, run_id).is_err());
+///
+/// undo_last(&conn)?;
+/// assert_eq!(fetch_run_by_id(&conn, run_id)?.run_name, "Run");
+/// # Ok::<(), lib_profit_taker_database::error::DatabaseError>(())
+/// ```
+pub fn record<T>(
+    conn: &Connection,
+    description: &str,
+    run_ids: &[i64],
+    operation: impl FnOnce(&Connection) -> Result<T>,
+) -> Result<T> {
+    with_savepoint(conn, "record_undo", |conn| {
+        let before_state = snapshot(conn, run_ids)?;
+        let value = operation(conn)?;
+        let after_state = snapshot(conn, run_ids)?;
+
+        conn.execute_batch("DELETE FROM undo_journal WHERE undone")?;
+        conn.prepare_cached(
+            "INSERT INTO undo_journal (description, created_at, before_state, after_state)
+            VALUES (?1, unixepoch(), ?2, ?3)",
+        )?
+        .execute(params![description, before_state, after_state])?;
+        conn.prepare_cached(
+            "DELETE FROM undo_journal
+            WHERE id NOT IN (SELECT id FROM undo_journal ORDER BY id DESC LIMIT ?1)",
+        )?
+        .execute([UNDO_JOURNAL_LIMIT])?;
+
+        Ok(value)
+    })
+}
+
+/// Undoes the latest operation that has not been undone yet.
+///
+/// The runs the operation changed are put back exactly as they were before it, which also
+/// discards any changes made to them since that were not recorded.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Option<JournalEntry>>` - The operation that was undone, or `None` if there was
+///   nothing to undo.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Json`](crate::error::DatabaseError::Json) if the journal entry is
+/// malformed, or another error if a query fails. In either case, nothing is undone.
+pub fn undo_last(conn: &Connection) -> Result<Option<JournalEntry>> {
+    with_savepoint(conn, "undo_last", |conn| {
+        let Some((entry, before_state)) = conn
+            .prepare_cached(
+                "SELECT id, description, created_at, before_state FROM undo_journal
+                WHERE NOT undone ORDER BY id DESC LIMIT 1",
+            )?
+            .query_row([], |row| {
+                Ok((entry_from_row(row)?, row.get::<_, String>(3)?))
+            })
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        restore(conn, &before_state)?;
+        conn.prepare_cached("UPDATE undo_journal SET undone = 1 WHERE id = ?1")?
+            .execute([entry.entry_id])?;
+
+        Ok(Some(entry))
+    })
+}
+
+/// Applies the latest undone operation again.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+///
+/// # Returns
+/// * `Result<Option<JournalEntry>>` - The operation that was redone, or `None` if there was
+///   nothing to redo.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::Json`](crate::error::DatabaseError::Json) if the journal entry is
+/// malformed, or another error if a query fails. In either case, nothing is redone.
+pub fn redo_last(conn: &Connection) -> Result<Option<JournalEntry>> {
+    with_savepoint(conn, "redo_last", |conn| {
+        // Operations are undone newest first, so the latest one undone is the oldest undone one
+        let Some((entry, after_state)) = conn
+            .prepare_cached(
+                "SELECT id, description, created_at, after_state FROM undo_journal
+                WHERE undone ORDER BY id ASC LIMIT 1",
+            )?
+            .query_row([], |row| {
+                Ok((entry_from_row(row)?, row.get::<_, String>(3)?))
+            })
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        restore(conn, &after_state)?;
+        conn.prepare_cached("UPDATE undo_journal SET undone = 0 WHERE id = ?1")?
+            .execute([entry.entry_id])?;
+
+        Ok(Some(entry))
+    })
+}
+
+/// Builds a [`JournalEntry`] from a row of `id`, `description`, and `created_at`.
+fn entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<JournalEntry> {
+    Ok(JournalEntry {
+        entry_id: row.get(0)?,
+        description: row.get(1)?,
+        created_at: row.get(2)?,
+    })
+}
+
+/// Returns the column linking the rows of a table to their run.
+fn run_key(table: &str) -> &'static str {
+    if table == "runs" {
+        "id"
+    } else {
+        "run_id"
+    }
+}
+
+/// Reads every row belonging to the given runs, as JSON.
+fn snapshot(conn: &Connection, run_ids: &[i64]) -> Result<String> {
+    let mut snapshots = Vec::with_capacity(run_ids.len());
+    for &run_id in run_ids {
+        let mut tables = Vec::with_capacity(RUN_TABLES.len());
+        for table in RUN_TABLES {
+            let mut stmt = conn.prepare_cached(&format!(
+                "SELECT * FROM {table} WHERE {} = ?1",
+                run_key(table)
+            ))?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+            let rows = stmt
+                .query_map([run_id], |row| {
+                    (0..columns.len())
+                        .map(|i| row.get_ref(i).map(SnapshotValue::from))
+                        .collect()
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            tables.push(TableSnapshot {
+                table: table.to_string(),
+                columns,
+                rows,
+            });
+        }
+
+        snapshots.push(RunSnapshot { run_id, tables });
+    }
+
+    Ok(serde_json::to_string(&snapshots)?)
+}
+
+/// Replaces every row belonging to the runs of a snapshot with the rows of the snapshot.
+fn restore(conn: &Connection, state: &str) -> Result<()> {
+    let snapshots: Vec<RunSnapshot> = serde_json::from_str(state)?;
+
+    for snapshot in snapshots {
+        // Children first, so no row is ever left without its run
+        for table in RUN_TABLES.iter().rev() {
+            conn.prepare_cached(&format!(
+                "DELETE FROM {table} WHERE {} = ?1",
+                run_key(table)
+            ))?
+            .execute([snapshot.run_id])?;
+        }
+
+        for table in snapshot.tables {
+            // The names come from the journal, so only the tables of runs are written to
+            let Some(&name) = RUN_TABLES.iter().find(|&&name| name == table.table) else {
+                continue;
+            };
+            let columns = table
+                .columns
+                .iter()
+                .map(|column| format!("\"{}\"", column.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let placeholders = vec!["?"; table.columns.len()].join(", ");
+
+            let mut stmt = conn.prepare_cached(&format!(
+                "INSERT INTO {name} ({columns}) VALUES ({placeholders})"
+            ))?;
+            for row in table.rows {
+                stmt.execute(params_from_iter(row.into_iter().map(Value::from)))?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! This module provides functions for changing the user-editable details of stored runs.
+//!
+//! These are the edits made from the frontend, such as renaming a run, marking it as a favorite
+//! or as bugged, or writing notes about it, as opposed to the timings recorded by the parser,
+//! which are never edited in place.
+//!
+//! The `bulk_set` function applies the same [`RunChanges`] to many runs at once, for cleaning up
+//! runs that were flagged wrong, such as after an import.
+//!
+//! The `complete_run` function is the exception to the above, as it finishes a run that the
+//! parser stored while it was still in progress with
+//! [`insert_partial_run`](crate::insert::insert_partial_run).
+
+use lib_profit_taker_core::Run;
+use rusqlite::{params, Connection};
+
+use crate::connection::{execute_retrying, with_savepoint};
+use crate::error::{DatabaseError, Result};
+use crate::fetch::{fetch_run_status, RunStatus};
+use crate::insert::update_run;
+use crate::tags::{add_tag, remove_tag};
+
+/// Renames a run.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to rename.
+/// * `name` - The new name of the run.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// the query fails.
+pub fn set_run_name(conn: &Connection, run_id: i64, name: &str) -> Result<()> {
+    let updated = execute_retrying(
+        conn,
+        "UPDATE runs SET run_name = ?2 WHERE id = ?1",
+        params![run_id, name],
+    )?;
+
+    ensure_updated(updated, run_id)
+}
+
+/// Marks a run as a favorite, or removes it from the favorites.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to update.
+/// * `is_favorite` - Whether the run should be a favorite.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// the query fails.
+pub fn set_favorite(conn: &Connection, run_id: i64, is_favorite: bool) -> Result<()> {
+    let updated = execute_retrying(
+        conn,
+        "UPDATE runs SET favorite = ?2 WHERE id = ?1",
+        params![run_id, is_favorite],
+    )?;
+
+    ensure_updated(updated, run_id)
+}
+
+/// Marks a run as bugged, or as not bugged.
+///
+/// Bugged runs are left out of most analytics, and have personal bests of their own.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to update.
+/// * `is_bugged` - Whether the run is bugged.
+/// * `reason` - Why the run is bugged, such as a shield phase desync, if known. This is ignored
+///   if the run is not bugged.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// the query fails.
+pub fn set_bugged(
+    conn: &Connection,
+    run_id: i64,
+    is_bugged: bool,
+    reason: Option<&str>,
+) -> Result<()> {
+    let reason = reason.filter(|_| is_bugged);
+
+    let updated = execute_retrying(
+        conn,
+        "UPDATE runs SET bugged_run = ?2, bugged_reason = ?3 WHERE id = ?1",
+        params![run_id, is_bugged, reason],
+    )?;
+
+    ensure_updated(updated, run_id)
+}
+
+/// Marks a run as aborted, or as not aborted.
+///
+/// Aborted runs are left out of analytics.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to update.
+/// * `is_aborted` - Whether the run was aborted.
+/// * `reason` - Why the run was aborted, if known. This is ignored if the run was not aborted.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// the query fails.
+pub fn set_aborted(
+    conn: &Connection,
+    run_id: i64,
+    is_aborted: bool,
+    reason: Option<&str>,
+) -> Result<()> {
+    let reason = reason.filter(|_| is_aborted);
+
+    // A run in progress stays in progress until it is completed, whatever it is marked as
+    let updated = execute_retrying(
+        conn,
+        "UPDATE runs SET
+            aborted_run = ?2,
+            aborted_reason = ?3,
+            status = CASE
+                WHEN status = 'in_progress' THEN status
+                WHEN ?2 THEN 'aborted'
+                ELSE 'completed'
+            END
+        WHERE id = ?1",
+        params![run_id, is_aborted, reason],
+    )?;
+
+    ensure_updated(updated, run_id)
+}
+
+/// Finishes a run that was stored while it was still in progress, replacing everything recorded
+/// about it so far with the finished run.
+///
+/// The run is stored as completed, or as aborted if `run` was aborted, and is included in
+/// analytics from then on.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run in progress, as returned by
+///   [`insert_partial_run`](crate::insert::insert_partial_run).
+/// * `run` - The finished run.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists,
+/// [`DatabaseError::InvalidData`] if the run is not in progress, or another error if a query
+/// fails.
+pub fn complete_run(conn: &Connection, run_id: i64, run: &Run) -> Result<()> {
+    with_savepoint(conn, "complete_run", |conn| {
+        if fetch_run_status(conn, run_id)? != RunStatus::InProgress {
+            return Err(DatabaseError::InvalidData(format!(
+                "run {run_id} is not in progress"
+            )));
+        }
+
+        update_run(conn, run_id, run)?;
+
+        let reason = run.aborted_reason.as_deref().filter(|_| run.is_aborted_run);
+        conn.prepare_cached(
+            "UPDATE runs SET
+                status = CASE WHEN ?2 THEN 'aborted' ELSE 'completed' END,
+                aborted_reason = ?3
+            WHERE id = ?1",
+        )?
+        .execute(params![run_id, run.is_aborted_run, reason])?;
+
+        Ok(())
+    })
+}
+
+/// Sets the notes of a run, replacing any previous notes.
+///
+/// Notes that are empty or only contain whitespace are removed instead.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_id` - The ID of the run to update.
+/// * `text` - The new notes of the run.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with the given ID exists, or another error if
+/// the query fails.
+pub fn set_run_note(conn: &Connection, run_id: i64, text: &str) -> Result<()> {
+    let notes = Some(text).filter(|text| !text.trim().is_empty());
+
+    let updated = execute_retrying(
+        conn,
+        "UPDATE runs SET notes = ?2 WHERE id = ?1",
+        params![run_id, notes],
+    )?;
+
+    ensure_updated(updated, run_id)
+}
+
+/// Changes to apply to many runs at once with `bulk_set`.
+///
+/// Each optional field either leaves that property of the runs as it is (`None`), or sets it to
+/// the given value (`Some(value)`). The default changes nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RunChanges {
+    /// Whether the runs are bugged.
+    pub bugged: Option<bool>,
+
+    /// Why the runs are bugged, if known. This is ignored unless `bugged` is `Some(true)`.
+    pub bugged_reason: Option<String>,
+
+    /// Whether the runs were aborted.
+    pub aborted: Option<bool>,
+
+    /// Why the runs were aborted, if known. This is ignored unless `aborted` is `Some(true)`.
+    pub aborted_reason: Option<String>,
+
+    /// Whether the runs are favorites.
+    pub favorite: Option<bool>,
+
+    /// Tags to add to the runs, which are kept if a run already has them.
+    pub add_tags: Vec<String>,
+
+    /// Tags to remove from the runs, which are ignored if a run does not have them.
+    pub remove_tags: Vec<String>,
+}
+
+/// Applies the same changes to many runs at once.
+///
+/// Either every run is changed, or, if anything fails, none of them are.
+///
+/// # Arguments
+/// * `conn` - A reference to the active SQLite database connection.
+/// * `run_ids` - The IDs of the runs to change.
+/// * `changes` - What to change about each run.
+///
+/// # Errors
+///
+/// Returns [`DatabaseError::RunNotFound`] if no run with one of the IDs exists,
+/// [`DatabaseError::InvalidData`] if a tag to add is empty, or another error if a query fails.
+pub fn bulk_set(conn: &Connection, run_ids: &[i64], changes: &RunChanges) -> Result<()> {
+    with_savepoint(conn, "bulk_set", |conn| {
+        for &run_id in run_ids {
+            if let Some(is_bugged) = changes.bugged {
+                set_bugged(conn, run_id, is_bugged, changes.bugged_reason.as_deref())?;
+            }
+            if let Some(is_aborted) = changes.aborted {
+                set_aborted(conn, run_id, is_aborted, changes.aborted_reason.as_deref())?;
+            }
+            if let Some(is_favorite) = changes.favorite {
+                set_favorite(conn, run_id, is_favorite)?;
+            }
+            for tag in &changes.add_tags {
+                add_tag(conn, run_id, tag)?;
+            }
+            for tag in &changes.remove_tags {
+                remove_tag(conn, run_id, tag)?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Turns an update that matched no rows into a [`DatabaseError::RunNotFound`].
+const fn ensure_updated(updated: usize, run_id: i64) -> Result<()> {
+    if updated == 0 {
+        return Err(DatabaseError::RunNotFound(run_id));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,235 @@
+//! This module provides consistency checks for runs, so that runs the parser got wrong do not
+//! pollute the statistics of the correct ones.
+//!
+//! The `validate_run` function lists everything that looks wrong with a run, such as phase times
+//! that do not add up to its total time or a phase without exactly four leg breaks. Such runs can
+//! then be rejected or marked as bugged when they are inserted, depending on the
+//! [`InvalidRunPolicy`] passed to `insert_validated_run`.
+
+use lib_profit_taker_core::{Phase, Run};
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// The largest difference, in seconds, between two times that should be equal for them to still
+/// be considered equal.
+///
+/// Times are summed from rounded log timestamps, so they rarely add up exactly.
+pub const TIME_TOLERANCE: f64 = 0.5;
+
+/// The number of phases of a complete run.
+pub const PHASE_COUNT: i32 = 4;
+
+/// The number of legs broken in every phase.
+pub const LEG_BREAKS_PER_PHASE: usize = 4;
+
+/// The largest number of shield changes a single phase can plausibly have.
+pub const MAX_SHIELD_CHANGES_PER_PHASE: usize = 20;
+
+/// The largest number of players in a squad, including the host.
+pub const MAX_SQUAD_SIZE: usize = 4;
+
+/// Something that looks wrong with a run.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum ValidationWarning {
+    /// The flight time and phase times of the run do not add up to its total time.
+    #[error("the segments add up to {segments_total}s, but the total time is {total_time}s")]
+    TotalTimeMismatch {
+        /// The total time of the run.
+        total_time: f64,
+
+        /// The flight time plus the time of every phase.
+        segments_total: f64,
+    },
+
+    /// A time of the run is negative, infinite, or not a number.
+    #[error("the {segment} time {time} is not a valid duration")]
+    InvalidTime {
+        /// The segment the time belongs to, such as `phase 2 shield`.
+        segment: String,
+
+        /// The invalid time.
+        time: f64,
+    },
+
+    /// A phase a complete run must have is missing.
+    #[error("phase {0} is missing")]
+    MissingPhase(i32),
+
+    /// A phase number appears more than once.
+    #[error("phase {0} appears more than once")]
+    DuplicatePhase(i32),
+
+    /// A phase number is outside of the phases of the fight.
+    #[error("phase {0} does not exist")]
+    UnexpectedPhase(i32),
+
+    /// A phase does not have exactly four leg breaks.
+    #[error("phase {phase_number} has {count} leg breaks instead of {LEG_BREAKS_PER_PHASE}")]
+    LegBreakCount {
+        /// The number of the phase.
+        phase_number: i32,
+
+        /// The number of leg breaks the phase has.
+        count: usize,
+    },
+
+    /// The same leg was broken more than once in a phase.
+    #[error("the {leg_position} leg was broken more than once in phase {phase_number}")]
+    DuplicateLegBreak {
+        /// The number of the phase.
+        phase_number: i32,
+
+        /// The name of the leg position, as stored in the database.
+        leg_position: String,
+    },
+
+    /// A phase has more shield changes than it plausibly could.
+    #[error("phase {phase_number} has {count} shield changes")]
+    ShieldChangeCount {
+        /// The number of the phase.
+        phase_number: i32,
+
+        /// The number of shield changes the phase has.
+        count: usize,
+    },
+}
+
+/// What to do with a run that fails validation when it is inserted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidRunPolicy {
+    /// Refuse to insert the run.
+    Reject,
+
+    /// Insert the run, but mark it as bugged, with the warnings as the reason, so that it is left
+    /// out of the statistics of valid runs.
+    #[default]
+    MarkBugged,
+}
+
+/// Checks a run for internal consistency.
+///
+/// Aborted runs are not expected to have every phase, nor four leg breaks in their last phase, but
+/// are otherwise checked the same way.
+///
+/// # Arguments
+/// * `run` - The run to check.
+///
+/// # Returns
+/// * `Vec<ValidationWarning>` - Everything that looks wrong with the run, or nothing if it looks
+///   valid.
+#[must_use]
+pub fn validate_run(run: &Run) -> Vec<ValidationWarning> {
+    let mut warnings = Vec::new();
+    let times = &run.total_times;
+
+    for (segment, time) in [
+        ("total", times.total_time),
+        ("flight", times.total_flight_time),
+        ("shield", times.total_shield_time),
+        ("leg", times.total_leg_time),
+        ("body", times.total_body_time),
+        ("pylon", times.total_pylon_time),
+    ] {
+        check_time(&mut warnings, segment, time);
+    }
+
+    let segments_total =
+        times.total_flight_time + run.phases.iter().map(|phase| phase.total_time).sum::<f64>();
+    if (segments_total - times.total_time).abs() > TIME_TOLERANCE {
+        warnings.push(ValidationWarning::TotalTimeMismatch {
+            total_time: times.total_time,
+            segments_total,
+        });
+    }
+
+    let mut phase_numbers = HashSet::new();
+    for phase in &run.phases {
+        if !(1..=PHASE_COUNT).contains(&phase.phase_number) {
+            warnings.push(ValidationWarning::UnexpectedPhase(phase.phase_number));
+        } else if !phase_numbers.insert(phase.phase_number) {
+            warnings.push(ValidationWarning::DuplicatePhase(phase.phase_number));
+        }
+    }
+
+    if !run.is_aborted_run {
+        for phase_number in 1..=PHASE_COUNT {
+            if !phase_numbers.contains(&phase_number) {
+                warnings.push(ValidationWarning::MissingPhase(phase_number));
+            }
+        }
+    }
+
+    let last_phase = run.phases.iter().map(|phase| phase.phase_number).max();
+    for phase in &run.phases {
+        let may_be_unfinished = run.is_aborted_run && Some(phase.phase_number) == last_phase;
+        validate_phase(&mut warnings, phase, may_be_unfinished);
+    }
+
+    warnings
+}
+
+/// Checks a single phase of a run for internal consistency.
+fn validate_phase(warnings: &mut Vec<ValidationWarning>, phase: &Phase, may_be_unfinished: bool) {
+    let phase_number = phase.phase_number;
+
+    for (segment, time) in [
+        ("", phase.total_time),
+        (" shield", phase.total_shield_time),
+        (" leg", phase.total_leg_time),
+        (" body kill", phase.total_body_kill_time),
+        (" pylon", phase.total_pylon_time),
+    ] {
+        check_time(warnings, &format!("phase {phase_number}{segment}"), time);
+    }
+
+    for shield_change in &phase.shield_changes {
+        check_time(
+            warnings,
+            &format!("phase {phase_number} shield change"),
+            shield_change.shield_time,
+        );
+    }
+
+    if phase.shield_changes.len() > MAX_SHIELD_CHANGES_PER_PHASE {
+        warnings.push(ValidationWarning::ShieldChangeCount {
+            phase_number,
+            count: phase.shield_changes.len(),
+        });
+    }
+
+    let count = phase.leg_breaks.len();
+    if count > LEG_BREAKS_PER_PHASE || (count < LEG_BREAKS_PER_PHASE && !may_be_unfinished) {
+        warnings.push(ValidationWarning::LegBreakCount {
+            phase_number,
+            count,
+        });
+    }
+
+    let mut leg_positions = HashSet::new();
+    for leg_break in &phase.leg_breaks {
+        check_time(
+            warnings,
+            &format!("phase {phase_number} leg break"),
+            leg_break.leg_break_time,
+        );
+
+        let leg_position = leg_break.leg_position.to_string();
+        if !leg_positions.insert(leg_position) {
+            warnings.push(ValidationWarning::DuplicateLegBreak {
+                phase_number,
+                leg_position: leg_position.to_string(),
+            });
+        }
+    }
+}
+
+/// Adds a warning if a time is not a valid duration.
+fn check_time(warnings: &mut Vec<ValidationWarning>, segment: &str, time: f64) {
+    if !time.is_finite() || time < 0.0 {
+        warnings.push(ValidationWarning::InvalidTime {
+            segment: segment.to_string(),
+            time,
+        });
+    }
+}
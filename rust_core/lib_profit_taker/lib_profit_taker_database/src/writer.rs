@@ -0,0 +1,236 @@
+//! This module provides a background writer, so that the log parser never has to wait on the
+//! database while a run is in progress.
+//!
+//! A [`WriterHandle`] owns a connection on a thread of its own. Writes sent to it are queued on a
+//! channel and grouped into batches, each committed in a single transaction, so that the cost of
+//! flushing to disk is paid once per batch instead of once per write. A batch is committed once
+//! it has been collecting writes for [`COMMIT_INTERVAL`], once it holds [`MAX_BATCH_SIZE`]
+//! writes, or when `flush` is called.
+//!
+//! Every write returns a [`PendingWrite`], which can be waited on to learn the outcome of the
+//! write once it has been committed, or ignored with `let _ =`.
+
+use lib_profit_taker_core::Run;
+use rusqlite::Connection;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::connection::transaction;
+use crate::error::{DatabaseError, Result};
+use crate::insert;
+
+/// The longest time a write waits for other writes to be batched with before it is committed.
+pub const COMMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The largest number of writes committed in a single transaction.
+pub const MAX_BATCH_SIZE: usize = 64;
+
+/// A write queued for the writer thread.
+enum Message {
+    /// Inserts a new run, replying with its ID.
+    InsertRun(Run, Sender<Result<i64>>),
+
+    /// Overwrites the run with the given ID.
+    UpdateRun(i64, Run, Sender<Result<()>>),
+
+    /// Commits the current batch immediately.
+    Flush(Sender<Result<()>>),
+}
+
+/// A handle to a writer thread, which applies writes to the database in the background.
+///
+/// Dropping the handle commits any queued writes and waits for the thread to exit.
+#[derive(Debug)]
+pub struct WriterHandle {
+    /// The sending half of the queue of writes. Only `None` once the handle is being closed.
+    sender: Option<Sender<Message>>,
+
+    /// The writer thread. Only `None` once the handle is being closed.
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WriterHandle {
+    /// Starts a writer thread that writes through the given connection.
+    ///
+    /// # Arguments
+    /// * `conn` - The connection to write through. It should not be used for anything else, so
+    ///   that the writer is the only one writing to the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::Io`] if the thread cannot be started.
+    pub fn spawn(conn: Connection) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("profit-taker-writer".to_string())
+            .spawn(move || write_messages(&conn, &receiver))?;
+
+        Ok(Self {
+            sender: Some(sender),
+            thread: Some(thread),
+        })
+    }
+
+    /// Queues a run to be inserted.
+    ///
+    /// # Arguments
+    /// * `run` - The run to insert. Its `run_id` field is ignored.
+    ///
+    /// # Returns
+    /// * `PendingWrite<i64>` - Resolves to the ID of the new run once it has been committed.
+    #[must_use]
+    pub fn insert_run(&self, run: Run) -> PendingWrite<i64> {
+        self.send(|reply| Message::InsertRun(run, reply))
+    }
+
+    /// Queues a run to be overwritten with a newer version of it. See [`insert::update_run`].
+    ///
+    /// # Arguments
+    /// * `run_id` - The ID of the run to overwrite.
+    /// * `run` - The new version of the run.
+    ///
+    /// # Returns
+    /// * `PendingWrite<()>` - Resolves once the new version has been committed.
+    #[must_use]
+    pub fn update_run(&self, run_id: i64, run: Run) -> PendingWrite<()> {
+        self.send(|reply| Message::UpdateRun(run_id, run, reply))
+    }
+
+    /// Commits every write queued so far, without waiting for the current batch to fill up.
+    ///
+    /// # Returns
+    /// * `PendingWrite<()>` - Resolves once the writes have been committed. Waiting on it
+    ///   reports whether the commit succeeded, but not whether each write did.
+    #[must_use]
+    pub fn flush(&self) -> PendingWrite<()> {
+        self.send(Message::Flush)
+    }
+
+    /// Commits any queued writes and waits for the writer thread to exit.
+    ///
+    /// This is what dropping the handle does, except that it reports whether the thread exited
+    /// cleanly.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatabaseError::TaskFailed`] if the writer thread panicked.
+    pub fn close(mut self) -> Result<()> {
+        self.stop()
+    }
+
+    /// Queues a message, creating the channel its reply is sent on.
+    fn send<T>(&self, message: impl FnOnce(Sender<Result<T>>) -> Message) -> PendingWrite<T> {
+        let (reply, receiver) = mpsc::channel();
+        if let Some(sender) = &self.sender {
+            // If the writer thread is gone, the reply is dropped too, which `wait` reports
+            let _ = sender.send(message(reply));
+        }
+
+        PendingWrite { receiver }
+    }
+
+    /// Closes the queue and waits for the writer thread to drain it and exit.
+    fn stop(&mut self) -> Result<()> {
+        drop(self.sender.take());
+
+        self.thread.take().map_or(Ok(()), |thread| {
+            thread.join().map_err(|_| DatabaseError::TaskFailed)
+        })
+    }
+}
+
+impl Drop for WriterHandle {
+    fn drop(&mut self) {
+        // There is no way to report a panic from here, and `close` exists for those who care
+        let _ = self.stop();
+    }
+}
+
+/// The outcome of a write queued on a [`WriterHandle`], available once it has been committed.
+#[derive(Debug)]
+pub struct PendingWrite<T> {
+    /// Receives the outcome of the write.
+    receiver: Receiver<Result<T>>,
+}
+
+impl<T> PendingWrite<T> {
+    /// Blocks until the write has been committed or has failed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error the write or its commit failed with, or [`DatabaseError::TaskFailed`] if
+    /// the writer thread stopped before reporting back.
+    pub fn wait(self) -> Result<T> {
+        self.receiver
+            .recv()
+            .map_err(|_| DatabaseError::TaskFailed)?
+    }
+}
+
+/// Applies queued writes in batches until every [`WriterHandle`] sender is dropped.
+fn write_messages(conn: &Connection, receiver: &Receiver<Message>) {
+    while let Ok(first) = receiver.recv() {
+        let deadline = Instant::now() + COMMIT_INTERVAL;
+        let mut batch = vec![first];
+
+        while batch.len() < MAX_BATCH_SIZE && !matches!(batch.last(), Some(Message::Flush(_))) {
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(message) => batch.push(message),
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        write_batch(conn, batch);
+    }
+}
+
+/// Applies a batch of writes in a single transaction, then replies to each of them.
+///
+/// Each write is applied in a savepoint of its own, so one failing write does not prevent the
+/// others from being committed.
+fn write_batch(conn: &Connection, batch: Vec<Message>) {
+    let mut results = Vec::with_capacity(batch.len());
+    let committed = transaction(conn, |conn| {
+        for message in &batch {
+            results.push(match message {
+                Message::InsertRun(run, _) => insert::insert_run(conn, run),
+                Message::UpdateRun(run_id, run, _) => {
+                    insert::update_run(conn, *run_id, run).map(|()| *run_id)
+                }
+                Message::Flush(_) => Ok(0),
+            });
+        }
+
+        Ok::<_, DatabaseError>(())
+    });
+
+    // The receivers having been dropped just means nobody is waiting on those writes
+    let mut results = results.into_iter();
+    for message in batch {
+        let result = committed
+            .as_ref()
+            .map_err(copy_error)
+            .and_then(|()| results.next().unwrap_or(Err(DatabaseError::TaskFailed)));
+
+        match message {
+            Message::InsertRun(_, reply) => {
+                let _ = reply.send(result);
+            }
+            Message::UpdateRun(_, _, reply) | Message::Flush(reply) => {
+                let _ = reply.send(result.map(|_| ()));
+            }
+        }
+    }
+}
+
+/// Copies the error a batch failed to commit with, so that every write in the batch can be told.
+fn copy_error(error: &DatabaseError) -> DatabaseError {
+    match error {
+        DatabaseError::ConstraintViolation(rusqlite::Error::SqliteFailure(code, message))
+        | DatabaseError::Sqlite(rusqlite::Error::SqliteFailure(code, message)) => {
+            rusqlite::Error::SqliteFailure(*code, message.clone()).into()
+        }
+        _ => DatabaseError::TaskFailed,
+    }
+}
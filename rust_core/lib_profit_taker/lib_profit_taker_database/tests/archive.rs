@@ -0,0 +1,49 @@
+//! Checks that archived runs leave the cached overview, which only covers the main database,
+//! while the runs that stay behind are still counted.
+
+use lib_profit_taker_core::Run;
+use lib_profit_taker_database::analytics::cached_overview;
+use lib_profit_taker_database::connection::open_in_memory;
+use lib_profit_taker_database::insert::insert_run;
+use lib_profit_taker_database::maintenance::{archive_runs, attach_archive, detach_archive};
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Returns a solo run started at the given Unix timestamp, taking the given number of seconds.
+fn run_at(time_stamp: i64, total_time: f64) -> Run {
+    let mut run = Run::new(0, time_stamp, "Run", "Player");
+    run.is_solo_run = true;
+    run.total_times.total_time = total_time;
+
+    run
+}
+
+#[test]
+fn archived_runs_leave_the_overview() {
+    let dir = std::env::temp_dir().join(format!("pta-archive-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let archive_path = dir.join("archive.db");
+    let archive_path = archive_path.to_str().unwrap();
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let now = i64::try_from(now.as_secs()).unwrap();
+
+    let conn = open_in_memory().unwrap();
+    insert_run(&conn, &run_at(1_675_271_234, 60.0)).unwrap();
+    insert_run(&conn, &run_at(now, 90.0)).unwrap();
+    assert_eq!(cached_overview(&conn).unwrap().unwrap().run_count, 2);
+
+    let archived = archive_runs(&conn, Duration::from_secs(365 * 24 * 60 * 60), archive_path);
+    assert_eq!(archived.unwrap(), 1);
+
+    let overview = cached_overview(&conn).unwrap().unwrap();
+    assert_eq!(overview.run_count, 1);
+    assert_eq!(overview.total_time.best.as_millis(), 90_000);
+
+    attach_archive(&conn, archive_path).unwrap();
+    assert_eq!(cached_overview(&conn).unwrap().unwrap().run_count, 1);
+    detach_archive(&conn).unwrap();
+
+    drop(conn);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,125 @@
+//! Checks that writes are retried while another connection holds the write lock for longer than
+//! the busy timeout, as set by the [`RetryPolicy`] of their connection or transaction.
+
+use lib_profit_taker_core::Run;
+use lib_profit_taker_database::connection::{ConnectionOptions, RetryPolicy};
+use lib_profit_taker_database::error::DatabaseError;
+use lib_profit_taker_database::insert::insert_run;
+use lib_profit_taker_database::update::set_favorite;
+use rusqlite::ErrorCode;
+use std::fs;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// How long the other connection holds the write lock, which is well within the retries of the
+/// default policy.
+const LOCK_DURATION: Duration = Duration::from_millis(150);
+
+/// Options that give up waiting for the lock straight away, so that only retries can wait.
+const OPTIONS: ConnectionOptions = ConnectionOptions::new().busy_timeout(Duration::ZERO);
+
+/// Returns the path of a new database for a test, removing any left over from an earlier run.
+fn database_path(name: &str) -> String {
+    let path = std::env::temp_dir()
+        .join(format!("pta-busy-retry-{name}-{}.db", std::process::id()))
+        .to_str()
+        .unwrap()
+        .to_owned();
+    remove_database(&path);
+
+    path
+}
+
+/// Removes a database along with its write-ahead log.
+fn remove_database(path: &str) {
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{path}{suffix}"));
+    }
+}
+
+/// Takes the write lock of the database from another thread for [`LOCK_DURATION`], returning once
+/// it is held.
+fn hold_write_lock(path: &str) -> thread::JoinHandle<()> {
+    let path = path.to_owned();
+    let (locked, is_locked) = mpsc::channel();
+    let holder = thread::spawn(move || {
+        let conn = OPTIONS.open(&path).unwrap();
+        conn.execute_batch("BEGIN IMMEDIATE").unwrap();
+        locked.send(()).unwrap();
+        thread::sleep(LOCK_DURATION);
+        conn.execute_batch("COMMIT").unwrap();
+    });
+    is_locked.recv().unwrap();
+
+    holder
+}
+
+#[test]
+fn single_statement_writes_are_retried() {
+    let path = database_path("statement");
+    let conn = OPTIONS.open(&path).unwrap();
+    let run_id = insert_run(&conn, &Run::new(0, 1_675_271_234, "Run", "Player")).unwrap();
+
+    let holder = hold_write_lock(&path);
+    set_favorite(&conn, run_id, true).unwrap();
+    holder.join().unwrap();
+
+    drop(conn);
+    remove_database(&path);
+}
+
+#[test]
+fn writes_are_retried_as_set_by_the_options_of_their_connection() {
+    let path = database_path("connection");
+    let run = Run::new(0, 1_675_271_234, "Run", "Player");
+
+    let never = OPTIONS
+        .retry_policy(RetryPolicy::NEVER)
+        .open(&path)
+        .unwrap();
+    let holder = hold_write_lock(&path);
+    let result = insert_run(&never, &run);
+    assert!(matches!(
+        result,
+        Err(DatabaseError::Sqlite(e)) if e.sqlite_error_code() == Some(ErrorCode::DatabaseBusy)
+    ));
+    holder.join().unwrap();
+
+    let patient = OPTIONS
+        .retry_policy(RetryPolicy::new().max_retries(20))
+        .open(&path)
+        .unwrap();
+    let holder = hold_write_lock(&path);
+    insert_run(&patient, &run).unwrap();
+    holder.join().unwrap();
+
+    drop(never);
+    drop(patient);
+    remove_database(&path);
+}
+
+#[test]
+fn transactions_are_retried_as_set_by_their_options() {
+    let path = database_path("transaction");
+    let conn = OPTIONS.open(&path).unwrap();
+    let run = Run::new(0, 1_675_271_234, "Run", "Player");
+
+    let holder = hold_write_lock(&path);
+    let never = OPTIONS.retry_policy(RetryPolicy::NEVER);
+    let result = never.transaction(&conn, |conn| insert_run(conn, &run));
+    assert!(matches!(
+        result,
+        Err(DatabaseError::Sqlite(e)) if e.sqlite_error_code() == Some(ErrorCode::DatabaseBusy)
+    ));
+    holder.join().unwrap();
+
+    let holder = hold_write_lock(&path);
+    OPTIONS
+        .transaction(&conn, |conn| insert_run(conn, &run))
+        .unwrap();
+    holder.join().unwrap();
+
+    drop(conn);
+    remove_database(&path);
+}
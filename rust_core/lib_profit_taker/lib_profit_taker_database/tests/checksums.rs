@@ -0,0 +1,60 @@
+//! Checks that migrating gives a checksum to every run that lost it, such as runs stored before
+//! checksums existed, and keeps the checksums of every other run.
+
+use lib_profit_taker_core::{Phase, Run, ShieldChange, StatusEffect};
+use lib_profit_taker_database::checksum::run_checksum;
+use lib_profit_taker_database::connection::open_in_memory;
+use lib_profit_taker_database::fetch::fetch_run_by_id;
+use lib_profit_taker_database::insert::insert_run;
+use lib_profit_taker_database::migrations::{migrate, migrate_to, LATEST_VERSION};
+use rusqlite::Connection;
+
+/// Reads the stored checksum of a run.
+fn stored_checksum(conn: &Connection, run_id: i64) -> Option<String> {
+    conn.query_row("SELECT checksum FROM runs WHERE id = ?1", [run_id], |row| {
+        row.get(0)
+    })
+    .unwrap()
+}
+
+/// Stores a run with a phase and a shield change, returning its ID.
+fn insert_test_run(conn: &Connection) -> i64 {
+    let mut run = Run::new(0, 1_675_271_234, "Run", "Player");
+    run.total_times.total_time = 61.234_567;
+    let mut phase = Phase::new(1);
+    phase
+        .shield_changes
+        .push(ShieldChange::new(2.345_678, StatusEffect::Impact));
+    run.phases.push(phase);
+
+    insert_run(conn, &run).unwrap()
+}
+
+#[test]
+fn migrate_backfills_missing_checksums() {
+    let conn = open_in_memory().unwrap();
+    let run_id = insert_test_run(&conn);
+    let expected = stored_checksum(&conn, run_id).unwrap();
+
+    conn.execute("UPDATE runs SET checksum = NULL", []).unwrap();
+    migrate(&conn).unwrap();
+
+    assert_eq!(stored_checksum(&conn, run_id), Some(expected.clone()));
+    assert_eq!(
+        run_checksum(&fetch_run_by_id(&conn, run_id).unwrap()),
+        expected
+    );
+}
+
+#[test]
+fn converting_durations_to_milliseconds_keeps_checksums() {
+    let conn = open_in_memory().unwrap();
+    let run_id = insert_test_run(&conn);
+    let expected = stored_checksum(&conn, run_id).unwrap();
+
+    // Reapplies the migration that stores every duration as whole milliseconds
+    migrate_to(&conn, LATEST_VERSION - 1).unwrap();
+    migrate_to(&conn, LATEST_VERSION).unwrap();
+
+    assert_eq!(stored_checksum(&conn, run_id), Some(expected));
+}
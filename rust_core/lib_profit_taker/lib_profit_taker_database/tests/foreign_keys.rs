@@ -0,0 +1,163 @@
+//! Checks that deleting a run removes every row belonging to it, through the `ON DELETE CASCADE`
+//! foreign keys of the schema as well as through the functions of the `delete` module.
+
+use lib_profit_taker_core::{
+    LegBreak, LegPosition, Phase, Run, ShieldChange, SquadMember, StatusEffect,
+};
+use lib_profit_taker_database::connection::{open_in_memory, ConnectionOptions};
+use lib_profit_taker_database::delete::{delete_run, purge_trashed, soft_delete_run};
+use lib_profit_taker_database::error::DatabaseError;
+use lib_profit_taker_database::insert::{insert_run, store_raw_log};
+use lib_profit_taker_database::maintenance::check_integrity;
+use lib_profit_taker_database::media::add_media;
+use lib_profit_taker_database::migrations::{migrate, migrate_to};
+use lib_profit_taker_database::tags::add_tag;
+use rusqlite::Connection;
+use std::time::Duration;
+
+/// The tables holding the rows that belong to a run.
+const CHILD_TABLES: [&str; 7] = [
+    "phases",
+    "shield_changes",
+    "leg_breaks",
+    "squad_members",
+    "tags",
+    "raw_logs",
+    "media",
+];
+
+/// Stores a run with a row in every child table, returning its ID.
+fn insert_full_run(conn: &Connection, time_stamp: i64) -> i64 {
+    let mut run = Run::new(0, time_stamp, "Run", "Player");
+    let mut phase = Phase::new(1);
+    phase
+        .shield_changes
+        .push(ShieldChange::new(2.5, StatusEffect::Impact));
+    phase
+        .leg_breaks
+        .push(LegBreak::new(1.5, LegPosition::FrontLeft, 1));
+    run.phases.push(phase);
+    run.squad_members.push(SquadMember::new("Friend"));
+
+    let run_id = insert_run(conn, &run).unwrap();
+    add_tag(conn, run_id, "tag").unwrap();
+    store_raw_log(conn, run_id, "log").unwrap();
+    add_media(conn, run_id, "https://example.com/video").unwrap();
+
+    run_id
+}
+
+/// Returns the number of rows of a child table that belong to a run.
+fn count_children(conn: &Connection, table: &str, run_id: i64) -> i64 {
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM {table} WHERE run_id = ?1"),
+        [run_id],
+        |row| row.get(0),
+    )
+    .unwrap()
+}
+
+/// Asserts that a run has no rows left in any child table.
+fn assert_no_children(conn: &Connection, run_id: i64) {
+    for table in CHILD_TABLES {
+        assert_eq!(
+            count_children(conn, table, run_id),
+            0,
+            "rows left in `{table}`"
+        );
+    }
+}
+
+/// Asserts that a run has at least one row in every child table.
+fn assert_all_children(conn: &Connection, run_id: i64) {
+    for table in CHILD_TABLES {
+        assert!(
+            count_children(conn, table, run_id) > 0,
+            "no rows in `{table}`"
+        );
+    }
+}
+
+#[test]
+fn deleting_a_run_row_cascades_to_every_child_table() {
+    let conn = open_in_memory().unwrap();
+    let deleted = insert_full_run(&conn, 1_700_000_000);
+    let kept = insert_full_run(&conn, 1_700_000_100);
+    assert_all_children(&conn, deleted);
+
+    conn.execute("DELETE FROM runs WHERE id = ?1", [deleted])
+        .unwrap();
+
+    assert_no_children(&conn, deleted);
+    assert_all_children(&conn, kept);
+    assert!(check_integrity(&conn).unwrap().is_healthy());
+}
+
+#[test]
+fn delete_run_removes_every_child_row() {
+    let conn = open_in_memory().unwrap();
+    let run_id = insert_full_run(&conn, 1_700_000_000);
+
+    delete_run(&conn, run_id).unwrap();
+
+    assert_no_children(&conn, run_id);
+    assert!(check_integrity(&conn).unwrap().is_healthy());
+}
+
+#[test]
+fn purge_trashed_removes_every_child_row() {
+    let conn = open_in_memory().unwrap();
+    let run_id = insert_full_run(&conn, 1_700_000_000);
+
+    soft_delete_run(&conn, run_id).unwrap();
+    assert_all_children(&conn, run_id);
+    assert_eq!(purge_trashed(&conn, Duration::ZERO).unwrap(), 1);
+
+    assert_no_children(&conn, run_id);
+    assert!(check_integrity(&conn).unwrap().is_healthy());
+}
+
+#[test]
+fn delete_run_cleans_up_without_foreign_keys() {
+    let conn = ConnectionOptions::default()
+        .foreign_keys(false)
+        .open(":memory:")
+        .unwrap();
+    let run_id = insert_full_run(&conn, 1_700_000_000);
+
+    delete_run(&conn, run_id).unwrap();
+
+    assert_no_children(&conn, run_id);
+}
+
+#[test]
+fn child_rows_need_an_existing_run() {
+    let conn = open_in_memory().unwrap();
+
+    let result = conn.execute(
+        "INSERT INTO squad_members (run_id, member_name) VALUES (?1, 'Friend')",
+        [12_345],
+    );
+
+    assert!(matches!(
+        result.map_err(DatabaseError::from),
+        Err(DatabaseError::ConstraintViolation(_))
+    ));
+}
+
+#[test]
+fn migrating_removes_rows_left_behind_without_foreign_keys() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.pragma_update(None, "foreign_keys", false).unwrap();
+    migrate(&conn).unwrap();
+    let run_id = insert_full_run(&conn, 1_700_000_000);
+    migrate_to(&conn, 17).unwrap();
+    // Without foreign keys, this leaves every child row behind
+    conn.execute("DELETE FROM runs WHERE id = ?1", [run_id])
+        .unwrap();
+    assert_all_children(&conn, run_id);
+
+    migrate(&conn).unwrap();
+
+    assert_no_children(&conn, run_id);
+}
@@ -0,0 +1,100 @@
+//! Checks that durations are stored as whole milliseconds, and that migrating from the schema that
+//! stored seconds and back keeps every time, including those in the undo journal.
+
+use lib_profit_taker_core::{LegBreak, LegPosition, Phase, Run, ShieldChange, StatusEffect};
+use lib_profit_taker_database::connection::open_in_memory;
+use lib_profit_taker_database::delete::delete_run;
+use lib_profit_taker_database::insert::insert_run;
+use lib_profit_taker_database::migrations::{migrate_to, LATEST_VERSION};
+use lib_profit_taker_database::undo::{record, undo_last};
+use rusqlite::Connection;
+
+/// The last version to store durations as seconds.
+const SECONDS_VERSION: u32 = LATEST_VERSION - 1;
+
+/// Stores a run with a phase, a shield change, and a leg break, returning its ID.
+fn insert_test_run(conn: &Connection) -> i64 {
+    let mut run = Run::new(0, 1_675_271_234, "Run", "Player");
+    run.total_times.total_time = 61.234_567;
+    let mut phase = Phase::new(1);
+    phase.total_time = 20.0015;
+    phase
+        .shield_changes
+        .push(ShieldChange::new(2.345_678, StatusEffect::Impact));
+    phase
+        .leg_breaks
+        .push(LegBreak::new(1.1, LegPosition::FrontLeft, 1));
+    run.phases.push(phase);
+
+    insert_run(conn, &run).unwrap()
+}
+
+/// Reads the type and value of the durations of the test run, as stored.
+fn stored_times(conn: &Connection) -> Vec<(String, String)> {
+    conn.query_row(
+        "SELECT typeof(total_time), total_time,
+                (SELECT typeof(phase_time) FROM phases), (SELECT phase_time FROM phases),
+                (SELECT typeof(shield_time) FROM shield_changes),
+                (SELECT shield_time FROM shield_changes),
+                (SELECT typeof(break_time) FROM leg_breaks), (SELECT break_time FROM leg_breaks)
+         FROM runs",
+        [],
+        |row| {
+            (0..4)
+                .map(|i| Ok((row.get(i * 2)?, row.get::<_, f64>(i * 2 + 1)?.to_string())))
+                .collect()
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn durations_are_stored_as_whole_milliseconds() {
+    let conn = open_in_memory().unwrap();
+    insert_test_run(&conn);
+
+    let integer = |millis: &str| ("integer".to_owned(), millis.to_owned());
+    assert_eq!(
+        stored_times(&conn),
+        [
+            integer("61235"),
+            integer("20002"),
+            integer("2346"),
+            integer("1100")
+        ]
+    );
+}
+
+#[test]
+fn migrating_from_seconds_and_back_keeps_times() {
+    let conn = open_in_memory().unwrap();
+    insert_test_run(&conn);
+    let expected = stored_times(&conn);
+
+    migrate_to(&conn, SECONDS_VERSION).unwrap();
+    let real = |seconds: &str| ("real".to_owned(), seconds.to_owned());
+    assert_eq!(
+        stored_times(&conn),
+        [real("61.235"), real("20.002"), real("2.346"), real("1.1")]
+    );
+
+    migrate_to(&conn, LATEST_VERSION).unwrap();
+    assert_eq!(stored_times(&conn), expected);
+}
+
+#[test]
+fn migrating_from_seconds_and_back_keeps_the_times_of_the_undo_journal() {
+    let conn = open_in_memory().unwrap();
+    let run_id = insert_test_run(&conn);
+    let expected = stored_times(&conn);
+    record(&conn, "Delete run", &[run_id], |conn| {
+        delete_run(conn, run_id)
+    })
+    .unwrap();
+
+    migrate_to(&conn, SECONDS_VERSION).unwrap();
+    migrate_to(&conn, LATEST_VERSION).unwrap();
+
+    undo_last(&conn).unwrap();
+    assert_eq!(stored_times(&conn), expected);
+}
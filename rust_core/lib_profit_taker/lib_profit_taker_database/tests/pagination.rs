@@ -0,0 +1,84 @@
+//! Checks that paging through runs with `fetch_runs_after` lists every run exactly once, in the
+//! same order as fetching them all at once, for every way runs can be sorted.
+
+use lib_profit_taker_core::{Phase, Run, ShieldChange, StatusEffect};
+use lib_profit_taker_database::connection::open_in_memory;
+use lib_profit_taker_database::fetch::{
+    fetch_runs_after, fetch_runs_paged, RunCursor, RunFilter, SortBy, SortOrder,
+};
+use lib_profit_taker_database::insert::insert_run;
+use rusqlite::Connection;
+
+/// The number of runs stored, spread over several pages.
+const RUN_COUNT: u32 = 11;
+
+/// The number of runs listed per page.
+const PAGE_SIZE: u32 = 3;
+
+/// Stores runs whose names, times, and shield change counts are in different orders, with ties,
+/// so that every sort has to fall back on the run ID somewhere.
+fn insert_runs(conn: &Connection) {
+    for i in 0..RUN_COUNT {
+        let mut run = Run::new(
+            0,
+            1_675_271_234 + i64::from(i * 7 % 5),
+            &format!("Run {}", i % 4),
+            "P",
+        );
+        run.total_times.total_time = f64::from(i * 3 % 7);
+
+        let mut phase = Phase::new(1);
+        for _ in 0..i % 3 {
+            phase
+                .shield_changes
+                .push(ShieldChange::new(1.0, StatusEffect::Impact));
+        }
+        run.phases.push(phase);
+
+        insert_run(conn, &run).unwrap();
+    }
+}
+
+/// Lists the IDs of every run by following the cursors of `fetch_runs_after`.
+fn paged_run_ids(conn: &Connection, sort: SortBy) -> Vec<i64> {
+    let mut run_ids = Vec::new();
+    let mut cursor = Some(RunCursor::new(sort, RunFilter::default()));
+
+    while let Some(current) = cursor {
+        let page = fetch_runs_after(conn, &current, PAGE_SIZE).unwrap();
+        assert!(page.runs.len() <= PAGE_SIZE as usize);
+        run_ids.extend(page.runs.iter().map(|run| run.run_id));
+        assert!(
+            run_ids.len() <= RUN_COUNT as usize,
+            "{sort:?} pages forever"
+        );
+        cursor = page.next;
+    }
+
+    run_ids
+}
+
+#[test]
+fn pages_list_every_run_once_in_order() {
+    let conn = open_in_memory().unwrap();
+    insert_runs(&conn);
+
+    for order in [SortOrder::Ascending, SortOrder::Descending] {
+        for sort in [
+            SortBy::Time(order),
+            SortBy::Date(order),
+            SortBy::Name(order),
+            SortBy::ShieldChanges(order),
+        ] {
+            let expected: Vec<i64> =
+                fetch_runs_paged(&conn, 0, RUN_COUNT, sort, &RunFilter::default())
+                    .unwrap()
+                    .iter()
+                    .map(|run| run.run_id)
+                    .collect();
+            assert_eq!(expected.len(), RUN_COUNT as usize);
+
+            assert_eq!(paged_run_ids(&conn, sort), expected, "{sort:?}");
+        }
+    }
+}
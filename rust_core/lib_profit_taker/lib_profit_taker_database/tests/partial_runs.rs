@@ -0,0 +1,57 @@
+//! Checks that a run stored while still in progress can be recovered, is left out of analytics
+//! until it is completed, and keeps the flags it was stored with.
+
+use lib_profit_taker_core::{Phase, Run};
+use lib_profit_taker_database::analytics::{cached_overview, rank_of_run};
+use lib_profit_taker_database::connection::open_in_memory;
+use lib_profit_taker_database::fetch::{
+    fetch_in_progress_runs, fetch_run_status, fetch_runs_paged, RunFilter, RunStatus, SortBy,
+};
+use lib_profit_taker_database::insert::insert_partial_run;
+use lib_profit_taker_database::update::complete_run;
+
+/// Returns a solo run with the given number of phases, each taking 20 seconds.
+fn run_with_phases(phase_count: i32) -> Run {
+    let mut run = Run::new(0, 1_675_271_234, "Run", "Player");
+    run.is_solo_run = true;
+    for phase_number in 1..=phase_count {
+        let mut phase = Phase::new(phase_number);
+        phase.total_time = 20.0;
+        run.phases.push(phase);
+    }
+    run.total_times.total_time = 20.0 * f64::from(phase_count);
+
+    run
+}
+
+#[test]
+fn runs_in_progress_are_recovered_then_included_once_completed() {
+    let conn = open_in_memory().unwrap();
+    let run_id = insert_partial_run(&conn, &run_with_phases(2)).unwrap();
+
+    let recovered = fetch_in_progress_runs(&conn).unwrap();
+    assert_eq!(recovered.len(), 1);
+    assert_eq!(recovered[0].phases.len(), 2);
+    assert!(!recovered[0].is_aborted_run);
+    assert_eq!(
+        fetch_run_status(&conn, run_id).unwrap(),
+        RunStatus::InProgress
+    );
+
+    let listed = fetch_runs_paged(&conn, 0, 10, SortBy::default(), &RunFilter::default());
+    assert!(listed.unwrap().is_empty());
+    assert!(cached_overview(&conn).unwrap().is_none());
+    assert!(rank_of_run(&conn, run_id).unwrap().is_none());
+
+    complete_run(&conn, run_id, &run_with_phases(4)).unwrap();
+
+    assert!(fetch_in_progress_runs(&conn).unwrap().is_empty());
+    assert_eq!(
+        fetch_run_status(&conn, run_id).unwrap(),
+        RunStatus::Completed
+    );
+    let listed = fetch_runs_paged(&conn, 0, 10, SortBy::default(), &RunFilter::default());
+    assert_eq!(listed.unwrap().len(), 1);
+    assert_eq!(cached_overview(&conn).unwrap().unwrap().run_count, 1);
+    assert_eq!(rank_of_run(&conn, run_id).unwrap().unwrap().rank, 1);
+}
@@ -0,0 +1,69 @@
+//! Checks that opening a database with recovery replaces it only if it is damaged, and returns any
+//! other error without touching it.
+
+use lib_profit_taker_core::Run;
+use lib_profit_taker_database::connection::{open_with_recovery, ConnectionOptions, Recovery};
+use lib_profit_taker_database::error::DatabaseError;
+use lib_profit_taker_database::insert::insert_run;
+use lib_profit_taker_database::migrations::{migrate_to, LATEST_VERSION};
+use std::fs;
+use std::path::Path;
+
+/// Returns the path of a new database for a test, in a directory of its own so that no backups or
+/// exports are found next to it.
+fn database_path(name: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("pta-recovery-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir.join("runs.db").to_str().unwrap().to_owned()
+}
+
+/// Removes the directory of a database made by [`database_path`].
+fn remove_database(path: &str) {
+    let _ = fs::remove_dir_all(Path::new(path).parent().unwrap());
+}
+
+#[test]
+fn damaged_databases_are_replaced() {
+    let path = database_path("damaged");
+    fs::write(
+        &path,
+        b"this is not a database, but it is long enough to look like one",
+    )
+    .unwrap();
+
+    let (_conn, report) = open_with_recovery(&path).unwrap();
+    assert_eq!(report.recovery, Recovery::StartedEmpty);
+    assert!(Path::new(&report.damaged_path.unwrap()).exists());
+
+    remove_database(&path);
+}
+
+#[test]
+fn failed_migrations_leave_the_database_untouched() {
+    let path = database_path("migration");
+    let conn = ConnectionOptions::new().open(&path).unwrap();
+    insert_run(&conn, &Run::new(0, 1_675_271_234, "Run", "Player")).unwrap();
+
+    // Makes the last migration fail the way it would if the disk were full, without any damage
+    migrate_to(&conn, LATEST_VERSION - 1).unwrap();
+    conn.execute_batch(
+        "CREATE TRIGGER fail_updates BEFORE UPDATE ON runs
+         BEGIN SELECT RAISE(ABORT, 'the disk is full'); END",
+    )
+    .unwrap();
+    drop(conn);
+
+    let result = open_with_recovery(&path);
+    assert!(matches!(result, Err(DatabaseError::MigrationFailed(_))));
+
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let runs: i64 = conn
+        .query_row("SELECT COUNT(*) FROM runs", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(runs, 1);
+
+    drop(conn);
+    remove_database(&path);
+}
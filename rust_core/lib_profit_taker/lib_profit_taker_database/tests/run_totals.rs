@@ -0,0 +1,56 @@
+//! Checks that the segment totals of a run are kept equal to the sums over its phases as the
+//! phases are inserted, changed, and deleted.
+
+use lib_profit_taker_core::{Phase, Run};
+use lib_profit_taker_database::connection::open_in_memory;
+use lib_profit_taker_database::insert::insert_run;
+use rusqlite::Connection;
+
+/// Reads the shield, leg, body, and pylon totals of a run, in milliseconds.
+fn stored_totals(conn: &Connection, run_id: i64) -> [i64; 4] {
+    conn.query_row(
+        "SELECT total_shield_time, total_leg_time, total_body_time, total_pylon_time
+        FROM runs WHERE id = ?1",
+        [run_id],
+        |row| Ok([row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?]),
+    )
+    .unwrap()
+}
+
+#[test]
+fn totals_follow_the_phases() {
+    let conn = open_in_memory().unwrap();
+
+    let mut run = Run::new(0, 1_675_271_234, "Run", "Player");
+    for phase_number in 1..=2 {
+        let mut phase = Phase::new(phase_number);
+        phase.total_shield_time = 1.5;
+        phase.total_leg_time = 2.25;
+        phase.total_body_kill_time = 3.0;
+        phase.total_pylon_time = f64::from(phase_number - 1) * 10.0;
+        run.phases.push(phase);
+    }
+    // Off by a rounding error from the sums, which the stored totals should not keep
+    run.total_times.total_shield_time = 3.000_4;
+    let run_id = insert_run(&conn, &run).unwrap();
+    assert_eq!(stored_totals(&conn, run_id), [3_000, 4_500, 6_000, 10_000]);
+
+    conn.execute(
+        "UPDATE phases SET leg_time = 5000 WHERE run_id = ?1 AND phase_number = 1",
+        [run_id],
+    )
+    .unwrap();
+    assert_eq!(stored_totals(&conn, run_id), [3_000, 7_250, 6_000, 10_000]);
+
+    conn.execute(
+        "DELETE FROM phases WHERE run_id = ?1 AND phase_number = 2",
+        [run_id],
+    )
+    .unwrap();
+    assert_eq!(stored_totals(&conn, run_id), [1_500, 5_000, 3_000, 0]);
+
+    // A run without phases keeps the last totals it had instead of dropping to zero
+    conn.execute("DELETE FROM phases WHERE run_id = ?1", [run_id])
+        .unwrap();
+    assert_eq!(stored_totals(&conn, run_id), [1_500, 5_000, 3_000, 0]);
+}
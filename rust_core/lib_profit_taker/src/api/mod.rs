@@ -18,7 +18,7 @@ pub fn create_db(path: String) -> String {
 
     // Try to create the database and run migrations
     match create_database(db_path) {
-        Ok(_) => "Database created successfully".to_string(),
+        Ok(()) => "Database created successfully".to_string(),
         Err(e) => {
             // Return the error as a string for Flutter
             format!("Error creating database: {}", e)
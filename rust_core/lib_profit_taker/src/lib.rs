@@ -11,5 +11,5 @@
 )]
 
 pub mod api;
-#[allow(clippy::nursery, clippy::pedantic, reason = "generated code")]
+#[allow(clippy::nursery, clippy::pedantic, deprecated, reason = "generated code")]
 mod frb_generated;